@@ -0,0 +1,104 @@
+#![no_std]
+#![no_main]
+
+extern crate lib;
+use lib::*;
+
+/// Parse a primary partition number (1 to 4) into its MBR slot
+fn parse_slot(arg: Option<&str>) -> Option<usize> {
+    arg?.parse::<usize>()
+        .ok()
+        .filter(|num| (1..=4).contains(num))
+        .map(|num| num - 1)
+}
+
+fn parse_u32(arg: Option<&str>) -> Option<u32> {
+    arg?.parse().ok()
+}
+
+/// Parse a partition type in hex, e.g. `06` or `0x0c`
+fn parse_type(arg: Option<&str>) -> Option<u8> {
+    let arg = arg?;
+    u8::from_str_radix(arg.trim_start_matches("0x"), 16).ok()
+}
+
+fn main() -> isize {
+    print!("Disk (e.g. hdb): ");
+    let binding = stdin().read_line();
+    let device = binding.trim();
+
+    if !sys_print_partitions(device) {
+        println!("Failed to read the partition table of {}", device);
+        return 1;
+    }
+
+    println!("Enter \"m\" for help, changes are written immediately.");
+
+    loop {
+        print!("[fdisk] ");
+
+        let binding = stdin().read_line();
+        let mut command = binding.trim().split(' ').filter(|arg| !arg.is_empty());
+        let op = command.next().unwrap_or("");
+        let ok = match op {
+            "m" => {
+                println!("\"p\" to print the partition table");
+                println!("\"n num start blocks\" to add a partition of type 0x83");
+                println!("\"d num\" to delete a partition");
+                println!("\"r num blocks\" to resize a partition");
+                println!("\"t num type\" to change the type of a partition, in hex");
+                println!("\"q\" to quit");
+                true
+            }
+            "p" => sys_print_partitions(device),
+            "n" => match (
+                parse_slot(command.next()),
+                parse_u32(command.next()),
+                parse_u32(command.next()),
+            ) {
+                (Some(slot), Some(begin), Some(total)) => {
+                    sys_create_partition(device, slot, begin, total)
+                }
+                _ => {
+                    println!("Usage: n num(1-4) start blocks");
+                    continue;
+                }
+            },
+            "d" => match parse_slot(command.next()) {
+                Some(slot) => sys_delete_partition(device, slot),
+                None => {
+                    println!("Usage: d num(1-4)");
+                    continue;
+                }
+            },
+            "r" => match (parse_slot(command.next()), parse_u32(command.next())) {
+                (Some(slot), Some(total)) => sys_resize_partition(device, slot, total),
+                _ => {
+                    println!("Usage: r num(1-4) blocks");
+                    continue;
+                }
+            },
+            "t" => match (parse_slot(command.next()), parse_type(command.next())) {
+                (Some(slot), Some(kind)) => sys_set_partition_type(device, slot, kind),
+                _ => {
+                    println!("Usage: t num(1-4) type");
+                    continue;
+                }
+            },
+            "q" => break,
+            "" => continue,
+            _ => {
+                println!("Unknown command: {}", op);
+                continue;
+            }
+        };
+
+        if !ok {
+            println!("Failed, see the kernel log for the reason");
+        }
+    }
+
+    0
+}
+
+entry!(main);
@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+extern crate lib;
+use lib::*;
+
+fn main() -> isize {
+    print!("Device (e.g. hdb1 or ram0): ");
+    let binding = stdin().read_line();
+    let device = binding.trim();
+
+    let mut buf = [0u8; 4096];
+    let problems = match sys_fsck(device, false, &mut buf) {
+        Some((0, _)) => {
+            println!("{} is clean", device);
+            return 0;
+        }
+        Some((problems, found)) => {
+            for line in found.lines() {
+                println!("{}: {}", device, line);
+            }
+            problems
+        }
+        None => {
+            println!("Failed to check {}, see the kernel log for the reason", device);
+            return 1;
+        }
+    };
+
+    print!("{} problems found, repair them? [y/N] ", problems);
+    if stdin().read_line().trim() != "y" {
+        return 1;
+    }
+
+    match sys_fsck(device, true, &mut buf) {
+        Some(_) => {
+            println!("{} repaired", device);
+            0
+        }
+        None => {
+            println!("Failed to repair {}", device);
+            1
+        }
+    }
+}
+
+entry!(main);
@@ -0,0 +1,160 @@
+#![no_std]
+#![no_main]
+
+extern crate lib;
+use lib::*;
+
+/// How many entries are read from the kernel at once
+const BATCH: usize = 16;
+
+#[derive(Default)]
+struct Options {
+    long: bool,
+    by_size: bool,
+    by_time: bool,
+    reverse: bool,
+}
+
+fn main() -> isize {
+    // run without arguments, e.g. by `run`, it asks for them
+    let binding = args().unwrap_or_else(|| {
+        print!("ls [-lStr] [path]: ");
+        stdin().read_line()
+    });
+
+    let mut options = Options::default();
+    let mut path = "/";
+    for arg in binding.split_whitespace() {
+        match arg.strip_prefix('-') {
+            Some(flags) => {
+                for flag in flags.chars() {
+                    match flag {
+                        'l' => options.long = true,
+                        'S' => options.by_size = true,
+                        't' => options.by_time = true,
+                        'r' => options.reverse = true,
+                        _ => {
+                            println!("Unknown option: -{}", flag);
+                            return 1;
+                        }
+                    }
+                }
+            }
+            None => path = arg,
+        }
+    }
+
+    let stat = match sys_file_stat(path) {
+        Some(stat) => stat,
+        None => {
+            println!("{}: no such file or directory", path);
+            return 1;
+        }
+    };
+
+    if !stat.is_dir() {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        print_entry(&Dirent::new(name, stat), &options);
+        return 0;
+    }
+
+    let mut entries = match read_dir(path) {
+        Some(entries) => entries,
+        None => {
+            println!("{}: failed to read the directory", path);
+            return 1;
+        }
+    };
+
+    if options.by_size {
+        entries.sort_by(|a, b| b.stat.size.cmp(&a.stat.size).then(a.name().cmp(b.name())));
+    } else if options.by_time {
+        entries.sort_by(|a, b| {
+            b.stat
+                .modified
+                .cmp(&a.stat.modified)
+                .then(a.name().cmp(b.name()))
+        });
+    } else {
+        entries.sort_by(|a, b| a.name().cmp(b.name()));
+    }
+    if options.reverse {
+        entries.reverse();
+    }
+
+    for entry in &entries {
+        print_entry(entry, &options);
+    }
+
+    0
+}
+
+/// Read every entry of the directory
+fn read_dir(path: &str) -> Option<vec::Vec<Dirent>> {
+    let fd = sys_open_file(path);
+    if fd == u8::MAX {
+        return None;
+    }
+
+    let mut entries = vec::Vec::new();
+    let mut buf = [Dirent::EMPTY; BATCH];
+    let result = loop {
+        match sys_get_dents(fd, &mut buf) {
+            Some(0) => break Some(entries),
+            Some(count) => entries.extend_from_slice(&buf[..count]),
+            None => break None,
+        }
+    };
+
+    sys_close_file(fd);
+    result
+}
+
+fn print_entry(entry: &Dirent, options: &Options) {
+    let suffix = if entry.stat.is_dir() { "/" } else { "" };
+    if !options.long {
+        println!("{}{}", entry.name(), suffix);
+        return;
+    }
+
+    let kind = match entry.stat {
+        stat if stat.is_dir() => 'd',
+        stat if stat.is_device() => 'c',
+        _ => '-',
+    };
+    let flag = |bit, ch| {
+        if entry.stat.attributes & bit != 0 {
+            ch
+        } else {
+            '-'
+        }
+    };
+    println!(
+        "{}{}{}{} {:>10} {} {}{}",
+        kind,
+        flag(Stat::READ_ONLY, 'r'),
+        flag(Stat::HIDDEN, 'h'),
+        flag(Stat::SYSTEM, 's'),
+        entry.stat.size,
+        format_time(entry.stat.modified),
+        entry.name(),
+        suffix
+    );
+}
+
+/// Format the time as `YYYY-MM-DD HH:MM`, dashes if it is not recorded
+fn format_time(timestamp: i64) -> string::String {
+    match DateTime::from_timestamp(timestamp, 0).filter(|_| timestamp != 0) {
+        Some(time) => format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}",
+            time.year(),
+            time.month(),
+            time.day(),
+            time.hour(),
+            time.minute()
+        ),
+        None => string::String::from("----------------"),
+    }
+}
+
+entry!(main);
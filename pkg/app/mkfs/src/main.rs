@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+extern crate lib;
+use lib::*;
+
+fn main() -> isize {
+    print!("Device (e.g. hdb1 or ram0): ");
+    let binding = stdin().read_line();
+    let device = binding.trim();
+
+    print!("Volume label (up to 11 characters): ");
+    let binding = stdin().read_line();
+    let label = match binding.trim() {
+        "" => "NO NAME",
+        label => label,
+    };
+
+    print!("All data on {} will be lost, continue? [y/N] ", device);
+    if stdin().read_line().trim() != "y" {
+        println!("Aborted");
+        return 1;
+    }
+
+    if !sys_mkfs(device, label) {
+        println!("Failed to format {}, see the kernel log for the reason", device);
+        return 1;
+    }
+
+    println!("{} formatted as Fat16", device);
+    0
+}
+
+entry!(main);
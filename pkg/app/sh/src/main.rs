@@ -17,9 +17,15 @@ fn main() -> isize {
         match op {
             "help" => {
                 println!("\"la\" to list all the apps");
-                println!("\"ls /path/to/your/dir \" to list all the files in directory");
+                println!("\"ls [-lStr] [/path]\" to list a directory");
                 println!("\"cat /path/to/your/dir \" to check the content of the file");
+                println!("\"hexdump /path [offset] [length]\" to dump a file or /dev/hda1");
+                println!("\"mkdir /path/to/your/dir \" to create a directory");
                 println!("\"run /path/to/your/app \" to run the app");
+                println!("\"mount [device fstype[,ro] /path]\" to list or mount file systems");
+                println!("\"umount /path \" to unmount the file system");
+                println!("\"sync\" to write the cached changes back to the disks");
+                println!("\"chmod +r|-r /path\" to set or clear read-only (also h, s)");
                 println!("\"ps\" to list all the processes");
                 println!("\"info\" to print current process info");
                 println!("\"exit\" to exit the shell");
@@ -28,7 +34,13 @@ fn main() -> isize {
                 sys_list_app();
             }
             "ls" => {
-                sys_list_dir(command.next().unwrap_or("/"));
+                let args: vec::Vec<&str> = command.collect();
+                let pid = sys_spawn_args("/APP/ls", &args.join(" "));
+                if pid == 0 {
+                    println!("Failed to run /APP/ls");
+                } else {
+                    sys_wait_pid(pid);
+                }
             }
             "cat" => {
                 let fd = sys_open_file(command.next().unwrap_or(""));
@@ -40,6 +52,22 @@ fn main() -> isize {
                 );
                 sys_close_file(fd);
             }
+            "hexdump" => match command.next() {
+                Some(path) => {
+                    let offset = command.next().and_then(|n| n.parse().ok()).unwrap_or(0);
+                    let length = command.next().and_then(|n| n.parse().ok()).unwrap_or(512);
+                    hexdump(path, offset, length);
+                }
+                None => println!("Usage: hexdump /path [offset] [length]"),
+            },
+            "mkdir" => match command.next() {
+                Some(path) => {
+                    if !sys_mkdir(path) {
+                        println!("Failed to create directory {}", path);
+                    }
+                }
+                None => println!("Usage: mkdir /path"),
+            },
             "run" => {
                 let path = command.next().unwrap();
                 let name: vec::Vec<&str> = path.rsplit('/').collect();
@@ -52,6 +80,36 @@ fn main() -> isize {
                     println!("{} exited with {}", name[0], sys_wait_pid(pid));
                 }
             }
+            "mount" => match (command.next(), command.next(), command.next()) {
+                (None, _, _) => sys_list_mount(),
+                (Some(device), Some(fs_type), Some(path)) => {
+                    if !sys_mount(device, fs_type, path) {
+                        println!("Failed to mount {} at {}", device, path);
+                    }
+                }
+                _ => println!("Usage: mount [device fstype[,ro] /path]"),
+            },
+            "umount" => match command.next() {
+                Some(path) => {
+                    if !sys_umount(path) {
+                        println!("Failed to unmount {}", path);
+                    }
+                }
+                None => println!("Usage: umount /path"),
+            },
+            "sync" => {
+                if !sys_sync() {
+                    println!("Failed to sync the file systems");
+                }
+            }
+            "chmod" => match (command.next().and_then(parse_attributes), command.next()) {
+                (Some((set, clear)), Some(path)) => {
+                    if !sys_chmod(path, set, clear) {
+                        println!("Failed to change the attributes of {}", path);
+                    }
+                }
+                _ => println!("Usage: chmod [+-][rhs] /path"),
+            },
             "ps" => {
                 sys_stat();
             }
@@ -70,4 +128,75 @@ fn main() -> isize {
     0
 }
 
+/// Parse `+rhs` or `-rhs` into the attribute bits to set and to clear
+fn parse_attributes(flags: &str) -> Option<(u32, u32)> {
+    let mut chars = flags.chars();
+    let set = match chars.next()? {
+        '+' => true,
+        '-' => false,
+        _ => return None,
+    };
+
+    let mut bits = 0;
+    for flag in chars {
+        bits |= match flag {
+            'r' => Stat::READ_ONLY,
+            'h' => Stat::HIDDEN,
+            's' => Stat::SYSTEM,
+            _ => return None,
+        };
+    }
+
+    match (bits, set) {
+        (0, _) => None,
+        (bits, true) => Some((bits, 0)),
+        (bits, false) => Some((0, bits)),
+    }
+}
+
+/// Print `length` bytes of the file from `offset`, 16 bytes per line
+fn hexdump(path: &str, offset: usize, length: usize) {
+    let fd = sys_open_file(path);
+    if fd == u8::MAX {
+        println!("Failed to open {}", path);
+        return;
+    }
+
+    if offset > 0 && sys_lseek(fd, offset as isize, SEEK_SET).is_none() {
+        println!("Failed to seek to {}", offset);
+        sys_close_file(fd);
+        return;
+    }
+
+    let buf = &mut [0u8; 512];
+    let mut done = 0;
+    while done < length {
+        let want = buf.len().min(length - done);
+        let read = match sys_read(fd, &mut buf[..want]) {
+            Some(0) | None => break,
+            Some(read) => read,
+        };
+
+        for (i, line) in buf[..read].chunks(16).enumerate() {
+            print!("{:08x} ", offset + done + i * 16);
+            for byte in line {
+                print!(" {:02x}", byte);
+            }
+            print!("{:width$}  |", "", width = (16 - line.len()) * 3);
+            for byte in line {
+                let ch = if byte.is_ascii_graphic() || *byte == b' ' {
+                    *byte as char
+                } else {
+                    '.'
+                };
+                print!("{}", ch);
+            }
+            println!("|");
+        }
+        done += read;
+    }
+
+    sys_close_file(fd);
+}
+
 entry!(main);
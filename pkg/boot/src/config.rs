@@ -17,6 +17,8 @@ pub struct Config<'a> {
     pub cmdline: &'a str,
     /// Load apps into memory, when no fs implemented in kernel
     pub load_apps: bool,
+    /// The size of the RAM disk, given in number of 4KiB pages, 0 means no RAM disk
+    pub ramdisk_size: u64,
 
     pub log_level: &'a str,
 }
@@ -29,6 +31,7 @@ const DEFAULT_CONFIG: Config = Config {
     kernel_path: "\\KERNEL.ELF",
     cmdline: "",
     load_apps: false,
+    ramdisk_size: 0,
     log_level: "Info",
 };
 
@@ -68,6 +71,7 @@ impl<'a> Config<'a> {
             "kernel_stack_auto_grow" => self.kernel_stack_auto_grow = r10,
             "cmdline" => self.cmdline = value,
             "load_apps" => self.load_apps = r10 != 0,
+            "ramdisk_size" => self.ramdisk_size = r16,
             "log_level" => self.log_level = value,
             _ => warn!("undefined config key: {}", key),
         }
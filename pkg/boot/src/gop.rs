@@ -0,0 +1,59 @@
+use uefi::proto::console::gop::{GraphicsOutput, PixelFormat};
+use uefi::table::boot::*;
+
+/// The framebuffer of the graphics mode set by the firmware
+#[derive(Debug, Clone, Copy)]
+pub struct FrameBuffer {
+    /// The physical address of the framebuffer
+    pub base: u64,
+    /// The size of the framebuffer in bytes
+    pub size: usize,
+    /// The horizontal resolution, in pixels
+    pub width: usize,
+    /// The vertical resolution, in pixels
+    pub height: usize,
+    /// The pixels in a scanline, may be more than the width
+    pub stride: usize,
+    /// The order of the colors in a 32-bit pixel, `Rgb` or `Bgr`
+    pub format: PixelFormat,
+}
+
+/// Get the framebuffer of the current graphics mode
+///
+/// Returns `None` without a graphics output, or if its pixels can not be
+/// written directly.
+pub fn get_framebuffer(bs: &BootServices) -> Option<FrameBuffer> {
+    let handle = bs.get_handle_for_protocol::<GraphicsOutput>().ok()?;
+
+    // not opened exclusively, the firmware keeps its console on the screen
+    let mut gop = unsafe {
+        bs.open_protocol::<GraphicsOutput>(
+            OpenProtocolParams {
+                handle,
+                agent: bs.image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }
+    .ok()?;
+
+    let info = gop.current_mode_info();
+    let format = info.pixel_format();
+    if !matches!(format, PixelFormat::Rgb | PixelFormat::Bgr) {
+        warn!("Unsupported pixel format of the framebuffer: {:?}", format);
+        return None;
+    }
+
+    let (width, height) = info.resolution();
+    let mut buffer = gop.frame_buffer();
+
+    Some(FrameBuffer {
+        base: buffer.as_mut_ptr() as u64,
+        size: buffer.size(),
+        width,
+        height,
+        stride: info.stride(),
+        format,
+    })
+}
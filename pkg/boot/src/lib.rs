@@ -4,7 +4,7 @@ use core::arch::asm;
 pub use uefi::data_types::chars::*;
 pub use uefi::data_types::*;
 pub use uefi::prelude::SystemTable;
-pub use uefi::proto::console::gop::{GraphicsOutput, ModeInfo};
+pub use uefi::proto::console::gop::{GraphicsOutput, ModeInfo, PixelFormat};
 pub use uefi::table::boot::{MemoryAttribute, MemoryDescriptor, MemoryType};
 pub use uefi::table::runtime::*;
 pub use uefi::table::Runtime;
@@ -19,9 +19,11 @@ use x86_64::VirtAddr;
 pub mod allocator;
 pub mod config;
 pub mod fs;
+pub mod gop;
 
 pub use allocator::*;
 pub use fs::*;
+pub use gop::*;
 use xmas_elf::ElfFile;
 
 #[macro_use]
@@ -55,11 +57,17 @@ pub struct BootInfo<'a> {
     /// log level of kernel
     pub log_level: &'a str,
 
+    /// The size of the RAM disk, given in number of 4KiB pages
+    pub ramdisk_size: u64,
+
     // Loaded apps
     pub loaded_apps: Option<ArrayVec<App<'static>, 16>>,
 
     // Kernel pages
     pub kernel_pages: KernelPages,
+
+    /// The framebuffer of the graphics output, if any
+    pub framebuffer: Option<FrameBuffer>,
 }
 
 /// Get current page table from CR3
@@ -54,6 +54,15 @@ fn efi_main(image: uefi::Handle, mut system_table: SystemTable<Boot>) -> Status
         set_entry(elf.header.pt2.entry_point() as usize);
     }
 
+    let framebuffer = get_framebuffer(bs);
+    match framebuffer {
+        Some(fb) => info!(
+            "Framebuffer: {}x{} at {:#x}, stride {}",
+            fb.width, fb.height, fb.base, fb.stride
+        ),
+        None => info!("No framebuffer available"),
+    }
+
     // Load MemoryMap
     let max_mmap_size = system_table.boot_services().memory_map_size();
     let mmap_storage = Box::leak(
@@ -69,7 +78,8 @@ fn efi_main(image: uefi::Handle, mut system_table: SystemTable<Boot>) -> Status
         .map(|m| m.phys_start + m.page_count * 0x1000)
         .max()
         .unwrap()
-        .max(0x1_0000_0000); // include IOAPIC MMIO area
+        .max(0x1_0000_0000) // include IOAPIC MMIO area
+        .max(framebuffer.map_or(0, |fb| fb.base + fb.size as u64));
 
     // Map ELF segments, kernel stack and physical memory to virtual memory
     let mut page_table = current_page_table();
@@ -141,8 +151,10 @@ fn efi_main(image: uefi::Handle, mut system_table: SystemTable<Boot>) -> Status
         physical_memory_offset: config.physical_memory_offset,
         system_table: runtime,
         log_level: config.log_level,
+        ramdisk_size: config.ramdisk_size,
         loaded_apps: apps,
         kernel_pages: kernelpages,
+        framebuffer,
     };
 
     // Align stack to 8 bytes
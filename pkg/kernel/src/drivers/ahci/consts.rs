@@ -0,0 +1,99 @@
+//! Constants and bitflags for the AHCI driver.
+//!
+//! reference: https://www.intel.com/content/dam/www/public/us/en/documents/technical-specifications/serial-ata-ahci-spec-rev1-3-1.pdf
+
+// registers of the HBA
+pub(super) const HBA_CAP: usize = 0x00;
+pub(super) const HBA_GHC: usize = 0x04;
+pub(super) const HBA_IS: usize = 0x08;
+pub(super) const HBA_PI: usize = 0x0C;
+pub(super) const HBA_VS: usize = 0x10;
+
+/// The registers of the first port, each one takes `PORT_SIZE` bytes
+pub(super) const PORT_BASE: usize = 0x100;
+pub(super) const PORT_SIZE: usize = 0x80;
+pub(super) const MAX_PORTS: usize = 32;
+
+// registers of a port
+pub(super) const PORT_CLB: usize = 0x00;
+pub(super) const PORT_CLBU: usize = 0x04;
+pub(super) const PORT_FB: usize = 0x08;
+pub(super) const PORT_FBU: usize = 0x0C;
+pub(super) const PORT_IS: usize = 0x10;
+pub(super) const PORT_IE: usize = 0x14;
+pub(super) const PORT_CMD: usize = 0x18;
+pub(super) const PORT_TFD: usize = 0x20;
+pub(super) const PORT_SIG: usize = 0x24;
+pub(super) const PORT_SSTS: usize = 0x28;
+pub(super) const PORT_SERR: usize = 0x30;
+pub(super) const PORT_CI: usize = 0x38;
+
+bitflags! {
+    /// The global HBA control register.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub(super) struct GlobalControl: u32 {
+        const RESET            = 1 << 0;
+        const INTERRUPT_ENABLE = 1 << 1;
+        /// The HBA is driven through AHCI, not the legacy IDE interface.
+        const AHCI_ENABLE      = 1 << 31;
+    }
+}
+
+bitflags! {
+    /// The command and status register of a port.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub(super) struct PortCommand: u32 {
+        /// The port processes the command list.
+        const START           = 1 << 0;
+        /// The port receives the FIS from the device.
+        const FIS_RECEIVE     = 1 << 4;
+        const FIS_RUNNING     = 1 << 14;
+        const LIST_RUNNING    = 1 << 15;
+    }
+}
+
+bitflags! {
+    /// The interrupt status and enable registers of a port.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub(super) struct PortInterrupt: u32 {
+        /// The device sent a register FIS, a command is done.
+        const D2H_REGISTER = 1 << 0;
+        /// The device sent a PIO setup FIS.
+        const PIO_SETUP    = 1 << 1;
+        /// The task file has an error.
+        const TASK_FILE_ERROR = 1 << 30;
+    }
+}
+
+bitflags! {
+    /// The task file status of a port, as the ATA status register.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub(super) struct TaskFile: u32 {
+        const ERROR         = 1 << 0;
+        const DATA_REQUEST  = 1 << 3;
+        const DEVICE_FAULT  = 1 << 5;
+        const BUSY          = 1 << 7;
+    }
+}
+
+/// The device detection of the SATA status, a device is present
+/// and the communication is established
+pub(super) const SSTS_DET_PRESENT: u32 = 0x3;
+/// The power management state of the SATA status, active
+pub(super) const SSTS_IPM_ACTIVE: u32 = 0x1;
+
+/// The signatures of the devices
+pub(super) const SIG_ATA: u32 = 0x0000_0101;
+pub(super) const SIG_ATAPI: u32 = 0xEB14_0101;
+
+/// The type of a register FIS from the host to the device
+pub(super) const FIS_TYPE_REG_H2D: u8 = 0x27;
+
+/// The ATA commands sent in the register FIS
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub(super) enum AtaCommand {
+    ReadDmaExt = 0x25,
+    WriteDmaExt = 0x35,
+    IdentifyDevice = 0xEC,
+}
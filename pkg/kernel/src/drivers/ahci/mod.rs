@@ -0,0 +1,294 @@
+//! AHCI SATA Drive
+//!
+//! The HBA is found on the PCI bus by its class (01:06, AHCI), its
+//! registers are in the memory space at BAR5. Each port with a SATA
+//! drive attached is a disk, named `sda` onwards.
+//!
+//! reference: https://wiki.osdev.org/AHCI
+//! reference: https://www.intel.com/content/dam/www/public/us/en/documents/technical-specifications/serial-ata-ahci-spec-rev1-3-1.pdf
+
+mod consts;
+mod port;
+
+use super::ata::{COMMAND_SETS, MAX_LBA48, MODEL, MODEL_SIZE, SERIAL, SERIAL_SIZE};
+use super::pci::{Bar, PciDevice, PciDriver};
+use crate::interrupt::MSI_VECTOR;
+use crate::memory::physical_to_virtual;
+use crate::proc::WaitQueue;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::{boxed::Box, format, string::String};
+use consts::*;
+use port::{AhciPort, MAX_SECTORS};
+use spin::Mutex;
+use storage::{Block512, BlockDevice, SizedBlock};
+
+const SECTOR_SIZE: usize = Block512::BLOCK_SIZE;
+
+/// The memory mapped registers of the HBA or of a port
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Registers {
+    base: u64,
+}
+
+impl Registers {
+    fn new(base: u64) -> Self {
+        Self { base }
+    }
+
+    pub fn read(&self, offset: usize) -> u32 {
+        unsafe { ((self.base as usize + offset) as *const u32).read_volatile() }
+    }
+
+    pub fn write(&self, offset: usize, value: u32) {
+        unsafe { ((self.base as usize + offset) as *mut u32).write_volatile(value) }
+    }
+
+    /// The registers of the port numbered `index`
+    fn port(&self, index: usize) -> Self {
+        Self::new(self.base + (PORT_BASE + index * PORT_SIZE) as u64)
+    }
+}
+
+/// The SATA drives found at boot, `sda` first
+static DISKS: Mutex<Vec<AhciDisk>> = Mutex::new(Vec::new());
+
+/// The processes waiting for a port or for their command, woken up by a
+/// port being freed or the interrupt of an HBA
+static WAITERS: WaitQueue = WaitQueue::new();
+
+/// The SATA drives found at boot
+pub fn disks() -> Vec<AhciDisk> {
+    DISKS.lock().clone()
+}
+
+/// The PCI driver of the AHCI controllers
+pub struct AhciDriver;
+
+pub static AHCI_DRIVER: AhciDriver = AhciDriver;
+
+/// The registers of the HBA at BAR5 of the function
+fn hba_registers(device: &PciDevice) -> Option<Registers> {
+    match device.bars[5] {
+        Some(Bar::Memory { address, .. }) => Some(Registers::new(physical_to_virtual(address))),
+        _ => None,
+    }
+}
+
+impl PciDriver for AhciDriver {
+    fn name(&self) -> &'static str {
+        "ahci"
+    }
+
+    fn ids(&self) -> &'static [(u16, u16)] {
+        &[]
+    }
+
+    /// Any mass storage controller with the AHCI interface
+    fn matches(&self, device: &PciDevice) -> bool {
+        (device.class, device.subclass, device.prog_if) == (0x01, 0x06, 0x01)
+    }
+
+    fn probe(&self, device: &PciDevice) -> bool {
+        let Some(hba) = hba_registers(device) else {
+            return false;
+        };
+
+        device.enable_bus_master();
+        hba.write(
+            HBA_GHC,
+            hba.read(HBA_GHC) | GlobalControl::AHCI_ENABLE.bits(),
+        );
+
+        let implemented = hba.read(HBA_PI);
+        let version = hba.read(HBA_VS);
+        info!(
+            "AHCI {}.{} at {}: {} ports",
+            version >> 16,
+            version & 0xFFFF,
+            device.address,
+            implemented.count_ones()
+        );
+
+        for index in (0..MAX_PORTS).filter(|index| implemented & (1 << index) != 0) {
+            let regs = hba.port(index);
+            if !AhciPort::has_drive(&regs) {
+                continue;
+            }
+
+            match AhciPort::new(regs).and_then(AhciDisk::probe) {
+                Some(disk) => {
+                    info!("Drive {} opened: {}", disk.name(), disk);
+                    DISKS.lock().push(disk);
+                }
+                None => warn!("Failed to open the drive on AHCI port {}", index),
+            }
+        }
+
+        // the line is only used if the HBA has no MSI
+        if !device.enable_msi(MSI_VECTOR) {
+            trace!(
+                "AHCI at {} interrupts on line {}",
+                device.address,
+                device.interrupt_line
+            );
+        }
+        hba.write(HBA_IS, u32::MAX);
+        hba.write(
+            HBA_GHC,
+            hba.read(HBA_GHC) | GlobalControl::INTERRUPT_ENABLE.bits(),
+        );
+
+        true
+    }
+
+    /// Clear the interrupts of the ports, then the ones of the HBA, and
+    /// wake up the processes waiting for their command to check the port
+    fn interrupt(&self, device: &PciDevice) {
+        let Some(hba) = hba_registers(device) else {
+            return;
+        };
+
+        let pending = hba.read(HBA_IS);
+        for index in (0..MAX_PORTS).filter(|index| pending & (1 << index) != 0) {
+            let port = hba.port(index);
+            port.write(PORT_IS, port.read(PORT_IS));
+        }
+        hba.write(HBA_IS, pending);
+        WAITERS.wake_all();
+    }
+}
+
+/// A SATA drive on an AHCI port
+#[derive(Clone)]
+pub struct AhciDisk {
+    index: usize,
+    blocks: u64,
+    model: Box<str>,
+    serial: Box<str>,
+    /// Only locked by a process, it waits while the command runs
+    port: Arc<Mutex<AhciPort>>,
+}
+
+impl AhciDisk {
+    /// Identify the drive on the port
+    fn probe(mut port: AhciPort) -> Option<Self> {
+        let res = port.identify()?;
+        let buf = res.map(u16::to_be_bytes).concat();
+
+        // only the 48-bit DMA commands are sent
+        if res[COMMAND_SETS] & (1 << 10) == 0 {
+            warn!("SATA drive without LBA48");
+            return None;
+        }
+
+        let serial = String::from_utf8_lossy(&buf[SERIAL..SERIAL + SERIAL_SIZE])
+            .trim()
+            .into();
+        let model = String::from_utf8_lossy(&buf[MODEL..MODEL + MODEL_SIZE])
+            .trim()
+            .into();
+        let blocks = res[MAX_LBA48..MAX_LBA48 + 4]
+            .iter()
+            .rev()
+            .fold(0, |blocks, word| blocks << 16 | *word as u64);
+
+        Some(Self {
+            index: DISKS.lock().len(),
+            blocks,
+            model,
+            serial,
+            port: Arc::new(Mutex::new(port)),
+        })
+    }
+
+    /// The drive numbered `index`, if it was found at boot
+    pub fn open(index: usize) -> Option<Self> {
+        DISKS.lock().get(index).cloned()
+    }
+
+    /// The name of the drive, `sda` for the first one
+    pub fn name(&self) -> String {
+        format!("sd{}", (b'a' + self.index as u8) as char)
+    }
+
+    /// Run a command on the port, waiting for it to be free
+    ///
+    /// The process is blocked until the port is freed instead of spinning on
+    /// the lock, the port is held for the whole command.
+    fn transfer(
+        &self,
+        command: AtaCommand,
+        block: u64,
+        sectors: usize,
+        fill: impl FnOnce(&mut [u8]),
+        drain: impl FnOnce(&[u8]),
+    ) -> storage::Result<()> {
+        let mut guard = None;
+        WAITERS.wait_until(|| {
+            guard = self.port.try_lock();
+            guard.is_some()
+        });
+        let mut port = guard.unwrap();
+
+        let buffer =
+            unsafe { core::slice::from_raw_parts_mut(port.buffer(), sectors * SECTOR_SIZE) };
+        fill(buffer);
+        let ret = port.issue(command, block, sectors);
+        if ret.is_ok() {
+            drain(buffer);
+        }
+
+        // the next process waiting for the port
+        drop(port);
+        WAITERS.wake_all();
+        ret
+    }
+}
+
+impl core::fmt::Display for AhciDisk {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let (size, unit) = crate::humanized_size(self.blocks * SECTOR_SIZE as u64);
+        write!(f, "{} {} ({} {})", self.model, self.serial, size, unit)
+    }
+}
+
+impl BlockDevice<Block512> for AhciDisk {
+    fn block_count(&self) -> storage::Result<usize> {
+        Ok(self.blocks as usize)
+    }
+
+    fn read_block(&self, offset: usize, block: &mut Block512) -> storage::Result<()> {
+        self.read_blocks(offset, core::slice::from_mut(block))
+    }
+
+    fn write_block(&self, offset: usize, block: &Block512) -> storage::Result<()> {
+        self.transfer(
+            AtaCommand::WriteDmaExt,
+            offset as u64,
+            1,
+            |buffer| buffer.copy_from_slice(block.as_ref()),
+            |_| {},
+        )
+    }
+
+    /// Reads the blocks with a command for each `MAX_SECTORS` of them
+    fn read_blocks(&self, offset: usize, blocks: &mut [Block512]) -> storage::Result<()> {
+        for (index, chunk) in blocks.chunks_mut(MAX_SECTORS).enumerate() {
+            let block = (offset + index * MAX_SECTORS) as u64;
+            self.transfer(
+                AtaCommand::ReadDmaExt,
+                block,
+                chunk.len(),
+                |_| {},
+                |buffer| {
+                    for (block, data) in chunk.iter_mut().zip(buffer.chunks(SECTOR_SIZE)) {
+                        block.as_mut().copy_from_slice(data);
+                    }
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
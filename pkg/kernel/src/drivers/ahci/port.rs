@@ -0,0 +1,267 @@
+//! AHCI Port
+//!
+//! A port issues one command at a time, in the first slot of its command
+//! list. The command list, the received FIS and the command table share a
+//! frame, the data goes through a buffer of contiguous frames described
+//! by a single PRDT entry.
+//!
+//! reference: https://wiki.osdev.org/AHCI
+
+use super::consts::*;
+use super::{Registers, WAITERS};
+use crate::interrupt::read_counter;
+use crate::memory::{get_frame_alloc_for_sure, physical_to_virtual, PAGE_SIZE};
+use alloc::boxed::Box;
+use storage::{Block512, DeviceError, SizedBlock};
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::structures::paging::{FrameAllocator, PhysFrame};
+
+/// The offsets of the structures in the frame of the port
+const COMMAND_LIST: u64 = 0x000;
+const RECEIVED_FIS: u64 = 0x400;
+const COMMAND_TABLE: u64 = 0x500;
+
+/// The offset of the PRDT in the command table
+const PRDT: u64 = 0x80;
+
+/// The length of a register FIS from the host to the device, in dwords
+const FIS_REG_H2D_DWORDS: u32 = 5;
+
+/// The contiguous frames of the buffer of each port, 64 KiB
+const DATA_FRAMES: usize = 16;
+
+const SECTOR_SIZE: usize = Block512::BLOCK_SIZE;
+
+/// The most sectors transferred by a single command
+pub(super) const MAX_SECTORS: usize = DATA_FRAMES * PAGE_SIZE as usize / SECTOR_SIZE;
+
+/// The ticks waited for the port to stop, the spec allows 500 ms
+const STOP_TIMEOUT: usize = 1_000_000;
+
+/// The timer ticks waited for a command, as long as for an ATA DMA transfer
+const COMMAND_TIMEOUT_TICKS: u64 = 100_000;
+
+#[derive(Debug)]
+pub(super) struct AhciPort {
+    regs: Registers,
+    /// The command list, the received FIS and the command table
+    frame: PhysFrame,
+    data: PhysFrame,
+}
+
+impl AhciPort {
+    /// Returns true if a SATA drive is attached and the link is up
+    pub fn has_drive(regs: &Registers) -> bool {
+        let status = regs.read(PORT_SSTS);
+        let detection = status & 0xF;
+        let power = (status >> 8) & 0xF;
+
+        match (detection, power, regs.read(PORT_SIG)) {
+            (SSTS_DET_PRESENT, SSTS_IPM_ACTIVE, SIG_ATA) => true,
+            (SSTS_DET_PRESENT, SSTS_IPM_ACTIVE, SIG_ATAPI) => {
+                info!("Ignoring the ATAPI device on an AHCI port");
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Rebase the port on new frames and start it, `None` if no frames are left
+    pub fn new(regs: Registers) -> Option<Self> {
+        let (frame, data) = without_interrupts(|| {
+            let mut alloc = get_frame_alloc_for_sure();
+            Some((
+                alloc.allocate_frame()?,
+                alloc.allocate_contiguous(DATA_FRAMES)?,
+            ))
+        })?;
+        let base = physical_to_virtual(frame.start_address().as_u64());
+        unsafe { core::ptr::write_bytes(base as *mut u8, 0, PAGE_SIZE as usize) };
+
+        let port = Self { regs, frame, data };
+        port.stop();
+
+        let physical = frame.start_address().as_u64();
+        let list = physical + COMMAND_LIST;
+        let fis = physical + RECEIVED_FIS;
+        regs.write(PORT_CLB, list as u32);
+        regs.write(PORT_CLBU, (list >> 32) as u32);
+        regs.write(PORT_FB, fis as u32);
+        regs.write(PORT_FBU, (fis >> 32) as u32);
+
+        // the command table of the first slot
+        let table = physical + COMMAND_TABLE;
+        unsafe {
+            let header = port.virt(COMMAND_LIST) as *mut u32;
+            header.add(2).write_volatile(table as u32);
+            header.add(3).write_volatile((table >> 32) as u32);
+        }
+
+        // the errors and interrupts are cleared by writing them
+        regs.write(PORT_SERR, u32::MAX);
+        regs.write(PORT_IS, u32::MAX);
+        regs.write(
+            PORT_IE,
+            (PortInterrupt::D2H_REGISTER | PortInterrupt::TASK_FILE_ERROR).bits(),
+        );
+
+        port.start();
+        Some(port)
+    }
+
+    /// The virtual address of an offset in the frame of the port
+    fn virt(&self, offset: u64) -> u64 {
+        physical_to_virtual(self.frame.start_address().as_u64() + offset)
+    }
+
+    /// The buffer of the port, `MAX_SECTORS` sectors
+    pub fn buffer(&self) -> *mut u8 {
+        physical_to_virtual(self.data.start_address().as_u64()) as *mut u8
+    }
+
+    fn command(&self) -> PortCommand {
+        PortCommand::from_bits_retain(self.regs.read(PORT_CMD))
+    }
+
+    fn set_command(&self, command: PortCommand) {
+        self.regs.write(PORT_CMD, command.bits());
+    }
+
+    fn task_file(&self) -> TaskFile {
+        TaskFile::from_bits_truncate(self.regs.read(PORT_TFD))
+    }
+
+    /// Stop processing the command list and receiving FIS
+    fn stop(&self) {
+        self.set_command(self.command() - PortCommand::START - PortCommand::FIS_RECEIVE);
+
+        let running = PortCommand::LIST_RUNNING | PortCommand::FIS_RUNNING;
+        for _ in 0..STOP_TIMEOUT {
+            if !self.command().intersects(running) {
+                return;
+            }
+            core::hint::spin_loop();
+        }
+        warn!("AHCI port did not stop");
+    }
+
+    /// Start processing the command list, once the drive is idle
+    fn start(&self) {
+        for _ in 0..STOP_TIMEOUT {
+            if !self
+                .task_file()
+                .intersects(TaskFile::BUSY | TaskFile::DATA_REQUEST)
+            {
+                break;
+            }
+            core::hint::spin_loop();
+        }
+
+        self.set_command(self.command() | PortCommand::FIS_RECEIVE);
+        self.set_command(self.command() | PortCommand::START);
+    }
+
+    /// Issue a command for `sectors` sectors at `block` in the first slot,
+    /// the data is in the buffer of the port
+    ///
+    /// The process is blocked until the drive is done, checking after each interrupt.
+    /// The port is restarted if the command fails or times out.
+    pub fn issue(
+        &mut self,
+        command: AtaCommand,
+        block: u64,
+        sectors: usize,
+    ) -> storage::Result<()> {
+        let write = command == AtaCommand::WriteDmaExt;
+        let bytes = (sectors * SECTOR_SIZE) as u32;
+
+        unsafe {
+            // the command header, the FIS length, the direction and one PRDT entry
+            let header = self.virt(COMMAND_LIST) as *mut u32;
+            let flags = FIS_REG_H2D_DWORDS | if write { 1 << 6 } else { 0 };
+            header.write_volatile(flags | 1 << 16);
+            header.add(1).write_volatile(0);
+
+            // the register FIS
+            let fis = self.virt(COMMAND_TABLE) as *mut u8;
+            core::ptr::write_bytes(fis, 0, PRDT as usize);
+            let lba = block.to_le_bytes();
+            let count = (sectors as u16).to_le_bytes();
+            let register = [
+                FIS_TYPE_REG_H2D,
+                1 << 7, // a command, not a control
+                command as u8,
+                0,
+                lba[0],
+                lba[1],
+                lba[2],
+                1 << 6, // LBA mode
+                lba[3],
+                lba[4],
+                lba[5],
+                0,
+                count[0],
+                count[1],
+            ];
+            core::ptr::copy_nonoverlapping(register.as_ptr(), fis, register.len());
+
+            // the PRDT entry, the byte count is stored minus one
+            let prdt = self.virt(COMMAND_TABLE + PRDT) as *mut u32;
+            let data = self.data.start_address().as_u64();
+            prdt.write_volatile(data as u32);
+            prdt.add(1).write_volatile((data >> 32) as u32);
+            prdt.add(2).write_volatile(0);
+            prdt.add(3).write_volatile(bytes - 1);
+        }
+
+        self.regs.write(PORT_IS, u32::MAX);
+        self.regs.write(PORT_CI, 1);
+
+        let deadline = read_counter() + COMMAND_TIMEOUT_TICKS;
+        let finished = WAITERS.wait_until_deadline(deadline, || {
+            self.regs.read(PORT_CI) & 1 == 0
+                || self
+                    .task_file()
+                    .intersects(TaskFile::ERROR | TaskFile::DEVICE_FAULT)
+        });
+
+        if !finished {
+            warn!("AHCI command {:?} timed out at block {}", command, block);
+        } else if self
+            .task_file()
+            .intersects(TaskFile::ERROR | TaskFile::DEVICE_FAULT)
+        {
+            warn!("AHCI command {:?} failed at block {}", command, block);
+        } else {
+            return Ok(());
+        }
+
+        self.recover();
+        let error = if write {
+            DeviceError::WriteError
+        } else {
+            DeviceError::ReadError
+        };
+        Err(error.into())
+    }
+
+    /// Restart the port after an error, which stops the command list
+    fn recover(&self) {
+        self.stop();
+        self.regs.write(PORT_SERR, u32::MAX);
+        self.regs.write(PORT_IS, u32::MAX);
+        self.start();
+    }
+
+    /// Identify the drive, `None` if the command fails
+    pub fn identify(&mut self) -> Option<Box<[u16; 256]>> {
+        self.issue(AtaCommand::IdentifyDevice, 0, 1).ok()?;
+
+        let data = unsafe { core::slice::from_raw_parts(self.buffer(), SECTOR_SIZE) };
+        let mut words = Box::new([0u16; 256]);
+        for (word, bytes) in words.iter_mut().zip(data.chunks(2)) {
+            *word = u16::from_le_bytes([bytes[0], bytes[1]]);
+        }
+        Some(words)
+    }
+}
@@ -2,13 +2,18 @@
 //!
 //! reference: https://wiki.osdev.org/IDE
 //! reference: https://wiki.osdev.org/ATA_PIO_Mode
+//! reference: https://wiki.osdev.org/ATA/ATAPI_using_DMA
 //! reference: https://github.com/theseus-os/Theseus/blob/HEAD/kernel/ata/src/lib.rs
 
 use super::consts::*;
+use super::dma::{BusMaster, DMA_TIMEOUT_TICKS};
+use super::INTERRUPTS;
+use crate::interrupt::read_counter;
 use alloc::boxed::Box;
+use storage::Block512;
 use x86_64::instructions::port::*;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 #[allow(dead_code)]
 pub struct AtaBus {
     id: u8,
@@ -31,13 +36,15 @@ pub struct AtaBus {
     alternate_status: PortReadOnly<u8>,
     control: PortWriteOnly<u8>,
     drive_blockess: PortReadOnly<u8>,
+    /// The DMA registers of the bus, if the IDE controller has them
+    bus_master: Option<BusMaster>,
 }
 
 impl AtaBus {
     pub fn new(id: u8, irq: u8, io_base: u16, ctrl_base: u16) -> Self {
         Self {
             id,
-            irq, // actually not used as we poll the status
+            irq, // the process waiting for the bus is woken up when it fires
             io_base,
             ctrl_base,
             data: Port::<u16>::new(io_base),
@@ -54,9 +61,24 @@ impl AtaBus {
             alternate_status: PortReadOnly::new(ctrl_base),
             control: PortWriteOnly::new(ctrl_base),
             drive_blockess: PortReadOnly::new(ctrl_base + 1),
+            bus_master: None,
         }
     }
 
+    /// Enables the interrupts of the bus, by clearing nIEN of the control port,
+    /// and its DMA transfers with the bus master registers of the IDE controller.
+    pub(super) fn init(&mut self, bus_master: Option<u16>) {
+        unsafe { self.control.write(0) }
+
+        // the secondary bus has its registers after the primary ones
+        self.bus_master = bus_master.and_then(|base| BusMaster::new(base + self.id as u16 * 8));
+    }
+
+    /// Returns true if the bus can transfer with DMA.
+    pub(super) fn has_dma(&self) -> bool {
+        self.bus_master.is_some()
+    }
+
     #[inline]
     fn read_data(&mut self) -> u16 {
         unsafe { self.data.read() }
@@ -109,6 +131,14 @@ impl AtaBus {
         self.status().contains(AtaStatus::ERROR)
     }
 
+    /// Waits until the drive is no longer BUSY, the process is blocked until
+    /// the bus interrupts.
+    ///
+    /// The interrupts are enabled by clearing nIEN in `init`.
+    fn wait_ready(&mut self) {
+        INTERRUPTS[self.id as usize].wait_until(|| !self.status().contains(AtaStatus::BUSY));
+    }
+
     /// Polls the `status` port until the given bit is set to the given value.
     #[inline]
     fn poll(&mut self, bit: AtaStatus, val: bool) {
@@ -128,21 +158,44 @@ impl AtaBus {
         warn!("ATA status register : {:?}", self.status());
     }
 
-    /// Writes the given command
+    /// Writes the given command for `count` sectors from `block`,
+    /// with a 48-bit address for the `Ext` commands.
     ///
     /// reference: https://wiki.osdev.org/ATA_PIO_Mode#28_bit_PIO
-    fn write_command(&mut self, drive: u8, block: u32, cmd: AtaCommand) -> storage::Result<()> {
+    /// reference: https://wiki.osdev.org/ATA_PIO_Mode#48_bit_PIO
+    fn write_command(
+        &mut self,
+        drive: u8,
+        block: u64,
+        count: u8,
+        cmd: AtaCommand,
+    ) -> storage::Result<()> {
         let bytes = block.to_le_bytes();
         unsafe {
-            // just 1 sector for current implementation
-            self.sector_count.write(1);
-
-            // store the LBA28 address into four 8-bit registers
-            // enable LBA28 mode by setting the drive register
-            self.lba_low.write(bytes[0]);
-            self.lba_mid.write(bytes[1]);
-            self.lba_high.write(bytes[2]);
-            self.drive.write((bytes[3] & 0x0F) | (drive | 0xE0));
+            if cmd.is_ext() {
+                // the high bytes first, the count is below 256
+                self.sector_count.write(0);
+                self.lba_low.write(bytes[3]);
+                self.lba_mid.write(bytes[4]);
+                self.lba_high.write(bytes[5]);
+
+                // then the low bytes, and LBA mode in the drive register
+                self.sector_count.write(count);
+                self.lba_low.write(bytes[0]);
+                self.lba_mid.write(bytes[1]);
+                self.lba_high.write(bytes[2]);
+                self.drive.write(0x40 | drive << 4);
+            } else {
+                // 0 stands for 256 sectors
+                self.sector_count.write(count);
+
+                // store the LBA28 address into four 8-bit registers
+                // enable LBA28 mode by setting the drive register
+                self.lba_low.write(bytes[0]);
+                self.lba_mid.write(bytes[1]);
+                self.lba_high.write(bytes[2]);
+                self.drive.write((bytes[3] & 0x0F) | 0xE0 | drive << 4);
+            }
             // write the command register (cmd as u8)
             self.command.write(cmd as u8);
         }
@@ -152,8 +205,13 @@ impl AtaBus {
             return Err(storage::DeviceError::UnknownDevice.into());
         }
 
-        // poll for the status to be not BUSY
-        self.poll(AtaStatus::BUSY, false);
+        Ok(())
+    }
+
+    /// Waits until the drive is ready to transfer the next sector of a PIO command.
+    fn wait_data(&mut self, cmd: AtaCommand) -> storage::Result<()> {
+        // wait until the status is not BUSY
+        self.wait_ready();
 
         if self.is_error() {
             warn!("ATA error: {:?} command error", cmd);
@@ -161,8 +219,7 @@ impl AtaBus {
             return Err(storage::DeviceError::InvalidOperation.into());
         }
 
-        // poll for the status to be not BUSY and DATA_REQUEST_READY
-        self.poll(AtaStatus::BUSY, false);
+        // poll for the status to be DATA_REQUEST_READY
         self.poll(AtaStatus::DATA_REQUEST_READY, true);
 
         Ok(())
@@ -174,10 +231,16 @@ impl AtaBus {
     pub(super) fn identify_drive(&mut self, drive: u8) -> storage::Result<AtaDeviceType> {
         info!("Identifying drive {}", drive);
 
+        // a bus without any drive floats, its status reads 0xFF
+        unsafe { self.drive.write(0xA0 | drive << 4) };
+        if self.status().bits() == 0xFF {
+            return Ok(AtaDeviceType::None);
+        }
+
         // use `AtaCommand::IdentifyDevice` to identify the drive
         // call `write_command` with `drive` and `0` as the block number
-        self.write_command(drive, 0, AtaCommand::IdentifyDevice)
-            .expect("");
+        self.write_command(drive, 0, 1, AtaCommand::IdentifyDevice)?;
+        self.wait_data(AtaCommand::IdentifyDevice)?;
 
         if self.status().is_empty() {
             return Ok(AtaDeviceType::None);
@@ -193,7 +256,8 @@ impl AtaBus {
         Ok(match (self.cylinder_low(), self.cylinder_high()) {
             // we only support PATA drives
             (0x00, 0x00) => AtaDeviceType::Pata(Box::new([0u16; 256].map(|_| self.read_data()))),
-            // ignore the data as we don't support following types
+            // ignore the data as we don't support following types,
+            // SATA drives are driven through AHCI instead, see `drivers::ahci`
             (0x14, 0xEB) => AtaDeviceType::PataPi,
             (0x3C, 0xC3) => AtaDeviceType::Sata,
             (0x69, 0x96) => AtaDeviceType::SataPi,
@@ -201,22 +265,28 @@ impl AtaBus {
         })
     }
 
-    /// Reads a block from the given drive and block number into the given buffer.
+    /// Reads the blocks from the given drive and block number,
+    /// in one command of up to 256 sectors.
     ///
     /// reference: https://wiki.osdev.org/ATA_PIO_Mode#28_bit_PIO
     /// reference: https://wiki.osdev.org/IDE#Read.2FWrite_From_ATA_Drive
     pub(super) fn read_pio(
         &mut self,
         drive: u8,
-        block: u32,
-        buf: &mut [u8],
+        block: u64,
+        blocks: &mut [Block512],
+        lba48: bool,
     ) -> storage::Result<()> {
-        self.write_command(drive, block, AtaCommand::ReadPio)?;
-
-        // read the data from the data port into the buffer
-        for chunk in buf.chunks_mut(2) {
-            let data = self.read_data().to_le_bytes();
-            chunk.copy_from_slice(&data)
+        let cmd = AtaCommand::ReadPio.with_lba48(lba48);
+        self.write_command(drive, block, blocks.len() as u8, cmd)?;
+
+        // the drive interrupts before each sector
+        for sector in blocks.iter_mut() {
+            self.wait_data(cmd)?;
+            for chunk in sector.as_mut().chunks_mut(2) {
+                let data = self.read_data().to_le_bytes();
+                chunk.copy_from_slice(&data)
+            }
         }
 
         if self.is_error() {
@@ -228,18 +298,32 @@ impl AtaBus {
         }
     }
 
-    /// Writes a block to the given drive and block number from the given buffer.
+    /// Writes the blocks to the given drive and block number,
+    /// in one command of up to 256 sectors.
     ///
     /// reference: https://wiki.osdev.org/ATA_PIO_Mode#28_bit_PIO
     /// reference: https://wiki.osdev.org/IDE#Read.2FWrite_From_ATA_Drive
-    pub(super) fn write_pio(&mut self, drive: u8, block: u32, buf: &[u8]) -> storage::Result<()> {
-        self.write_command(drive, block, AtaCommand::WritePio)?;
-
-        // write the data from the buffer into the data port
-        for chunk in buf.chunks(2) {
-            self.write_data(u16::from_le_bytes(chunk.try_into().unwrap()));
+    pub(super) fn write_pio(
+        &mut self,
+        drive: u8,
+        block: u64,
+        blocks: &[Block512],
+        lba48: bool,
+    ) -> storage::Result<()> {
+        let cmd = AtaCommand::WritePio.with_lba48(lba48);
+        self.write_command(drive, block, blocks.len() as u8, cmd)?;
+
+        // the drive interrupts after each sector
+        for sector in blocks.iter() {
+            self.wait_data(cmd)?;
+            for chunk in sector.chunks(2) {
+                self.write_data(u16::from_le_bytes(chunk.try_into().unwrap()));
+            }
         }
 
+        // wait until the drive has written the last sector
+        self.wait_ready();
+
         if self.is_error() {
             debug!("ATA error: data write error");
             self.debug();
@@ -248,4 +332,88 @@ impl AtaBus {
             Ok(())
         }
     }
+
+    /// Transfers `count` sectors from the given block with DMA,
+    /// the data is in the buffer of the bus master.
+    ///
+    /// reference: https://wiki.osdev.org/ATA/ATAPI_using_DMA#The_Command_Byte
+    fn transfer_dma(
+        &mut self,
+        drive: u8,
+        block: u64,
+        count: usize,
+        cmd: AtaCommand,
+    ) -> storage::Result<()> {
+        let read = matches!(cmd, AtaCommand::ReadDma | AtaCommand::ReadDmaExt);
+        let mut bus_master = self
+            .bus_master
+            .take()
+            .ok_or(storage::DeviceError::InvalidOperation)?;
+
+        bus_master.prepare(count, read);
+        let ret = self.write_command(drive, block, count as u8, cmd);
+        let mut timed_out = false;
+        if ret.is_ok() {
+            bus_master.start();
+            // the bus master sets INTERRUPT with the interrupt of the drive
+            let deadline = read_counter() + DMA_TIMEOUT_TICKS;
+            timed_out = !INTERRUPTS[self.id as usize].wait_until_deadline(deadline, || {
+                bus_master.status().contains(BusMasterStatus::INTERRUPT)
+            });
+            bus_master.stop();
+        }
+
+        let failed = timed_out || bus_master.status().contains(BusMasterStatus::ERROR);
+        self.bus_master = Some(bus_master);
+        ret?;
+
+        if failed || self.is_error() {
+            if timed_out {
+                warn!("ATA error: {:?} transfer timed out", cmd);
+            }
+            debug!("ATA error: {:?} transfer error", cmd);
+            self.debug();
+            return Err(if read {
+                storage::DeviceError::ReadError.into()
+            } else {
+                storage::DeviceError::WriteError.into()
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Reads the blocks from the given drive and block number with DMA,
+    /// up to `MAX_DMA_SECTORS` at once.
+    pub(super) fn read_dma(
+        &mut self,
+        drive: u8,
+        block: u64,
+        blocks: &mut [Block512],
+        lba48: bool,
+    ) -> storage::Result<()> {
+        let cmd = AtaCommand::ReadDma.with_lba48(lba48);
+        self.transfer_dma(drive, block, blocks.len(), cmd)?;
+        if let Some(bus_master) = self.bus_master.as_ref() {
+            bus_master.copy_to(blocks);
+        }
+        Ok(())
+    }
+
+    /// Writes the blocks to the given drive and block number with DMA,
+    /// up to `MAX_DMA_SECTORS` at once.
+    pub(super) fn write_dma(
+        &mut self,
+        drive: u8,
+        block: u64,
+        blocks: &[Block512],
+        lba48: bool,
+    ) -> storage::Result<()> {
+        self.bus_master
+            .as_mut()
+            .ok_or(storage::DeviceError::InvalidOperation)?
+            .copy_from(blocks);
+        let cmd = AtaCommand::WriteDma.with_lba48(lba48);
+        self.transfer_dma(drive, block, blocks.len(), cmd)
+    }
 }
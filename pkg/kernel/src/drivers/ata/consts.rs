@@ -38,6 +38,33 @@ bitflags! {
     }
 }
 
+/// The size of a sector, in bytes.
+pub(super) const SECTOR_SIZE: usize = 512;
+
+bitflags! {
+    /// The command register of a bus master IDE channel.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub(super) struct BusMasterCommand: u8 {
+        /// Set to start the transfer, cleared to stop it.
+        const START = 0x01;
+        /// Set for a transfer from the drive to the memory.
+        const READ  = 0x08;
+    }
+}
+
+bitflags! {
+    /// The status register of a bus master IDE channel.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub(super) struct BusMasterStatus: u8 {
+        /// Set while the transfer is in progress.
+        const ACTIVE    = 0x01;
+        /// Set when the transfer failed, cleared by writing it.
+        const ERROR     = 0x02;
+        /// Set when the drive raised its interrupt, cleared by writing it.
+        const INTERRUPT = 0x04;
+    }
+}
+
 #[repr(u8)]
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -72,6 +99,32 @@ pub(super) enum AtaCommand {
     IdentifyDevice = 0xEC,
 }
 
+impl AtaCommand {
+    /// The 48-bit LBA variant of a read or write command, if `lba48`.
+    pub(super) fn with_lba48(self, lba48: bool) -> Self {
+        match (self, lba48) {
+            (AtaCommand::ReadPio, true) => AtaCommand::ReadPioExt,
+            (AtaCommand::ReadDma, true) => AtaCommand::ReadDmaExt,
+            (AtaCommand::WritePio, true) => AtaCommand::WritePioExt,
+            (AtaCommand::WriteDma, true) => AtaCommand::WriteDmaExt,
+            (AtaCommand::CacheFlush, true) => AtaCommand::CacheFlushExt,
+            (cmd, _) => cmd,
+        }
+    }
+
+    /// Returns true if the command takes a 48-bit LBA.
+    pub(super) fn is_ext(self) -> bool {
+        matches!(
+            self,
+            AtaCommand::ReadPioExt
+                | AtaCommand::ReadDmaExt
+                | AtaCommand::WritePioExt
+                | AtaCommand::WriteDmaExt
+                | AtaCommand::CacheFlushExt
+        )
+    }
+}
+
 /// The possible types of drive devices that can be attached to an IDE controller via ATA.
 pub(super) enum AtaDeviceType {
     /// A parallel ATA (PATA) drive, like a hard drive.
@@ -0,0 +1,192 @@
+//! Bus Master IDE
+//!
+//! reference: https://wiki.osdev.org/ATA/ATAPI_using_DMA
+
+use super::consts::*;
+use crate::drivers::pci::{Bar, PciDevice, PciDriver};
+use crate::memory::{get_frame_alloc_for_sure, physical_to_virtual, PAGE_SIZE};
+use alloc::vec::Vec;
+use storage::Block512;
+use x86_64::instructions::port::*;
+use x86_64::structures::paging::{FrameAllocator, FrameDeallocator, PhysFrame};
+
+/// The frames of the buffer of each bus, 64 KiB
+const DMA_FRAMES: usize = 16;
+
+/// The sectors in a frame of the buffer
+const SECTORS_PER_FRAME: usize = PAGE_SIZE as usize / SECTOR_SIZE;
+
+/// The most sectors transferred by a single DMA command
+pub const MAX_DMA_SECTORS: usize = DMA_FRAMES * SECTORS_PER_FRAME;
+
+/// The timer ticks a DMA transfer may take before it is abandoned,
+/// several seconds
+pub(super) const DMA_TIMEOUT_TICKS: u64 = 100_000;
+
+/// The table and the buffer are addressed with 32 bits
+const DMA_LIMIT: u64 = 1 << 32;
+
+/// The I/O base of the bus master registers, found by [`IDE_DRIVER`]
+static BUS_MASTER_BASE: spin::Once<u16> = spin::Once::new();
+
+/// The I/O base of the bus master registers, if the IDE controller has them
+pub(super) fn bus_master_base() -> Option<u16> {
+    BUS_MASTER_BASE.get().copied()
+}
+
+/// The PCI driver of the IDE controllers of QEMU, for their bus master (BAR4)
+pub struct IdeDriver;
+
+pub static IDE_DRIVER: IdeDriver = IdeDriver;
+
+impl PciDriver for IdeDriver {
+    fn name(&self) -> &'static str {
+        "ata_piix"
+    }
+
+    fn ids(&self) -> &'static [(u16, u16)] {
+        // PIIX3 (i440fx) and PIIX4 IDE
+        &[(0x8086, 0x7010), (0x8086, 0x7111)]
+    }
+
+    fn probe(&self, device: &PciDevice) -> bool {
+        let Some(Bar::Io { port, .. }) = device.bars[4] else {
+            // the registers are not in the I/O space
+            return false;
+        };
+
+        device.enable_bus_master();
+        BUS_MASTER_BASE.call_once(|| port);
+
+        info!("IDE Bus Master at {}: {:#x}", device.address, port);
+        true
+    }
+}
+
+/// The bus master registers of a channel, with the buffer of its transfers
+#[derive(Debug)]
+pub(super) struct BusMaster {
+    command: Port<u8>,
+    status: Port<u8>,
+    prdt: PortWriteOnly<u32>,
+    /// The physical region descriptor table, an entry for each frame
+    table: PhysFrame,
+    frames: Vec<PhysFrame>,
+}
+
+impl BusMaster {
+    /// The registers of the channel at `base`, `None` if no frames are left
+    /// below 4 GiB, the bus then transfers with PIO
+    pub fn new(base: u16) -> Option<Self> {
+        let (table, frames) = x86_64::instructions::interrupts::without_interrupts(|| {
+            let mut alloc = get_frame_alloc_for_sure();
+            let mut frames = Vec::with_capacity(DMA_FRAMES + 1);
+            for _ in 0..=DMA_FRAMES {
+                match alloc.allocate_frame() {
+                    Some(frame) if frame.start_address().as_u64() + PAGE_SIZE <= DMA_LIMIT => {
+                        frames.push(frame)
+                    }
+                    frame => {
+                        for frame in frame.into_iter().chain(frames) {
+                            unsafe { alloc.deallocate_frame(frame) };
+                        }
+                        warn!("No frames left below 4 GiB for the IDE bus master");
+                        return None;
+                    }
+                }
+            }
+            let table = frames.pop()?;
+            Some((table, frames))
+        })?;
+
+        Some(Self {
+            command: Port::new(base),
+            status: Port::new(base + 2),
+            prdt: PortWriteOnly::new(base + 4),
+            table,
+            frames,
+        })
+    }
+
+    #[inline]
+    pub fn status(&mut self) -> BusMasterStatus {
+        BusMasterStatus::from_bits_truncate(unsafe { self.status.read() })
+    }
+
+    /// The sector at `index` of the buffer
+    fn sector(&self, index: usize) -> *mut u8 {
+        let frame = self.frames[index / SECTORS_PER_FRAME]
+            .start_address()
+            .as_u64();
+        let offset = (index % SECTORS_PER_FRAME * SECTOR_SIZE) as u64;
+        physical_to_virtual(frame + offset) as *mut u8
+    }
+
+    /// Fill the table for `sectors` and set up the registers,
+    /// `read` for a transfer from the drive to the memory
+    pub fn prepare(&mut self, sectors: usize, read: bool) {
+        let table = physical_to_virtual(self.table.start_address().as_u64()) as *mut u64;
+        let count = sectors.div_ceil(SECTORS_PER_FRAME);
+
+        for (index, frame) in self.frames.iter().take(count).enumerate() {
+            // address, byte count, and the end of table flag on the last one
+            let bytes = if index + 1 == count {
+                (sectors - index * SECTORS_PER_FRAME) * SECTOR_SIZE
+            } else {
+                PAGE_SIZE as usize
+            };
+            let end = if index + 1 == count { 1 << 63 } else { 0 };
+            let entry = frame.start_address().as_u64() | ((bytes as u64) << 32) | end;
+            unsafe { table.add(index).write_volatile(entry) };
+        }
+
+        let direction = if read {
+            BusMasterCommand::READ
+        } else {
+            BusMasterCommand::empty()
+        };
+
+        unsafe {
+            self.command.write(direction.bits());
+            // below `DMA_LIMIT`, checked in `new`
+            self.prdt.write(self.table.start_address().as_u64() as u32);
+            // the interrupt and error bits are cleared by writing them
+            self.status
+                .write((BusMasterStatus::INTERRUPT | BusMasterStatus::ERROR).bits());
+        }
+    }
+
+    /// Start the transfer, once the command is sent to the drive
+    pub fn start(&mut self) {
+        unsafe {
+            let command = self.command.read();
+            self.command.write(command | BusMasterCommand::START.bits());
+        }
+    }
+
+    /// Stop the transfer, once the drive has interrupted
+    pub fn stop(&mut self) {
+        unsafe {
+            let command = self.command.read();
+            self.command
+                .write(command & !BusMasterCommand::START.bits());
+        }
+    }
+
+    /// Copy the blocks to the buffer, before a write
+    pub fn copy_from(&mut self, blocks: &[Block512]) {
+        for (index, block) in blocks.iter().enumerate() {
+            let sector =
+                unsafe { core::slice::from_raw_parts_mut(self.sector(index), SECTOR_SIZE) };
+            sector.copy_from_slice(block.as_ref());
+        }
+    }
+
+    /// Copy the buffer to the blocks, after a read
+    pub fn copy_to(&self, blocks: &mut [Block512]) {
+        for (index, block) in blocks.iter_mut().enumerate() {
+            let sector = unsafe { core::slice::from_raw_parts(self.sector(index), SECTOR_SIZE) };
+            block.as_mut().copy_from_slice(sector);
+        }
+    }
+}
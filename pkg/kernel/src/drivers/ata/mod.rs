@@ -2,15 +2,22 @@
 //!
 //! reference: https://wiki.osdev.org/IDE
 //! reference: https://wiki.osdev.org/ATA_PIO_Mode
+//! reference: https://wiki.osdev.org/ATA/ATAPI_using_DMA
 //! reference: https://github.com/theseus-os/Theseus/blob/HEAD/kernel/ata/src/lib.rs
 
 mod bus;
 mod consts;
+mod dma;
 
-use alloc::{boxed::Box, string::String};
+pub use dma::IDE_DRIVER;
+
+use crate::proc::WaitQueue;
+use alloc::{boxed::Box, format, string::String};
 use bus::AtaBus;
 use consts::AtaDeviceType;
+use core::sync::atomic::{AtomicUsize, Ordering};
 use spin::Mutex;
+use x86_64::instructions::port::PortReadOnly;
 
 pub const SERIAL: usize = 20;
 pub const SERIAL_SIZE: usize = 20; // 20 bytes
@@ -18,13 +25,28 @@ pub const MODEL: usize = 54;
 pub const MODEL_SIZE: usize = 40; // 40 bytes
 pub const MAX_LBA: usize = 120;
 pub const MAX_LBA_SIZE: usize = 4; // 4 bytes (unsigned int)
+pub const CAPABILITIES: usize = 49; // word, bit 8 set if DMA is supported
+pub const COMMAND_SETS: usize = 83; // word, bit 10 set if LBA48 is supported
+pub const MAX_LBA48: usize = 100; // words 100 to 103 (unsigned long)
+
+/// The blocks addressable without LBA48, 128 GiB
+const LBA28_BLOCKS: u64 = 1 << 28;
+
+/// The most sectors transferred by a single command, the size of the DMA buffer
+const MAX_SECTORS: usize = dma::MAX_DMA_SECTORS;
+
+/// The IRQ, I/O base and control base of the primary and secondary buses
+const BUS_PORTS: [(u8, u16, u16); 2] = [(14, 0x1F0, 0x3F6), (15, 0x170, 0x376)];
 
 lazy_static! {
     pub static ref BUSES: [Mutex<AtaBus>; 2] = {
-        let buses = [
-            Mutex::new(AtaBus::new(0, 14, 0x1F0, 0x3F6)),
-            Mutex::new(AtaBus::new(1, 15, 0x170, 0x376)),
-        ];
+        let bus_master = dma::bus_master_base();
+        let buses = [0, 1].map(|id| {
+            let (irq, io_base, ctrl_base) = BUS_PORTS[id as usize];
+            let mut bus = AtaBus::new(id, irq, io_base, ctrl_base);
+            bus.init(bus_master);
+            Mutex::new(bus)
+        });
 
         info!("Initialized ATA Buses.");
 
@@ -32,47 +54,171 @@ lazy_static! {
     };
 }
 
+/// The requests of each bus, served one at a time in their arrival order
+static QUEUES: [RequestQueue; 2] = [RequestQueue::new(), RequestQueue::new()];
+
+/// The processes waiting for the interrupt of each bus
+static INTERRUPTS: [WaitQueue; 2] = [WaitQueue::new(), WaitQueue::new()];
+
+/// A ticket lock, the process waiting for its turn is blocked instead of
+/// spinning on the bus with interrupts enabled
+struct RequestQueue {
+    next: AtomicUsize,
+    serving: AtomicUsize,
+    waiters: WaitQueue,
+}
+
+/// The turn of a request on its bus, the next one is served once dropped
+struct Request<'a>(&'a RequestQueue);
+
+impl RequestQueue {
+    const fn new() -> Self {
+        Self {
+            next: AtomicUsize::new(0),
+            serving: AtomicUsize::new(0),
+            waiters: WaitQueue::new(),
+        }
+    }
+
+    fn enter(&self) -> Request<'_> {
+        let ticket = self.next.fetch_add(1, Ordering::SeqCst);
+        self.waiters
+            .wait_until(|| self.serving.load(Ordering::SeqCst) == ticket);
+        Request(self)
+    }
+}
+
+impl Drop for Request<'_> {
+    fn drop(&mut self) {
+        self.0.serving.fetch_add(1, Ordering::SeqCst);
+        self.0.waiters.wake_all();
+    }
+}
+
+/// Acknowledge the interrupt of the given bus and wake up the process
+/// waiting for it
+///
+/// Reading the status clears the pending interrupt of the drive. It is read
+/// from its port directly, the bus is locked by the process being woken up.
+pub fn acknowledge_irq(bus: u8) {
+    let (_, io_base, _) = BUS_PORTS[bus as usize];
+    unsafe {
+        PortReadOnly::<u8>::new(io_base + 7).read();
+    }
+    INTERRUPTS[bus as usize].wake_all();
+}
+
 #[derive(Clone)]
 pub struct AtaDrive {
     pub bus: u8,
     pub drive: u8,
-    blocks: u32,
+    blocks: u64,
+    /// The drive supports DMA transfers
+    dma: bool,
+    /// The drive supports 48-bit addresses
+    lba48: bool,
     model: Box<str>,
     serial: Box<str>,
 }
 
+/// The drives found at boot, by bus and drive number
+static DRIVES: spin::Once<[[Option<AtaDrive>; 2]; 2]> = spin::Once::new();
+
+/// Probe the two drives of both buses and register the ones found
+pub fn init() {
+    DRIVES.call_once(|| [0, 1].map(|bus| [0, 1].map(|drive| AtaDrive::probe(bus, drive))));
+    info!("Found {} ATA drives.", drives().count());
+}
+
+/// The drives found at boot
+pub fn drives() -> impl Iterator<Item = &'static AtaDrive> {
+    DRIVES.get().into_iter().flatten().flatten().flatten()
+}
+
 impl AtaDrive {
+    /// The drive at the given bus and drive number, if it was found at boot
     pub fn open(bus: u8, drive: u8) -> Option<Self> {
+        DRIVES
+            .get()?
+            .get(bus as usize)?
+            .get(drive as usize)?
+            .clone()
+    }
+
+    /// Identify the drive at the given bus and drive number
+    fn probe(bus: u8, drive: u8) -> Option<Self> {
         trace!("Opening drive {}@{}...", bus, drive);
 
-        // we only support PATA drives
-        if let Ok(AtaDeviceType::Pata(res)) = BUSES[bus as usize].lock().identify_drive(drive) {
-            let buf = res.map(u16::to_be_bytes).concat();
-            let serial = String::from_utf8_lossy(&buf[SERIAL..SERIAL + SERIAL_SIZE])
-                .trim()
-                .into();
-            let model = String::from_utf8_lossy(&buf[MODEL..MODEL + MODEL_SIZE])
-                .trim()
-                .into();
-            let blocks =
-                u32::from_be_bytes(buf[MAX_LBA..MAX_LBA + 4].try_into().unwrap()).rotate_left(16);
-            let ata_drive = Self {
-                bus,
-                drive,
-                model,
-                serial,
-                blocks,
-            };
-            info!("Drive {} opened", ata_drive);
-            Some(ata_drive)
+        let identified = {
+            let _request = QUEUES[bus as usize].enter();
+            BUSES[bus as usize].lock().identify_drive(drive)
+        };
+
+        // only PATA drives, a SATA drive is driven by the AHCI driver
+        match identified {
+            Ok(AtaDeviceType::Pata(res)) => {
+                let ata_drive = Self::from_identify(bus, drive, &res);
+                info!("Drive {} opened", ata_drive);
+                Some(ata_drive)
+            }
+            Ok(AtaDeviceType::None) | Err(_) => {
+                trace!("No drive at {}@{}", bus, drive);
+                None
+            }
+            Ok(_) => {
+                warn!(
+                    "Drive {}@{} is not a PATA drive, use the AHCI mode for SATA",
+                    bus, drive
+                );
+                None
+            }
+        }
+    }
+
+    /// Parse the data returned by IDENTIFY DEVICE
+    fn from_identify(bus: u8, drive: u8, res: &[u16; 256]) -> Self {
+        let dma = res[CAPABILITIES] & (1 << 8) != 0;
+        let lba48 = res[COMMAND_SETS] & (1 << 10) != 0;
+        let buf = res.map(u16::to_be_bytes).concat();
+        let serial = String::from_utf8_lossy(&buf[SERIAL..SERIAL + SERIAL_SIZE])
+            .trim()
+            .into();
+        let model = String::from_utf8_lossy(&buf[MODEL..MODEL + MODEL_SIZE])
+            .trim()
+            .into();
+        let blocks = if lba48 {
+            res[MAX_LBA48..MAX_LBA48 + 4]
+                .iter()
+                .rev()
+                .fold(0, |blocks, word| blocks << 16 | *word as u64)
         } else {
-            warn!("Drive {}@{} is not a PATA drive", bus, drive);
-            None
+            u32::from_be_bytes(buf[MAX_LBA..MAX_LBA + 4].try_into().unwrap()).rotate_left(16) as u64
+        };
+
+        Self {
+            bus,
+            drive,
+            model,
+            serial,
+            blocks,
+            dma,
+            lba48,
         }
     }
 
+    /// The name of the drive, `hda` to `hdd`
+    pub fn name(&self) -> String {
+        let disk = (b'a' + self.bus * 2 + self.drive) as char;
+        format!("hd{}", disk)
+    }
+
+    /// Returns true if the blocks can only be addressed with LBA48
+    fn needs_lba48(&self, offset: usize, count: usize) -> bool {
+        self.lba48 && (offset + count) as u64 > LBA28_BLOCKS
+    }
+
     fn humanized_size(&self) -> (f32, &'static str) {
-        info!("Calculating humanized size for drive {}", self);
+        trace!("Calculating humanized size for drive {}", self.name());
         let size = self.block_size();
         let count = self.block_count().unwrap();
         let bytes = size * count;
@@ -95,14 +241,38 @@ impl BlockDevice<Block512> for AtaDrive {
     }
 
     fn read_block(&self, offset: usize, block: &mut Block512) -> storage::Result<()> {
-        BUSES[self.bus as usize]
-            .lock()
-            .read_pio(self.drive, offset as u32, block.as_mut())
+        self.read_blocks(offset, core::slice::from_mut(block))
     }
 
     fn write_block(&self, offset: usize, block: &Block512) -> storage::Result<()> {
-        BUSES[self.bus as usize]
-            .lock()
-            .write_pio(self.drive, offset as u32, block.as_ref())
+        let _request = QUEUES[self.bus as usize].enter();
+        let mut bus = BUSES[self.bus as usize].lock();
+        let blocks = core::slice::from_ref(block);
+        let lba48 = self.needs_lba48(offset, 1);
+
+        if self.dma && bus.has_dma() {
+            bus.write_dma(self.drive, offset as u64, blocks, lba48)
+        } else {
+            bus.write_pio(self.drive, offset as u64, blocks, lba48)
+        }
+    }
+
+    /// Reads the blocks with a command for each `MAX_SECTORS` of them,
+    /// with DMA if both the drive and the IDE controller support it
+    fn read_blocks(&self, offset: usize, blocks: &mut [Block512]) -> storage::Result<()> {
+        for (index, chunk) in blocks.chunks_mut(MAX_SECTORS).enumerate() {
+            let block = offset + index * MAX_SECTORS;
+            let lba48 = self.needs_lba48(block, chunk.len());
+            let _request = QUEUES[self.bus as usize].enter();
+            let mut bus = BUSES[self.bus as usize].lock();
+
+            if self.dma && bus.has_dma() {
+                bus.read_dma(self.drive, block as u64, chunk, lba48)?;
+            } else {
+                bus.read_pio(self.drive, block as u64, chunk, lba48)?;
+            }
+        }
+
+        Ok(())
     }
 }
@@ -0,0 +1,334 @@
+//! Device file system
+//!
+//! Lists the devices as files under `/dev`. The nodes are not opened
+//! as files of the file system, `DevFs::open` opens them as a `Resource`
+//! of the process.
+
+use super::disk::{disks, Disk};
+use super::filesystem;
+use super::ramdisk::*;
+use crate::resource::{DirHandle, OpenMode, Resource, StdIO};
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use storage::*;
+
+/// The mount point of the device file system
+pub const DEVFS_PATH: &str = "/dev";
+
+const BLOCK_SIZE: usize = Block512::BLOCK_SIZE;
+
+/// Bumped when a partition table may have changed
+static PARTITIONS_VERSION: AtomicUsize = AtomicUsize::new(0);
+
+/// Drop the partitions listed by the device file systems, they are read
+/// again on the next lookup
+pub fn partitions_changed() {
+    PARTITIONS_VERSION.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A device file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DevNode {
+    /// Discards writes, reads nothing
+    Null,
+    /// Discards writes, reads zeros
+    Zero,
+    /// Discards writes, reads random bytes
+    Random,
+    /// The kernel console
+    Console,
+    /// The first serial port
+    Serial,
+    /// A disk, a partition or the RAM disk, by its device name
+    Disk(String),
+}
+
+impl DevNode {
+    pub fn name(&self) -> &str {
+        match self {
+            DevNode::Null => "null",
+            DevNode::Zero => "zero",
+            DevNode::Random => "random",
+            DevNode::Console => "console",
+            DevNode::Serial => "ttyS0",
+            DevNode::Disk(name) => name,
+        }
+    }
+
+    /// Open the device as a resource
+    ///
+    /// The console reads the input when opened for reading,
+    /// and prints when opened for writing. Disks can only be read, the
+    /// file systems mounted on them are synced first.
+    pub fn open(&self, mode: OpenMode) -> Result<Resource> {
+        Ok(match (self, mode) {
+            (DevNode::Null, _) => Resource::Null,
+            (DevNode::Zero, _) => Resource::Zero,
+            (DevNode::Random, _) => Resource::Random,
+            (DevNode::Console, OpenMode::Read) => Resource::Console(StdIO::Stdin),
+            (DevNode::Console, _) => Resource::Console(StdIO::Stdout),
+            (DevNode::Serial, _) => Resource::Serial,
+            (DevNode::Disk(name), OpenMode::Read) => {
+                Resource::Block(BlockFile::new(filesystem::open_device_synced(name)?)?)
+            }
+            (DevNode::Disk(_), _) => return Err(FsError::ReadOnly),
+        })
+    }
+
+    /// The metadata of the node, `len` is its size in bytes
+    fn metadata(&self, len: usize) -> Metadata {
+        Metadata::new(
+            self.name().to_string(),
+            FileType::Device,
+            len,
+            None,
+            None,
+            None,
+        )
+    }
+}
+
+/// The device file system, mounted at `/dev`
+pub struct DevFs {
+    /// The disks found when mounting, e.g. `hda`
+    disks: Vec<String>,
+    /// The nodes and their sizes in bytes, listed at a `PARTITIONS_VERSION`
+    nodes: spin::Mutex<Option<(usize, Vec<(DevNode, usize)>)>>,
+}
+
+impl DevFs {
+    /// The disks found at boot, the partitions are listed when first needed
+    pub fn new() -> Self {
+        Self {
+            disks: disks().iter().map(Disk::name).collect(),
+            nodes: spin::Mutex::new(None),
+        }
+    }
+
+    /// Open the node at the path, or the root directory, as a resource
+    pub fn open(&self, path: &str, mode: OpenMode) -> Result<Resource> {
+        if !Self::is_root(path) {
+            return self.find(path)?.0.open(mode);
+        }
+
+        if mode != OpenMode::Read {
+            return Err(FsError::NotAFile);
+        }
+        Ok(Resource::Dir(DirHandle::new(
+            self.metadata(path)?,
+            self.read_dir(path)?.collect(),
+        )))
+    }
+
+    /// The nodes with their sizes, the partition tables are only read
+    /// again once changed
+    fn nodes(&self) -> Vec<(DevNode, usize)> {
+        let version = PARTITIONS_VERSION.load(Ordering::Relaxed);
+        if let Some((cached, nodes)) = &*self.nodes.lock() {
+            if *cached == version {
+                return nodes.clone();
+            }
+        }
+
+        // the tables are read out of the lock, a change meanwhile
+        // bumps the version again
+        let nodes = self.list_nodes();
+        *self.nodes.lock() = Some((version, nodes.clone()));
+        nodes
+    }
+
+    fn list_nodes(&self) -> Vec<(DevNode, usize)> {
+        let mut nodes = vec![
+            (DevNode::Null, 0),
+            (DevNode::Zero, 0),
+            (DevNode::Random, 0),
+            (DevNode::Console, 0),
+            (DevNode::Serial, 0),
+        ];
+
+        if let Some(ramdisk) = get_ramdisk() {
+            let blocks = ramdisk.block_count().unwrap_or_default();
+            nodes.push((DevNode::Disk(RAMDISK_DEVICE.into()), blocks * BLOCK_SIZE));
+        }
+
+        for disk in &self.disks {
+            let blocks =
+                Disk::open(disk).map_or(0, |drive| drive.block_count().unwrap_or_default());
+            nodes.push((DevNode::Disk(disk.clone()), blocks * BLOCK_SIZE));
+            // a disk without a partition table has no partitions to list
+            for (index, blocks) in filesystem::partition_sizes(disk).unwrap_or_default() {
+                let name = format!("{}{}", disk, index);
+                nodes.push((DevNode::Disk(name), blocks * BLOCK_SIZE));
+            }
+        }
+
+        nodes
+    }
+
+    fn find(&self, path: &str) -> Result<(DevNode, usize)> {
+        let name = path.trim_start_matches('/');
+        self.nodes()
+            .into_iter()
+            .find(|(node, _)| node.name() == name)
+            .ok_or(FsError::FileNotFound)
+    }
+
+    fn is_root(path: &str) -> bool {
+        path.trim_matches('/').is_empty()
+    }
+}
+
+impl Default for DevFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl core::fmt::Debug for DevFs {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("DevFs").field("disks", &self.disks).finish()
+    }
+}
+
+impl FileSystem for DevFs {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = Metadata> + Send>> {
+        if !Self::is_root(path) {
+            return Err(FsError::NotADirectory);
+        }
+
+        let entries = self
+            .nodes()
+            .iter()
+            .map(|(node, len)| node.metadata(*len))
+            .collect::<Vec<_>>();
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    /// Device nodes are opened as resources, see `DevFs::open`
+    fn open_file(&self, _path: &str) -> Result<FileHandle> {
+        Err(FsError::NotSupported)
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        if Self::is_root(path) {
+            return Ok(Metadata::new(
+                String::from("/"),
+                FileType::Directory,
+                0,
+                None,
+                None,
+                None,
+            ));
+        }
+
+        let (node, len) = self.find(path)?;
+        Ok(node.metadata(len))
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        Ok(Self::is_root(path) || self.find(path).is_ok())
+    }
+
+    fn as_any(&self) -> Option<&dyn core::any::Any> {
+        Some(self)
+    }
+}
+
+/// A block device read as a file, writing is not supported
+/// so that a mounted disk is not changed behind its file system
+pub struct BlockFile {
+    device: Box<dyn BlockDevice<Block512>>,
+    /// The current offset, in bytes
+    offset: usize,
+    /// The size of the device, in bytes
+    size: usize,
+}
+
+impl BlockFile {
+    pub fn new(device: Box<dyn BlockDevice<Block512>>) -> Result<Self> {
+        let size = device.block_count()? * BLOCK_SIZE;
+        Ok(Self {
+            device,
+            offset: 0,
+            size,
+        })
+    }
+
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let len = buf.len().min(self.size - self.offset);
+        let mut block = Block512::default();
+        let mut read = 0;
+
+        while read < len {
+            let offset = self.offset + read;
+            self.device.read_block(offset / BLOCK_SIZE, &mut block)?;
+
+            let start = offset % BLOCK_SIZE;
+            let count = (BLOCK_SIZE - start).min(len - read);
+            buf[read..read + count].copy_from_slice(&block[start..start + count]);
+            read += count;
+        }
+
+        self.offset += read;
+        Ok(read)
+    }
+
+    /// The size of the device, in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<usize> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.size.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+        }
+        .filter(|offset| *offset <= self.size)
+        .ok_or(FsError::InvalidOffset)?;
+
+        self.offset = offset;
+        Ok(offset)
+    }
+}
+
+impl core::fmt::Debug for BlockFile {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("BlockFile")
+            .field("offset", &self.offset)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+/// The state of the fallback generator, seeded from the time stamp counter
+static RANDOM_STATE: spin::Mutex<u64> = spin::Mutex::new(0);
+
+/// Fill the buffer with random bytes
+///
+/// `rdrand` is used if the CPU has it, a xorshift generator otherwise.
+/// Neither is meant for cryptography.
+pub fn fill_random(buf: &mut [u8]) {
+    let rdrand = x86_64::instructions::random::RdRand::new();
+    let mut state = RANDOM_STATE.lock();
+
+    for chunk in buf.chunks_mut(8) {
+        let value = match rdrand.and_then(|rdrand| rdrand.get_u64()) {
+            Some(value) => value,
+            None => {
+                if *state == 0 {
+                    *state = unsafe { core::arch::x86_64::_rdtsc() } | 1;
+                }
+                *state ^= *state << 13;
+                *state ^= *state >> 7;
+                *state ^= *state << 17;
+                *state
+            }
+        };
+        chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
+    }
+}
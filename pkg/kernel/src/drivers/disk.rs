@@ -0,0 +1,79 @@
+//! Disks
+//!
+//! The whole disks found at boot by the disk drivers, named by their
+//! driver: `hda` to `hdd` on ATA, `sda` onwards on AHCI and `vda`
+//! onwards on virtio.
+
+use super::ahci::{self, AhciDisk};
+use super::ata::{drives, AtaDrive};
+use super::virtio::{self, VirtioBlk};
+use alloc::string::String;
+use alloc::vec::Vec;
+use storage::{Block512, BlockDevice};
+
+/// A whole disk, on any of the disk drivers
+#[derive(Clone)]
+pub enum Disk {
+    Ata(AtaDrive),
+    Ahci(AhciDisk),
+    Virtio(VirtioBlk),
+}
+
+/// The disks found at boot, the ATA drives first
+pub fn disks() -> Vec<Disk> {
+    drives()
+        .cloned()
+        .map(Disk::Ata)
+        .chain(ahci::disks().into_iter().map(Disk::Ahci))
+        .chain(virtio::disks().into_iter().map(Disk::Virtio))
+        .collect()
+}
+
+impl Disk {
+    /// The disk with the given name, e.g. `hda`, if it was found at boot
+    pub fn open(name: &str) -> Option<Self> {
+        disks().into_iter().find(|disk| disk.name() == name)
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            Disk::Ata(drive) => drive.name(),
+            Disk::Ahci(disk) => disk.name(),
+            Disk::Virtio(disk) => disk.name(),
+        }
+    }
+}
+
+impl BlockDevice<Block512> for Disk {
+    fn block_count(&self) -> storage::Result<usize> {
+        match self {
+            Disk::Ata(drive) => drive.block_count(),
+            Disk::Ahci(disk) => disk.block_count(),
+            Disk::Virtio(disk) => disk.block_count(),
+        }
+    }
+
+    fn read_block(&self, offset: usize, block: &mut Block512) -> storage::Result<()> {
+        match self {
+            Disk::Ata(drive) => drive.read_block(offset, block),
+            Disk::Ahci(disk) => disk.read_block(offset, block),
+            Disk::Virtio(disk) => disk.read_block(offset, block),
+        }
+    }
+
+    fn write_block(&self, offset: usize, block: &Block512) -> storage::Result<()> {
+        match self {
+            Disk::Ata(drive) => drive.write_block(offset, block),
+            Disk::Ahci(disk) => disk.write_block(offset, block),
+            Disk::Virtio(disk) => disk.write_block(offset, block),
+        }
+    }
+
+    fn read_blocks(&self, offset: usize, blocks: &mut [Block512]) -> storage::Result<()> {
+        match self {
+            Disk::Ata(drive) => drive.read_blocks(offset, blocks),
+            Disk::Ahci(disk) => disk.read_blocks(offset, blocks),
+            Disk::Virtio(disk) => disk.read_blocks(offset, blocks),
+        }
+    }
+}
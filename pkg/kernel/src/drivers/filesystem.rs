@@ -1,87 +1,420 @@
-use super::ata::*;
+use super::devfs::*;
+use super::disk::Disk;
+use super::ramdisk::*;
+use crate::proc::{ProcFs, PROCFS_PATH};
 use alloc::boxed::Box;
 use alloc::format;
-use alloc::string::ToString;
-use storage::fat16::Fat16;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use storage::ext2::Ext2;
+use storage::fat16::{CheckReport, Fat16, FormatOptions};
+use storage::fat32::Fat32;
+use storage::gpt::*;
 use storage::mbr::*;
+use storage::tmpfs::TmpFs;
 use storage::*;
 
-pub static ROOTFS: spin::Once<Mount> = spin::Once::new();
+pub static ROOTFS: spin::Once<MountTable> = spin::Once::new();
 
-pub fn get_rootfs() -> &'static Mount {
+/// The number of sectors cached for each mounted device (512 KiB)
+const BLOCK_CACHE_SIZE: usize = 1024;
+
+/// The devices tried for the root file system, in order, and its type
+///
+/// A virtio disk is preferred, it is much faster than the emulated ATA disk,
+/// then a SATA disk, as on the q35 machine.
+const ROOT_DEVICES: [&str; 3] = ["vda1", "sda1", "hda1"];
+const ROOT_FS_TYPE: &str = "auto";
+
+/// The directory of the installed apps on the root file system, protected
+/// from changes
+const APP_PATH: &str = "/APP";
+
+/// The mount point and size limit of the tmpfs, kept on the kernel heap
+const TMPFS_PATH: &str = "/tmp";
+const TMPFS_SIZE: usize = 2 * 1024 * 1024;
+
+/// The MBR type of new partitions, `Linux` like fdisk does
+pub const DEFAULT_PARTITION_TYPE: u8 = 0x83;
+
+pub fn get_rootfs() -> &'static MountTable {
     ROOTFS.get().unwrap()
 }
 
 pub fn init() {
-    info!("Opening disk device...");
-
-    let drive = AtaDrive::open(0, 0).expect("Failed to open disk device");
+    info!("Mounting filesystem...");
 
-    // only get the first partition
-    let part = MbrTable::parse(drive)
-        .expect("Failed to parse MBR")
-        .partitions()
-        .expect("Failed to get partitions")
-        .remove(0);
+    ROOTFS.call_once(MountTable::new);
+    let root = ROOT_DEVICES
+        .into_iter()
+        .find(|device| open_device(device).is_ok())
+        .unwrap_or(ROOT_DEVICES[ROOT_DEVICES.len() - 1]);
+    mount(root, ROOT_FS_TYPE, "/", false).expect("Failed to mount root filesystem");
 
-    info!("Disk device opened: {:#?}", part);
+    if let Err(err) = mount("tmpfs", "tmpfs", TMPFS_PATH, false) {
+        warn!("Failed to mount tmpfs at {}: {:?}", TMPFS_PATH, err);
+    }
 
-    info!("Mounting filesystem...");
+    if let Err(err) = mount("devfs", "devfs", DEVFS_PATH, false) {
+        warn!("Failed to mount devfs at {}: {:?}", DEVFS_PATH, err);
+    }
 
-    ROOTFS.call_once(|| Mount::new(Box::new(Fat16::new(part)), "/".into()));
+    if let Err(err) = mount("proc", "procfs", PROCFS_PATH, false) {
+        warn!("Failed to mount procfs at {}: {:?}", PROCFS_PATH, err);
+    }
 
     trace!("Root filesystem: {:#?}", ROOTFS.get().unwrap());
 
     info!("Initialized Filesystem.");
 }
 
-pub fn ls(root_path: &str) {
-    let iter = match get_rootfs().read_dir(root_path) {
-        Ok(iter) => iter,
-        Err(err) => {
-            warn!("{:?}", err);
-            return;
+/// Split a device name into the disk name and the partition number
+///
+/// The disks are named by their driver, e.g. `hda` or `vda`, a suffix `N`
+/// selects partition number `N`, e.g. `hda1` is the first primary partition
+/// and `hda5` the first logical partition of the primary master.
+fn parse_device(name: &str) -> Option<(&str, Option<usize>)> {
+    let disk = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    if disk.is_empty() {
+        return None;
+    }
+
+    let partition = match &name[disk.len()..] {
+        "" => None,
+        num => Some(num.parse::<usize>().ok().filter(|n| *n > 0)?),
+    };
+
+    Some((disk, partition))
+}
+
+fn open_fs(
+    device: impl BlockDevice<Block512>,
+    fs_type: &str,
+    part_type: Option<u8>,
+) -> Result<(Box<dyn FileSystem>, &'static str)> {
+    let device = CachedDevice::new(device, BLOCK_CACHE_SIZE);
+
+    let fs_type = match fs_type {
+        "auto" => detect_fs(&device, part_type)?,
+        name => name,
+    };
+
+    Ok(match fs_type {
+        "fat16" => (Box::new(Fat16::try_new(device)?), "fat16"),
+        "fat32" => (Box::new(Fat32::try_new(device)?), "fat32"),
+        "ext2" => (Box::new(Ext2::try_new(device)?), "ext2"),
+        _ => return Err(FsError::NotSupported),
+    })
+}
+
+/// Detect the file system type, checking for Ext2 before FAT
+fn detect_fs(device: &impl BlockDevice<Block512>, part_type: Option<u8>) -> Result<&'static str> {
+    if Ext2::detect(device)? {
+        return Ok("ext2");
+    }
+
+    detect_fat(device, part_type).map(|fat| fat.name())
+}
+
+/// Detect the FAT variant from the BPB, using the partition type as a hint
+fn detect_fat(device: &impl BlockDevice<Block512>, part_type: Option<u8>) -> Result<FatType> {
+    let hint = part_type.and_then(FatType::from_partition_type);
+
+    match (FatType::detect(device)?, hint) {
+        (Some(detected), Some(hint)) if detected != hint => {
+            warn!(
+                "Partition type {:#04x} suggests {:?}, but the BPB is {:?}",
+                part_type.unwrap(),
+                hint,
+                detected
+            );
+            Ok(detected)
         }
+        (Some(detected), _) => Ok(detected),
+        (None, _) => Err(FsError::InvalidFileSystem),
+    }
+}
+
+/// Mount the file system on the device at the path
+///
+/// `fs_type` can be `auto` to detect the file system.
+/// A `tmpfs`, `devfs` or `procfs` needs no device, its device name is only listed.
+/// Nothing can be changed on a file system mounted `read_only`.
+pub fn mount(device: &str, fs_type: &str, path: &str, read_only: bool) -> Result<()> {
+    let (fs, fs_type): (Box<dyn FileSystem>, &str) = match (device, fs_type) {
+        (_, "tmpfs") => (Box::new(TmpFs::new(TMPFS_SIZE)), "tmpfs"),
+        (_, "devfs") => (Box::new(DevFs::new()), "devfs"),
+        (_, "procfs") => (Box::new(ProcFs), "procfs"),
+        (RAMDISK_DEVICE, _) => {
+            let disk = get_ramdisk().ok_or(DeviceError::UnknownDevice)?;
+            open_fs(disk.clone(), fs_type, None)?
+        }
+        _ => open_disk_fs(device, fs_type)?,
     };
 
-    // format and print the file metadata
+    let mut mount = Mount::new(fs, path.into()).with_source(device, fs_type);
+    if read_only {
+        mount = mount.read_only();
+    }
+    // the apps of the root file system can not be changed, nothing is
+    // written to the disk to enforce it
+    if path == "/" {
+        mount = mount.protect(APP_PATH);
+    }
+    get_rootfs().mount(mount)
+}
+
+/// Open the file system on a disk or one of its partitions
+fn open_disk_fs(device: &str, fs_type: &str) -> Result<(Box<dyn FileSystem>, &'static str)> {
+    let (disk, partition) = parse_device(device).ok_or(DeviceError::UnknownDevice)?;
+
+    info!("Opening disk device {}...", device);
+    let drive = Disk::open(disk).ok_or(DeviceError::UnknownDevice)?;
+
+    match partition {
+        None => open_fs(drive, fs_type, None),
+        Some(index) => {
+            let (part, part_type) = open_partition(drive, index)?;
+            info!("Disk device opened: {:#?}", part);
+            open_fs(part, fs_type, part_type)
+        }
+    }
+}
+
+/// Open the partition numbered `index` of the drive
+///
+/// GPT is used if the disk has a protective MBR, the MBR otherwise.
+/// MBR logical partitions are numbered from 5, like `hda5`.
+/// Returns the MBR partition type as a hint for the file system.
+fn open_partition(drive: Disk, index: usize) -> Result<(Partition<Disk, Block512>, Option<u8>)> {
+    let part = partitions(drive)?
+        .into_iter()
+        .find(|part| part.index() == index)
+        .ok_or(DeviceError::UnknownDevice)?;
+    let part_type = part.kind().mbr_type();
+
+    Ok((part, part_type))
+}
+
+/// The partitions of the drive, from the GPT if the disk has a
+/// protective MBR, from the MBR otherwise
+fn partitions(drive: Disk) -> Result<Vec<Partition<Disk, Block512>>> {
+    if GptTable::detect(&drive)? {
+        GptTable::parse(drive)?.partitions()
+    } else {
+        MbrTable::parse(drive)?.partitions()
+    }
+}
+
+/// The partition numbers of a whole disk with their sizes in blocks,
+/// e.g. `[(1, 2048), (2, 4096)]` for `hda`
+pub fn partition_sizes(device: &str) -> Result<Vec<(usize, usize)>> {
+    partitions(open_disk(device)?)?
+        .iter()
+        .map(|part| Ok((part.index(), part.block_count()?)))
+        .collect()
+}
+
+/// Open a whole disk, e.g. `hda`
+fn open_disk(device: &str) -> Result<Disk> {
+    match parse_device(device).ok_or(DeviceError::UnknownDevice)? {
+        (disk, None) => Ok(Disk::open(disk).ok_or(DeviceError::UnknownDevice)?),
+        (_, Some(_)) => Err(FsError::InvalidOperation),
+    }
+}
+
+/// Print the partition table of a whole disk
+pub fn print_partitions(device: &str) -> Result<()> {
+    let drive = open_disk(device)?;
+    let disk_blocks = drive.block_count()?;
+
     println!(
-        "{:<12} {:<12} {:<12} {:<20} {:<20} {:<20}",
-        "Name", "Type", "Size", "Created Time", "Last Modified", "Last Access"
+        "{:<8} {:<38} {:>10} {:>10} {:>10}",
+        "Device", "Type", "Start", "End", "Blocks"
     );
-    for meta in iter {
-        let filetype = {
-            if meta.is_dir() {
-                "Directory"
-            } else {
-                "File"
+    let print = |index: usize, kind: &dyn core::fmt::Display, begin: usize, total: usize| {
+        println!(
+            "{:<8} {:<38} {:>10} {:>10} {:>10}",
+            format!("{}{}", device, index),
+            kind,
+            begin,
+            (begin + total).saturating_sub(1),
+            total
+        );
+    };
+
+    if GptTable::detect(&drive)? {
+        println!("Disk {}: {} blocks, GPT", device, disk_blocks);
+        for part in GptTable::parse(drive)?.partitions()? {
+            if let PartitionKind::Gpt(guid) = part.kind() {
+                print(part.index(), &guid, part.offset(), part.block_count()?);
             }
-        };
-        let mut name = meta.name;
-        if filetype == "Directory" {
-            name.push('/');
         }
-        let (num, unit) = crate::humanized_size(meta.len as u64);
-        let size = format!("{:.2} {}", num, unit);
-        let created_time = meta
-            .created
-            .unwrap()
-            .format("%Y-%m-%d %H:%M:%S")
-            .to_string();
-        let last_modified = meta
-            .modified
-            .unwrap()
-            .format("%Y-%m-%d %H:%M:%S")
-            .to_string();
-        let last_access = meta
-            .accessed
-            .unwrap()
-            .format("%Y-%m-%d %H:%M:%S")
-            .to_string();
+        return Ok(());
+    }
 
-        println!(
-            "{:<12} {:<12} {:<12} {:<20} {:<20} {:<20}",
-            name, filetype, size, created_time, last_modified, last_access
-        );
+    println!("Disk {}: {} blocks, MBR", device, disk_blocks);
+    let table = MbrTable::parse(drive)?;
+
+    // the extended partition is listed too, to show the used space
+    for (slot, entry) in table.entries().iter().enumerate() {
+        if entry.is_used() {
+            let kind = format!(
+                "{:#04x}{}",
+                entry.partition_type(),
+                if entry.is_active() { " *" } else { "" }
+            );
+            print(
+                slot + 1,
+                &kind,
+                entry.begin_lba() as usize,
+                entry.total_lba() as usize,
+            );
+        }
+    }
+    for part in table.partitions()?.iter().filter(|part| part.index() > 4) {
+        if let PartitionKind::Mbr(kind) = part.kind() {
+            print(
+                part.index(),
+                &format!("{:#04x}", kind),
+                part.offset(),
+                part.block_count()?,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Edit the MBR of a whole disk and write it back
+///
+/// The disk and the partition in `slot` must not be mounted, nor the
+/// logical partitions if it is the extended one, the mounted file
+/// system would not see the change.
+pub fn edit_partitions(
+    device: &str,
+    slot: usize,
+    edit: impl FnOnce(&mut MbrTable<Disk, Block512>) -> Result<()>,
+) -> Result<()> {
+    let drive = open_disk(device)?;
+    if GptTable::detect(&drive)? {
+        return Err(FsError::NotSupported);
+    }
+
+    let mut table = MbrTable::parse(drive)?;
+
+    // the logical partitions are numbered from 5
+    let extended = table.entries().get(slot).is_some_and(|e| e.is_extended());
+    let logical = if extended {
+        table.logical_entries().count()
+    } else {
+        0
+    };
+    let busy = core::iter::once(slot + 1)
+        .chain(5..5 + logical)
+        .any(|index| in_use(&format!("{}{}", device, index)));
+    if busy {
+        return Err(DeviceError::Busy.into());
+    }
+
+    edit(&mut table)?;
+    let ret = table.write();
+    partitions_changed();
+    ret
+}
+
+/// Open a device by name, the RAM disk, a whole disk or a partition
+pub fn open_device(device: &str) -> Result<Box<dyn BlockDevice<Block512>>> {
+    if device == RAMDISK_DEVICE {
+        let disk = get_ramdisk().ok_or(DeviceError::UnknownDevice)?;
+        return Ok(Box::new(disk.clone()));
+    }
+
+    let (disk, partition) = parse_device(device).ok_or(DeviceError::UnknownDevice)?;
+    let drive = Disk::open(disk).ok_or(DeviceError::UnknownDevice)?;
+
+    Ok(match partition {
+        None => Box::new(drive),
+        Some(index) => Box::new(open_partition(drive, index)?.0),
+    })
+}
+
+/// The file systems mounted on the device, the disk it is on, or one of
+/// its partitions
+fn mounts_on(device: &str) -> Vec<Arc<Mount>> {
+    let target = parse_device(device);
+    get_rootfs()
+        .mounts()
+        .into_iter()
+        .filter(|mount| match (parse_device(&mount.device), target) {
+            (Some((disk, part)), Some((t_disk, t_part))) => {
+                disk == t_disk && (part.is_none() || t_part.is_none() || part == t_part)
+            }
+            _ => *mount.device == *device,
+        })
+        .collect()
+}
+
+/// Returns true if the device, the disk it is on, or one of its
+/// partitions is mounted
+fn in_use(device: &str) -> bool {
+    !mounts_on(device).is_empty()
+}
+
+/// Open the device to read it as a file
+///
+/// The cached changes of the file systems mounted on it are written back
+/// first, so that it is read as they left it when opened.
+pub fn open_device_synced(device: &str) -> Result<Box<dyn BlockDevice<Block512>>> {
+    for mount in mounts_on(device) {
+        mount.sync()?;
+    }
+    open_device(device)
+}
+
+/// Format the device as an empty Fat16 volume
+pub fn format_fat16(device: &str, label: &str, volume_id: u32) -> Result<()> {
+    if in_use(device) {
+        return Err(DeviceError::Busy.into());
+    }
+
+    let options = FormatOptions {
+        label: label.into(),
+        volume_id,
+        ..Default::default()
+    };
+
+    info!("Formatting {} as Fat16...", device);
+    let ret = Fat16::format(&open_device(device)?, &options);
+    // formatting a whole disk overwrites its partition table
+    partitions_changed();
+    ret
+}
+
+/// Check the Fat16 volume on the device, the problems found are in the report
+///
+/// The device must not be mounted, so that nothing changes while
+/// it is checked or repaired.
+pub fn check_fat16(device: &str, repair: bool) -> Result<CheckReport> {
+    if in_use(device) {
+        return Err(DeviceError::Busy.into());
+    }
+
+    Fat16::try_new(open_device(device)?)?.check(repair)
+}
+
+/// Unmount the file system at the path, writing its cached changes back
+pub fn umount(path: &str) -> Result<()> {
+    get_rootfs().umount(path)?.sync()
+}
+
+/// Write the cached changes of every mounted file system back to its device
+pub fn sync() -> Result<()> {
+    get_rootfs().sync()
+}
+
+pub fn list_mounts() {
+    for mount in get_rootfs().mounts() {
+        println!("{}", mount);
     }
 }
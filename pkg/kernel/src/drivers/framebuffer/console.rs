@@ -0,0 +1,427 @@
+//! Framebuffer Console
+//!
+//! A text terminal drawn with the bitmap font, each glyph doubled in
+//! height to a cell of 8x16 pixels. The output is scrolled up once the
+//! cursor moves past the last row. A line feed also moves the cursor to
+//! the first column, as the programs print a bare `\n`.
+//!
+//! It understands the subset of the ANSI escape sequences sent by the
+//! kernel and the shell: cursor movement and positioning, erasing the
+//! screen or the line, the colors and attributes of SGR, saving the cursor
+//! and hiding it. The other sequences are ignored.
+//!
+//! reference: https://en.wikipedia.org/wiki/ANSI_escape_code
+
+use super::font::{glyph, GLYPH_HEIGHT, GLYPH_WIDTH};
+use super::{Color, FrameBuffer};
+use core::fmt;
+
+const CELL_WIDTH: usize = GLYPH_WIDTH;
+const CELL_HEIGHT: usize = GLYPH_HEIGHT * 2;
+
+/// The scanlines of the cell covered by the cursor, at its bottom
+const CURSOR_HEIGHT: usize = 2;
+
+const TAB_WIDTH: usize = 8;
+
+/// The most parameters kept for a control sequence
+const MAX_PARAMS: usize = 16;
+
+/// The 16 colors of the SGR, the normal ones then the bright ones
+const PALETTE: [Color; 16] = [
+    Color::new(0, 0, 0),
+    Color::new(170, 0, 0),
+    Color::new(0, 170, 0),
+    Color::new(170, 85, 0),
+    Color::new(0, 0, 170),
+    Color::new(170, 0, 170),
+    Color::new(0, 170, 170),
+    Color::new(170, 170, 170),
+    Color::new(85, 85, 85),
+    Color::new(255, 85, 85),
+    Color::new(85, 255, 85),
+    Color::new(255, 255, 85),
+    Color::new(85, 85, 255),
+    Color::new(255, 85, 255),
+    Color::new(85, 255, 255),
+    Color::new(255, 255, 255),
+];
+
+const DEFAULT_FOREGROUND: u8 = 7;
+const DEFAULT_BACKGROUND: u8 = 0;
+
+/// The color of the 256-color palette, the 16 colors, a 6x6x6 color cube
+/// and 24 shades of grey
+fn indexed_color(index: u8) -> Color {
+    const LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+    match index {
+        0..=15 => PALETTE[index as usize],
+        16..=231 => {
+            let index = (index - 16) as usize;
+            Color::new(LEVELS[index / 36], LEVELS[index / 6 % 6], LEVELS[index % 6])
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            Color::new(level, level, level)
+        }
+    }
+}
+
+/// A color set by the SGR
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Paint {
+    Indexed(u8),
+    Rgb(Color),
+}
+
+/// The attributes of the characters printed
+#[derive(Debug, Clone, Copy)]
+struct Style {
+    foreground: Paint,
+    background: Paint,
+    /// The normal foreground colors are drawn bright
+    bold: bool,
+    /// The foreground and background are swapped
+    reverse: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            foreground: Paint::Indexed(DEFAULT_FOREGROUND),
+            background: Paint::Indexed(DEFAULT_BACKGROUND),
+            bold: false,
+            reverse: false,
+        }
+    }
+}
+
+impl Style {
+    /// The foreground and background colors of the characters
+    fn colors(&self) -> (Color, Color) {
+        let foreground = match self.foreground {
+            Paint::Indexed(index) if self.bold && index < 8 => indexed_color(index + 8),
+            Paint::Indexed(index) => indexed_color(index),
+            Paint::Rgb(color) => color,
+        };
+        let background = match self.background {
+            Paint::Indexed(index) => indexed_color(index),
+            Paint::Rgb(color) => color,
+        };
+
+        if self.reverse {
+            (background, foreground)
+        } else {
+            (foreground, background)
+        }
+    }
+
+    /// The color of the cells erased, the background without the reverse
+    fn erase_color(&self) -> Color {
+        Style {
+            reverse: false,
+            ..*self
+        }
+        .colors()
+        .1
+    }
+}
+
+/// Where the console is in an escape sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    /// After the escape
+    Escape,
+    /// In a control sequence, after `ESC [`
+    Csi,
+}
+
+pub struct Console {
+    fb: FrameBuffer,
+    columns: usize,
+    rows: usize,
+    /// The cursor, the column is `columns` when the last one was printed
+    /// and the next character wraps to the next line
+    column: usize,
+    row: usize,
+    saved: (usize, usize),
+    style: Style,
+    cursor_visible: bool,
+    /// The cursor is drawn on the screen
+    cursor_drawn: bool,
+    state: State,
+    params: [u16; MAX_PARAMS],
+    /// The index of the parameter being read
+    param: usize,
+    /// The control sequence starts with `?`
+    private: bool,
+}
+
+impl Console {
+    pub fn new(fb: FrameBuffer) -> Self {
+        let mut console = Self {
+            columns: (fb.width() / CELL_WIDTH).max(1),
+            rows: (fb.height() / CELL_HEIGHT).max(1),
+            fb,
+            column: 0,
+            row: 0,
+            saved: (0, 0),
+            style: Style::default(),
+            cursor_visible: true,
+            cursor_drawn: false,
+            state: State::Ground,
+            params: [0; MAX_PARAMS],
+            param: 0,
+            private: false,
+        };
+        console.clear();
+        console.toggle_cursor();
+        console
+    }
+
+    /// The columns and rows of the console
+    pub fn size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// Reset the attributes and clear the screen
+    pub fn clear(&mut self) {
+        self.style = Style::default();
+        self.column = 0;
+        self.row = 0;
+        let (width, height) = (self.fb.width(), self.fb.height());
+        self.fb.fill(0, 0, width, height, self.style.erase_color());
+    }
+
+    /// Draw or remove the cursor, an underline in the cell
+    fn toggle_cursor(&mut self) {
+        if !self.cursor_drawn && !self.cursor_visible {
+            return;
+        }
+        let column = self.column.min(self.columns - 1);
+        self.fb.invert(
+            column * CELL_WIDTH,
+            (self.row + 1) * CELL_HEIGHT - CURSOR_HEIGHT,
+            CELL_WIDTH,
+            CURSOR_HEIGHT,
+        );
+        self.cursor_drawn = !self.cursor_drawn;
+    }
+
+    /// Draw the character in the cell at `(column, row)`
+    fn draw(&mut self, column: usize, row: usize, ch: char) {
+        let (foreground, background) = self.style.colors();
+        let (foreground, background) = (self.fb.pixel(foreground), self.fb.pixel(background));
+        let (left, top) = (column * CELL_WIDTH, row * CELL_HEIGHT);
+
+        for (y, line) in glyph(ch).iter().enumerate() {
+            for x in 0..GLYPH_WIDTH {
+                let pixel = if line & (1 << x) != 0 {
+                    foreground
+                } else {
+                    background
+                };
+                // each line of the glyph is drawn twice
+                self.fb.write(left + x, top + y * 2, pixel);
+                self.fb.write(left + x, top + y * 2 + 1, pixel);
+            }
+        }
+    }
+
+    /// Erase the cells from `start` to `end` of the row, excluded
+    fn erase(&mut self, row: usize, start: usize, end: usize) {
+        let color = self.style.erase_color();
+        self.fb.fill(
+            start * CELL_WIDTH,
+            row * CELL_HEIGHT,
+            (end - start) * CELL_WIDTH,
+            CELL_HEIGHT,
+            color,
+        );
+    }
+
+    /// Erase the rows from `start` to `end`, excluded
+    fn erase_rows(&mut self, start: usize, end: usize) {
+        for row in start..end {
+            self.erase(row, 0, self.columns);
+        }
+    }
+
+    /// Move the cursor down, scrolling up at the last row
+    fn line_feed(&mut self) {
+        if self.row + 1 < self.rows {
+            self.row += 1;
+        } else {
+            let color = self.style.erase_color();
+            self.fb
+                .scroll_up(self.rows * CELL_HEIGHT, CELL_HEIGHT, color);
+        }
+    }
+
+    fn print(&mut self, ch: char) {
+        if self.column >= self.columns {
+            self.column = 0;
+            self.line_feed();
+        }
+        self.draw(self.column, self.row, ch);
+        self.column += 1;
+    }
+
+    fn put(&mut self, ch: char) {
+        match self.state {
+            State::Ground => match ch {
+                '\x1b' => self.state = State::Escape,
+                '\n' => {
+                    self.column = 0;
+                    self.line_feed();
+                }
+                '\x0b' | '\x0c' => self.line_feed(),
+                '\r' => self.column = 0,
+                '\x08' => self.column = self.column.min(self.columns - 1).saturating_sub(1),
+                '\t' => {
+                    self.column = ((self.column / TAB_WIDTH + 1) * TAB_WIDTH).min(self.columns - 1)
+                }
+                ch if ch.is_control() => {}
+                ch => self.print(ch),
+            },
+            State::Escape => {
+                self.state = State::Ground;
+                match ch {
+                    '[' => {
+                        self.params = [0; MAX_PARAMS];
+                        self.param = 0;
+                        self.private = false;
+                        self.state = State::Csi;
+                    }
+                    '7' => self.saved = (self.column, self.row),
+                    '8' => (self.column, self.row) = self.saved,
+                    'c' => self.clear(),
+                    _ => {}
+                }
+            }
+            State::Csi => match ch {
+                '0'..='9' => {
+                    let param = &mut self.params[self.param];
+                    *param = param
+                        .saturating_mul(10)
+                        .saturating_add(ch as u16 - '0' as u16);
+                }
+                ';' => self.param = (self.param + 1).min(MAX_PARAMS - 1),
+                '?' => self.private = true,
+                '\x40'..='\x7e' => {
+                    self.state = State::Ground;
+                    self.control(ch);
+                }
+                // the intermediate bytes are ignored
+                _ => {}
+            },
+        }
+    }
+
+    /// The parameter at `index`, 1 if it is missing or 0
+    fn count(&self, index: usize) -> usize {
+        self.params[index].max(1) as usize
+    }
+
+    /// Run the control sequence ended by `command`
+    fn control(&mut self, command: char) {
+        if self.private {
+            if self.params[0] == 25 && matches!(command, 'h' | 'l') {
+                self.cursor_visible = command == 'h';
+            }
+            return;
+        }
+
+        let (last_column, last_row) = (self.columns - 1, self.rows - 1);
+        let count = self.count(0);
+        match command {
+            'A' => self.row = self.row.saturating_sub(count),
+            'B' => self.row = (self.row + count).min(last_row),
+            'C' => self.column = (self.column + count).min(last_column),
+            'D' => self.column = self.column.min(last_column).saturating_sub(count),
+            'E' => (self.column, self.row) = (0, (self.row + count).min(last_row)),
+            'F' => (self.column, self.row) = (0, self.row.saturating_sub(count)),
+            'G' => self.column = (count - 1).min(last_column),
+            'd' => self.row = (count - 1).min(last_row),
+            'H' | 'f' => {
+                self.row = (count - 1).min(last_row);
+                self.column = (self.count(1) - 1).min(last_column);
+            }
+            'J' => match self.params[0] {
+                0 => {
+                    self.erase(self.row, self.column.min(self.columns), self.columns);
+                    self.erase_rows(self.row + 1, self.rows);
+                }
+                1 => {
+                    self.erase_rows(0, self.row);
+                    self.erase(self.row, 0, self.column.min(last_column) + 1);
+                }
+                _ => self.erase_rows(0, self.rows),
+            },
+            'K' => match self.params[0] {
+                0 => self.erase(self.row, self.column.min(self.columns), self.columns),
+                1 => self.erase(self.row, 0, self.column.min(last_column) + 1),
+                _ => self.erase(self.row, 0, self.columns),
+            },
+            'm' => self.select_graphics(),
+            's' => self.saved = (self.column, self.row),
+            'u' => (self.column, self.row) = self.saved,
+            _ => {}
+        }
+    }
+
+    /// Set the attributes of the characters, `ESC [ ... m`
+    fn select_graphics(&mut self) {
+        let params = self.params;
+        let mut params = params[..=self.param]
+            .iter()
+            .map(|param| (*param).min(u8::MAX as u16) as u8);
+
+        while let Some(param) = params.next() {
+            match param {
+                0 => self.style = Style::default(),
+                1 => self.style.bold = true,
+                22 => self.style.bold = false,
+                7 => self.style.reverse = true,
+                27 => self.style.reverse = false,
+                30..=37 => self.style.foreground = Paint::Indexed(param - 30),
+                39 => self.style.foreground = Paint::Indexed(DEFAULT_FOREGROUND),
+                40..=47 => self.style.background = Paint::Indexed(param - 40),
+                49 => self.style.background = Paint::Indexed(DEFAULT_BACKGROUND),
+                90..=97 => self.style.foreground = Paint::Indexed(param - 90 + 8),
+                100..=107 => self.style.background = Paint::Indexed(param - 100 + 8),
+                38 | 48 => {
+                    // `5;n` for the 256 colors, `2;r;g;b` for any color
+                    let paint = match params.next() {
+                        Some(5) => params.next().map(Paint::Indexed),
+                        Some(2) => match (params.next(), params.next(), params.next()) {
+                            (Some(r), Some(g), Some(b)) => Some(Paint::Rgb(Color::new(r, g, b))),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    match (param, paint) {
+                        (38, Some(paint)) => self.style.foreground = paint,
+                        (_, Some(paint)) => self.style.background = paint,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.cursor_drawn {
+            self.toggle_cursor();
+        }
+        for ch in s.chars() {
+            self.put(ch);
+        }
+        self.toggle_cursor();
+        Ok(())
+    }
+}
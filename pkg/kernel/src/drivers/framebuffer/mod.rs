@@ -0,0 +1,130 @@
+//! Framebuffer
+//!
+//! The linear framebuffer of the graphics mode set by the firmware, found
+//! by the bootloader through the GOP. Each pixel is 32 bits, the colors in
+//! the order of the pixel format. A text console is drawn on it, mirroring
+//! the output of `print!`.
+//!
+//! reference: https://wiki.osdev.org/GOP
+
+mod console;
+mod font;
+
+use crate::memory::physical_to_virtual;
+use boot::{BootInfo, PixelFormat};
+pub use console::Console;
+
+once_mutex!(pub CONSOLE: Console);
+
+guard_access_fn!(pub get_console(CONSOLE: Console));
+
+/// Set up the text console on the framebuffer, if the bootloader found one
+pub fn init(boot_info: &'static BootInfo) {
+    let Some(info) = boot_info.framebuffer else {
+        info!("No framebuffer, the console is only on the serial port.");
+        return;
+    };
+
+    let console = Console::new(FrameBuffer::new(&info));
+    let (columns, rows) = console.size();
+    init_CONSOLE(console);
+
+    info!(
+        "Framebuffer Console Initialized: {}x{} pixels, {}x{} characters.",
+        info.width, info.height, columns, rows
+    );
+}
+
+/// A color, 8 bits for each channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Color {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+}
+
+impl Color {
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+}
+
+/// The framebuffer, through the physical memory mapping
+pub struct FrameBuffer {
+    base: u64,
+    width: usize,
+    height: usize,
+    /// The pixels in a scanline
+    stride: usize,
+    format: PixelFormat,
+}
+
+impl FrameBuffer {
+    fn new(info: &boot::FrameBuffer) -> Self {
+        Self {
+            base: physical_to_virtual(info.base),
+            width: info.width,
+            height: info.height,
+            stride: info.stride,
+            format: info.format,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The value of a pixel of the color
+    pub fn pixel(&self, color: Color) -> u32 {
+        let (r, g, b) = (color.r as u32, color.g as u32, color.b as u32);
+        match self.format {
+            PixelFormat::Rgb => r | g << 8 | b << 16,
+            _ => b | g << 8 | r << 16,
+        }
+    }
+
+    fn at(&self, x: usize, y: usize) -> *mut u32 {
+        (self.base as *mut u32).wrapping_add(y * self.stride + x)
+    }
+
+    /// Set the pixel at `(x, y)` to a value given by `pixel`
+    pub fn write(&mut self, x: usize, y: usize, pixel: u32) {
+        if x < self.width && y < self.height {
+            unsafe { self.at(x, y).write_volatile(pixel) }
+        }
+    }
+
+    /// Fill the rectangle at `(x, y)` with the color, clipped to the screen
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Color) {
+        let pixel = self.pixel(color);
+        for y in y..(y + height).min(self.height) {
+            for x in x..(x + width).min(self.width) {
+                unsafe { self.at(x, y).write_volatile(pixel) }
+            }
+        }
+    }
+
+    /// Invert the colors of the rectangle at `(x, y)`, twice restores it
+    pub fn invert(&mut self, x: usize, y: usize, width: usize, height: usize) {
+        for y in y..(y + height).min(self.height) {
+            for x in x..(x + width).min(self.width) {
+                let pixel = self.at(x, y);
+                unsafe { pixel.write_volatile(pixel.read_volatile() ^ 0x00FF_FFFF) }
+            }
+        }
+    }
+
+    /// Move the top `height` scanlines up by `lines`, the ones left at
+    /// the bottom are filled with the color
+    pub fn scroll_up(&mut self, height: usize, lines: usize, color: Color) {
+        let height = height.min(self.height);
+        let lines = lines.min(height);
+        let kept = height - lines;
+        unsafe { core::ptr::copy(self.at(0, lines), self.at(0, 0), kept * self.stride) }
+        self.fill(0, kept, self.width, lines, color);
+    }
+}
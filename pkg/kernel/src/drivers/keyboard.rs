@@ -0,0 +1,286 @@
+//! PS/2 Keyboard
+//!
+//! The i8042 controller translates the scancodes of the keyboard to the
+//! set 1, one byte for each press and release, the release with the high
+//! bit set. The keys are passed to the terminal as the bytes a serial
+//! terminal would send, e.g. `\r` for enter and ANSI escape sequences for
+//! the arrow and function keys.
+//!
+//! reference: https://wiki.osdev.org/%228042%22_PS/2_Controller
+//! reference: https://wiki.osdev.org/PS/2_Keyboard#Scan_Code_Set_1
+
+use super::tty;
+use x86_64::instructions::port::*;
+
+const DATA_PORT: u16 = 0x60;
+/// The status register when read, the command register when written
+const STATUS_PORT: u16 = 0x64;
+
+// controller commands
+const READ_CONFIG: u8 = 0x20;
+const WRITE_CONFIG: u8 = 0x60;
+const DISABLE_FIRST_PORT: u8 = 0xAD;
+const ENABLE_FIRST_PORT: u8 = 0xAE;
+const DISABLE_SECOND_PORT: u8 = 0xA7;
+
+bitflags! {
+    /// The status register of the controller.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    struct Status: u8 {
+        /// A byte can be read from the data port.
+        const OUTPUT_FULL = 0x01;
+        /// The controller has not read the last byte written yet.
+        const INPUT_FULL  = 0x02;
+    }
+}
+
+bitflags! {
+    /// The configuration byte of the controller.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    struct Config: u8 {
+        const FIRST_INTERRUPT  = 0x01;
+        const SECOND_INTERRUPT = 0x02;
+        const FIRST_CLOCK_OFF  = 0x10;
+        const TRANSLATION      = 0x40;
+    }
+}
+
+bitflags! {
+    /// The modifier keys held down, and the caps lock state.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    struct Modifiers: u8 {
+        const LEFT_SHIFT  = 0x01;
+        const RIGHT_SHIFT = 0x02;
+        const LEFT_CTRL   = 0x04;
+        const RIGHT_CTRL  = 0x08;
+        const LEFT_ALT    = 0x10;
+        const RIGHT_ALT   = 0x20;
+        const CAPS_LOCK   = 0x40;
+    }
+}
+
+impl Modifiers {
+    fn shift(&self) -> bool {
+        self.intersects(Self::LEFT_SHIFT | Self::RIGHT_SHIFT)
+    }
+
+    fn ctrl(&self) -> bool {
+        self.intersects(Self::LEFT_CTRL | Self::RIGHT_CTRL)
+    }
+
+    fn alt(&self) -> bool {
+        self.intersects(Self::LEFT_ALT | Self::RIGHT_ALT)
+    }
+}
+
+/// The prefix of the extended scancodes
+const EXTENDED: u8 = 0xE0;
+/// The bit set in the scancode of a release
+const RELEASE: u8 = 0x80;
+
+/// The characters of the scancodes `0x00` to `0x39`, on a US layout
+const NORMAL: &[u8; 0x3A] =
+    b"\0\x1b1234567890-=\x7f\tqwertyuiop[]\r\0asdfghjkl;'`\0\\zxcvbnm,./\0*\0 ";
+const SHIFTED: &[u8; 0x3A] =
+    b"\0\x1b!@#$%^&*()_+\x7f\tQWERTYUIOP{}\r\0ASDFGHJKL:\"~\0|ZXCVBNM<>?\0*\0 ";
+
+/// The characters of the keypad, `0x47` to `0x53`, with num lock on
+const KEYPAD: &[u8; 13] = b"789-456+1230.";
+
+/// The escape sequences of the function keys `F1` to `F10`, then `F11` and `F12`
+const FUNCTION_KEYS: [&[u8]; 12] = [
+    b"\x1bOP",
+    b"\x1bOQ",
+    b"\x1bOR",
+    b"\x1bOS",
+    b"\x1b[15~",
+    b"\x1b[17~",
+    b"\x1b[18~",
+    b"\x1b[19~",
+    b"\x1b[20~",
+    b"\x1b[21~",
+    b"\x1b[23~",
+    b"\x1b[24~",
+];
+
+once_mutex!(KEYBOARD: Keyboard);
+
+guard_access_fn!(get_keyboard(KEYBOARD: Keyboard));
+
+/// Set up the controller for the keyboard interrupts, with the
+/// scancodes translated to the set 1
+pub fn init() {
+    let mut keyboard = Keyboard::new();
+    keyboard.init();
+    init_KEYBOARD(keyboard);
+
+    info!("PS/2 Keyboard Initialized.");
+}
+
+/// Decode the scancode waiting in the controller, if any
+///
+/// Should be called on every keyboard interrupt.
+pub fn receive() {
+    if let Some(mut keyboard) = get_keyboard() {
+        if let Some(scancode) = keyboard.read() {
+            keyboard.decode(scancode, tty::receive);
+        }
+    }
+}
+
+struct Keyboard {
+    data: Port<u8>,
+    status: Port<u8>,
+    modifiers: Modifiers,
+    /// The previous byte was the extended prefix
+    extended: bool,
+}
+
+impl Keyboard {
+    fn new() -> Self {
+        Self {
+            data: Port::new(DATA_PORT),
+            status: Port::new(STATUS_PORT),
+            modifiers: Modifiers::empty(),
+            extended: false,
+        }
+    }
+
+    fn init(&mut self) {
+        self.command(DISABLE_FIRST_PORT);
+        self.command(DISABLE_SECOND_PORT);
+
+        // flush the bytes left by the firmware
+        while self.read().is_some() {}
+
+        self.command(READ_CONFIG);
+        self.wait(Status::OUTPUT_FULL, true);
+        let config = Config::from_bits_retain(unsafe { self.data.read() });
+        let config = (config | Config::FIRST_INTERRUPT | Config::TRANSLATION)
+            - Config::SECOND_INTERRUPT
+            - Config::FIRST_CLOCK_OFF;
+
+        self.command(WRITE_CONFIG);
+        self.wait(Status::INPUT_FULL, false);
+        unsafe { self.data.write(config.bits()) };
+
+        self.command(ENABLE_FIRST_PORT);
+    }
+
+    fn status(&mut self) -> Status {
+        Status::from_bits_truncate(unsafe { self.status.read() })
+    }
+
+    /// Wait for the status bit to be set or cleared, the controller
+    /// answers in a few microseconds, or not at all without a keyboard
+    fn wait(&mut self, bit: Status, set: bool) {
+        for _ in 0..100_000 {
+            if self.status().contains(bit) == set {
+                return;
+            }
+            core::hint::spin_loop();
+        }
+    }
+
+    fn command(&mut self, command: u8) {
+        self.wait(Status::INPUT_FULL, false);
+        unsafe { self.status.write(command) };
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        if self.status().contains(Status::OUTPUT_FULL) {
+            Some(unsafe { self.data.read() })
+        } else {
+            None
+        }
+    }
+
+    /// Track the modifiers and emit the bytes of the key pressed, if any
+    fn decode(&mut self, scancode: u8, mut emit: impl FnMut(u8)) {
+        if scancode == EXTENDED {
+            self.extended = true;
+            return;
+        }
+
+        let extended = core::mem::take(&mut self.extended);
+        let pressed = scancode & RELEASE == 0;
+        let code = scancode & !RELEASE;
+
+        let modifier = match (extended, code) {
+            (false, 0x2A) => Modifiers::LEFT_SHIFT,
+            (false, 0x36) => Modifiers::RIGHT_SHIFT,
+            (false, 0x1D) => Modifiers::LEFT_CTRL,
+            (true, 0x1D) => Modifiers::RIGHT_CTRL,
+            (false, 0x38) => Modifiers::LEFT_ALT,
+            (true, 0x38) => Modifiers::RIGHT_ALT,
+            (false, 0x3A) => {
+                if pressed {
+                    self.modifiers.toggle(Modifiers::CAPS_LOCK);
+                }
+                return;
+            }
+            _ => Modifiers::empty(),
+        };
+
+        if !modifier.is_empty() {
+            self.modifiers.set(modifier, pressed);
+            return;
+        }
+        if !pressed {
+            return;
+        }
+
+        let sequence: &[u8] = match (extended, code) {
+            (true, 0x48) => b"\x1b[A",
+            (true, 0x50) => b"\x1b[B",
+            (true, 0x4D) => b"\x1b[C",
+            (true, 0x4B) => b"\x1b[D",
+            (true, 0x47) => b"\x1b[H",
+            (true, 0x4F) => b"\x1b[F",
+            (true, 0x52) => b"\x1b[2~",
+            (true, 0x53) => b"\x1b[3~",
+            (true, 0x49) => b"\x1b[5~",
+            (true, 0x51) => b"\x1b[6~",
+            (true, 0x1C) => b"\r",
+            (true, 0x35) => b"/",
+            (false, 0x3B..=0x44) => FUNCTION_KEYS[(code - 0x3B) as usize],
+            (false, 0x57) => FUNCTION_KEYS[10],
+            (false, 0x58) => FUNCTION_KEYS[11],
+            (false, 0x47..=0x53) => core::slice::from_ref(&KEYPAD[(code - 0x47) as usize]),
+            (false, 0x00..=0x39) => {
+                if let Some(ch) = self.character(code) {
+                    // alt sends an escape before the key, like xterm
+                    if self.modifiers.alt() {
+                        emit(0x1B);
+                    }
+                    emit(ch);
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        sequence.iter().copied().for_each(emit);
+    }
+
+    /// The character of a key of the main block, with the modifiers applied
+    fn character(&self, code: u8) -> Option<u8> {
+        let letter = NORMAL[code as usize].is_ascii_lowercase();
+        // caps lock only shifts the letters
+        let shifted =
+            self.modifiers.shift() ^ (letter && self.modifiers.contains(Modifiers::CAPS_LOCK));
+
+        let ch = if shifted {
+            SHIFTED[code as usize]
+        } else {
+            NORMAL[code as usize]
+        };
+
+        match ch {
+            0 => None,
+            // ctrl maps `@` to `_` to the control characters, e.g. ctrl-c to 0x03
+            b'@'..=b'_' | b'a'..=b'z' if self.modifiers.ctrl() => Some(ch & 0x1F),
+            _ => Some(ch),
+        }
+    }
+}
@@ -1,5 +1,13 @@
+pub mod ahci;
 pub mod ata;
+pub mod devfs;
+pub mod disk;
 pub mod filesystem;
-pub mod input;
+pub mod framebuffer;
+pub mod keyboard;
+pub mod pci;
+pub mod ramdisk;
 pub mod serial;
+pub mod tty;
 mod uart16550;
+pub mod virtio;
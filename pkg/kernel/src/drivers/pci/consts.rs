@@ -0,0 +1,68 @@
+//! Constants for the PCI configuration space.
+//!
+//! reference: https://wiki.osdev.org/PCI
+
+/// The port selecting a register, for the configuration mechanism #1.
+pub(super) const CONFIG_ADDRESS: u16 = 0xCF8;
+/// The port of the selected register.
+pub(super) const CONFIG_DATA: u16 = 0xCFC;
+
+// registers of the common header
+pub(super) const VENDOR_ID: u8 = 0x00;
+pub(super) const DEVICE_ID: u8 = 0x02;
+pub(super) const COMMAND: u8 = 0x04;
+pub(super) const STATUS: u8 = 0x06;
+pub(super) const REVISION_ID: u8 = 0x08;
+pub(super) const HEADER_TYPE: u8 = 0x0E;
+pub(super) const BAR0: u8 = 0x10;
+pub(super) const SECONDARY_BUS: u8 = 0x19;
+pub(super) const CAPABILITIES: u8 = 0x34;
+pub(super) const INTERRUPT_LINE: u8 = 0x3C;
+pub(super) const INTERRUPT_PIN: u8 = 0x3D;
+
+// header types
+pub(super) const HEADER_GENERAL: u8 = 0x00;
+pub(super) const HEADER_BRIDGE: u8 = 0x01;
+
+// bits of the command register
+pub(super) const COMMAND_IO: u16 = 1 << 0;
+pub(super) const COMMAND_MEMORY: u16 = 1 << 1;
+pub(super) const COMMAND_BUS_MASTER: u16 = 1 << 2;
+pub(super) const COMMAND_INTX_DISABLE: u16 = 1 << 10;
+
+/// The status bit telling the capability list is present.
+pub(super) const STATUS_CAPABILITIES: u16 = 1 << 4;
+
+// capability ids
+pub(super) const CAPABILITY_MSI: u8 = 0x05;
+pub(super) const CAPABILITY_MSIX: u8 = 0x11;
+
+/// The address of the messages, to the local APIC of the first CPU.
+pub(super) const MSI_ADDRESS: u32 = 0xFEE0_0000;
+
+/// The name of a class and subclass, for the listing at boot.
+pub(super) fn class_name(class: u8, subclass: u8) -> &'static str {
+    match (class, subclass) {
+        (0x01, 0x00) => "SCSI storage controller",
+        (0x01, 0x01) => "IDE interface",
+        (0x01, 0x06) => "SATA controller",
+        (0x01, 0x08) => "Non-Volatile memory controller",
+        (0x01, _) => "Mass storage controller",
+        (0x02, 0x00) => "Ethernet controller",
+        (0x02, _) => "Network controller",
+        (0x03, 0x00) => "VGA compatible controller",
+        (0x03, _) => "Display controller",
+        (0x04, _) => "Multimedia controller",
+        (0x05, _) => "Memory controller",
+        (0x06, 0x00) => "Host bridge",
+        (0x06, 0x01) => "ISA bridge",
+        (0x06, 0x04) => "PCI bridge",
+        (0x06, _) => "Bridge",
+        (0x07, _) => "Communication controller",
+        (0x08, _) => "System peripheral",
+        (0x0C, 0x03) => "USB controller",
+        (0x0C, 0x05) => "SMBus",
+        (0x0C, _) => "Serial bus controller",
+        _ => "Unclassified device",
+    }
+}
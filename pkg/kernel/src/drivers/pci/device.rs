@@ -0,0 +1,342 @@
+//! PCI Function
+//!
+//! reference: https://wiki.osdev.org/PCI#Configuration_Space
+//! reference: https://wiki.osdev.org/PCI#Base_Address_Registers
+
+use super::consts::*;
+use alloc::vec::Vec;
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::instructions::port::*;
+
+/// The address of a function on the PCI bus
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PciAddress {
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl PciAddress {
+    pub fn new(bus: u8, device: u8, function: u8) -> Self {
+        Self {
+            bus,
+            device,
+            function,
+        }
+    }
+
+    /// Select a register of the configuration space, with the mechanism #1
+    fn select(&self, offset: u8) {
+        let address = 0x8000_0000
+            | (self.bus as u32) << 16
+            | (self.device as u32) << 11
+            | (self.function as u32) << 8
+            | (offset as u32 & 0xFC);
+        unsafe { PortWriteOnly::<u32>::new(CONFIG_ADDRESS).write(address) };
+    }
+
+    /// Read the 32-bit register at `offset` of the configuration space
+    pub fn read(&self, offset: u8) -> u32 {
+        // the address and data ports must not be split by an interrupt
+        without_interrupts(|| {
+            self.select(offset);
+            unsafe { PortReadOnly::<u32>::new(CONFIG_DATA).read() }
+        })
+    }
+
+    /// Write the 32-bit register at `offset` of the configuration space
+    pub fn write(&self, offset: u8, value: u32) {
+        without_interrupts(|| {
+            self.select(offset);
+            unsafe { PortWriteOnly::<u32>::new(CONFIG_DATA).write(value) };
+        })
+    }
+
+    /// Read the 16-bit field at `offset`, in its register
+    pub fn read_u16(&self, offset: u8) -> u16 {
+        (self.read(offset) >> ((offset & 0x2) * 8)) as u16
+    }
+
+    /// Read the 8-bit field at `offset`, in its register
+    pub fn read_u8(&self, offset: u8) -> u8 {
+        (self.read(offset) >> ((offset & 0x3) * 8)) as u8
+    }
+
+    /// Write the 16-bit field at `offset`, keeping the rest of its register
+    pub fn write_u16(&self, offset: u8, value: u16) {
+        let shift = (offset & 0x2) * 8;
+        let register = self.read(offset) & !(0xFFFF << shift);
+        self.write(offset, register | (value as u32) << shift);
+    }
+}
+
+impl core::fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{:02x}:{:02x}.{}", self.bus, self.device, self.function)
+    }
+}
+
+/// A decoded base address register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+    /// Registers in the I/O space
+    Io { port: u16, size: u32 },
+    /// Registers or memory in the memory space, below 4 GiB if not `wide`
+    Memory {
+        address: u64,
+        size: u64,
+        prefetchable: bool,
+        wide: bool,
+    },
+}
+
+/// A capability of the capability list, for the interrupts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    /// Message signaled interrupts
+    Msi { offset: u8, vectors: u8, wide: bool },
+    /// The extended message signaled interrupts, with their table in a BAR
+    MsiX {
+        offset: u8,
+        table_size: u16,
+        table_bar: u8,
+        table_offset: u32,
+    },
+    /// A capability without a decoder, by its id
+    Other { offset: u8, id: u8 },
+}
+
+/// A function on the PCI bus, with its header read at enumeration
+#[derive(Debug, Clone)]
+pub struct PciDevice {
+    pub address: PciAddress,
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub class: u8,
+    pub subclass: u8,
+    pub prog_if: u8,
+    pub revision: u8,
+    pub header_type: u8,
+    pub interrupt_line: u8,
+    pub interrupt_pin: u8,
+    pub bars: [Option<Bar>; 6],
+    pub capabilities: Vec<Capability>,
+}
+
+impl PciDevice {
+    /// Read the header of the function, `None` if there is no function
+    pub fn read(address: PciAddress) -> Option<Self> {
+        let vendor_id = address.read_u16(VENDOR_ID);
+        if vendor_id == 0xFFFF {
+            return None;
+        }
+
+        let class = address.read(REVISION_ID);
+        let header_type = address.read_u8(HEADER_TYPE) & 0x7F;
+        let mut device = Self {
+            address,
+            vendor_id,
+            device_id: address.read_u16(DEVICE_ID),
+            class: (class >> 24) as u8,
+            subclass: (class >> 16) as u8,
+            prog_if: (class >> 8) as u8,
+            revision: class as u8,
+            header_type,
+            interrupt_line: address.read_u8(INTERRUPT_LINE),
+            interrupt_pin: address.read_u8(INTERRUPT_PIN),
+            bars: [None; 6],
+            capabilities: Vec::new(),
+        };
+
+        // bridges have only two BARs, and other registers after them
+        match header_type {
+            HEADER_GENERAL => device.decode_bars(6),
+            HEADER_BRIDGE => device.decode_bars(2),
+            _ => {}
+        }
+        device.capabilities = device.read_capabilities();
+
+        Some(device)
+    }
+
+    /// Returns true if the function has other functions next to it
+    pub fn is_multifunction(address: PciAddress) -> bool {
+        address.read_u8(HEADER_TYPE) & 0x80 != 0
+    }
+
+    /// Returns true if the function is a PCI-to-PCI bridge
+    pub fn is_bridge(&self) -> bool {
+        self.header_type == HEADER_BRIDGE
+    }
+
+    /// The bus behind a PCI-to-PCI bridge
+    pub fn secondary_bus(&self) -> u8 {
+        self.address.read_u8(SECONDARY_BUS)
+    }
+
+    /// Let the function access the memory by itself, for DMA
+    pub fn enable_bus_master(&self) {
+        let command = self.address.read_u16(COMMAND);
+        self.address
+            .write_u16(COMMAND, command | COMMAND_BUS_MASTER);
+    }
+
+    /// The offset of the MSI capability, and if its address is 64-bit
+    pub fn msi(&self) -> Option<(u8, bool)> {
+        self.capabilities
+            .iter()
+            .find_map(|capability| match capability {
+                Capability::Msi { offset, wide, .. } => Some((*offset, *wide)),
+                _ => None,
+            })
+    }
+
+    /// Deliver the interrupts of the function as messages to `vector`
+    /// of the first CPU instead of its line, false if it has no MSI
+    pub fn enable_msi(&self, vector: u8) -> bool {
+        let Some((offset, wide)) = self.msi() else {
+            return false;
+        };
+
+        let address = self.address;
+        address.write(offset + 4, MSI_ADDRESS);
+        let data = if wide {
+            address.write(offset + 8, 0);
+            offset + 12
+        } else {
+            offset + 8
+        };
+        address.write_u16(data, vector as u16);
+
+        // a single message, then the line is not used anymore
+        let control = address.read_u16(offset + 2);
+        address.write_u16(offset + 2, (control & !(0x7 << 4)) | 1);
+        let command = address.read_u16(COMMAND);
+        address.write_u16(COMMAND, command | COMMAND_INTX_DISABLE);
+
+        true
+    }
+
+    /// Whether the interrupts of the function are delivered as messages,
+    /// see `enable_msi`
+    pub fn msi_enabled(&self) -> bool {
+        self.msi()
+            .is_some_and(|(offset, _)| self.address.read_u16(offset + 2) & 1 != 0)
+    }
+
+    /// Decode the first `count` BARs
+    ///
+    /// The size is found by writing all ones and reading back the bits
+    /// that stuck, with the decoding disabled meanwhile.
+    fn decode_bars(&mut self, count: usize) {
+        let address = self.address;
+        let command = address.read_u16(COMMAND);
+        address.write_u16(COMMAND, command & !(COMMAND_IO | COMMAND_MEMORY));
+
+        let size_of = |offset: u8| {
+            let original = address.read(offset);
+            address.write(offset, 0xFFFF_FFFF);
+            let mask = address.read(offset);
+            address.write(offset, original);
+            mask
+        };
+
+        let mut index = 0;
+        while index < count {
+            let offset = BAR0 + index as u8 * 4;
+            let bar = address.read(offset);
+
+            if bar & 0x1 != 0 {
+                let mask = size_of(offset) & !0x3;
+                if mask != 0 {
+                    self.bars[index] = Some(Bar::Io {
+                        port: (bar & 0xFFFC) as u16,
+                        size: (!mask & 0xFFFF) + 1,
+                    });
+                }
+                index += 1;
+                continue;
+            }
+
+            let wide = (bar >> 1) & 0x3 == 0x2 && index + 1 < count;
+            let low_mask = size_of(offset) & !0xF;
+            let (base, mask) = if wide {
+                let high = address.read(offset + 4);
+                let high_mask = size_of(offset + 4);
+                (
+                    (high as u64) << 32 | (bar & !0xF) as u64,
+                    (high_mask as u64) << 32 | low_mask as u64,
+                )
+            } else {
+                ((bar & !0xF) as u64, 0xFFFF_FFFF_0000_0000 | low_mask as u64)
+            };
+
+            if low_mask != 0 {
+                self.bars[index] = Some(Bar::Memory {
+                    address: base,
+                    size: !mask + 1,
+                    prefetchable: bar & 0x8 != 0,
+                    wide,
+                });
+            }
+            index += if wide { 2 } else { 1 };
+        }
+
+        address.write_u16(COMMAND, command);
+    }
+
+    /// Walk the capability list, if the status register has one
+    fn read_capabilities(&self) -> Vec<Capability> {
+        let address = self.address;
+        let mut capabilities = Vec::new();
+
+        if address.read_u16(STATUS) & STATUS_CAPABILITIES == 0 {
+            return capabilities;
+        }
+
+        let mut offset = address.read_u8(CAPABILITIES) & 0xFC;
+        // a broken list could loop, there are at most 48 capabilities
+        while offset != 0 && capabilities.len() < 48 {
+            let id = address.read_u8(offset);
+            let control = address.read_u16(offset + 2);
+
+            capabilities.push(match id {
+                CAPABILITY_MSI => Capability::Msi {
+                    offset,
+                    vectors: 1 << ((control >> 1) & 0x7),
+                    wide: control & (1 << 7) != 0,
+                },
+                CAPABILITY_MSIX => {
+                    let table = address.read(offset + 4);
+                    Capability::MsiX {
+                        offset,
+                        table_size: (control & 0x7FF) + 1,
+                        table_bar: (table & 0x7) as u8,
+                        table_offset: table & !0x7,
+                    }
+                }
+                id => Capability::Other { offset, id },
+            });
+
+            offset = address.read_u8(offset + 1) & 0xFC;
+        }
+
+        capabilities
+    }
+}
+
+impl core::fmt::Display for PciDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "{} {} [{:02x}{:02x}]: {:04x}:{:04x} (rev {:02x})",
+            self.address,
+            class_name(self.class, self.subclass),
+            self.class,
+            self.subclass,
+            self.vendor_id,
+            self.device_id,
+            self.revision
+        )
+    }
+}
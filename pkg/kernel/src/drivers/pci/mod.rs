@@ -0,0 +1,148 @@
+//! PCI Bus
+//!
+//! The functions on the bus are enumerated once at boot, through the
+//! configuration mechanism #1 (ports `0xCF8` / `0xCFC`), following the
+//! PCI-to-PCI bridges. Each function is then offered to the drivers in
+//! [`DRIVERS`], and the first one matching its vendor and device id binds it.
+//!
+//! The interrupt lines are shared, an interrupt on one is passed to
+//! the drivers of every function bound on it. The functions using MSI
+//! share a single vector the same way.
+//!
+//! reference: https://wiki.osdev.org/PCI
+
+mod consts;
+mod device;
+
+pub use device::*;
+
+use super::virtio::VIRTIO_BLK_DRIVER;
+use crate::interrupt::{enable_level_irq, PCI_IRQS};
+use alloc::vec::Vec;
+
+/// A driver of PCI functions, bound by their vendor and device id,
+/// or by their class
+pub trait PciDriver: Sync {
+    /// The name of the driver, for the listing
+    fn name(&self) -> &'static str;
+
+    /// The `(vendor, device)` ids the driver supports
+    fn ids(&self) -> &'static [(u16, u16)];
+
+    /// Returns true if the driver supports the function, by its ids by default
+    fn matches(&self, device: &PciDevice) -> bool {
+        self.ids().contains(&(device.vendor_id, device.device_id))
+    }
+
+    /// Set up a function the driver supports, returns true if bound
+    fn probe(&self, device: &PciDevice) -> bool;
+
+    /// Acknowledge an interrupt on the line of a bound function,
+    /// it may come from another function sharing the line
+    fn interrupt(&self, _device: &PciDevice) {}
+}
+
+/// The known drivers, in the order they are offered the functions
+static DRIVERS: &[&dyn PciDriver] = &[
+    &super::ata::IDE_DRIVER,
+    &super::ahci::AHCI_DRIVER,
+    &VIRTIO_BLK_DRIVER,
+];
+
+static DEVICES: spin::Once<Vec<PciDevice>> = spin::Once::new();
+
+/// The functions bound to a driver, and whether they were set up to use MSI
+static BOUND: spin::Once<Vec<(&'static PciDevice, &'static dyn PciDriver, bool)>> =
+    spin::Once::new();
+
+/// Enumerate the bus, bind the drivers and print the functions found
+pub fn init() {
+    let devices = DEVICES.call_once(|| {
+        let mut devices = Vec::new();
+        scan_bus(0, &mut devices);
+        devices
+    });
+
+    let mut bound = Vec::new();
+    for device in devices {
+        let driver = DRIVERS
+            .iter()
+            .filter(|driver| driver.matches(device))
+            .find(|driver| driver.probe(device));
+
+        match driver {
+            Some(driver) => {
+                info!("{} ({})", device, driver.name());
+                // the driver enables MSI in `probe`, if it uses it
+                bound.push((device, *driver, device.msi_enabled()));
+            }
+            None => info!("{}", device),
+        }
+    }
+
+    let bound = BOUND.call_once(|| bound);
+    for line in PCI_IRQS {
+        if bound
+            .iter()
+            .any(|(device, _, msi)| !msi && device.interrupt_line == line)
+        {
+            enable_level_irq(line, 0);
+        }
+    }
+
+    info!("Enumerated {} PCI functions.", devices.len());
+}
+
+/// Pass a message to the drivers bound on a function using MSI
+pub fn handle_msi() {
+    for (device, driver, msi) in BOUND.get().into_iter().flatten() {
+        if *msi {
+            driver.interrupt(device);
+        }
+    }
+}
+
+/// Pass an interrupt on the line to the drivers bound on it
+pub fn handle_irq(line: u8) {
+    for (device, driver, msi) in BOUND.get().into_iter().flatten() {
+        if !msi && device.interrupt_line == line {
+            driver.interrupt(device);
+        }
+    }
+}
+
+/// The functions found at boot
+pub fn devices() -> impl Iterator<Item = &'static PciDevice> {
+    DEVICES.get().into_iter().flatten()
+}
+
+/// Find a function by its class and subclass
+pub fn find_class(class: u8, subclass: u8) -> impl Iterator<Item = &'static PciDevice> {
+    devices().filter(move |device| device.class == class && device.subclass == subclass)
+}
+
+fn scan_bus(bus: u8, devices: &mut Vec<PciDevice>) {
+    for device in 0..32 {
+        let address = PciAddress::new(bus, device, 0);
+        let Some(first) = PciDevice::read(address) else {
+            continue;
+        };
+
+        let mut functions = alloc::vec![first];
+        if PciDevice::is_multifunction(address) {
+            functions
+                .extend((1..8).filter_map(|function| {
+                    PciDevice::read(PciAddress::new(bus, device, function))
+                }));
+        }
+
+        for found in functions {
+            // the bus numbers only grow behind a bridge, this guards a loop
+            let secondary = found.is_bridge().then(|| found.secondary_bus());
+            devices.push(found);
+            if let Some(secondary) = secondary.filter(|secondary| *secondary > bus) {
+                scan_bus(secondary, devices);
+            }
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! RAM disk
+//!
+//! A block device backed by physical frames, reached through the
+//! physical memory mapping. It starts zeroed and its content is lost
+//! on shutdown, so it needs a file system made on it before mounting.
+
+use crate::memory::*;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use storage::*;
+use x86_64::structures::paging::{FrameAllocator, FrameDeallocator, PhysFrame};
+
+/// The device name of the RAM disk
+pub const RAMDISK_DEVICE: &str = "ram0";
+
+const BLOCK_SIZE: usize = Block512::BLOCK_SIZE;
+const BLOCKS_PER_FRAME: usize = PAGE_SIZE as usize / BLOCK_SIZE;
+
+pub static RAMDISK: spin::Once<RamDisk> = spin::Once::new();
+
+pub fn init(pages: u64) {
+    if pages == 0 {
+        info!("RAM disk disabled.");
+        return;
+    }
+
+    match RamDisk::new(pages as usize) {
+        Some(disk) => {
+            let (size, unit) = crate::humanized_size(pages * PAGE_SIZE);
+            info!(
+                "RAM disk {} initialized: {:.3} {}",
+                RAMDISK_DEVICE, size, unit
+            );
+            RAMDISK.call_once(|| disk);
+        }
+        None => warn!("Failed to allocate {} pages for the RAM disk", pages),
+    }
+}
+
+pub fn get_ramdisk() -> Option<&'static RamDisk> {
+    RAMDISK.get()
+}
+
+/// The frames are never freed, the RAM disk lives as long as the kernel
+#[derive(Clone)]
+pub struct RamDisk {
+    frames: Arc<[PhysFrame]>,
+}
+
+impl RamDisk {
+    /// Allocate and zero `pages` frames
+    pub fn new(pages: usize) -> Option<Self> {
+        let mut alloc = get_frame_alloc_for_sure();
+        let mut frames = Vec::with_capacity(pages);
+
+        for _ in 0..pages {
+            let Some(frame) = alloc.allocate_frame() else {
+                for frame in frames {
+                    unsafe { alloc.deallocate_frame(frame) };
+                }
+                return None;
+            };
+
+            unsafe {
+                core::ptr::write_bytes(Self::frame_ptr(&frame), 0, PAGE_SIZE as usize);
+            }
+            frames.push(frame);
+        }
+
+        Some(Self {
+            frames: frames.into(),
+        })
+    }
+
+    fn frame_ptr(frame: &PhysFrame) -> *mut u8 {
+        physical_to_virtual(frame.start_address().as_u64()) as *mut u8
+    }
+
+    /// The address of the block at `offset`
+    fn block_ptr(&self, offset: usize) -> Result<*mut u8> {
+        let frame = self
+            .frames
+            .get(offset / BLOCKS_PER_FRAME)
+            .ok_or(FsError::InvalidOffset)?;
+        let ptr = Self::frame_ptr(frame);
+        Ok(unsafe { ptr.add(offset % BLOCKS_PER_FRAME * BLOCK_SIZE) })
+    }
+}
+
+impl BlockDevice<Block512> for RamDisk {
+    fn block_count(&self) -> Result<usize> {
+        Ok(self.frames.len() * BLOCKS_PER_FRAME)
+    }
+
+    fn read_block(&self, offset: usize, block: &mut Block512) -> Result<()> {
+        let ptr = self.block_ptr(offset)?;
+        unsafe {
+            core::ptr::copy_nonoverlapping(ptr, block.as_mut().as_mut_ptr(), BLOCK_SIZE);
+        }
+        Ok(())
+    }
+
+    fn write_block(&self, offset: usize, block: &Block512) -> Result<()> {
+        let ptr = self.block_ptr(offset)?;
+        unsafe {
+            core::ptr::copy_nonoverlapping(block.as_ref().as_ptr(), ptr, BLOCK_SIZE);
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for RamDisk {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RamDisk")
+            .field("pages", &self.frames.len())
+            .finish()
+    }
+}
@@ -0,0 +1,360 @@
+//! Terminal
+//!
+//! The line discipline between the keyboard and serial port and the
+//! processes reading the console. In canonical mode, the input is edited
+//! and echoed here, and a read blocks until a whole line is ready. In raw
+//! mode, the bytes are passed on as they come. The interrupt and suspend
+//! characters send signals to the foreground process, the one the shell
+//! waits for.
+//!
+//! The terminal is locked in the keyboard and serial interrupts, so only
+//! with interrupts disabled.
+//!
+//! reference: https://man7.org/linux/man-pages/man3/termios.3.html
+
+use crate::proc::{self, ProcessId, Signal, WaitQueue};
+use alloc::{collections::VecDeque, vec::Vec};
+use syscall_def::tty::Termios;
+use x86_64::instructions::interrupts;
+
+/// The bytes of a line being edited
+const MAX_CANON: usize = 255;
+/// The bytes ready to be read
+const MAX_INPUT: usize = 4096;
+
+once_mutex!(TTY: Tty);
+
+/// The processes waiting for input, woken up once there is some or a
+/// signal comes
+static READERS: WaitQueue = WaitQueue::new();
+
+guard_access_fn!(get_tty(TTY: Tty));
+
+pub fn init() {
+    init_TTY(Tty::new());
+
+    info!("Terminal Initialized.");
+}
+
+/// Handle a byte from the keyboard or serial port
+///
+/// Should be called in their interrupts.
+pub fn receive(byte: u8) {
+    let (signal, readable) = {
+        let mut tty = get_tty_for_sure();
+        let signal = tty.receive(byte);
+        (signal, tty.is_readable())
+    };
+
+    // out of the terminal lock, the process manager may pass the foreground
+    if let Some((pid, signal)) = signal {
+        proc::signal(pid, signal);
+    }
+    if readable || signal.is_some() {
+        READERS.wake_all();
+    }
+}
+
+/// Read from the terminal into `buf`, waiting until there is input
+///
+/// The process is blocked until a line is ready, or a byte in raw mode.
+/// Returns `Some(0)` at end of file, and `None` if the process was
+/// interrupted by a signal before.
+pub fn read(buf: &mut [u8]) -> Option<usize> {
+    if buf.is_empty() {
+        return Some(0);
+    }
+
+    let mut count = None;
+    READERS.wait_until(|| {
+        if proc::signal_pending() {
+            return true;
+        }
+        count = get_tty_for_sure().read(buf);
+        count.is_some()
+    });
+    count
+}
+
+pub fn attributes() -> Termios {
+    interrupts::without_interrupts(|| get_tty_for_sure().termios)
+}
+
+/// Set the attributes of the terminal, discarding the input not read yet
+/// if `flush`
+pub fn set_attributes(termios: Termios, flush: bool) {
+    interrupts::without_interrupts(|| get_tty_for_sure().set_termios(termios, flush));
+    // the line being edited may be readable in raw mode
+    READERS.wake_all();
+}
+
+/// Make the process the leader of the terminal, its children waited for
+/// are sent the signals
+pub fn set_session(pid: ProcessId) {
+    interrupts::without_interrupts(|| {
+        let mut tty = get_tty_for_sure();
+        tty.session = Some(pid);
+        tty.set_foreground(Some(pid));
+    })
+}
+
+/// Pass the foreground from a process to another, if it has it
+pub fn pass_foreground(from: ProcessId, to: ProcessId) {
+    interrupts::without_interrupts(|| {
+        let mut tty = get_tty_for_sure();
+        if tty.foreground == Some(from) {
+            tty.set_foreground(Some(to));
+        }
+    })
+}
+
+pub struct Tty {
+    termios: Termios,
+    /// The line being edited, in canonical mode
+    line: Vec<u8>,
+    /// The bytes ready to be read
+    ready: VecDeque<u8>,
+    /// The length of each line in `ready`, in canonical mode, 0 for an end
+    /// of file
+    lines: VecDeque<usize>,
+    /// The bytes of a UTF-8 character being echoed
+    echoing: Vec<u8>,
+    /// The process leading the terminal, never sent signals
+    session: Option<ProcessId>,
+    /// The process sent the signals
+    foreground: Option<ProcessId>,
+    /// The foreground process was suspended
+    suspended: bool,
+}
+
+impl Tty {
+    fn new() -> Self {
+        Self {
+            termios: Termios::default(),
+            line: Vec::with_capacity(MAX_CANON),
+            ready: VecDeque::new(),
+            lines: VecDeque::new(),
+            echoing: Vec::new(),
+            session: None,
+            foreground: None,
+            suspended: false,
+        }
+    }
+
+    fn set_foreground(&mut self, pid: Option<ProcessId>) {
+        self.foreground = pid;
+        self.suspended = false;
+    }
+
+    fn is_set(&self, flag: u32) -> bool {
+        self.termios.lflag & flag != 0
+    }
+
+    /// Whether the byte is the control character, 0 disables it
+    fn is_control(&self, byte: u8, index: usize) -> bool {
+        let ch = self.termios.cc[index];
+        ch != 0 && ch == byte
+    }
+
+    fn set_termios(&mut self, termios: Termios, flush: bool) {
+        let was_canonical = self.termios.is_canonical();
+        self.termios = termios;
+
+        if flush {
+            self.flush();
+            return;
+        }
+
+        match (was_canonical, termios.is_canonical()) {
+            // the line being edited is passed on as it is
+            (true, false) => {
+                self.ready.extend(self.line.drain(..));
+                self.lines.clear();
+            }
+            // the bytes not read yet make a line
+            (false, true) if !self.ready.is_empty() => {
+                self.lines.clear();
+                self.lines.push_back(self.ready.len());
+            }
+            _ => {}
+        }
+    }
+
+    fn flush(&mut self) {
+        self.line.clear();
+        self.ready.clear();
+        self.lines.clear();
+    }
+
+    /// Handle a byte of input, returns the signal to send, if any
+    fn receive(&mut self, byte: u8) -> Option<(ProcessId, Signal)> {
+        let byte = match byte {
+            b'\r' if self.termios.iflag & Termios::ICRNL != 0 => b'\n',
+            _ => byte,
+        };
+
+        if self.is_set(Termios::ISIG) {
+            if self.is_control(byte, Termios::VINTR) {
+                self.flush();
+                self.echo_control(byte);
+                self.echo(b"\n\r");
+                return self.signal(Signal::Interrupt);
+            }
+            if self.is_control(byte, Termios::VSUSP) {
+                self.echo_control(byte);
+                let signal = if self.suspended {
+                    Signal::Continue
+                } else {
+                    Signal::Suspend
+                };
+                let signal = self.signal(signal);
+                if signal.is_some() {
+                    self.suspended = !self.suspended;
+                }
+                return signal;
+            }
+        }
+
+        if !self.termios.is_canonical() {
+            if self.ready.len() < MAX_INPUT {
+                self.ready.push_back(byte);
+                self.echo_byte(byte);
+            } else {
+                warn!("Terminal input is full. Dropping byte {:#x}", byte);
+            }
+            return None;
+        }
+
+        if self.is_control(byte, Termios::VERASE) || byte == 0x08 {
+            self.erase();
+        } else if self.is_control(byte, Termios::VKILL) {
+            while !self.line.is_empty() {
+                self.erase();
+            }
+        } else if self.is_control(byte, Termios::VEOF) {
+            self.end_line();
+        } else if byte == b'\n' {
+            self.line.push(byte);
+            self.end_line();
+            self.echo(b"\n\r");
+        } else if self.line.len() < MAX_CANON - 1 {
+            // a byte is kept for the newline
+            self.line.push(byte);
+            self.echo_byte(byte);
+        }
+
+        None
+    }
+
+    /// The signal for the foreground process, unless it leads the terminal
+    fn signal(&self, signal: Signal) -> Option<(ProcessId, Signal)> {
+        match self.foreground {
+            Some(pid) if self.foreground != self.session => Some((pid, signal)),
+            _ => None,
+        }
+    }
+
+    /// Pass the line being edited on, an empty one is an end of file
+    fn end_line(&mut self) {
+        if self.ready.len() + self.line.len() > MAX_INPUT {
+            warn!("Terminal input is full. Dropping a line.");
+            self.line.clear();
+            return;
+        }
+
+        self.lines.push_back(self.line.len());
+        self.ready.extend(self.line.drain(..));
+    }
+
+    /// Erase the last character of the line
+    fn erase(&mut self) {
+        while let Some(byte) = self.line.pop() {
+            // the continuation bytes of a UTF-8 character
+            if byte & 0xC0 == 0x80 {
+                continue;
+            }
+            if self.is_set(Termios::ECHO) {
+                let columns = if self.shows_control(byte) { 2 } else { 1 };
+                for _ in 0..columns {
+                    self.echo(b"\x08 \x08");
+                }
+            }
+            return;
+        }
+    }
+
+    /// Whether a reader may have input, a line in canonical mode
+    fn is_readable(&self) -> bool {
+        if self.termios.is_canonical() {
+            !self.lines.is_empty()
+        } else {
+            !self.ready.is_empty()
+        }
+    }
+
+    /// Read into `buf`, returns `None` if there is not enough input yet
+    fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if !self.termios.is_canonical() {
+            let min = self.termios.cc[Termios::VMIN] as usize;
+            if self.ready.len() < min.min(buf.len()) {
+                return None;
+            }
+            let count = self.ready.len().min(buf.len());
+            for (byte, ch) in buf.iter_mut().zip(self.ready.drain(..count)) {
+                *byte = ch;
+            }
+            return Some(count);
+        }
+
+        // at most a line at a time, the rest of it is left for the next read
+        let len = self.lines.pop_front()?;
+        let count = len.min(buf.len());
+        for (byte, ch) in buf.iter_mut().zip(self.ready.drain(..count)) {
+            *byte = ch;
+        }
+        if count < len {
+            self.lines.push_front(len - count);
+        }
+        Some(count)
+    }
+
+    /// Whether the byte is echoed as `^X`
+    fn shows_control(&self, byte: u8) -> bool {
+        self.is_set(Termios::ECHOCTL)
+            && (byte < 0x20 || byte == 0x7F)
+            && byte != b'\n'
+            && byte != b'\t'
+    }
+
+    fn echo_control(&mut self, byte: u8) {
+        if self.is_set(Termios::ECHO) && self.shows_control(byte) {
+            self.echo(&[b'^', byte ^ 0x40]);
+        }
+    }
+
+    /// Echo a byte of input, the bytes of a UTF-8 character at once
+    fn echo_byte(&mut self, byte: u8) {
+        if !self.is_set(Termios::ECHO) {
+            return;
+        }
+        if self.shows_control(byte) {
+            self.echo_control(byte);
+            return;
+        }
+
+        self.echoing.push(byte);
+        match core::str::from_utf8(&self.echoing) {
+            Ok(s) => print!("{}", s),
+            // wait for the rest of the character
+            Err(e) if e.error_len().is_none() => return,
+            Err(_) => print!("{}", alloc::string::String::from_utf8_lossy(&self.echoing)),
+        }
+        self.echoing.clear();
+    }
+
+    fn echo(&self, bytes: &[u8]) {
+        if self.is_set(Termios::ECHO) {
+            print!("{}", core::str::from_utf8(bytes).unwrap_or_default());
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//! Virtio Block Device
+//!
+//! A request is a chain of three descriptors, its header, the data and
+//! the status byte written by the device. Each request slot owns its
+//! chain and a DMA buffer, so the descriptors are only filled once.
+//!
+//! reference: https://docs.oasis-open.org/virtio/virtio/v1.1/cs01/virtio-v1.1-cs01.html#x1-2390002
+
+use super::queue::{DescriptorFlags, VirtQueue};
+use super::*;
+use crate::drivers::pci::{Bar, PciDevice, PciDriver};
+use crate::memory::{get_frame_alloc_for_sure, physical_to_virtual, PAGE_SIZE};
+use crate::proc::WaitQueue;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use alloc::{format, string::String};
+use spin::Mutex;
+use storage::{Block512, BlockDevice, DeviceError, FsError, SizedBlock};
+use x86_64::instructions::interrupts::without_interrupts;
+use x86_64::structures::paging::{FrameAllocator, PhysFrame};
+
+/// The device id of the transitional block device
+const VIRTIO_BLK_DEVICE: u16 = 0x1001;
+
+/// The feature bit of a read-only device
+const FEATURE_READ_ONLY: u32 = 1 << 5;
+
+/// The request types
+const REQUEST_IN: u32 = 0;
+const REQUEST_OUT: u32 = 1;
+
+/// The status written by the device for a successful request
+const STATUS_OK: u8 = 0;
+
+/// The offset of the capacity in the configuration, in sectors (u64)
+const CAPACITY: u16 = 0x00;
+
+const SECTOR_SIZE: usize = Block512::BLOCK_SIZE;
+
+/// The requests in flight at once on a device
+const SLOTS: usize = 4;
+
+/// The contiguous frames of the buffer of each slot, 64 KiB
+const DATA_FRAMES: usize = 16;
+
+/// The most sectors transferred by a single request
+const MAX_SECTORS: usize = DATA_FRAMES * PAGE_SIZE as usize / SECTOR_SIZE;
+
+/// The bytes of each slot in the header frame, the header and the status
+const HEADER_STRIDE: usize = 32;
+const STATUS_OFFSET: usize = 16;
+
+/// The block devices found at boot, `vda` first
+static DISKS: Mutex<Vec<VirtioBlk>> = Mutex::new(Vec::new());
+
+/// The processes waiting for a slot or for their request, woken up by a
+/// slot being freed or the interrupt of a device
+static WAITERS: WaitQueue = WaitQueue::new();
+
+/// The block devices found at boot
+pub fn disks() -> Vec<VirtioBlk> {
+    DISKS.lock().clone()
+}
+
+/// The PCI driver of the virtio block devices
+pub struct VirtioBlkDriver;
+
+pub static VIRTIO_BLK_DRIVER: VirtioBlkDriver = VirtioBlkDriver;
+
+impl PciDriver for VirtioBlkDriver {
+    fn name(&self) -> &'static str {
+        "virtio-blk"
+    }
+
+    fn ids(&self) -> &'static [(u16, u16)] {
+        &[(VIRTIO_VENDOR, VIRTIO_BLK_DEVICE)]
+    }
+
+    fn probe(&self, device: &PciDevice) -> bool {
+        let Some(Bar::Io { port, .. }) = device.bars[0] else {
+            // only the legacy interface is supported
+            return false;
+        };
+
+        device.enable_bus_master();
+        let regs = LegacyPci::new(port);
+        let Some(inner) = Device::new(regs) else {
+            regs.set_status(regs.status() | DeviceStatus::FAILED);
+            warn!(
+                "Failed to set up the virtio block device at {}",
+                device.address
+            );
+            return false;
+        };
+
+        let mut disks = DISKS.lock();
+        let disk = VirtioBlk {
+            index: disks.len(),
+            blocks: inner.blocks,
+            read_only: inner.read_only,
+            device: Arc::new(inner),
+        };
+        info!("Drive {} opened: {} blocks", disk.name(), disk.blocks);
+        disks.push(disk);
+
+        true
+    }
+
+    /// Reading the status deasserts the line, the process waiting for
+    /// its request checks the used ring once woken up
+    fn interrupt(&self, device: &PciDevice) {
+        if let Some(Bar::Io { port, .. }) = device.bars[0] {
+            LegacyPci::new(port).isr_status();
+        }
+        WAITERS.wake_all();
+    }
+}
+
+/// A request slot, its descriptors start at `3 * index`
+#[derive(Debug)]
+struct Slot {
+    index: usize,
+    data: PhysFrame,
+}
+
+/// The queue of a device, with the slots it is not using
+#[derive(Debug)]
+struct Requests {
+    queue: VirtQueue,
+    free: Vec<usize>,
+    /// The slots the device is done with, until their process sees it
+    done: [bool; SLOTS],
+}
+
+impl Requests {
+    /// Returns true once the device is done with the slot
+    fn take_done(&mut self, slot: usize) -> bool {
+        while let Some((head, _)) = self.queue.pop_used() {
+            self.done[head as usize / 3] = true;
+        }
+        core::mem::take(&mut self.done[slot])
+    }
+}
+
+/// A virtio block device, shared by the handles of the disk
+#[derive(Debug)]
+struct Device {
+    regs: LegacyPci,
+    blocks: u64,
+    read_only: bool,
+    /// The headers and statuses of the slots
+    headers: PhysFrame,
+    slots: Vec<Slot>,
+    /// Only locked with interrupts disabled
+    requests: Mutex<Requests>,
+}
+
+impl Device {
+    /// Set up the request queue of the device, `None` if it fails
+    fn new(regs: LegacyPci) -> Option<Self> {
+        regs.reset();
+        let features = regs.negotiate(FEATURE_READ_ONLY);
+        let mut queue = regs.setup_queue(0)?;
+
+        let count = SLOTS.min(queue.size() as usize / 3);
+        if count == 0 {
+            return None;
+        }
+        let (headers, data) = without_interrupts(|| {
+            let mut alloc = get_frame_alloc_for_sure();
+            let headers = alloc.allocate_frame()?;
+            let data = (0..count)
+                .map(|_| alloc.allocate_contiguous(DATA_FRAMES))
+                .collect::<Option<Vec<_>>>()?;
+            Some((headers, data))
+        })?;
+
+        let slots = data
+            .into_iter()
+            .enumerate()
+            .map(|(index, data)| Slot { index, data })
+            .collect::<Vec<_>>();
+
+        let header_base = headers.start_address().as_u64();
+        for slot in &slots {
+            let head = slot.index as u16 * 3;
+            let header = header_base + (slot.index * HEADER_STRIDE) as u64;
+            queue.set_descriptor(head, header, 16, DescriptorFlags::NEXT, head + 1);
+            queue.set_descriptor(
+                head + 1,
+                slot.data.start_address().as_u64(),
+                0,
+                DescriptorFlags::NEXT,
+                head + 2,
+            );
+            queue.set_descriptor(
+                head + 2,
+                header + STATUS_OFFSET as u64,
+                1,
+                DescriptorFlags::WRITE,
+                0,
+            );
+        }
+
+        regs.set_status(regs.status() | DeviceStatus::DRIVER_OK);
+
+        let blocks = regs.config(CAPACITY) as u64 | (regs.config(CAPACITY + 4) as u64) << 32;
+
+        Some(Self {
+            regs,
+            blocks,
+            read_only: features & FEATURE_READ_ONLY != 0,
+            headers,
+            requests: Mutex::new(Requests {
+                queue,
+                free: (0..slots.len()).rev().collect(),
+                done: [false; SLOTS],
+            }),
+            slots,
+        })
+    }
+
+    /// The header and the status of the slot
+    fn header(&self, slot: &Slot) -> *mut u8 {
+        let address = self.headers.start_address().as_u64() + (slot.index * HEADER_STRIDE) as u64;
+        physical_to_virtual(address) as *mut u8
+    }
+
+    /// The buffer of the slot, `MAX_SECTORS` sectors
+    fn buffer(&self, slot: &Slot) -> *mut u8 {
+        physical_to_virtual(slot.data.start_address().as_u64()) as *mut u8
+    }
+
+    /// Send a request for `sectors` sectors at `sector` and wait for it,
+    /// `fill` and `drain` copy the data to and from the buffer
+    fn request(
+        &self,
+        read: bool,
+        sector: u64,
+        sectors: usize,
+        fill: impl FnOnce(&mut [u8]),
+        drain: impl FnOnce(&[u8]),
+    ) -> storage::Result<()> {
+        // wait for a free slot, the process is blocked meanwhile
+        let mut index = None;
+        WAITERS.wait_until(|| {
+            index = self.requests.lock().free.pop();
+            index.is_some()
+        });
+        let slot = &self.slots[index.unwrap()];
+
+        let header = self.header(slot);
+        unsafe {
+            (header as *mut u32).write_volatile(if read { REQUEST_IN } else { REQUEST_OUT });
+            (header.add(4) as *mut u32).write_volatile(0);
+            (header.add(8) as *mut u64).write_volatile(sector);
+            header.add(STATUS_OFFSET).write_volatile(0xFF);
+        }
+        let buffer =
+            unsafe { core::slice::from_raw_parts_mut(self.buffer(slot), sectors * SECTOR_SIZE) };
+        fill(buffer);
+
+        let head = slot.index as u16 * 3;
+        let flags = if read {
+            DescriptorFlags::NEXT | DescriptorFlags::WRITE
+        } else {
+            DescriptorFlags::NEXT
+        };
+        without_interrupts(|| {
+            let mut requests = self.requests.lock();
+            requests.queue.set_descriptor(
+                head + 1,
+                slot.data.start_address().as_u64(),
+                (sectors * SECTOR_SIZE) as u32,
+                flags,
+                head + 2,
+            );
+            requests.queue.push(head);
+            self.regs.notify(0);
+        });
+
+        // woken up by the interrupt of the device
+        WAITERS.wait_until(|| self.requests.lock().take_done(slot.index));
+
+        let status = unsafe { header.add(STATUS_OFFSET).read_volatile() };
+        if status == STATUS_OK {
+            drain(buffer);
+        }
+        without_interrupts(|| self.requests.lock().free.push(slot.index));
+        WAITERS.wake_all();
+
+        match (status, read) {
+            (STATUS_OK, _) => Ok(()),
+            (_, true) => Err(DeviceError::ReadError.into()),
+            (_, false) => Err(DeviceError::WriteError.into()),
+        }
+    }
+}
+
+/// A handle of a virtio block device found at boot
+#[derive(Debug, Clone)]
+pub struct VirtioBlk {
+    index: usize,
+    blocks: u64,
+    read_only: bool,
+    device: Arc<Device>,
+}
+
+impl VirtioBlk {
+    /// The device numbered `index`, if it was found at boot
+    pub fn open(index: usize) -> Option<Self> {
+        DISKS.lock().get(index).cloned()
+    }
+
+    /// The name of the device, `vda` for the first one
+    pub fn name(&self) -> String {
+        format!("vd{}", (b'a' + self.index as u8) as char)
+    }
+}
+
+impl BlockDevice<Block512> for VirtioBlk {
+    fn block_count(&self) -> storage::Result<usize> {
+        Ok(self.blocks as usize)
+    }
+
+    fn read_block(&self, offset: usize, block: &mut Block512) -> storage::Result<()> {
+        self.read_blocks(offset, core::slice::from_mut(block))
+    }
+
+    fn write_block(&self, offset: usize, block: &Block512) -> storage::Result<()> {
+        if self.read_only {
+            return Err(FsError::ReadOnly);
+        }
+
+        self.device.request(
+            false,
+            offset as u64,
+            1,
+            |buffer| buffer.copy_from_slice(block.as_ref()),
+            |_| {},
+        )
+    }
+
+    /// Reads the blocks with a request for each `MAX_SECTORS` of them
+    fn read_blocks(&self, offset: usize, blocks: &mut [Block512]) -> storage::Result<()> {
+        for (index, chunk) in blocks.chunks_mut(MAX_SECTORS).enumerate() {
+            let sector = (offset + index * MAX_SECTORS) as u64;
+            self.device.request(
+                true,
+                sector,
+                chunk.len(),
+                |_| {},
+                |buffer| {
+                    for (block, data) in chunk.iter_mut().zip(buffer.chunks(SECTOR_SIZE)) {
+                        block.as_mut().copy_from_slice(data);
+                    }
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
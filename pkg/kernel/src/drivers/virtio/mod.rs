@@ -0,0 +1,117 @@
+//! Virtio Devices
+//!
+//! The devices are driven through the legacy PCI interface, their
+//! registers are in the I/O space at BAR0. QEMU offers it on the
+//! transitional devices, e.g. `-drive if=virtio`.
+//!
+//! reference: https://docs.oasis-open.org/virtio/virtio/v1.1/virtio-v1.1.html
+//! reference: https://wiki.osdev.org/Virtio
+
+mod blk;
+mod queue;
+
+pub use blk::{disks, VirtioBlk, VIRTIO_BLK_DRIVER};
+
+use queue::VirtQueue;
+use x86_64::instructions::port::*;
+
+/// The vendor id of the virtio devices
+const VIRTIO_VENDOR: u16 = 0x1AF4;
+
+bitflags! {
+    /// The device status register.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    struct DeviceStatus: u8 {
+        /// The driver has noticed the device.
+        const ACKNOWLEDGE = 0x01;
+        /// The driver knows how to drive the device.
+        const DRIVER      = 0x02;
+        /// The driver is set up and ready to drive the device.
+        const DRIVER_OK   = 0x04;
+        /// The driver has given up on the device.
+        const FAILED      = 0x80;
+    }
+}
+
+/// The registers of a device in the legacy interface
+///
+/// The device specific configuration follows them, at `CONFIG`
+/// since MSI-X is not enabled.
+#[derive(Debug, Clone, Copy)]
+struct LegacyPci {
+    base: u16,
+}
+
+impl LegacyPci {
+    const DEVICE_FEATURES: u16 = 0x00;
+    const DRIVER_FEATURES: u16 = 0x04;
+    const QUEUE_ADDRESS: u16 = 0x08;
+    const QUEUE_SIZE: u16 = 0x0C;
+    const QUEUE_SELECT: u16 = 0x0E;
+    const QUEUE_NOTIFY: u16 = 0x10;
+    const DEVICE_STATUS: u16 = 0x12;
+    const ISR_STATUS: u16 = 0x13;
+    const CONFIG: u16 = 0x14;
+
+    fn new(base: u16) -> Self {
+        Self { base }
+    }
+
+    /// Reset the device and tell it that it has a driver
+    fn reset(&self) {
+        self.set_status(DeviceStatus::empty());
+        self.set_status(DeviceStatus::ACKNOWLEDGE);
+        self.set_status(DeviceStatus::ACKNOWLEDGE | DeviceStatus::DRIVER);
+    }
+
+    fn status(&self) -> DeviceStatus {
+        DeviceStatus::from_bits_truncate(unsafe {
+            PortReadOnly::<u8>::new(self.base + Self::DEVICE_STATUS).read()
+        })
+    }
+
+    fn set_status(&self, status: DeviceStatus) {
+        unsafe { PortWriteOnly::<u8>::new(self.base + Self::DEVICE_STATUS).write(status.bits()) };
+    }
+
+    /// Accept the features of `supported` the device offers, and return them
+    fn negotiate(&self, supported: u32) -> u32 {
+        let offered = unsafe { PortReadOnly::<u32>::new(self.base + Self::DEVICE_FEATURES).read() };
+        let accepted = offered & supported;
+        unsafe { PortWriteOnly::<u32>::new(self.base + Self::DRIVER_FEATURES).write(accepted) };
+        accepted
+    }
+
+    /// Allocate the queue numbered `index` and give it to the device,
+    /// `None` if the device has no such queue or no frames are left
+    fn setup_queue(&self, index: u16) -> Option<VirtQueue> {
+        let size = unsafe {
+            PortWriteOnly::<u16>::new(self.base + Self::QUEUE_SELECT).write(index);
+            PortReadOnly::<u16>::new(self.base + Self::QUEUE_SIZE).read()
+        };
+        if size == 0 {
+            return None;
+        }
+
+        let queue = VirtQueue::new(size)?;
+        let frame = queue.frame().start_address().as_u64() >> 12;
+        unsafe { PortWriteOnly::<u32>::new(self.base + Self::QUEUE_ADDRESS).write(frame as u32) };
+
+        Some(queue)
+    }
+
+    /// Tell the device the queue has new buffers
+    fn notify(&self, index: u16) {
+        unsafe { PortWriteOnly::<u16>::new(self.base + Self::QUEUE_NOTIFY).write(index) };
+    }
+
+    /// Read and clear the interrupt status, the line is deasserted by reading it
+    fn isr_status(&self) -> u8 {
+        unsafe { PortReadOnly::<u8>::new(self.base + Self::ISR_STATUS).read() }
+    }
+
+    /// Read a 32-bit field of the device specific configuration
+    fn config(&self, offset: u16) -> u32 {
+        unsafe { PortReadOnly::<u32>::new(self.base + Self::CONFIG + offset).read() }
+    }
+}
@@ -0,0 +1,136 @@
+//! Virtqueue
+//!
+//! The split virtqueue of the legacy interface, the descriptor table,
+//! the available ring and the used ring in physically contiguous frames,
+//! with the used ring on its own page.
+//!
+//! reference: https://docs.oasis-open.org/virtio/virtio/v1.1/cs01/virtio-v1.1-cs01.html#x1-240006
+
+use crate::memory::{get_frame_alloc_for_sure, physical_to_virtual, PAGE_SIZE};
+use core::sync::atomic::{fence, Ordering};
+use x86_64::structures::paging::PhysFrame;
+
+bitflags! {
+    /// The flags of a descriptor.
+    #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+    pub(super) struct DescriptorFlags: u16 {
+        /// The buffer continues in the descriptor at `next`.
+        const NEXT  = 0x1;
+        /// The buffer is written by the device (vs read).
+        const WRITE = 0x2;
+    }
+}
+
+const DESCRIPTOR_SIZE: usize = 16;
+
+/// The ring header, `flags` and `idx`
+const RING_HEADER: usize = 4;
+
+const USED_ELEMENT_SIZE: usize = 8;
+
+#[derive(Debug)]
+pub(super) struct VirtQueue {
+    size: u16,
+    frame: PhysFrame,
+    /// The virtual address of the first frame
+    base: u64,
+    /// The offset of the used ring, page aligned
+    used: usize,
+    /// The index of the next available entry
+    next_avail: u16,
+    /// The index of the next used entry to read
+    last_used: u16,
+}
+
+impl VirtQueue {
+    /// Allocate a queue of `size` entries, `None` if no frames are left
+    pub fn new(size: u16) -> Option<Self> {
+        let entries = size as usize;
+        let page = PAGE_SIZE as usize;
+        let used =
+            (entries * DESCRIPTOR_SIZE + RING_HEADER + entries * 2 + 2).next_multiple_of(page);
+        let total = used + (RING_HEADER + entries * USED_ELEMENT_SIZE + 2).next_multiple_of(page);
+
+        let frame = x86_64::instructions::interrupts::without_interrupts(|| {
+            get_frame_alloc_for_sure().allocate_contiguous(total / page)
+        })?;
+        let base = physical_to_virtual(frame.start_address().as_u64());
+        unsafe { core::ptr::write_bytes(base as *mut u8, 0, total) };
+
+        Some(Self {
+            size,
+            frame,
+            base,
+            used,
+            next_avail: 0,
+            last_used: 0,
+        })
+    }
+
+    pub fn size(&self) -> u16 {
+        self.size
+    }
+
+    /// The first frame of the queue, given to the device
+    pub fn frame(&self) -> PhysFrame {
+        self.frame
+    }
+
+    fn avail(&self) -> *mut u16 {
+        (self.base as usize + self.size as usize * DESCRIPTOR_SIZE) as *mut u16
+    }
+
+    fn used(&self) -> *mut u16 {
+        (self.base as usize + self.used) as *mut u16
+    }
+
+    /// Fill the descriptor at `index`, `next` is only read with `NEXT`
+    pub fn set_descriptor(
+        &mut self,
+        index: u16,
+        address: u64,
+        len: u32,
+        flags: DescriptorFlags,
+        next: u16,
+    ) {
+        let descriptor = (self.base as usize + index as usize * DESCRIPTOR_SIZE) as *mut u8;
+        unsafe {
+            (descriptor as *mut u64).write_volatile(address);
+            (descriptor.add(8) as *mut u32).write_volatile(len);
+            (descriptor.add(12) as *mut u16).write_volatile(flags.bits());
+            (descriptor.add(14) as *mut u16).write_volatile(next);
+        }
+    }
+
+    /// Make the chain starting at `head` available to the device
+    pub fn push(&mut self, head: u16) {
+        let avail = self.avail();
+        let slot = (self.next_avail % self.size) as usize;
+        self.next_avail = self.next_avail.wrapping_add(1);
+
+        unsafe {
+            avail.add(2 + slot).write_volatile(head);
+            // the entry must be seen before the index that publishes it
+            fence(Ordering::SeqCst);
+            avail.add(1).write_volatile(self.next_avail);
+        }
+        fence(Ordering::SeqCst);
+    }
+
+    /// The head of the next chain the device is done with, and the bytes it wrote
+    pub fn pop_used(&mut self) -> Option<(u16, u32)> {
+        let used = self.used();
+        let index = unsafe { used.add(1).read_volatile() };
+        if index == self.last_used {
+            return None;
+        }
+        fence(Ordering::SeqCst);
+
+        let slot = (self.last_used % self.size) as usize;
+        self.last_used = self.last_used.wrapping_add(1);
+
+        let element = unsafe { (used.add(2) as *mut u32).add(slot * 2) };
+        let (id, len) = unsafe { (element.read_volatile(), element.add(1).read_volatile()) };
+        Some((id as u16, len))
+    }
+}
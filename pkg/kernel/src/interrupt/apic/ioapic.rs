@@ -72,6 +72,13 @@ impl IoApic {
         trace!("Enable IOApic: IRQ={}, CPU={}", irq, cpuid);
     }
 
+    pub fn enable_level(&mut self, irq: u8, cpuid: u8) {
+        // Mark interrupt level-triggered, active high, for the
+        // lines shared by PCI functions, and routed to the given cpuid.
+        self.write_irq(irq, RedirectionEntry::LEVEL, cpuid);
+        trace!("Enable IOApic (level): IRQ={}, CPU={}", irq, cpuid);
+    }
+
     pub fn disable(&mut self, irq: u8, cpuid: u8) {
         self.write_irq(irq, RedirectionEntry::DISABLED, cpuid);
     }
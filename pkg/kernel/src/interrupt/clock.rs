@@ -9,10 +9,16 @@ pub unsafe fn register_idt(idt: &mut InterruptDescriptorTable) {
     idt[Interrupts::IrqBase as u8 + Irq::Timer as u8]
         .set_handler_fn(clock_handler)
         .set_stack_index(gdt::TIMER_IST_INDEX);
+    // raised with interrupts disabled, it never runs during the timer
+    idt[Interrupts::Reschedule as u8]
+        .set_handler_fn(reschedule_handler)
+        .set_stack_index(gdt::TIMER_IST_INDEX);
 }
 
 pub extern "C" fn clock(mut context: ProcessContext) {
     x86_64::instructions::interrupts::without_interrupts(|| {
+        inc_counter();
+        wake_expired(read_counter());
         switch(&mut context);
         super::ack();
     });
@@ -20,6 +26,15 @@ pub extern "C" fn clock(mut context: ProcessContext) {
 
 as_handler!(clock);
 
+/// Switch to the next process, without a tick
+pub extern "C" fn reschedule(mut context: ProcessContext) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        switch(&mut context);
+    });
+}
+
+as_handler!(reschedule);
+
 static COUNTER: AtomicU64 = AtomicU64::new(0);
 
 #[inline]
@@ -27,6 +27,8 @@ pub enum Interrupts {
 
     IrqBase = 0x20,
     Syscall = 0x80,
+    /// Switch out of a process blocked in the kernel
+    Reschedule = 0x81,
 }
 
 /// https://www.computerhope.com/jargon/i/irq.htm
@@ -41,8 +43,11 @@ pub enum Irq {
     Floppy = 6,
     Parallel = 7,
     RealTimeClock = 8,
+    Pci0 = 10,
+    Pci1 = 11,
     Ide0 = 14,
     Ide1 = 15,
     Error = 19,
+    Msi = 24,
     Spurious = 31,
 }
@@ -0,0 +1,21 @@
+use crate::ata;
+
+use super::consts::*;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame};
+
+pub unsafe fn register_idt(idt: &mut InterruptDescriptorTable) {
+    idt[Interrupts::IrqBase as u8 + Irq::Ide0 as u8].set_handler_fn(ide0_handler);
+    idt[Interrupts::IrqBase as u8 + Irq::Ide1 as u8].set_handler_fn(ide1_handler);
+}
+
+/// The primary bus finished a command, the process waiting for it is woken up
+pub extern "x86-interrupt" fn ide0_handler(_st: InterruptStackFrame) {
+    ata::acknowledge_irq(0);
+    super::ack();
+}
+
+/// The secondary bus finished a command
+pub extern "x86-interrupt" fn ide1_handler(_st: InterruptStackFrame) {
+    ata::acknowledge_irq(1);
+    super::ack();
+}
@@ -2,10 +2,14 @@ mod apic;
 mod clock;
 mod consts;
 mod exceptions;
+mod ide;
+mod keyboard;
+mod pci;
 mod serial;
 mod syscall;
 
 pub use clock::read_counter;
+pub use pci::{MSI_VECTOR, PCI_IRQS};
 
 use crate::{interrupt::consts::Irq, memory::physical_to_virtual};
 use apic::*;
@@ -18,6 +22,9 @@ lazy_static! {
             exceptions::register_idt(&mut idt);
             clock::register_idt(&mut idt);
             serial::register_idt(&mut idt);
+            keyboard::register_idt(&mut idt);
+            ide::register_idt(&mut idt);
+            pci::register_idt(&mut idt);
             syscall::register_idt(&mut idt);
         }
         idt
@@ -36,6 +43,11 @@ pub fn init() {
 
     // enable serial irq with IO APIC (use enable_irq)
     enable_irq(Irq::Serial0 as u8, 0);
+    // the keys typed in a graphical window come from the ps/2 keyboard
+    enable_irq(Irq::Keyboard as u8, 0);
+    // the process doing disk I/O is blocked until the bus interrupts
+    enable_irq(Irq::Ide0 as u8, 0);
+    enable_irq(Irq::Ide1 as u8, 0);
     info!("Interrupts Initialized.");
 }
 
@@ -45,6 +57,22 @@ pub fn enable_irq(irq: u8, cpuid: u8) {
     ioapic.enable(irq, cpuid);
 }
 
+/// Enable a level-triggered interrupt, for the lines shared by PCI functions
+#[inline(always)]
+pub fn enable_level_irq(irq: u8, cpuid: u8) {
+    let mut ioapic = unsafe { IoApic::new(physical_to_virtual(IOAPIC_ADDR)) };
+    ioapic.enable_level(irq, cpuid);
+}
+
+/// Switch to the next process from the kernel, once the current one
+/// blocked itself it runs again when woken up
+#[inline(always)]
+pub fn reschedule() {
+    unsafe {
+        core::arch::asm!("int {}", const consts::Interrupts::Reschedule as u8);
+    }
+}
+
 #[inline(always)]
 pub fn ack() {
     let mut lapic = unsafe { XApic::new(physical_to_virtual(LAPIC_ADDR)) };
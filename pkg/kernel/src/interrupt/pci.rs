@@ -0,0 +1,36 @@
+use crate::drivers::pci;
+
+use super::consts::*;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame};
+
+/// The lines the firmware routes the PCI interrupts to on QEMU
+pub const PCI_IRQS: [u8; 2] = [Irq::Pci0 as u8, Irq::Pci1 as u8];
+
+/// The vector of the message signaled interrupts, past the I/O APIC lines
+pub const MSI_VECTOR: u8 = Interrupts::IrqBase as u8 + Irq::Msi as u8;
+
+pub unsafe fn register_idt(idt: &mut InterruptDescriptorTable) {
+    idt[Interrupts::IrqBase as u8 + Irq::Pci0 as u8].set_handler_fn(pci0_handler);
+    idt[Interrupts::IrqBase as u8 + Irq::Pci1 as u8].set_handler_fn(pci1_handler);
+    idt[MSI_VECTOR].set_handler_fn(msi_handler);
+}
+
+/// A function on the first PCI line interrupted
+///
+/// The line is level-triggered, the drivers deassert it before the ack.
+pub extern "x86-interrupt" fn pci0_handler(_st: InterruptStackFrame) {
+    pci::handle_irq(Irq::Pci0 as u8);
+    super::ack();
+}
+
+/// A function on the second PCI line interrupted
+pub extern "x86-interrupt" fn pci1_handler(_st: InterruptStackFrame) {
+    pci::handle_irq(Irq::Pci1 as u8);
+    super::ack();
+}
+
+/// A function using MSI interrupted
+pub extern "x86-interrupt" fn msi_handler(_st: InterruptStackFrame) {
+    pci::handle_msi();
+    super::ack();
+}
@@ -1,4 +1,4 @@
-use crate::{input, serial::get_serial_for_sure};
+use crate::{serial::get_serial_for_sure, tty};
 
 use super::consts::*;
 use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame};
@@ -15,12 +15,12 @@ pub extern "x86-interrupt" fn serial_handler(_st: InterruptStackFrame) {
 /// Receive character from uart 16550
 /// Should be called on every interrupt
 fn receive() {
-    // receive character from uart 16550, put it into INPUT_BUFFER
+    // receive character from uart 16550, pass it to the terminal
     let mut serial = get_serial_for_sure();
     let data = serial.receive();
     drop(serial);
 
     if let Some(data) = data {
-        input::push_key(data);
+        tty::receive(data);
     }
 }
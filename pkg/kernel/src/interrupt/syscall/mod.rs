@@ -35,6 +35,9 @@ pub struct SyscallArgs {
     pub arg0: usize,
     pub arg1: usize,
     pub arg2: usize,
+    pub arg3: usize,
+    pub arg4: usize,
+    pub arg5: usize,
 }
 
 pub fn dispatcher(context: &mut ProcessContext) {
@@ -43,6 +46,9 @@ pub fn dispatcher(context: &mut ProcessContext) {
         context.regs.rdi,
         context.regs.rsi,
         context.regs.rdx,
+        context.regs.r10,
+        context.regs.r8,
+        context.regs.r9,
     );
     match args.syscall {
         // fd: arg0 as u8, buf: &[u8] (ptr: arg1 as *const u8, len: arg2)
@@ -56,8 +62,9 @@ pub fn dispatcher(context: &mut ProcessContext) {
         Syscall::GetPid => context.set_rax(sys_get_pid() as usize),
         // addr: arg0 as usize -> res: usize
         Syscall::Brk => context.set_rax(sys_brk(&args) as usize),
-        // path: &str (ptr: arg0 as *const u8, len: arg1) -> pid: u16
-        // spawn process from path
+        // path: &str (ptr: arg0 as *const u8, len: arg1),
+        // args: &str (ptr: arg2 as *const u8, len: arg3), null for none -> pid: u16
+        // spawn process from path, the args are its ARGS environment variable
         Syscall::Spawn => context.set_rax(sys_spawn_process(&args)),
         // ret: arg0 as isize
         // exit process with retcode
@@ -65,20 +72,40 @@ pub fn dispatcher(context: &mut ProcessContext) {
         // pid: arg0 as u16 -> status: isize
         // block itself and wait until the process exit and be woke up
         Syscall::WaitPid => sys_wait_pid(&args, context),
-        // path: &str (ptr: arg0 as *const u8, len: arg1) -> fd: u8
-        // open file and return fd
+        // path: &str (ptr: arg0 as *const u8, len: arg1), mode: arg2 as u8 -> fd: u8
+        // open, write, create or append to file and return fd
         Syscall::Open => context.set_rax(sys_open_file(&args)),
         // fd: arg0 as u8 -> ret: isize
         // close file by fd
         Syscall::Close => context.set_rax(sys_close_file(&args) as usize),
+        // fd: arg0 as u8, offset: arg1 as isize, whence: arg2 as u8 -> offset: isize
+        // reposition the offset of fd
+        Syscall::Lseek => context.set_rax(sys_lseek(&args) as usize),
+        // fd: arg0 as u8, op: arg1, termios: arg2 as *mut Termios -> ret: isize
+        // get or set the attributes of a terminal, op is TCGETATTR, TCSANOW or TCSAFLUSH
+        Syscall::TtyAttr => context.set_rax(sys_tty_attr(&args) as usize),
+        // path: &str (ptr: arg0 as *const u8, len: arg1) -> ret: isize
+        // create a directory
+        Syscall::Mkdir => context.set_rax(sys_mkdir(&args) as usize),
+        // path: &str (ptr: arg0 as *const u8, len: arg1),
+        // set: arg2 as u8, clear: arg3 as u8 -> ret: isize
+        // set and clear the attribute bits of the file at path
+        Syscall::Chmod => context.set_rax(sys_chmod(&args) as usize),
+        // path: &str (ptr: arg0 as *const u8, len: arg1), stat: arg2 as *mut Stat -> ret: isize
+        // get the status of the file at path
+        Syscall::FileStat => context.set_rax(sys_file_stat(&args) as usize),
+        // fd: arg0 as u8, stat: arg1 as *mut Stat -> ret: isize
+        // get the status of an open file
+        Syscall::Fstat => context.set_rax(sys_fstat(&args) as usize),
+        // fd: arg0 as u8, buf: &mut [Dirent] (ptr: arg1 as *mut Dirent, len: arg2)
+        // -> count: isize
+        // read the next entries of an open directory, 0 at the end
+        Syscall::GetDents => context.set_rax(sys_get_dents(&args) as usize),
 
         // None
         Syscall::Stat => sys_list_process(),
         // None
         Syscall::ListApp => sys_list_app(),
-        // path: &str (arg0 as *const u8, arg1 as len)
-        // list directory by path
-        Syscall::ListDir => sys_list_dir(&args),
         // layout: arg0 as *const Layout -> ptr: *mut u8
         Syscall::Allocate => context.set_rax(sys_allocate(&args)),
         // ptr: arg0 as *mut u8
@@ -92,18 +119,61 @@ pub fn dispatcher(context: &mut ProcessContext) {
         Syscall::Fork => sys_fork(context),
         // op: u8, key: u32, val: usize -> ret: any
         Syscall::Sem => sys_sem(&args, context),
+        // device: &str (arg0 as *const u8, arg1 as len),
+        // fs_type: &str (arg2 as *const u8, arg3 as len), with `,ro` to mount read-only
+        // path: &str (arg4 as *const u8, arg5 as len) -> ret: isize
+        // mount the device at path
+        Syscall::Mount => context.set_rax(sys_mount(&args) as usize),
+        // path: &str (arg0 as *const u8, arg1 as len) -> ret: isize
+        // unmount the file system at path
+        Syscall::Umount => context.set_rax(sys_umount(&args) as usize),
+        // None -> ret: isize
+        // write the cached changes of all file systems back to their devices
+        Syscall::Sync => context.set_rax(sys_sync() as usize),
+        // None
+        // list all mounted file systems
+        Syscall::ListMount => sys_list_mount(),
+        // op: arg0 as u8, device: &str (arg1 as *const u8, arg2 as len),
+        // slot: arg3, args: arg4, arg5 -> ret: isize
+        // print or edit the partition table of a disk
+        Syscall::Partition => context.set_rax(sys_partition(&args) as usize),
+        // device: &str (arg0 as *const u8, arg1 as len),
+        // label: &str (arg2 as *const u8, arg3 as len) -> ret: isize
+        // format the device as Fat16
+        Syscall::Mkfs => context.set_rax(sys_mkfs(&args) as usize),
+        // key: &str (arg0 as *const u8, arg1 as len),
+        // buf: &mut [u8] (arg2 as *mut u8, arg3 as len) -> len: isize
+        // get an environment variable of the current process, -1 if missing or too long
+        Syscall::GetEnv => context.set_rax(sys_get_env(&args) as usize),
+        // device: &str (arg0 as *const u8, arg1 as len), repair: arg2 as bool,
+        // buf: &mut [u8] (arg3 as *mut u8, arg4 as len), len: arg5 as *mut usize
+        // -> ret: isize, the number of problems found
+        // check and optionally repair the Fat16 volume on the device,
+        // the problems are written to buf one per line, len is set to the bytes written
+        Syscall::Fsck => context.set_rax(sys_fsck(&args) as usize),
         // Unknown
         Syscall::Unknown => warn!("Unhandled syscall: {:x?}", context.regs.rax),
     }
 }
 
 impl SyscallArgs {
-    pub fn new(syscall: Syscall, arg0: usize, arg1: usize, arg2: usize) -> Self {
+    pub fn new(
+        syscall: Syscall,
+        arg0: usize,
+        arg1: usize,
+        arg2: usize,
+        arg3: usize,
+        arg4: usize,
+        arg5: usize,
+    ) -> Self {
         Self {
             syscall,
             arg0,
             arg1,
             arg2,
+            arg3,
+            arg4,
+            arg5,
         }
     }
 }
@@ -112,11 +182,14 @@ impl core::fmt::Display for SyscallArgs {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         write!(
             f,
-            "SYSCALL: {:<10} (0x{:016x}, 0x{:016x}, 0x{:016x})",
+            "SYSCALL: {:<10} (0x{:016x}, 0x{:016x}, 0x{:016x}, 0x{:016x}, 0x{:016x}, 0x{:016x})",
             format!("{:?}", self.syscall),
             self.arg0,
             self.arg1,
-            self.arg2
+            self.arg2,
+            self.arg3,
+            self.arg4,
+            self.arg5
         )
     }
 }
@@ -2,9 +2,13 @@ use x86_64::VirtAddr;
 
 use super::SyscallArgs;
 use crate::proc::*;
+use crate::resource::{to_stat, OpenMode};
 use crate::runtime::get_uefi_runtime_for_sure;
-use crate::{filesystem, proc};
+use crate::{filesystem, proc, tty};
 use core::alloc::Layout;
+use storage::{FileAttributes, FileSystem, FsError, SeekFrom};
+use syscall_def::fs::{Dirent, Stat};
+use syscall_def::tty::*;
 
 pub fn sys_spawn_process(args: &SyscallArgs) -> usize {
     // get app by path
@@ -16,8 +20,10 @@ pub fn sys_spawn_process(args: &SyscallArgs) -> usize {
             args.arg1,
         ))
     };
+    // the arguments, if any, are passed in the environment
+    let spawn_args = (args.arg2 != 0).then(|| unsafe { user_str(args.arg2, args.arg3) });
     // spawn the process by name
-    let ret = proc::spawn(path);
+    let ret = proc::spawn(path, spawn_args);
     // handle spawn error, return 0 if failed
     if ret.is_none() {
         return 0;
@@ -53,17 +59,6 @@ pub fn sys_list_process() {
     proc::print_process_list();
 }
 
-pub fn sys_list_dir(args: &SyscallArgs) {
-    // get path by args
-    let path = unsafe {
-        core::str::from_utf8_unchecked(core::slice::from_raw_parts(
-            args.arg0 as *const u8,
-            args.arg1,
-        ))
-    };
-    filesystem::ls(path);
-}
-
 pub fn sys_wait_pid(args: &SyscallArgs, context: &mut ProcessContext) {
     let pid = ProcessId(args.arg0 as u16);
     wait_pid(pid, context);
@@ -150,10 +145,266 @@ pub fn sys_open_file(args: &SyscallArgs) -> usize {
             args.arg1,
         ))
     };
-    open_file(path) as usize
+    let mode = match args.arg2 as u8 {
+        0 => OpenMode::Read,
+        1 => OpenMode::Create,
+        2 => OpenMode::Append,
+        3 => OpenMode::Write,
+        _ => return u8::MAX as usize,
+    };
+    open_file(path, mode) as usize
 }
 
 pub fn sys_close_file(args: &SyscallArgs) -> bool {
     let fd = args.arg0 as u8;
     close_file(fd)
 }
+
+pub fn sys_lseek(args: &SyscallArgs) -> isize {
+    let fd = args.arg0 as u8;
+    let offset = args.arg1 as isize;
+    let pos = match args.arg2 as u8 {
+        0 => SeekFrom::Start(args.arg1),
+        1 => SeekFrom::Current(offset),
+        2 => SeekFrom::End(offset),
+        _ => return -1,
+    };
+    proc::seek(fd, pos)
+}
+
+pub fn sys_tty_attr(args: &SyscallArgs) -> isize {
+    if !proc::is_tty(args.arg0 as u8) {
+        return -1;
+    }
+
+    let termios = args.arg2 as *mut Termios;
+    match args.arg1 {
+        TCGETATTR => unsafe { *termios = tty::attributes() },
+        TCSANOW => tty::set_attributes(unsafe { *termios }, false),
+        TCSAFLUSH => tty::set_attributes(unsafe { *termios }, true),
+        _ => return -1,
+    }
+    0
+}
+
+/// Get a string passed by the user as a pointer and a length
+unsafe fn user_str<'a>(ptr: usize, len: usize) -> &'a str {
+    core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr as *const u8, len))
+}
+
+pub fn sys_mount(args: &SyscallArgs) -> isize {
+    let (device, fs_type, path) = unsafe {
+        (
+            user_str(args.arg0, args.arg1),
+            user_str(args.arg2, args.arg3),
+            user_str(args.arg4, args.arg5),
+        )
+    };
+
+    // options follow the type, e.g. `fat16,ro`
+    let mut options = fs_type.split(',');
+    let fs_type = options.next().unwrap_or_default();
+    let mut read_only = false;
+    for option in options {
+        match option {
+            "ro" => read_only = true,
+            "rw" => read_only = false,
+            _ => {
+                warn!("Unknown mount option: {}", option);
+                return -1;
+            }
+        }
+    }
+
+    match proc::blocking(|| filesystem::mount(device, fs_type, path, read_only)) {
+        Ok(()) => 0,
+        Err(err) => {
+            warn!("Failed to mount {} at {}: {:?}", device, path, err);
+            -1
+        }
+    }
+}
+
+pub fn sys_umount(args: &SyscallArgs) -> isize {
+    let path = unsafe { user_str(args.arg0, args.arg1) };
+
+    match proc::blocking(|| filesystem::umount(path)) {
+        Ok(()) => 0,
+        Err(err) => {
+            warn!("Failed to unmount {}: {:?}", path, err);
+            -1
+        }
+    }
+}
+
+pub fn sys_mkdir(args: &SyscallArgs) -> isize {
+    let path = unsafe { user_str(args.arg0, args.arg1) };
+
+    match proc::blocking(|| filesystem::get_rootfs().create_dir(path)) {
+        Ok(()) => 0,
+        Err(err) => {
+            warn!("Failed to create directory {}: {:?}", path, err);
+            -1
+        }
+    }
+}
+
+pub fn sys_chmod(args: &SyscallArgs) -> isize {
+    let path = unsafe { user_str(args.arg0, args.arg1) };
+    let set = FileAttributes::from_bits_truncate(args.arg2 as u8);
+    let clear = FileAttributes::from_bits_truncate(args.arg3 as u8);
+
+    let fs = filesystem::get_rootfs();
+    let result = proc::blocking(|| {
+        fs.metadata(path)
+            .and_then(|meta| fs.set_attributes(path, (meta.attributes | set) - clear))
+    });
+
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            warn!("Failed to change the attributes of {}: {:?}", path, err);
+            -1
+        }
+    }
+}
+
+pub fn sys_file_stat(args: &SyscallArgs) -> isize {
+    let path = unsafe { user_str(args.arg0, args.arg1) };
+
+    match proc::blocking(|| filesystem::get_rootfs().metadata(path)) {
+        Ok(meta) => {
+            unsafe { *(args.arg2 as *mut Stat) = to_stat(&meta) };
+            0
+        }
+        Err(err) => {
+            warn!("Failed to stat {}: {:?}", path, err);
+            -1
+        }
+    }
+}
+
+pub fn sys_fstat(args: &SyscallArgs) -> isize {
+    match proc::stat(args.arg0 as u8) {
+        Some(stat) => {
+            unsafe { *(args.arg1 as *mut Stat) = stat };
+            0
+        }
+        None => -1,
+    }
+}
+
+pub fn sys_get_dents(args: &SyscallArgs) -> isize {
+    let buf = unsafe { core::slice::from_raw_parts_mut(args.arg1 as *mut Dirent, args.arg2) };
+    proc::read_dir(args.arg0 as u8, buf)
+}
+
+pub fn sys_get_env(args: &SyscallArgs) -> isize {
+    let key = unsafe { user_str(args.arg0, args.arg1) };
+    let buf = unsafe { core::slice::from_raw_parts_mut(args.arg2 as *mut u8, args.arg3) };
+
+    match proc::env(key) {
+        Some(value) if value.len() <= buf.len() => {
+            buf[..value.len()].copy_from_slice(value.as_bytes());
+            value.len() as isize
+        }
+        _ => -1,
+    }
+}
+
+pub fn sys_sync() -> isize {
+    match proc::blocking(filesystem::sync) {
+        Ok(()) => 0,
+        Err(err) => {
+            warn!("Failed to sync the file systems: {:?}", err);
+            -1
+        }
+    }
+}
+
+pub fn sys_list_mount() {
+    proc::blocking(filesystem::list_mounts);
+}
+
+pub fn sys_partition(args: &SyscallArgs) -> isize {
+    let device = unsafe { user_str(args.arg1, args.arg2) };
+    let slot = args.arg3;
+    // the begin, size or type, only valid for the operations using them
+    let arg4 = u32::try_from(args.arg4);
+    let arg5 = u32::try_from(args.arg5);
+
+    let ret = proc::blocking(|| match (args.arg0, arg4, arg5) {
+        (0, _, _) => filesystem::print_partitions(device),
+        (1, Ok(begin), Ok(total)) => filesystem::edit_partitions(device, slot, |table| {
+            table.create(slot, filesystem::DEFAULT_PARTITION_TYPE, begin, total)
+        }),
+        (2, _, _) => filesystem::edit_partitions(device, slot, |table| table.delete(slot)),
+        (3, Ok(total), _) => {
+            filesystem::edit_partitions(device, slot, |table| table.resize(slot, total))
+        }
+        (4, Ok(kind), _) if kind <= u8::MAX as u32 => {
+            filesystem::edit_partitions(device, slot, |table| table.set_type(slot, kind as u8))
+        }
+        _ => Err(FsError::InvalidOperation),
+    });
+
+    match ret {
+        Ok(()) => 0,
+        Err(err) => {
+            warn!("Failed to edit the partitions of {}: {:?}", device, err);
+            -1
+        }
+    }
+}
+
+pub fn sys_mkfs(args: &SyscallArgs) -> isize {
+    let (device, label) = unsafe {
+        (
+            user_str(args.arg0, args.arg1),
+            user_str(args.arg2, args.arg3),
+        )
+    };
+
+    // the serial number is the current date and time, in the FAT encoding
+    let time = get_uefi_runtime_for_sure().get_time();
+    let date = ((time.year().saturating_sub(1980) as u32) << 9)
+        | ((time.month() as u32) << 5)
+        | (time.day() as u32);
+    let clock =
+        ((time.hour() as u32) << 11) | ((time.minute() as u32) << 5) | (time.second() as u32 / 2);
+    let volume_id = (date << 16) | clock;
+
+    match proc::blocking(|| filesystem::format_fat16(device, label, volume_id)) {
+        Ok(()) => 0,
+        Err(err) => {
+            warn!("Failed to format {}: {:?}", device, err);
+            -1
+        }
+    }
+}
+
+pub fn sys_fsck(args: &SyscallArgs) -> isize {
+    let device = unsafe { user_str(args.arg0, args.arg1) };
+    let buf = unsafe { core::slice::from_raw_parts_mut(args.arg3 as *mut u8, args.arg4) };
+
+    match proc::blocking(|| filesystem::check_fat16(device, args.arg2 != 0)) {
+        Ok(report) => {
+            // the problems one per line, as many as fit in the buffer
+            let mut len = 0;
+            for problem in &report.problems {
+                let line = alloc::format!("{}\n", problem);
+                let Some(dest) = buf.get_mut(len..len + line.len()) else {
+                    break;
+                };
+                dest.copy_from_slice(line.as_bytes());
+                len += line.len();
+            }
+            unsafe { *(args.arg5 as *mut usize) = len };
+            report.problems.len() as isize
+        }
+        Err(err) => {
+            warn!("Failed to check {}: {:?}", device, err);
+            -1
+        }
+    }
+}
@@ -40,13 +40,19 @@ pub fn init(boot_info: &'static BootInfo) {
     logger::init(boot_info.log_level); // init logger syste
     runtime::init(boot_info); // init runtime system
     memory::address::init(boot_info);
+    framebuffer::init(boot_info); // init framebuffer console
     memory::gdt::init(); // init gdt
     memory::allocator::init(); // init kernel heap allocator
     proc::init(boot_info);
     memory::init(boot_info); // init memory manager
+    ramdisk::init(boot_info.ramdisk_size); // init ram disk
+    tty::init(); // init terminal
+    keyboard::init(); // init ps/2 keyboard
     interrupt::init(); // init interrupts
 
     x86_64::instructions::interrupts::enable();
+    pci::init(); // enumerate the pci bus
+    ata::init(); // probe the ata drives
     filesystem::init(); // init filesystem
 
     info!("Test stack grow.");
@@ -59,6 +65,9 @@ pub fn init(boot_info: &'static BootInfo) {
 
 pub fn shutdown(boot_info: &'static BootInfo) -> ! {
     info!("YatSenOS shutting down.");
+    if let Err(err) = filesystem::sync() {
+        warn!("Failed to sync the file systems: {:?}", err);
+    }
     unsafe {
         boot_info.system_table.runtime_services().reset(
             boot::ResetType::SHUTDOWN,
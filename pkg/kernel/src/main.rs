@@ -16,5 +16,8 @@ pub fn spawn_init() -> proc::ProcessId {
     // NOTE: you may want to clear the screen before starting the shell
     print!("\x1b[1;1H\x1b[2J");
     // proc::list_app();
-    proc::spawn("app/sh").unwrap()
+    let pid = proc::spawn("app/sh", None).unwrap();
+    // the shell leads the terminal, ^C and ^Z go to the programs it waits for
+    tty::set_session(pid);
+    pid
 }
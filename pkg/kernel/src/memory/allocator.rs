@@ -1,3 +1,4 @@
+use core::alloc::{GlobalAlloc, Layout};
 use linked_list_allocator::LockedHeap;
 use x86_64::VirtAddr;
 
@@ -5,7 +6,23 @@ pub const HEAP_SIZE: usize = 8 * 1024 * 1024; // 8 MiB
 
 /// Use linked_list_allocator for kernel heap
 #[global_allocator]
-pub static ALLOCATOR: LockedHeap = LockedHeap::empty();
+pub static ALLOCATOR: KernelHeap = KernelHeap(LockedHeap::empty());
+
+/// The kernel heap, locked with interrupts disabled
+///
+/// The kernel runs with interrupts enabled while it waits for the disk,
+/// and the interrupt handlers allocate too (e.g. the ready queue).
+pub struct KernelHeap(LockedHeap);
+
+unsafe impl GlobalAlloc for KernelHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        x86_64::instructions::interrupts::without_interrupts(|| self.0.alloc(layout))
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        x86_64::instructions::interrupts::without_interrupts(|| self.0.dealloc(ptr, layout))
+    }
+}
 
 pub fn init() {
     // static buffer for kernel heap
@@ -16,7 +33,7 @@ pub fn init() {
     let heap_end = heap_start + HEAP_SIZE as u64;
 
     unsafe {
-        ALLOCATOR.lock().init(HEAP.as_mut_ptr(), HEAP_SIZE);
+        ALLOCATOR.0.lock().init(HEAP.as_mut_ptr(), HEAP_SIZE);
     }
 
     debug!(
@@ -32,6 +49,6 @@ pub fn init() {
 }
 
 #[alloc_error_handler]
-fn alloc_error_handler(layout: alloc::alloc::Layout) -> ! {
+fn alloc_error_handler(layout: Layout) -> ! {
     panic!("Allocation error: {:?}", layout);
 }
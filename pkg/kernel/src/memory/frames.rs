@@ -45,6 +45,29 @@ impl BootInfoFrameAllocator {
     pub fn frames_recycled(&self) -> usize {
         self.recycled.len()
     }
+
+    /// Allocate `count` physically contiguous frames and return the first one,
+    /// for the devices reading a structure larger than a frame
+    ///
+    /// The recycled frames are not used, the frames skipped over are recycled.
+    pub fn allocate_contiguous(&mut self, count: usize) -> Option<PhysFrame> {
+        let mut run: Vec<PhysFrame> = Vec::with_capacity(count);
+
+        while run.len() < count {
+            let Some(frame) = self.frames.next() else {
+                self.recycled.append(&mut run);
+                return None;
+            };
+            self.used += 1;
+
+            if run.last().is_some_and(|last| *last + 1 != frame) {
+                self.recycled.append(&mut run);
+            }
+            run.push(frame);
+        }
+
+        run.first().copied()
+    }
 }
 
 unsafe impl FrameAllocator<Size4KiB> for BootInfoFrameAllocator {
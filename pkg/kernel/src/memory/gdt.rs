@@ -1,3 +1,4 @@
+use core::ptr::{addr_of, addr_of_mut};
 use lazy_static::lazy_static;
 use x86_64::registers::segmentation::Segment;
 use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector};
@@ -11,80 +12,83 @@ pub const SYSCALL_IST_INDEX: u16 = 3;
 
 pub const IST_SIZES: [usize; 5] = [0x1000, 0x1000, 0x1000, 0x1000, 0x1000];
 
-lazy_static! {
-    static ref TSS: TaskStateSegment = {
-        let mut tss = TaskStateSegment::new();
-
-        // initialize the TSS with the static buffers
-        // will be allocated on the bss section when the kernel is load
-        tss.privilege_stack_table[0] = {
-            const STACK_SIZE: usize = IST_SIZES[0];
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-            let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
-            let stack_end = stack_start + STACK_SIZE as u64;
-            info!(
-                "Privilege Stack  : 0x{:016x}-0x{:016x}",
-                stack_start.as_u64(),
-                stack_end.as_u64()
-            );
-            stack_end
-        };
-
-        // fill tss.interrupt_stack_table with the static stack buffers
-        tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = {
-            const STACK_SIZE: usize = IST_SIZES[1];
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-            let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
-            let stack_end = stack_start + STACK_SIZE as u64;
-            info!(
-                "Double Fault IST : 0x{:016x}-0x{:016x}",
-                stack_start.as_u64(),
-                stack_end.as_u64()
-            );
-            stack_end
-        };
-
-        tss.interrupt_stack_table[PAGE_FAULT_IST_INDEX as usize] = {
-            const STACK_SIZE: usize = IST_SIZES[2];
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-            let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
-            let stack_end = stack_start + STACK_SIZE as u64;
-            info!(
-                "Page Fault IST   : 0x{:016x}-0x{:016x}",
-                stack_start.as_u64(),
-                stack_end.as_u64()
-            );
-            stack_end
-        };
-
-        tss.interrupt_stack_table[TIMER_IST_INDEX as usize] = {
-            const STACK_SIZE: usize = IST_SIZES[3];
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-            let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
-            let stack_end = stack_start + STACK_SIZE as u64;
-            info!(
-                "Timer IST        : 0x{:016x}-0x{:016x}",
-                stack_start.as_u64(),
-                stack_end.as_u64()
-            );
-            stack_end
-        };
-
-        tss.interrupt_stack_table[SYSCALL_IST_INDEX as usize] = {
-            const STACK_SIZE: usize = IST_SIZES[4];
-            static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
-            let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
-            let stack_end = stack_start + STACK_SIZE as u64;
-            info!(
-                "Syscall IST      : 0x{:016x}-0x{:016x}",
-                stack_start.as_u64(),
-                stack_end.as_u64()
-            );
-            stack_end
-        };
-
-        tss
+/// Only written through `addr_of_mut!`, the CPU reads it on interrupts
+static mut TSS: TaskStateSegment = TaskStateSegment::new();
+
+/// Fill the TSS with the static stacks, before the GDT refers to it
+fn init_tss() {
+    let mut tss = TaskStateSegment::new();
+
+    // initialize the TSS with the static buffers
+    // will be allocated on the bss section when the kernel is load
+    tss.privilege_stack_table[0] = {
+        const STACK_SIZE: usize = IST_SIZES[0];
+        static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+        let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
+        let stack_end = stack_start + STACK_SIZE as u64;
+        info!(
+            "Privilege Stack  : 0x{:016x}-0x{:016x}",
+            stack_start.as_u64(),
+            stack_end.as_u64()
+        );
+        stack_end
+    };
+
+    // fill tss.interrupt_stack_table with the static stack buffers
+    tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] = {
+        const STACK_SIZE: usize = IST_SIZES[1];
+        static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+        let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
+        let stack_end = stack_start + STACK_SIZE as u64;
+        info!(
+            "Double Fault IST : 0x{:016x}-0x{:016x}",
+            stack_start.as_u64(),
+            stack_end.as_u64()
+        );
+        stack_end
+    };
+
+    tss.interrupt_stack_table[PAGE_FAULT_IST_INDEX as usize] = {
+        const STACK_SIZE: usize = IST_SIZES[2];
+        static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+        let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
+        let stack_end = stack_start + STACK_SIZE as u64;
+        info!(
+            "Page Fault IST   : 0x{:016x}-0x{:016x}",
+            stack_start.as_u64(),
+            stack_end.as_u64()
+        );
+        stack_end
+    };
+
+    tss.interrupt_stack_table[TIMER_IST_INDEX as usize] = {
+        const STACK_SIZE: usize = IST_SIZES[3];
+        static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+        let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
+        let stack_end = stack_start + STACK_SIZE as u64;
+        info!(
+            "Timer IST        : 0x{:016x}-0x{:016x}",
+            stack_start.as_u64(),
+            stack_end.as_u64()
+        );
+        stack_end
     };
+
+    tss.interrupt_stack_table[SYSCALL_IST_INDEX as usize] = {
+        const STACK_SIZE: usize = IST_SIZES[4];
+        static mut STACK: [u8; STACK_SIZE] = [0; STACK_SIZE];
+        let stack_start = VirtAddr::from_ptr(unsafe { STACK.as_ptr() });
+        let stack_end = stack_start + STACK_SIZE as u64;
+        info!(
+            "Syscall IST      : 0x{:016x}-0x{:016x}",
+            stack_start.as_u64(),
+            stack_end.as_u64()
+        );
+        stack_end
+    };
+
+    // SAFETY: called once at boot, before the TSS is loaded
+    unsafe { addr_of_mut!(TSS).write(tss) };
 }
 
 lazy_static! {
@@ -92,7 +96,8 @@ lazy_static! {
         let mut gdt = GlobalDescriptorTable::new();
         let code_selector = gdt.append(Descriptor::kernel_code_segment());
         let data_selector = gdt.append(Descriptor::kernel_data_segment());
-        let tss_selector = gdt.append(Descriptor::tss_segment(&TSS));
+        // SAFETY: the TSS is set up by `init` first, and not moved
+        let tss_selector = gdt.append(Descriptor::tss_segment(unsafe { &*addr_of!(TSS) }));
         let user_code_selector = gdt.append(Descriptor::user_code_segment());
         let user_data_selector = gdt.append(Descriptor::user_data_segment());
         (
@@ -127,6 +132,7 @@ pub fn init() {
     use x86_64::instructions::tables::load_tss;
     use x86_64::PrivilegeLevel;
 
+    init_tss();
     GDT.0.load();
     unsafe {
         CS::set_reg(GDT.1.code_selector);
@@ -150,6 +156,18 @@ pub fn init() {
     info!("GDT Initialized.");
 }
 
+/// Set the stack used by the syscall handler
+///
+/// Each process has its own, as a process can be switched out in a syscall
+/// while it waits for the disk.
+pub fn set_syscall_stack(stack_end: VirtAddr) {
+    // SAFETY: the TSS is only read by the CPU on an interrupt,
+    // and this is called with interrupts disabled
+    unsafe {
+        (*addr_of_mut!(TSS)).interrupt_stack_table[SYSCALL_IST_INDEX as usize] = stack_end;
+    }
+}
+
 pub fn get_selector() -> &'static KernelSelectors {
     &GDT.1
 }
@@ -2,7 +2,7 @@ use volatile::{access::ReadOnly, VolatileRef};
 use x86_64::{
     registers::rflags::RFlags,
     structures::{gdt::SegmentSelector, idt::InterruptStackFrameValue},
-    VirtAddr,
+    PrivilegeLevel, VirtAddr,
 };
 
 use crate::{memory::gdt::get_user_selector, RegistersValue};
@@ -50,6 +50,11 @@ impl ProcessContext {
         &self.value.stack_frame
     }
 
+    /// The process was interrupted in user mode, not in a syscall
+    pub fn is_user_mode(&self) -> bool {
+        self.value.stack_frame.code_segment.rpl() == PrivilegeLevel::Ring3
+    }
+
     pub fn init_stack_frame(&mut self, entry: VirtAddr, stack_top: VirtAddr) {
         self.value.stack_frame.stack_pointer = stack_top;
         self.value.stack_frame.instruction_pointer = entry;
@@ -1,8 +1,9 @@
 use alloc::{collections::BTreeMap, string::String, sync::Arc};
 use spin::RwLock;
-use storage::FileSystem;
+use storage::{FileSystem, FsError, SeekFrom};
+use syscall_def::fs::{Dirent, Stat};
 
-use crate::{filesystem::get_rootfs, resource::*};
+use crate::{devfs::DevFs, filesystem::get_rootfs, resource::*, tty};
 
 use super::*;
 use sync::SemaphoreSet;
@@ -47,6 +48,10 @@ impl ProcessData {
     }
 
     pub fn read(&self, fd: u8, buf: &mut [u8]) -> isize {
+        // the terminal is waited for out of the locks of the resources
+        if self.resources.read().reads_tty(fd) {
+            return tty::read(buf).map_or(-1, |count| count as isize);
+        }
         self.resources.read().read(fd, buf)
     }
 
@@ -54,6 +59,10 @@ impl ProcessData {
         self.resources.read().write(fd, buf)
     }
 
+    pub fn seek(&self, fd: u8, pos: SeekFrom) -> isize {
+        self.resources.read().seek(fd, pos)
+    }
+
     pub fn sem_wait(&self, key: u32, pid: ProcessId) -> SemaphoreResult {
         self.semaphores.write().wait(key, pid)
     }
@@ -70,12 +79,71 @@ impl ProcessData {
         self.semaphores.write().remove(key)
     }
 
-    pub fn open_file(&self, path: &str) -> u8 {
-        let handle = get_rootfs().open_file(path).unwrap();
-        self.resources.write().open(Resource::File(handle))
+    /// Open the file and return its fd, `u8::MAX` if it can not be opened
+    ///
+    /// Directories can be opened for reading, to list them with `read_dir`.
+    pub fn open_file(&self, path: &str, mode: OpenMode) -> u8 {
+        match open_path(path, mode) {
+            Ok(res) => self.resources.write().open(res),
+            Err(err) => {
+                warn!("Failed to open {}: {:?}", path, err);
+                u8::MAX
+            }
+        }
     }
 
     pub fn close_file(&self, fd: u8) -> bool {
         self.resources.write().close(fd)
     }
+
+    pub fn stat(&self, fd: u8) -> Option<Stat> {
+        self.resources.read().stat(fd)
+    }
+
+    pub fn read_dir(&self, fd: u8, buf: &mut [Dirent]) -> isize {
+        self.resources.read().read_dir(fd, buf)
+    }
+
+    pub fn is_tty(&self, fd: u8) -> bool {
+        self.resources.read().is_tty(fd)
+    }
+}
+
+/// Open a file or directory of the root file system
+///
+/// The nodes of a device file system are opened by it, as devices.
+fn open_path(path: &str, mode: OpenMode) -> storage::Result<Resource> {
+    let fs = get_rootfs();
+    if let Ok((mount, path)) = fs.resolve(path) {
+        if let Some(devfs) = mount.fs.as_any().and_then(|fs| fs.downcast_ref::<DevFs>()) {
+            return devfs.open(mount.trim_mount_point(&path), mode);
+        }
+    }
+
+    Ok(match mode {
+        OpenMode::Read => {
+            let meta = fs.metadata(path)?;
+            if meta.is_dir() {
+                Resource::Dir(DirHandle::new(meta, fs.read_dir(path)?.collect()))
+            } else {
+                Resource::File(fs.open_file(path)?, mode)
+            }
+        }
+        OpenMode::Write => {
+            let meta = fs.metadata(path)?;
+            if meta.is_dir() {
+                return Err(FsError::NotAFile);
+            }
+            if meta.is_read_only() {
+                return Err(FsError::ReadOnly);
+            }
+
+            // an append handle moved to the start keeps the content
+            let mut file = fs.append_file(path)?;
+            file.seek(SeekFrom::Start(0))?;
+            Resource::File(file, mode)
+        }
+        OpenMode::Create => Resource::File(fs.create_file(path)?, mode),
+        OpenMode::Append => Resource::File(fs.append_file(path)?, mode),
+    })
 }
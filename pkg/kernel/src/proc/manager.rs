@@ -72,10 +72,20 @@ impl ProcessManager {
     }
 
     #[inline]
-    fn get_proc(&self, pid: &ProcessId) -> Option<Arc<Process>> {
+    pub(super) fn get_proc(&self, pid: &ProcessId) -> Option<Arc<Process>> {
         self.processes.read().get(pid).cloned()
     }
 
+    /// The processes that are not dead, by pid
+    pub fn alive_pids(&self) -> Vec<ProcessId> {
+        self.processes
+            .read()
+            .iter()
+            .filter(|(_, p)| p.read().status() != ProgramStatus::Dead)
+            .map(|(pid, _)| *pid)
+            .collect()
+    }
+
     #[inline]
     pub fn block_proc(&self, pid: &ProcessId) {
         self.get_proc(pid).unwrap().write().block();
@@ -90,17 +100,38 @@ impl ProcessManager {
         self.push_ready(pid);
     }
 
+    /// Wake up the process blocked waiting in the kernel, not a stopped
+    /// or dead one
+    pub fn wake_blocked(&self, pid: ProcessId) {
+        let Some(proc) = self.get_proc(&pid) else {
+            return;
+        };
+
+        let mut inner = proc.write();
+        if inner.status() == ProgramStatus::Blocked && !inner.is_stopped() {
+            inner.pause();
+            drop(inner);
+            self.push_ready(pid);
+        }
+    }
+
     pub fn wake_waiting(&self, ret: isize) {
-        let now_pid = get_pid();
+        self.wake_waiters(get_pid(), ret);
+    }
+
+    /// Wake up the processes waiting for `pid`, with its exit code
+    pub fn wake_waiters(&self, pid: ProcessId, ret: isize) {
         let mut wait_proc = self.waiting_processes.lock();
-        if let Some(wait_set) = wait_proc.remove(&now_pid) {
-            for pid in wait_set {
-                self.get_proc(&pid)
+        if let Some(wait_set) = wait_proc.remove(&pid) {
+            for waiter in wait_set {
+                self.get_proc(&waiter)
                     .unwrap()
                     .write()
                     .context()
                     .set_rax(ret as usize);
-                self.wake_up(pid);
+                self.wake_up(waiter);
+                // the terminal goes back to the process that waited
+                crate::tty::pass_foreground(pid, waiter);
             }
         }
     }
@@ -215,6 +246,64 @@ impl ProcessManager {
         proc.kill(ret);
     }
 
+    /// Send the signal to the process
+    ///
+    /// A stopped process is continued or killed right away. The others act
+    /// on the signal once switched out by the timer in user mode, out of
+    /// any syscall and its locks.
+    pub fn signal(&self, pid: ProcessId, signal: Signal) {
+        let Some(proc) = self.get_proc(&pid) else {
+            return;
+        };
+
+        let (status, stopped) = {
+            let inner = proc.read();
+            (inner.status(), inner.is_stopped())
+        };
+        if status == ProgramStatus::Dead {
+            return;
+        }
+
+        trace!("Signal {:?} to Process {:?}", signal, pid);
+
+        match (signal, stopped) {
+            (Signal::Continue, true) => {
+                proc.write().cont();
+                self.wake_up(pid);
+            }
+            (Signal::Continue, false) => proc.write().cancel_suspend(),
+            (Signal::Interrupt, true) => {
+                self.wake_waiters(pid, INTERRUPTED);
+                self.kill(pid, INTERRUPTED);
+            }
+            (signal, _) => proc.write().set_signal(signal),
+        }
+    }
+
+    /// Act on the signal of the current process switched out by the timer,
+    /// returns true if it can still run
+    pub fn handle_signal(&self, context: &ProcessContext) -> bool {
+        // in a syscall, it may hold locks or wait for the disk
+        if !context.is_user_mode() {
+            return true;
+        }
+
+        let proc = self.current();
+        let signal = proc.write().take_signal();
+        match signal {
+            Some(Signal::Interrupt) => {
+                self.wake_waiting(INTERRUPTED);
+                self.kill_self(INTERRUPTED);
+                false
+            }
+            Some(Signal::Suspend) => {
+                proc.write().stop();
+                false
+            }
+            _ => true,
+        }
+    }
+
     pub fn print_process_list(&self) {
         let mut output =
             String::from("  PID | PPID | Process Name |  Ticks  |   Memory  | Status\n");
@@ -275,20 +364,12 @@ impl ProcessManager {
         child
     }
 
-    pub fn read(&self, fd: u8, buf: &mut [u8]) -> isize {
-        self.current().read().read(fd, buf)
-    }
-
-    pub fn write(&self, fd: u8, buf: &[u8]) -> isize {
-        self.current().write().write(fd, buf)
-    }
-
-    pub fn open_file(&self, path: &str) -> u8 {
-        self.current().write().open_file(path)
-    }
-
-    pub fn close_file(&self, fd: u8) -> bool {
-        self.current().write().close_file(fd)
+    /// The data of the current process, to do its I/O without its lock
+    ///
+    /// The timer switches processes with the lock of the current one,
+    /// it can not be held while the process waits for the disk.
+    pub fn current_data(&self) -> ProcessData {
+        ProcessData::clone(&self.current().read())
     }
 
     pub fn brk(&self, addr: Option<VirtAddr>) -> Option<VirtAddr> {
@@ -5,16 +5,19 @@ mod paging;
 mod pid;
 mod process;
 mod processor;
+mod procfs;
 mod sync;
 mod vm;
+mod wait;
 
 use crate::filesystem::get_rootfs;
 use crate::proc::vm::ProcessVm;
+use crate::resource::OpenMode;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 pub use manager::*;
 use process::*;
-use storage::FileSystem;
+use storage::{FileSystem, SeekFrom};
 use xmas_elf::ElfFile;
 
 use alloc::string::{String, ToString};
@@ -22,6 +25,8 @@ pub use context::ProcessContext;
 pub use data::ProcessData;
 pub use paging::PageTableContext;
 pub use pid::ProcessId;
+pub use procfs::{ProcFs, PROCFS_PATH};
+pub use wait::{wake_expired, WaitQueue};
 
 use x86_64::structures::idt::PageFaultErrorCode;
 use x86_64::VirtAddr;
@@ -40,6 +45,21 @@ pub enum ProgramStatus {
     Dead,
 }
 
+/// The signals sent to a process by the terminal
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Signal {
+    /// Kill the process, `^C`
+    Interrupt,
+    /// Stop the process until it is continued, `^Z`
+    Suspend,
+    /// Run the stopped process again
+    Continue,
+}
+
+/// The exit code of a process killed by `Signal::Interrupt`, as shells
+/// report it
+pub const INTERRUPTED: isize = 130;
+
 /// init process manager
 pub fn init(boot_info: &'static boot::BootInfo) {
     let proc_vm = ProcessVm::new(PageTableContext::new()).init_kernel_vm(&boot_info.kernel_pages);
@@ -61,11 +81,29 @@ pub fn switch(context: &mut ProcessContext) {
         // switch to the next process
         let manager = get_process_manager();
         let pid = manager.save_current(context);
-        manager.push_ready(pid);
+        // a process blocked in the kernel is pushed back once woken up
+        if manager.handle_signal(context) && manager.current().read().is_ready() {
+            manager.push_ready(pid);
+        }
         manager.switch_next(context);
     });
 }
 
+/// Send the signal to the process, see `ProcessManager::signal`
+pub fn signal(pid: ProcessId, signal: Signal) {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        get_process_manager().signal(pid, signal)
+    })
+}
+
+/// The current process has a signal to act on, a syscall waiting for
+/// input returns early for it
+pub fn signal_pending() -> bool {
+    x86_64::instructions::interrupts::without_interrupts(|| {
+        get_process_manager().current().read().signal().is_some()
+    })
+}
+
 pub fn print_process_list() {
     x86_64::instructions::interrupts::without_interrupts(|| {
         get_process_manager().print_process_list();
@@ -129,23 +167,29 @@ pub fn list_app() {
 //     elf_spawn(name.to_string(), &app.elf)
 // }
 
-pub fn spawn(path: &str) -> Option<ProcessId> {
+/// Spawn the app at the path, with the arguments in its environment
+pub fn spawn(path: &str, args: Option<&str>) -> Option<ProcessId> {
     let name: Vec<&str> = path.rsplit('/').collect();
-    let mut handle = get_rootfs().open_file(path).expect("Cannot open file");
     let mut buf = Vec::new();
-    let elf = {
+    blocking(|| {
+        let mut handle = get_rootfs().open_file(path).expect("Cannot open file");
         handle.read_all(&mut buf).expect("");
-        ElfFile::new(buf.as_slice()).unwrap()
-    };
-    elf_spawn(name[0].to_string(), &elf)
+    });
+    let elf = ElfFile::new(buf.as_slice()).unwrap();
+
+    let mut proc_data = ProcessData::new();
+    if let Some(args) = args {
+        proc_data.set_env(syscall_def::ARGS_ENV, args);
+    }
+    elf_spawn(name[0].to_string(), &elf, proc_data)
 }
 
-pub fn elf_spawn(name: String, elf: &ElfFile) -> Option<ProcessId> {
+pub fn elf_spawn(name: String, elf: &ElfFile, proc_data: ProcessData) -> Option<ProcessId> {
     let pid = x86_64::instructions::interrupts::without_interrupts(|| {
         let manager = get_process_manager();
         let process_name = name.to_lowercase();
         let parent = Arc::downgrade(&manager.current());
-        let pid = manager.spawn(elf, name, Some(parent), None);
+        let pid = manager.spawn(elf, name, Some(parent), Some(proc_data));
 
         debug!("Spawned process: {}#{}", process_name, pid);
         pid
@@ -154,12 +198,41 @@ pub fn elf_spawn(name: String, elf: &ElfFile) -> Option<ProcessId> {
     Some(pid)
 }
 
+/// Run `f` with interrupts enabled, for the I/O that waits for the disk
+///
+/// The process waits in the disk driver and the others keep running,
+/// so `f` must not hold a lock also taken with interrupts disabled
+/// (e.g. the process manager or a process).
+pub fn blocking<R>(f: impl FnOnce() -> R) -> R {
+    use x86_64::instructions::interrupts;
+
+    let enabled = interrupts::are_enabled();
+    interrupts::enable();
+    let ret = f();
+    if !enabled {
+        interrupts::disable();
+    }
+    ret
+}
+
+/// The data of the current process, out of the process manager
+fn current_data() -> ProcessData {
+    x86_64::instructions::interrupts::without_interrupts(|| get_process_manager().current_data())
+}
+
 pub fn read(fd: u8, buf: &mut [u8]) -> isize {
-    x86_64::instructions::interrupts::without_interrupts(|| get_process_manager().read(fd, buf))
+    let data = current_data();
+    blocking(|| data.read(fd, buf))
 }
 
 pub fn write(fd: u8, buf: &[u8]) -> isize {
-    x86_64::instructions::interrupts::without_interrupts(|| get_process_manager().write(fd, buf))
+    let data = current_data();
+    blocking(|| data.write(fd, buf))
+}
+
+pub fn seek(fd: u8, pos: SeekFrom) -> isize {
+    let data = current_data();
+    blocking(|| data.seek(fd, pos))
 }
 
 pub fn fork(context: &mut ProcessContext) {
@@ -209,6 +282,8 @@ pub fn wait_pid(pid: ProcessId, context: &mut ProcessContext) {
             manager.save_current(context);
             manager.block_proc(&now_pid);
             manager.add_waiting(pid);
+            // the terminal goes to the process waited for
+            crate::tty::pass_foreground(now_pid, pid);
             manager.switch_next(context);
         } else {
             let exit_code = get_process_manager().get_exit_code(pid).unwrap();
@@ -280,10 +355,27 @@ pub fn remove_sem(key: u32) -> usize {
     })
 }
 
-pub fn open_file(path: &str) -> u8 {
-    x86_64::instructions::interrupts::without_interrupts(|| get_process_manager().open_file(path))
+pub fn open_file(path: &str, mode: OpenMode) -> u8 {
+    let data = current_data();
+    blocking(|| data.open_file(path, mode))
 }
 
 pub fn close_file(fd: u8) -> bool {
-    x86_64::instructions::interrupts::without_interrupts(|| get_process_manager().close_file(fd))
+    let data = current_data();
+    blocking(|| data.close_file(fd))
+}
+
+pub fn stat(fd: u8) -> Option<syscall_def::fs::Stat> {
+    let data = current_data();
+    blocking(|| data.stat(fd))
+}
+
+pub fn read_dir(fd: u8, buf: &mut [syscall_def::fs::Dirent]) -> isize {
+    let data = current_data();
+    blocking(|| data.read_dir(fd, buf))
+}
+
+pub fn is_tty(fd: u8) -> bool {
+    let data = current_data();
+    blocking(|| data.is_tty(fd))
 }
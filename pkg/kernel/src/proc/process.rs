@@ -3,9 +3,12 @@ use super::*;
 use crate::humanized_size;
 use crate::memory::*;
 use crate::proc::paging::PageTableContext;
+use alloc::boxed::Box;
+use alloc::format;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::sync::Weak;
+use alloc::vec;
 use alloc::vec::Vec;
 use spin::*;
 use vm::*;
@@ -27,6 +30,32 @@ pub struct ProcessInner {
     context: ProcessContext,
     proc_data: Option<ProcessData>,
     proc_vm: Option<ProcessVm>,
+    kernel_stack: KernelStack,
+    /// The signal to act on once the process is out of its syscalls
+    signal: Option<Signal>,
+    /// Stopped by `Signal::Suspend`, until continued
+    stopped: bool,
+}
+
+/// The size of the stack a process runs its syscalls on
+const KERNEL_STACK_SIZE: usize = 0x4000;
+
+/// The stack a process runs its syscalls on
+///
+/// A process waiting for the disk is switched out in the middle of its
+/// syscall, so the syscalls can not share a single stack.
+struct KernelStack(Box<[u8]>);
+
+impl KernelStack {
+    fn new() -> Self {
+        Self(vec![0; KERNEL_STACK_SIZE].into_boxed_slice())
+    }
+
+    /// The end of the stack, aligned to 16 bytes
+    fn end(&self) -> VirtAddr {
+        let start = VirtAddr::from_ptr(self.0.as_ptr());
+        (start + self.0.len() as u64).align_down(16u64)
+    }
 }
 
 impl Process {
@@ -67,6 +96,9 @@ impl Process {
             children: Vec::new(),
             proc_vm: Some(proc_vm),
             proc_data: Some(proc_data.unwrap_or_default()),
+            kernel_stack: KernelStack::new(),
+            signal: None,
+            stopped: false,
         };
 
         trace!("New process {}#{} created.", &inner.name, pid);
@@ -123,6 +155,58 @@ impl Process {
 
         child_proc
     }
+
+    /// The content of `/proc/<pid>/status`, one `Key: value` line each
+    pub fn status_info(&self) -> String {
+        x86_64::instructions::interrupts::without_interrupts(|| {
+            let inner = self.inner.read();
+            let memory = inner.proc_vm.as_ref().map_or(0, |vm| vm.memory_usage());
+            format!(
+                "Name: {}\nState: {:?}\nPid: {}\nPPid: {}\nTicks: {}\nMemory: {}\n",
+                inner.name,
+                inner.status,
+                self.pid.0,
+                inner.parent().map(|p| p.pid.0).unwrap_or(0),
+                inner.ticks_passed,
+                memory
+            )
+        })
+    }
+
+    /// The content of `/proc/<pid>/maps`, empty once the process is dead
+    pub fn maps_info(&self) -> String {
+        x86_64::instructions::interrupts::without_interrupts(|| {
+            let inner = self.inner.read();
+            inner
+                .proc_vm
+                .as_ref()
+                .map(ProcessVm::maps)
+                .unwrap_or_default()
+        })
+    }
+
+    /// The content of `/proc/<pid>/fds`, one `fd resource` line each
+    ///
+    /// The resources are not read with interrupts disabled, the process
+    /// may hold one while it waits for the disk or the terminal, it is
+    /// shown as `<busy>` instead of waited for.
+    pub fn fds_info(&self) -> String {
+        let resources = x86_64::instructions::interrupts::without_interrupts(|| {
+            let inner = self.inner.read();
+            inner.proc_data.as_ref().map(|data| data.resources.clone())
+        });
+
+        let mut output = String::new();
+        if let Some(resources) = resources {
+            for (fd, res) in resources.read().handles.iter() {
+                match res.try_lock() {
+                    Some(res) => output += &format!("{} {:?}\n", fd, res),
+                    None => output += &format!("{} <busy>\n", fd),
+                }
+            }
+        }
+        output
+    }
 }
 
 impl ProcessInner {
@@ -150,6 +234,40 @@ impl ProcessInner {
         self.status = ProgramStatus::Blocked;
     }
 
+    pub fn signal(&self) -> Option<Signal> {
+        self.signal
+    }
+
+    pub fn set_signal(&mut self, signal: Signal) {
+        self.signal = Some(signal);
+    }
+
+    pub fn take_signal(&mut self) -> Option<Signal> {
+        self.signal.take()
+    }
+
+    /// Drop a suspend not acted on yet
+    pub fn cancel_suspend(&mut self) {
+        if self.signal == Some(Signal::Suspend) {
+            self.signal = None;
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Stop the process, it is not scheduled until continued
+    pub fn stop(&mut self) {
+        self.stopped = true;
+        self.block();
+    }
+
+    /// Mark the stopped process as running again, it still has to be woken up
+    pub fn cont(&mut self) {
+        self.stopped = false;
+    }
+
     pub fn exit_code(&self) -> Option<isize> {
         self.exit_code
     }
@@ -183,12 +301,14 @@ impl ProcessInner {
     }
 
     /// Save the process's context
-    /// mark the process as ready
+    /// mark the process as ready, unless it blocked itself
     pub(super) fn save(&mut self, context: &ProcessContext) {
         // save the process's context
         if self.status != ProgramStatus::Dead {
             self.context.save(context);
-            self.pause();
+            if self.status == ProgramStatus::Running {
+                self.pause();
+            }
         }
     }
 
@@ -200,6 +320,8 @@ impl ProcessInner {
         self.context.restore(context);
         // restore the process's page table
         self.vm().page_table.load();
+        // run the process's syscalls on its own stack
+        gdt::set_syscall_stack(self.kernel_stack.end());
     }
 
     pub fn init_stack_frame(&mut self, entry: VirtAddr, stack_top: VirtAddr) {
@@ -238,6 +360,7 @@ impl ProcessInner {
 
         // take and drop unused resources
         // recycle process stack
+        // the kernel stack is kept, the exit syscall is still running on it
         self.proc_vm.take();
         self.proc_data.take();
     }
@@ -297,17 +420,12 @@ impl ProcessInner {
             context: child_context,
             proc_vm: Some(proc_vm),
             proc_data: Some(child_proc_data),
+            kernel_stack: KernelStack::new(),
+            signal: None,
+            stopped: false,
         }
     }
 
-    pub fn open_file(&mut self, path: &str) -> u8 {
-        self.proc_data.as_mut().unwrap().open_file(path)
-    }
-
-    pub fn close_file(&mut self, fd: u8) -> bool {
-        self.proc_data.as_mut().unwrap().close_file(fd)
-    }
-
     pub fn brk(&self, addr: Option<VirtAddr>) -> Option<VirtAddr> {
         self.proc_vm.as_ref().unwrap().brk(addr)
     }
@@ -0,0 +1,230 @@
+//! Process file system
+//!
+//! Exposes the processes and the kernel state as text files under `/proc`.
+//! The content of a file is generated when it is opened, later reads see
+//! that snapshot.
+//!
+//! The file system is used with interrupts enabled, the process manager and
+//! the frame allocator are only locked with them disabled, as the timer does.
+
+use super::*;
+use crate::interrupt::read_counter;
+use crate::memory::{get_frame_alloc_for_sure, PAGE_SIZE};
+use crate::runtime::get_uefi_runtime_for_sure;
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::vec;
+use storage::*;
+use x86_64::instructions::interrupts::without_interrupts;
+
+/// The mount point of the process file system
+pub const PROCFS_PATH: &str = "/proc";
+
+/// A path in the process file system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Node {
+    Root,
+    /// `/meminfo`, the frame counts of the frame allocator
+    MemInfo,
+    /// `/uptime`, the seconds since boot and the timer ticks
+    Uptime,
+    /// `/<pid>`
+    Process(ProcessId),
+    /// `/<pid>/status`
+    Status(ProcessId),
+    /// `/<pid>/maps`
+    Maps(ProcessId),
+    /// `/<pid>/fds`
+    Fds(ProcessId),
+}
+
+impl Node {
+    fn parse(path: &str) -> Result<Self> {
+        let mut parts = path.split('/').filter(|part| !part.is_empty());
+
+        let node = match (parts.next(), parts.next()) {
+            (None, _) => Node::Root,
+            (Some("meminfo"), None) => Node::MemInfo,
+            (Some("uptime"), None) => Node::Uptime,
+            (Some(pid), file) => {
+                let pid = pid
+                    .parse::<u16>()
+                    .map(ProcessId)
+                    .map_err(|_| FsError::FileNotFound)?;
+                if !still_alive(pid) {
+                    return Err(FsError::FileNotFound);
+                }
+
+                match file {
+                    None => Node::Process(pid),
+                    Some("status") => Node::Status(pid),
+                    Some("maps") => Node::Maps(pid),
+                    Some("fds") => Node::Fds(pid),
+                    Some(_) => return Err(FsError::FileNotFound),
+                }
+            }
+        };
+
+        match parts.next() {
+            None => Ok(node),
+            Some(_) => Err(FsError::FileNotFound),
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        matches!(self, Node::Root | Node::Process(_))
+    }
+
+    fn name(&self) -> String {
+        match self {
+            Node::Root => String::from("/"),
+            Node::MemInfo => String::from("meminfo"),
+            Node::Uptime => String::from("uptime"),
+            Node::Process(pid) => pid.0.to_string(),
+            Node::Status(_) => String::from("status"),
+            Node::Maps(_) => String::from("maps"),
+            Node::Fds(_) => String::from("fds"),
+        }
+    }
+
+    /// Generate the content of a file
+    fn content(&self) -> Result<String> {
+        let proc = |pid: &ProcessId| {
+            without_interrupts(|| get_process_manager().get_proc(pid)).ok_or(FsError::FileNotFound)
+        };
+
+        Ok(match self {
+            Node::Root | Node::Process(_) => return Err(FsError::NotAFile),
+            Node::MemInfo => without_interrupts(meminfo),
+            Node::Uptime => format!(
+                "{} {}\n",
+                get_uefi_runtime_for_sure().uptime(),
+                read_counter()
+            ),
+            Node::Status(pid) => proc(pid)?.status_info(),
+            Node::Maps(pid) => proc(pid)?.maps_info(),
+            Node::Fds(pid) => proc(pid)?.fds_info(),
+        })
+    }
+
+    fn metadata(&self) -> Result<Metadata> {
+        let (entry_type, len) = if self.is_dir() {
+            (FileType::Directory, 0)
+        } else {
+            (FileType::File, self.content()?.len())
+        };
+
+        Ok(Metadata::new(
+            self.name(),
+            entry_type,
+            len,
+            None,
+            None,
+            None,
+        ))
+    }
+}
+
+/// The memory of the frame allocator, in KiB, one `Key: value` line each
+fn meminfo() -> String {
+    let alloc = get_frame_alloc_for_sure();
+    let kib = |frames: usize| frames * PAGE_SIZE as usize / 1024;
+
+    format!(
+        "Total: {} kB\nUsed: {} kB\nRecycled: {} kB\n",
+        kib(alloc.frames_total()),
+        kib(alloc.frames_used() - alloc.frames_recycled()),
+        kib(alloc.frames_recycled())
+    )
+}
+
+/// The process file system, mounted at `/proc`
+#[derive(Debug, Default)]
+pub struct ProcFs;
+
+impl FileSystem for ProcFs {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = Metadata> + Send>> {
+        let nodes = match Node::parse(path)? {
+            Node::Root => {
+                let mut nodes = vec![Node::MemInfo, Node::Uptime];
+                let pids = without_interrupts(|| get_process_manager().alive_pids());
+                nodes.extend(pids.into_iter().map(Node::Process));
+                nodes
+            }
+            Node::Process(pid) => vec![Node::Status(pid), Node::Maps(pid), Node::Fds(pid)],
+            _ => return Err(FsError::NotADirectory),
+        };
+
+        let entries = nodes
+            .iter()
+            .map(Node::metadata)
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn open_file(&self, path: &str) -> Result<FileHandle> {
+        let node = Node::parse(path)?;
+        let content = node.content()?;
+        let meta = Metadata::new(node.name(), FileType::File, content.len(), None, None, None);
+
+        Ok(FileHandle::new(meta, Box::new(ProcFile::new(content))))
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        Node::parse(path)?.metadata()
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        Ok(Node::parse(path).is_ok())
+    }
+}
+
+/// An open file of the process file system, read only
+struct ProcFile {
+    offset: usize,
+    data: String,
+}
+
+impl ProcFile {
+    fn new(data: String) -> Self {
+        Self { offset: 0, data }
+    }
+}
+
+impl Read for ProcFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let data = self.data.as_bytes();
+        let start = self.offset.min(data.len());
+        let len = buf.len().min(data.len() - start);
+
+        buf[..len].copy_from_slice(&data[start..start + len]);
+        self.offset = start + len;
+        Ok(len)
+    }
+}
+
+impl Write for ProcFile {
+    fn write(&mut self, _buf: &[u8]) -> Result<usize> {
+        Err(FsError::NotSupported)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ProcFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize> {
+        let len = self.data.len();
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+        }
+        .filter(|offset| *offset <= len)
+        .ok_or(FsError::InvalidOffset)?;
+
+        self.offset = offset;
+        Ok(offset)
+    }
+}
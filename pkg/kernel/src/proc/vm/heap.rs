@@ -1,3 +1,4 @@
+use core::ops::Range;
 use core::sync::atomic::{AtomicU64, Ordering};
 
 use alloc::sync::Arc;
@@ -122,6 +123,11 @@ impl Heap {
     pub fn memory_usage(&self) -> u64 {
         self.end.load(Ordering::Relaxed) - self.base.as_u64()
     }
+
+    /// The addresses from the base to the current end
+    pub fn range(&self) -> Range<VirtAddr> {
+        self.base..VirtAddr::new(self.end.load(Ordering::Relaxed))
+    }
 }
 
 impl core::fmt::Debug for Heap {
@@ -1,5 +1,5 @@
 use crate::{humanized_size, memory::*, ProcessId};
-use alloc::{format, vec::Vec};
+use alloc::{format, string::String, vec::Vec};
 use boot::KernelPages;
 use x86_64::{
     structures::paging::{
@@ -124,6 +124,34 @@ impl ProcessVm {
         self.stack.memory_usage() + self.heap.memory_usage() + self.code_usage
     }
 
+    /// The mapped regions, one `start-end name` line each
+    ///
+    /// Forked processes share the code of the first process,
+    /// so only it lists the code regions.
+    pub(super) fn maps(&self) -> String {
+        let mut output = String::new();
+        for range in self.code.iter() {
+            let start = range.start.start_address().as_u64();
+            let end = range.end.start_address().as_u64() + range.end.size();
+            output += &format!("{:#018x}-{:#018x} code\n", start, end);
+        }
+
+        let stack = self.stack.range();
+        output += &format!(
+            "{:#018x}-{:#018x} stack\n",
+            stack.start.as_u64(),
+            stack.end.as_u64()
+        );
+        let heap = self.heap.range();
+        output += &format!(
+            "{:#018x}-{:#018x} heap\n",
+            heap.start.as_u64(),
+            heap.end.as_u64()
+        );
+
+        output
+    }
+
     pub(super) fn clean_up(&mut self) -> Result<(), UnmapError> {
         let mapper = &mut self.page_table.mapper();
         let dealloc = &mut *get_frame_alloc_for_sure();
@@ -1,3 +1,4 @@
+use core::ops::Range;
 use core::ptr::copy_nonoverlapping;
 
 use elf::map_pages;
@@ -78,6 +79,11 @@ impl Stack {
         self.usage
     }
 
+    /// The mapped addresses, from the bottom to the top
+    pub fn range(&self) -> Range<VirtAddr> {
+        self.range.start.start_address()..self.range.end.start_address()
+    }
+
     pub fn stack_min_addr(&self) -> VirtAddr {
         self.range.start.start_address()
     }
@@ -0,0 +1,127 @@
+use super::*;
+use crate::interrupt::{read_counter, reschedule};
+use alloc::collections::BTreeMap;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+/// The deadlines of the processes waiting with a timeout, in timer ticks
+static DEADLINES: Mutex<BTreeMap<ProcessId, u64>> = Mutex::new(BTreeMap::new());
+
+/// The checks counted as a timer tick when spinning with interrupts
+/// disabled, the timer does not tick then
+const SPINS_PER_TICK: u64 = 1_000;
+
+/// The processes blocked in the kernel until an event, e.g. the interrupt
+/// of a device, wakes them up
+///
+/// Locked with interrupts disabled only, it is woken up in the interrupt
+/// handlers.
+pub struct WaitQueue {
+    waiters: Mutex<Vec<ProcessId>>,
+}
+
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self {
+            waiters: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Block the current process until `done` returns true, it is checked
+    /// again each time the queue is woken up
+    pub fn wait_until(&self, done: impl FnMut() -> bool) {
+        self.wait(None, done);
+    }
+
+    /// Block the current process until `done` returns true or the timer
+    /// reaches `deadline`, returns false if it timed out
+    pub fn wait_until_deadline(&self, deadline: u64, done: impl FnMut() -> bool) -> bool {
+        self.wait(Some(deadline), done)
+    }
+
+    /// Wake up the processes waiting, to check again
+    pub fn wake_all(&self) {
+        interrupts::without_interrupts(|| {
+            let mut waiters = self.waiters.lock();
+            if waiters.is_empty() {
+                return;
+            }
+            let manager = get_process_manager();
+            for pid in waiters.drain(..) {
+                manager.wake_blocked(pid);
+            }
+        })
+    }
+
+    /// `done` is checked with interrupts disabled, so that the event can
+    /// not come between the check and the block.
+    ///
+    /// Spins if interrupts are disabled, e.g. at boot, nothing could wake
+    /// the process up, and the deadline is counted in checks. The kernel
+    /// process halts instead of blocking, it is the one left to run when
+    /// the others wait.
+    fn wait(&self, deadline: Option<u64>, mut done: impl FnMut() -> bool) -> bool {
+        let expired = || deadline.is_some_and(|deadline| read_counter() >= deadline);
+
+        if !interrupts::are_enabled() {
+            let mut spins = read_counter() * SPINS_PER_TICK;
+            loop {
+                if done() {
+                    return true;
+                }
+                spins += 1;
+                if deadline.is_some_and(|deadline| spins / SPINS_PER_TICK >= deadline) {
+                    return false;
+                }
+                core::hint::spin_loop();
+            }
+        }
+
+        let pid = get_pid();
+        let ret = loop {
+            interrupts::disable();
+            if done() {
+                break true;
+            }
+            if expired() {
+                break false;
+            }
+
+            if pid == KERNEL_PID {
+                interrupts::enable_and_hlt();
+                continue;
+            }
+
+            self.waiters.lock().push(pid);
+            if let Some(deadline) = deadline {
+                DEADLINES.lock().insert(pid, deadline);
+            }
+            get_process_manager().block_proc(&pid);
+            // back here once woken up, still with interrupts disabled
+            reschedule();
+        };
+
+        // woken up by either the queue or the timer, the other still has it
+        self.waiters.lock().retain(|&waiter| waiter != pid);
+        DEADLINES.lock().remove(&pid);
+        interrupts::enable();
+        ret
+    }
+}
+
+/// Wake up the processes waiting past their deadline
+///
+/// Should be called in the timer interrupt.
+pub fn wake_expired(now: u64) {
+    let mut deadlines = DEADLINES.lock();
+    if deadlines.is_empty() {
+        return;
+    }
+    let manager = get_process_manager();
+    deadlines.retain(|&pid, &mut deadline| {
+        if deadline <= now {
+            manager.wake_blocked(pid);
+        }
+        deadline > now
+    });
+}
@@ -1,3 +1,4 @@
+use crate::drivers::framebuffer::{get_console, CONSOLE};
 use crate::{drivers::serial::get_serial, serial::SERIAL};
 use core::fmt::*;
 use x86_64::instructions::interrupts;
@@ -60,6 +61,10 @@ pub fn print_internal(args: Arguments) {
         if let Some(mut serial) = get_serial() {
             serial.write_fmt(args).unwrap();
         }
+        // mirror to the screen, if there is a framebuffer
+        if let Some(mut console) = get_console() {
+            console.write_fmt(args).unwrap();
+        }
     });
 }
 
@@ -68,6 +73,9 @@ pub fn print_internal(args: Arguments) {
 fn panic(info: &core::panic::PanicInfo) -> ! {
     // force unlock serial for panic output
     unsafe { SERIAL.get().unwrap().force_unlock() };
+    if let Some(console) = CONSOLE.get() {
+        unsafe { console.force_unlock() };
+    }
     error!("ERROR: panic!\n\n{:#?}", info);
     loop {}
 }
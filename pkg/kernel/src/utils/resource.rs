@@ -1,7 +1,23 @@
-use crate::drivers::input::*;
-use alloc::{collections::BTreeMap, string::String};
+use crate::drivers::devfs::{fill_random, BlockFile};
+use crate::drivers::serial::get_serial_for_sure;
+use crate::drivers::tty;
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
 use spin::Mutex;
-use storage::FileHandle;
+use storage::{FileHandle, FsTime, Metadata, SeekFrom};
+use syscall_def::fs::{Dirent, Stat};
+
+/// How a file is opened
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Open an existing file
+    Read,
+    /// Open an existing file for writing, from its start without truncating it
+    Write,
+    /// Create the file, or truncate it if it exists
+    Create,
+    /// Open the file at its end, creating it if needed
+    Append,
+}
 
 #[derive(Debug, Clone)]
 pub enum StdIO {
@@ -30,8 +46,11 @@ impl Default for ResourceSet {
 }
 
 impl ResourceSet {
+    /// Open the resource at the lowest free fd, `u8::MAX` if none is left
     pub fn open(&mut self, res: Resource) -> u8 {
-        let fd = self.handles.len() as u8;
+        let Some(fd) = (0..u8::MAX).find(|fd| !self.handles.contains_key(fd)) else {
+            return u8::MAX;
+        };
         self.handles.insert(fd, Mutex::new(res));
         fd
     }
@@ -55,18 +74,130 @@ impl ResourceSet {
             -1
         }
     }
+
+    pub fn seek(&self, fd: u8, pos: SeekFrom) -> isize {
+        if let Some(offset) = self.handles.get(&fd).and_then(|h| h.lock().seek(pos)) {
+            offset as isize
+        } else {
+            -1
+        }
+    }
+
+    pub fn stat(&self, fd: u8) -> Option<Stat> {
+        self.handles.get(&fd).map(|h| h.lock().stat())
+    }
+
+    /// Whether the file is a terminal, its attributes can be set
+    pub fn is_tty(&self, fd: u8) -> bool {
+        self.handles.get(&fd).is_some_and(|h| h.lock().is_tty())
+    }
+
+    /// Whether reading the file waits for the terminal
+    pub fn reads_tty(&self, fd: u8) -> bool {
+        self.handles.get(&fd).is_some_and(|h| {
+            matches!(
+                *h.lock(),
+                Resource::Console(StdIO::Stdin) | Resource::Serial
+            )
+        })
+    }
+
+    /// Read the next entries of a directory, returns how many were read
+    pub fn read_dir(&self, fd: u8, buf: &mut [Dirent]) -> isize {
+        match self.handles.get(&fd).map(|h| h.lock()) {
+            Some(mut res) => match &mut *res {
+                Resource::Dir(dir) => dir.read_entries(buf) as isize,
+                _ => -1,
+            },
+            None => -1,
+        }
+    }
+}
+
+/// The status of a file, from its metadata
+pub fn to_stat(meta: &Metadata) -> Stat {
+    let time = |time: Option<FsTime>| time.map_or(0, |t| t.timestamp());
+    let kind = if meta.is_dir() {
+        Stat::DIRECTORY
+    } else if meta.is_device() {
+        Stat::DEVICE
+    } else {
+        Stat::FILE
+    };
+
+    Stat {
+        kind,
+        size: meta.len as u64,
+        created: time(meta.created),
+        modified: time(meta.modified),
+        accessed: time(meta.accessed),
+        attributes: meta.attributes.bits() as u32,
+    }
+}
+
+/// An open directory, the entries are listed when it is opened
+pub struct DirHandle {
+    pub meta: Metadata,
+    entries: Vec<Metadata>,
+    /// The index of the next entry to read
+    next: usize,
+}
+
+impl DirHandle {
+    pub fn new(meta: Metadata, entries: Vec<Metadata>) -> Self {
+        Self {
+            meta,
+            entries,
+            next: 0,
+        }
+    }
+
+    pub fn read_entries(&mut self, buf: &mut [Dirent]) -> usize {
+        let entries = &self.entries[self.next..];
+        let count = buf.len().min(entries.len());
+
+        for (entry, meta) in buf.iter_mut().zip(entries) {
+            *entry = Dirent::new(&meta.name, to_stat(meta));
+        }
+
+        self.next += count;
+        count
+    }
+
+    /// Seek to an entry, only rewinding and seeking from the start
+    pub fn seek(&mut self, pos: SeekFrom) -> Option<usize> {
+        match pos {
+            SeekFrom::Start(index) if index <= self.entries.len() => {
+                self.next = index;
+                Some(index)
+            }
+            _ => None,
+        }
+    }
 }
 
 pub enum Resource {
-    File(FileHandle),
+    /// A file, only written if it is not opened with `OpenMode::Read`
+    File(FileHandle, OpenMode),
     Console(StdIO),
     Null,
+    /// `/dev/zero`
+    Zero,
+    /// `/dev/random`
+    Random,
+    /// `/dev/ttyS0`, the serial port, read through the line discipline of
+    /// the terminal
+    Serial,
+    /// A disk or partition, e.g. `/dev/hda1`
+    Block(BlockFile),
+    /// A directory, read with `getdents`
+    Dir(DirHandle),
 }
 
 impl Resource {
     pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
         match self {
-            Resource::File(file) => {
+            Resource::File(file, _) => {
                 if let Ok(size) = file.read(buf) {
                     Some(size)
                 } else {
@@ -74,24 +205,28 @@ impl Resource {
                 }
             }
             Resource::Console(stdio) => match stdio {
-                &mut StdIO::Stdin => {
-                    // just read from kernel input buffer
-                    if let Some(ch) = try_pop_key() {
-                        buf[0] = ch;
-                        Some(1)
-                    } else {
-                        Some(0)
-                    }
-                }
+                &mut StdIO::Stdin => tty::read(buf),
                 _ => None,
             },
             Resource::Null => Some(0),
+            Resource::Zero => {
+                buf.fill(0);
+                Some(buf.len())
+            }
+            Resource::Random => {
+                fill_random(buf);
+                Some(buf.len())
+            }
+            Resource::Serial => tty::read(buf),
+            Resource::Block(file) => file.read(buf).ok(),
+            Resource::Dir(_) => None,
         }
     }
 
     pub fn write(&mut self, buf: &[u8]) -> Option<usize> {
         match self {
-            Resource::File(_file) => Some(0),
+            Resource::File(_, OpenMode::Read) => None,
+            Resource::File(file, _) => file.write(buf).ok(),
             Resource::Console(stdio) => match *stdio {
                 StdIO::Stdin => None,
                 StdIO::Stdout => {
@@ -103,7 +238,55 @@ impl Resource {
                     Some(buf.len())
                 }
             },
-            Resource::Null => Some(buf.len()),
+            Resource::Null | Resource::Zero | Resource::Random => Some(buf.len()),
+            Resource::Serial => {
+                x86_64::instructions::interrupts::without_interrupts(|| {
+                    let mut serial = get_serial_for_sure();
+                    for byte in buf {
+                        serial.send(*byte);
+                    }
+                });
+                Some(buf.len())
+            }
+            Resource::Block(_) | Resource::Dir(_) => None,
+        }
+    }
+
+    pub fn seek(&mut self, pos: SeekFrom) -> Option<usize> {
+        match self {
+            Resource::File(file, _) => file.seek(pos).ok(),
+            Resource::Console(_) | Resource::Serial => None,
+            Resource::Null | Resource::Zero | Resource::Random => Some(0),
+            Resource::Block(file) => file.seek(pos).ok(),
+            Resource::Dir(dir) => dir.seek(pos),
+        }
+    }
+
+    pub fn is_tty(&self) -> bool {
+        matches!(self, Resource::Console(_) | Resource::Serial)
+    }
+
+    /// The status of the open file, devices have no times
+    ///
+    /// The size of a file is refreshed, it may have been written since opened.
+    pub fn stat(&mut self) -> Stat {
+        match self {
+            Resource::File(file, _) => {
+                if let Ok(size) = file.size() {
+                    file.meta.len = size;
+                }
+                to_stat(&file.meta)
+            }
+            Resource::Dir(dir) => to_stat(&dir.meta),
+            Resource::Block(file) => Stat {
+                kind: Stat::DEVICE,
+                size: file.size() as u64,
+                ..Default::default()
+            },
+            _ => Stat {
+                kind: Stat::DEVICE,
+                ..Default::default()
+            },
         }
     }
 }
@@ -111,9 +294,14 @@ impl Resource {
 impl core::fmt::Debug for Resource {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
-            Resource::File(file) => write!(f, "File({:?})", file),
+            Resource::File(file, mode) => write!(f, "File({:?}, {:?})", file, mode),
             Resource::Console(stdio) => write!(f, "Console({:?})", stdio),
             Resource::Null => write!(f, "Null"),
+            Resource::Zero => write!(f, "Zero"),
+            Resource::Random => write!(f, "Random"),
+            Resource::Serial => write!(f, "Serial"),
+            Resource::Block(file) => write!(f, "Block({:?})", file),
+            Resource::Dir(dir) => write!(f, "Dir({})", dir.meta.name),
         }
     }
 }
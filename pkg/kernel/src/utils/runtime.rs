@@ -2,20 +2,46 @@ use boot::{BootInfo, RuntimeServices, Time};
 
 pub struct UefiRuntime {
     runtime_service: &'static RuntimeServices,
+    /// The time at boot, in seconds since the Unix epoch
+    boot_time: u64,
 }
 
 once_mutex!(UEFI_RUNTIME: UefiRuntime);
 
 impl UefiRuntime {
     pub unsafe fn new(boot_info: &'static BootInfo) -> Self {
+        let runtime_service = boot_info.system_table.runtime_services();
+        let boot_time = timestamp(&runtime_service.get_time().unwrap());
         Self {
-            runtime_service: boot_info.system_table.runtime_services(),
+            runtime_service,
+            boot_time,
         }
     }
 
     pub fn get_time(&self) -> Time {
         self.runtime_service.get_time().unwrap()
     }
+
+    /// Seconds since boot, by the real time clock
+    pub fn uptime(&self) -> u64 {
+        timestamp(&self.get_time()).saturating_sub(self.boot_time)
+    }
+}
+
+/// Seconds since the Unix epoch, ignoring the time zone
+///
+/// reference: <https://howardhinnant.github.io/date_algorithms.html#days_from_civil>
+fn timestamp(time: &Time) -> u64 {
+    let (month, day) = (time.month() as i64, time.day() as i64);
+    let year = time.year() as i64 - (month <= 2) as i64;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+
+    (days * 86400 + time.hour() as i64 * 3600 + time.minute() as i64 * 60 + time.second() as i64)
+        as u64
 }
 
 guard_access_fn!(pub get_uefi_runtime(UEFI_RUNTIME: UefiRuntime));
@@ -1,6 +1,6 @@
 use crate::*;
-use alloc::string::{String, ToString};
-use alloc::vec;
+use alloc::string::String;
+use alloc::vec::Vec;
 
 pub struct Stdin;
 pub struct Stdout;
@@ -11,38 +11,29 @@ impl Stdin {
         Self
     }
 
+    /// Read a line, edited and echoed by the terminal, without its newline
+    ///
+    /// At the end of file, returns what was read before it.
     pub fn read_line(&self) -> String {
-        // allocate string
-        let mut line = String::new();
+        let mut line = Vec::new();
+        let mut buf = [0u8; 256];
 
-        // read from input buffer char by char
         loop {
-            let buf: &mut [u8] = &mut [0u8; 256];
-            let ret = sys_read(0, buf);
-
-            if ret.is_none() {
-                continue;
-            } else {
-                for i in 0..ret.unwrap() {
-                    let c = buf[i];
-                    // handle backspace / enter... and finally return the string
-                    match c {
-                        13 => {
-                            sys_write(1, "\n".as_bytes());
-                            return line;
-                        }
-                        0x08 | 0x7F => {
-                            line.pop();
-                            sys_write(1, "\x08\x20\x08".as_bytes());
-                        }
-                        _ => {
-                            line.push(c as char);
-                            sys_write(1, &mut [c]);
-                        }
-                    };
+            match sys_read(0, &mut buf) {
+                // interrupted by a signal
+                None => continue,
+                Some(0) => break,
+                Some(count) => {
+                    line.extend_from_slice(&buf[..count]);
+                    if line.last() == Some(&b'\n') {
+                        line.pop();
+                        break;
+                    }
                 }
             }
         }
+
+        String::from_utf8_lossy(&line).into_owned()
     }
 }
 
@@ -24,6 +24,8 @@ pub use chrono::*;
 pub use io::*;
 pub use sync::*;
 pub use syscall::*;
+pub use syscall_def::fs::{Dirent, Stat, NAME_MAX};
+pub use syscall_def::tty::{Termios, TCSAFLUSH, TCSANOW};
 
 pub fn init() {
     #[cfg(feature = "brk_alloc")]
@@ -1,5 +1,7 @@
 use core::time::Duration;
 
+use syscall_def::fs::{Dirent, Stat};
+use syscall_def::tty::{Termios, TCGETATTR};
 use syscall_def::Syscall;
 
 #[inline(always)]
@@ -42,9 +44,127 @@ pub fn sys_list_app() {
     syscall!(Syscall::ListApp);
 }
 
+/// Mount the device at the path, `fs_type` followed by `,ro` mounts it read-only
 #[inline(always)]
-pub fn sys_list_dir(path: &str) {
-    syscall!(Syscall::ListDir, path.as_ptr() as u64, path.len() as u64);
+pub fn sys_mount(device: &str, fs_type: &str, path: &str) -> bool {
+    syscall!(
+        Syscall::Mount,
+        device.as_ptr() as u64,
+        device.len() as u64,
+        fs_type.as_ptr() as u64,
+        fs_type.len() as u64,
+        path.as_ptr() as u64,
+        path.len() as u64
+    ) == 0
+}
+
+#[inline(always)]
+pub fn sys_umount(path: &str) -> bool {
+    syscall!(Syscall::Umount, path.as_ptr() as u64, path.len() as u64) == 0
+}
+
+/// Write the cached changes of all file systems back to their devices
+#[inline(always)]
+pub fn sys_sync() -> bool {
+    syscall!(Syscall::Sync) == 0
+}
+
+#[inline(always)]
+pub fn sys_list_mount() {
+    syscall!(Syscall::ListMount);
+}
+
+#[inline(always)]
+pub fn sys_print_partitions(device: &str) -> bool {
+    syscall!(
+        Syscall::Partition,
+        0,
+        device.as_ptr() as u64,
+        device.len() as u64
+    ) == 0
+}
+
+/// Create a primary partition in `slot` (0 to 3), with the Linux type
+#[inline(always)]
+pub fn sys_create_partition(device: &str, slot: usize, begin: u32, total: u32) -> bool {
+    syscall!(
+        Syscall::Partition,
+        1,
+        device.as_ptr() as u64,
+        device.len() as u64,
+        slot,
+        begin,
+        total
+    ) == 0
+}
+
+#[inline(always)]
+pub fn sys_delete_partition(device: &str, slot: usize) -> bool {
+    syscall!(
+        Syscall::Partition,
+        2,
+        device.as_ptr() as u64,
+        device.len() as u64,
+        slot
+    ) == 0
+}
+
+#[inline(always)]
+pub fn sys_resize_partition(device: &str, slot: usize, total: u32) -> bool {
+    syscall!(
+        Syscall::Partition,
+        3,
+        device.as_ptr() as u64,
+        device.len() as u64,
+        slot,
+        total
+    ) == 0
+}
+
+#[inline(always)]
+pub fn sys_set_partition_type(device: &str, slot: usize, kind: u8) -> bool {
+    syscall!(
+        Syscall::Partition,
+        4,
+        device.as_ptr() as u64,
+        device.len() as u64,
+        slot,
+        kind
+    ) == 0
+}
+
+/// Format the device as an empty Fat16 volume
+#[inline(always)]
+pub fn sys_mkfs(device: &str, label: &str) -> bool {
+    syscall!(
+        Syscall::Mkfs,
+        device.as_ptr() as u64,
+        device.len() as u64,
+        label.as_ptr() as u64,
+        label.len() as u64
+    ) == 0
+}
+
+/// Check the Fat16 volume on the device, returns the number of problems
+/// found and as many of them as fit in `buf`, one per line
+#[inline(always)]
+pub fn sys_fsck<'a>(device: &str, repair: bool, buf: &'a mut [u8]) -> Option<(usize, &'a str)> {
+    let mut len = 0usize;
+    let ret = syscall!(
+        Syscall::Fsck,
+        device.as_ptr() as u64,
+        device.len() as u64,
+        repair as u64,
+        buf.as_mut_ptr() as u64,
+        buf.len() as u64,
+        &mut len as *mut usize as u64
+    ) as isize;
+    if ret.is_negative() {
+        None
+    } else {
+        let problems = core::str::from_utf8(&buf[..len]).unwrap_or_default();
+        Some((ret as usize, problems))
+    }
 }
 
 #[inline(always)]
@@ -64,7 +184,51 @@ pub fn sys_deallocate(ptr: *mut u8, layout: &core::alloc::Layout) -> usize {
 
 #[inline(always)]
 pub fn sys_spawn(path: &str) -> u16 {
-    syscall!(Syscall::Spawn, path.as_ptr() as u64, path.len() as u64) as u16
+    syscall!(
+        Syscall::Spawn,
+        path.as_ptr() as u64,
+        path.len() as u64,
+        0,
+        0
+    ) as u16
+}
+
+/// Spawn the app with its arguments, it reads them with `args`
+#[inline(always)]
+pub fn sys_spawn_args(path: &str, args: &str) -> u16 {
+    syscall!(
+        Syscall::Spawn,
+        path.as_ptr() as u64,
+        path.len() as u64,
+        args.as_ptr() as u64,
+        args.len() as u64
+    ) as u16
+}
+
+/// Get an environment variable into `buf`, returns its length
+#[inline(always)]
+pub fn sys_get_env(key: &str, buf: &mut [u8]) -> Option<usize> {
+    let ret = syscall!(
+        Syscall::GetEnv,
+        key.as_ptr() as u64,
+        key.len() as u64,
+        buf.as_mut_ptr() as u64,
+        buf.len() as u64
+    ) as isize;
+    if ret.is_negative() {
+        None
+    } else {
+        Some(ret as usize)
+    }
+}
+
+/// The arguments the process was spawned with, `None` if it was spawned without
+pub fn args() -> Option<alloc::string::String> {
+    let mut buf = [0u8; 256];
+    let len = sys_get_env(syscall_def::ARGS_ENV, &mut buf)?;
+    core::str::from_utf8(&buf[..len])
+        .ok()
+        .map(alloc::string::String::from)
 }
 
 #[inline(always)]
@@ -115,7 +279,42 @@ pub fn sys_sem_wait(key: u32) -> bool {
 
 #[inline(always)]
 pub fn sys_open_file(path: &str) -> u8 {
-    syscall!(Syscall::Open, path.as_ptr() as u64, path.len() as u64) as u8
+    syscall!(Syscall::Open, path.as_ptr() as u64, path.len() as u64, 0) as u8
+}
+
+/// Create the file, or truncate it if it exists
+#[inline(always)]
+pub fn sys_create_file(path: &str) -> u8 {
+    syscall!(Syscall::Open, path.as_ptr() as u64, path.len() as u64, 1) as u8
+}
+
+/// Open the file at its end, creating it if needed
+#[inline(always)]
+pub fn sys_append_file(path: &str) -> u8 {
+    syscall!(Syscall::Open, path.as_ptr() as u64, path.len() as u64, 2) as u8
+}
+
+/// Open an existing file for writing from its start, without truncating it
+#[inline(always)]
+pub fn sys_write_file(path: &str) -> u8 {
+    syscall!(Syscall::Open, path.as_ptr() as u64, path.len() as u64, 3) as u8
+}
+
+#[inline(always)]
+pub fn sys_mkdir(path: &str) -> bool {
+    syscall!(Syscall::Mkdir, path.as_ptr() as u64, path.len() as u64) == 0
+}
+
+/// Set and clear the `Stat::READ_ONLY`, `Stat::HIDDEN` and `Stat::SYSTEM` bits
+#[inline(always)]
+pub fn sys_chmod(path: &str, set: u32, clear: u32) -> bool {
+    syscall!(
+        Syscall::Chmod,
+        path.as_ptr() as u64,
+        path.len() as u64,
+        set as u64,
+        clear as u64
+    ) == 0
 }
 
 #[inline(always)]
@@ -123,6 +322,95 @@ pub fn sys_close_file(fd: u8) -> bool {
     syscall!(Syscall::Close, fd as u64) == 0
 }
 
+pub const SEEK_SET: u8 = 0;
+pub const SEEK_CUR: u8 = 1;
+pub const SEEK_END: u8 = 2;
+
+#[inline(always)]
+pub fn sys_lseek(fd: u8, offset: isize, whence: u8) -> Option<usize> {
+    let ret = syscall!(Syscall::Lseek, fd as u64, offset as u64, whence as u64) as isize;
+    if ret.is_negative() {
+        None
+    } else {
+        Some(ret as usize)
+    }
+}
+
+/// Get the attributes of the terminal open as fd
+#[inline(always)]
+pub fn sys_tcgetattr(fd: u8) -> Option<Termios> {
+    let mut termios = Termios::default();
+    let ret = syscall!(
+        Syscall::TtyAttr,
+        fd as u64,
+        TCGETATTR as u64,
+        &mut termios as *mut Termios
+    ) as isize;
+    if ret.is_negative() {
+        None
+    } else {
+        Some(termios)
+    }
+}
+
+/// Set the attributes of the terminal open as fd, `action` is `TCSANOW`,
+/// or `TCSAFLUSH` to discard the input not read yet
+#[inline(always)]
+pub fn sys_tcsetattr(fd: u8, action: usize, termios: &Termios) -> bool {
+    syscall!(
+        Syscall::TtyAttr,
+        fd as u64,
+        action as u64,
+        termios as *const Termios
+    ) == 0
+}
+
+/// Get the status of the file or directory at the path
+#[inline(always)]
+pub fn sys_file_stat(path: &str) -> Option<Stat> {
+    let mut stat = Stat::default();
+    let ret = syscall!(
+        Syscall::FileStat,
+        path.as_ptr() as u64,
+        path.len() as u64,
+        &mut stat as *mut Stat
+    ) as isize;
+    if ret.is_negative() {
+        None
+    } else {
+        Some(stat)
+    }
+}
+
+/// Get the status of an open file
+#[inline(always)]
+pub fn sys_fstat(fd: u8) -> Option<Stat> {
+    let mut stat = Stat::default();
+    let ret = syscall!(Syscall::Fstat, fd as u64, &mut stat as *mut Stat) as isize;
+    if ret.is_negative() {
+        None
+    } else {
+        Some(stat)
+    }
+}
+
+/// Read the next entries of a directory opened with `sys_open_file`,
+/// returns how many were read, 0 at the end of the directory
+#[inline(always)]
+pub fn sys_get_dents(fd: u8, buf: &mut [Dirent]) -> Option<usize> {
+    let ret = syscall!(
+        Syscall::GetDents,
+        fd as u64,
+        buf.as_mut_ptr() as u64,
+        buf.len() as u64
+    ) as isize;
+    if ret.is_negative() {
+        None
+    } else {
+        Some(ret as usize)
+    }
+}
+
 #[inline(always)]
 pub fn sys_brk(addr: Option<usize>) -> Option<usize> {
     const BRK_FAILED: usize = !0;
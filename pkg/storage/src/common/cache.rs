@@ -0,0 +1,419 @@
+use super::*;
+use alloc::collections::BTreeMap;
+use core::marker::PhantomData;
+use spin::Mutex;
+
+/// Statistics of a block cache
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Accesses served from the cache
+    pub hits: usize,
+    /// Accesses that had to go to the device
+    pub misses: usize,
+    /// Blocks dropped to make room for others
+    pub evictions: usize,
+    /// Dirty blocks written to the device
+    pub writebacks: usize,
+}
+
+struct CacheEntry<B> {
+    block: B,
+    dirty: bool,
+    /// The last access time, the key of this entry in `CacheState::lru`
+    stamp: u64,
+}
+
+struct CacheState<B> {
+    entries: BTreeMap<usize, CacheEntry<B>>,
+    /// Offsets of the cached blocks, ordered from least to most recently used
+    lru: BTreeMap<u64, usize>,
+    clock: u64,
+    stats: CacheStats,
+}
+
+impl<B: BlockTrait> CacheState<B> {
+    fn touch(&mut self, offset: usize) -> Option<&mut CacheEntry<B>> {
+        let stamp = self.clock;
+        let entry = self.entries.get_mut(&offset)?;
+
+        self.lru.remove(&entry.stamp);
+        self.lru.insert(stamp, offset);
+        entry.stamp = stamp;
+        self.clock += 1;
+
+        Some(entry)
+    }
+}
+
+/// A write-back LRU cache in front of a block device
+///
+/// Written blocks are kept in memory until they are evicted,
+/// or until `sync` is called. The cache is flushed on drop,
+/// errors at that point are only logged.
+pub struct CachedDevice<T, B>
+where
+    T: BlockDevice<B>,
+    B: BlockTrait,
+{
+    inner: T,
+    capacity: usize,
+    state: Mutex<CacheState<B>>,
+    _block: PhantomData<B>,
+}
+
+impl<T, B> CachedDevice<T, B>
+where
+    T: BlockDevice<B>,
+    B: BlockTrait,
+{
+    /// Wrap the device with a cache holding at most `capacity` blocks
+    pub fn new(inner: T, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity: capacity.max(1),
+            state: Mutex::new(CacheState {
+                entries: BTreeMap::new(),
+                lru: BTreeMap::new(),
+                clock: 0,
+                stats: CacheStats::default(),
+            }),
+            _block: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline]
+    pub fn stats(&self) -> CacheStats {
+        self.state.lock().stats
+    }
+
+    /// Write back dirty blocks and drop everything from the cache
+    pub fn invalidate(&self) -> Result<()> {
+        self.sync()?;
+
+        let mut state = self.state.lock();
+        state.entries.clear();
+        state.lru.clear();
+        Ok(())
+    }
+
+    /// Insert a block, evicting the least recently used one if full
+    fn insert(
+        &self,
+        state: &mut CacheState<B>,
+        offset: usize,
+        block: B,
+        dirty: bool,
+    ) -> Result<()> {
+        if state.entries.len() >= self.capacity {
+            if let Some((_, victim)) = state.lru.first_key_value().map(|(k, v)| (*k, *v)) {
+                let entry = &state.entries[&victim];
+                if entry.dirty {
+                    self.inner.write_block(victim, &entry.block)?;
+                    state.stats.writebacks += 1;
+                }
+
+                let stamp = entry.stamp;
+                state.lru.remove(&stamp);
+                state.entries.remove(&victim);
+                state.stats.evictions += 1;
+            }
+        }
+
+        let stamp = state.clock;
+        state.clock += 1;
+        state.lru.insert(stamp, offset);
+        state.entries.insert(
+            offset,
+            CacheEntry {
+                block,
+                dirty,
+                stamp,
+            },
+        );
+
+        Ok(())
+    }
+}
+
+impl<T, B> BlockDevice<B> for CachedDevice<T, B>
+where
+    T: BlockDevice<B>,
+    B: BlockTrait,
+{
+    fn block_count(&self) -> Result<usize> {
+        self.inner.block_count()
+    }
+
+    fn read_block(&self, offset: usize, block: &mut B) -> Result<()> {
+        let mut state = self.state.lock();
+
+        if let Some(entry) = state.touch(offset) {
+            block.as_mut().copy_from_slice(entry.block.as_ref());
+            state.stats.hits += 1;
+            return Ok(());
+        }
+
+        state.stats.misses += 1;
+        self.inner.read_block(offset, block)?;
+        self.insert(&mut state, offset, block.clone(), false)
+    }
+
+    /// Reads the cached blocks from the cache,
+    /// each run of the others with a single request to the device
+    fn read_blocks(&self, offset: usize, blocks: &mut [B]) -> Result<()> {
+        let mut state = self.state.lock();
+        let mut index = 0;
+
+        while index < blocks.len() {
+            if let Some(entry) = state.touch(offset + index) {
+                blocks[index].as_mut().copy_from_slice(entry.block.as_ref());
+                state.stats.hits += 1;
+                index += 1;
+                continue;
+            }
+
+            let end = (index + 1..blocks.len())
+                .find(|i| state.entries.contains_key(&(offset + i)))
+                .unwrap_or(blocks.len());
+            state.stats.misses += end - index;
+            self.inner
+                .read_blocks(offset + index, &mut blocks[index..end])?;
+            for (i, block) in blocks[index..end].iter().enumerate() {
+                self.insert(&mut state, offset + index + i, block.clone(), false)?;
+            }
+            index = end;
+        }
+
+        Ok(())
+    }
+
+    fn write_block(&self, offset: usize, block: &B) -> Result<()> {
+        let mut state = self.state.lock();
+
+        if let Some(entry) = state.touch(offset) {
+            entry.block.as_mut().copy_from_slice(block.as_ref());
+            entry.dirty = true;
+            state.stats.hits += 1;
+            return Ok(());
+        }
+
+        // the whole block is overwritten, no need to read it first
+        state.stats.misses += 1;
+        if offset >= self.inner.block_count()? {
+            return Err(FsError::InvalidOffset);
+        }
+        self.insert(&mut state, offset, block.clone(), true)
+    }
+
+    /// Write all dirty blocks back to the device, then sync it
+    fn sync(&self) -> Result<()> {
+        let mut state = self.state.lock();
+        let mut written = 0;
+
+        for (offset, entry) in state.entries.iter_mut().filter(|(_, e)| e.dirty) {
+            self.inner.write_block(*offset, &entry.block)?;
+            entry.dirty = false;
+            written += 1;
+        }
+
+        state.stats.writebacks += written;
+        drop(state);
+        self.inner.sync()
+    }
+}
+
+impl<T, B> Drop for CachedDevice<T, B>
+where
+    T: BlockDevice<B>,
+    B: BlockTrait,
+{
+    fn drop(&mut self) {
+        if let Err(err) = self.sync() {
+            warn!("Failed to flush the block cache: {:?}", err);
+        }
+    }
+}
+
+impl<T, B> core::fmt::Debug for CachedDevice<T, B>
+where
+    T: BlockDevice<B> + core::fmt::Debug,
+    B: BlockTrait,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CachedDevice")
+            .field("inner", &self.inner)
+            .field("capacity", &self.capacity)
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+
+    /// A device that counts the accesses to it
+    #[derive(Default)]
+    struct CountingDevice {
+        blocks: StdMutex<Vec<Block512>>,
+        reads: StdMutex<usize>,
+        writes: StdMutex<usize>,
+    }
+
+    impl CountingDevice {
+        fn new(count: usize) -> Self {
+            Self {
+                blocks: StdMutex::new(vec![Block512::default(); count]),
+                ..Default::default()
+            }
+        }
+
+        fn reads(&self) -> usize {
+            *self.reads.lock().unwrap()
+        }
+
+        fn writes(&self) -> usize {
+            *self.writes.lock().unwrap()
+        }
+
+        fn byte(&self, offset: usize) -> u8 {
+            self.blocks.lock().unwrap()[offset][0]
+        }
+    }
+
+    impl BlockDevice<Block512> for &'static CountingDevice {
+        fn block_count(&self) -> Result<usize> {
+            Ok(self.blocks.lock().unwrap().len())
+        }
+
+        fn read_block(&self, offset: usize, block: &mut Block512) -> Result<()> {
+            *self.reads.lock().unwrap() += 1;
+            let blocks = self.blocks.lock().unwrap();
+            *block = blocks.get(offset).ok_or(FsError::InvalidOffset)?.clone();
+            Ok(())
+        }
+
+        fn write_block(&self, offset: usize, block: &Block512) -> Result<()> {
+            *self.writes.lock().unwrap() += 1;
+            let mut blocks = self.blocks.lock().unwrap();
+            *blocks.get_mut(offset).ok_or(FsError::InvalidOffset)? = block.clone();
+            Ok(())
+        }
+    }
+
+    fn filled(byte: u8) -> Block512 {
+        Block512::new(&[byte; 512])
+    }
+
+    #[test]
+    fn test_cache_hits() {
+        let device: &'static CountingDevice = Box::leak(Box::new(CountingDevice::new(16)));
+        let cache = CachedDevice::new(device, 4);
+        let mut block = Block512::default();
+
+        for _ in 0..3 {
+            for offset in 0..4 {
+                cache.read_block(offset, &mut block).unwrap();
+            }
+        }
+
+        assert_eq!(device.reads(), 4);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (8, 4, 0));
+    }
+
+    #[test]
+    fn test_cache_lru_eviction() {
+        let device: &'static CountingDevice = Box::leak(Box::new(CountingDevice::new(16)));
+        let cache = CachedDevice::new(device, 2);
+        let mut block = Block512::default();
+
+        cache.read_block(0, &mut block).unwrap();
+        cache.read_block(1, &mut block).unwrap();
+        // 0 becomes the most recently used, 1 is evicted
+        cache.read_block(0, &mut block).unwrap();
+        cache.read_block(2, &mut block).unwrap();
+        cache.read_block(0, &mut block).unwrap();
+        assert_eq!(device.reads(), 3);
+
+        cache.read_block(1, &mut block).unwrap();
+        assert_eq!(device.reads(), 4);
+        assert_eq!(cache.stats().evictions, 2);
+    }
+
+    #[test]
+    fn test_cache_write_back() {
+        let device: &'static CountingDevice = Box::leak(Box::new(CountingDevice::new(16)));
+        let cache = CachedDevice::new(device, 2);
+        let mut block = Block512::default();
+
+        cache.write_block(0, &filled(1)).unwrap();
+        cache.write_block(0, &filled(2)).unwrap();
+        cache.read_block(0, &mut block).unwrap();
+        assert_eq!(block[0], 2);
+        assert_eq!((device.writes(), device.reads()), (0, 0));
+
+        // evicting the dirty block writes it back
+        cache.write_block(1, &filled(3)).unwrap();
+        cache.read_block(2, &mut block).unwrap();
+        assert_eq!(device.writes(), 1);
+        assert_eq!(device.byte(0), 2);
+        assert_eq!(device.byte(1), 0);
+
+        cache.sync().unwrap();
+        assert_eq!(device.byte(1), 3);
+        assert_eq!(cache.stats().writebacks, 2);
+
+        // clean blocks are not written again
+        cache.sync().unwrap();
+        assert_eq!(device.writes(), 2);
+
+        cache.write_block(3, &filled(4)).unwrap();
+        drop(cache);
+        assert_eq!(device.byte(3), 4);
+    }
+
+    #[test]
+    fn test_cache_read_blocks() {
+        let device: &'static CountingDevice = Box::leak(Box::new(CountingDevice::new(16)));
+        let cache = CachedDevice::new(device, 8);
+        let mut blocks = vec![Block512::default(); 4];
+
+        // the dirty cached block is read instead of the device one
+        cache.write_block(2, &filled(9)).unwrap();
+        cache.read_blocks(0, &mut blocks).unwrap();
+        assert_eq!(
+            blocks.iter().map(|b| b[0]).collect::<Vec<_>>(),
+            [0, 0, 9, 0]
+        );
+        assert_eq!(device.reads(), 3);
+
+        cache.read_blocks(1, &mut blocks).unwrap();
+        assert_eq!(device.reads(), 4);
+        assert_eq!(blocks[1][0], 9);
+
+        // the write of block 2 is a miss too
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (4, 5));
+    }
+
+    #[test]
+    fn test_cache_out_of_range() {
+        let device: &'static CountingDevice = Box::leak(Box::new(CountingDevice::new(4)));
+        let cache = CachedDevice::new(device, 2);
+        let mut block = Block512::default();
+
+        assert_eq!(cache.read_block(4, &mut block), Err(FsError::InvalidOffset));
+        assert_eq!(
+            cache.write_block(4, &filled(1)),
+            Err(FsError::InvalidOffset)
+        );
+        assert_eq!(cache.stats().evictions, 0);
+    }
+}
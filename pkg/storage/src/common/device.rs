@@ -14,8 +14,49 @@ where
     /// Writes a block to the device from the provided buffer
     fn write_block(&self, offset: usize, block: &B) -> Result<()>;
 
+    /// Reads the consecutive blocks starting at `offset` into the provided buffers
+    ///
+    /// Devices that transfer several blocks in one request override it,
+    /// the default reads them one by one.
+    fn read_blocks(&self, offset: usize, blocks: &mut [B]) -> Result<()> {
+        for (index, block) in blocks.iter_mut().enumerate() {
+            self.read_block(offset + index, block)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the blocks kept in memory back to the device
+    ///
+    /// Devices that write through have nothing to do, the default.
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
     /// Returns the block size of the device
     fn block_size(&self) -> usize {
         B::size()
     }
 }
+
+/// A device opened by name, when its type is only known at runtime
+impl<B: BlockTrait> BlockDevice<B> for Box<dyn BlockDevice<B>> {
+    fn block_count(&self) -> Result<usize> {
+        (**self).block_count()
+    }
+
+    fn read_block(&self, offset: usize, block: &mut B) -> Result<()> {
+        (**self).read_block(offset, block)
+    }
+
+    fn write_block(&self, offset: usize, block: &B) -> Result<()> {
+        (**self).write_block(offset, block)
+    }
+
+    fn read_blocks(&self, offset: usize, blocks: &mut [B]) -> Result<()> {
+        (**self).read_blocks(offset, blocks)
+    }
+
+    fn sync(&self) -> Result<()> {
+        (**self).sync()
+    }
+}
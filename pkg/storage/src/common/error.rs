@@ -32,6 +32,14 @@ pub enum FsError {
     DeviceError(DeviceError),
     /// Invalid path.
     InvalidPath(String),
+    /// The entry already exists.
+    AlreadyExists,
+    /// The device does not hold a valid file system.
+    InvalidFileSystem,
+    /// The device does not hold a valid partition table.
+    InvalidPartitionTable,
+    /// The partition overlaps another partition.
+    PartitionOverlap,
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
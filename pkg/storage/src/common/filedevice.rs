@@ -0,0 +1,73 @@
+//! File block device
+//!
+//! A block device over an image file on the host, so that the file
+//! systems can be tested against images made by the host tools.
+
+use super::*;
+use std::fs::File;
+use std::io::{Read as _, Seek as _, Write as _};
+use std::path::Path;
+use std::sync::Mutex;
+
+const BLOCK_SIZE: usize = Block512::BLOCK_SIZE;
+
+/// A block device backed by an image file, trailing bytes are ignored
+pub struct FileBlockDevice {
+    file: Mutex<File>,
+    blocks: usize,
+}
+
+impl FileBlockDevice {
+    /// Open the image for reading and writing
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = File::options().read(true).write(true).open(path)?;
+        Self::new(file)
+    }
+
+    pub fn new(file: File) -> std::io::Result<Self> {
+        let blocks = file.metadata()?.len() as usize / BLOCK_SIZE;
+        Ok(Self {
+            file: Mutex::new(file),
+            blocks,
+        })
+    }
+
+    /// Seek to the block at `offset`
+    fn seek(&self, file: &mut File, offset: usize) -> Result<()> {
+        if offset >= self.blocks {
+            return Err(FsError::InvalidOffset);
+        }
+
+        file.seek(std::io::SeekFrom::Start((offset * BLOCK_SIZE) as u64))
+            .map_err(|_| FsError::InvalidOffset)?;
+        Ok(())
+    }
+}
+
+impl BlockDevice<Block512> for FileBlockDevice {
+    fn block_count(&self) -> Result<usize> {
+        Ok(self.blocks)
+    }
+
+    fn read_block(&self, offset: usize, block: &mut Block512) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+        self.seek(&mut file, offset)?;
+        file.read_exact(block.as_mut())
+            .map_err(|_| DeviceError::ReadError.into())
+    }
+
+    fn write_block(&self, offset: usize, block: &Block512) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+        self.seek(&mut file, offset)?;
+        file.write_all(block.as_ref())
+            .map_err(|_| DeviceError::WriteError.into())
+    }
+}
+
+impl core::fmt::Debug for FileBlockDevice {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("FileBlockDevice")
+            .field("blocks", &self.blocks)
+            .finish()
+    }
+}
@@ -11,6 +11,14 @@ impl FileHandle {
     pub fn new(meta: Metadata, file: Box<dyn FileIO + Send>) -> Self {
         Self { meta, file }
     }
+
+    /// The current size of the file, `meta` is from when it was opened
+    pub fn size(&mut self) -> Result<usize> {
+        let offset = self.file.seek(SeekFrom::Current(0))?;
+        let size = self.file.seek(SeekFrom::End(0))?;
+        self.file.seek(SeekFrom::Start(offset))?;
+        Ok(size)
+    }
 }
 
 impl Deref for FileHandle {
@@ -41,6 +41,11 @@ pub trait FileSystem: Debug + Sync + Send {
         Err(FsError::NotSupported)
     }
 
+    /// Creates a directory at this path, its parent must exist
+    fn create_dir(&self, _path: &str) -> Result<()> {
+        Err(FsError::NotSupported)
+    }
+
     /// Copies the src path to the destination path within the same filesystem
     fn copy_file(&self, _src: &str, _dst: &str) -> Result<()> {
         Err(FsError::NotSupported)
@@ -55,4 +60,19 @@ pub trait FileSystem: Debug + Sync + Send {
     fn move_dir(&self, _src: &str, _dst: &str) -> Result<()> {
         Err(FsError::NotSupported)
     }
+
+    /// Replaces the attributes of the file or directory at this path
+    fn set_attributes(&self, _path: &str, _attributes: FileAttributes) -> Result<()> {
+        Err(FsError::NotSupported)
+    }
+
+    /// Writes the changes kept in memory back to the device
+    fn sync(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// The file system as `Any`, for the users of a concrete type to find it
+    fn as_any(&self) -> Option<&dyn core::any::Any> {
+        None
+    }
 }
@@ -1,4 +1,5 @@
 use crate::*;
+use bitflags::bitflags;
 use chrono::{DateTime, Utc};
 
 pub type FsTime = DateTime<Utc>;
@@ -10,6 +11,22 @@ pub enum FileType {
     File,
     /// A Directory
     Directory,
+    /// A device node, e.g. in a devfs
+    Device,
+}
+
+bitflags! {
+    /// Attributes of a file entry, with the bits of the Fat attribute byte
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub struct FileAttributes: u8 {
+        /// The entry can not be written, removed or renamed,
+        /// nothing can be added to or removed from a read-only directory
+        const READ_ONLY = 0x01;
+        /// The entry is not listed
+        const HIDDEN    = 0x02;
+        /// The entry belongs to the system
+        const SYSTEM    = 0x04;
+    }
 }
 
 #[derive(Debug)]
@@ -27,6 +44,8 @@ pub struct Metadata {
     pub modified: Option<FsTime>,
     /// Access time of the file
     pub accessed: Option<FsTime>,
+    /// Attributes of the entry, empty if the file system has none
+    pub attributes: FileAttributes,
 }
 
 impl Metadata {
@@ -46,9 +65,17 @@ impl Metadata {
             modified,
             accessed,
             entry_type,
+            attributes: FileAttributes::empty(),
         }
     }
 
+    /// Set the attributes of the entry
+    #[inline]
+    pub fn with_attributes(mut self, attributes: FileAttributes) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
     /// Return `true` if the entry is a file
     #[inline]
     pub fn is_file(&self) -> bool {
@@ -60,4 +87,16 @@ impl Metadata {
     pub fn is_dir(&self) -> bool {
         self.entry_type == FileType::Directory
     }
+
+    /// Return `true` if the entry is a device node
+    #[inline]
+    pub fn is_device(&self) -> bool {
+        self.entry_type == FileType::Device
+    }
+
+    /// Return `true` if the entry can not be changed
+    #[inline]
+    pub fn is_read_only(&self) -> bool {
+        self.attributes.contains(FileAttributes::READ_ONLY)
+    }
 }
@@ -2,19 +2,28 @@
 mod macros;
 
 mod block;
+mod cache;
 mod device;
 mod error;
+#[cfg(feature = "std")]
+mod filedevice;
 mod filehandle;
 mod filesystem;
 mod io;
 mod metadata;
 mod mount;
+#[cfg(any(test, feature = "std"))]
+#[doc(hidden)]
+pub mod testing;
 
 use super::*;
 
 pub use block::*;
+pub use cache::*;
 pub use device::*;
 pub use error::*;
+#[cfg(feature = "std")]
+pub use filedevice::*;
 pub use filehandle::*;
 pub use filesystem::*;
 pub use io::*;
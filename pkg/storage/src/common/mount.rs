@@ -1,4 +1,6 @@
 use super::*;
+use alloc::collections::BTreeMap;
+use spin::RwLock;
 
 /// Mount a file system to a specific path
 ///
@@ -6,17 +8,108 @@ use super::*;
 pub struct Mount {
     pub fs: Box<dyn FileSystem>,
     pub mount_point: Box<str>,
+    /// The device the file system is loaded from
+    pub device: Box<str>,
+    /// The name of the file system type
+    pub fs_type: Box<str>,
+    /// Nothing on the file system can be changed through the mount
+    pub read_only: bool,
+    /// Directories in which nothing can be changed through the mount,
+    /// whatever their attributes on the file system
+    pub protected: Vec<Box<str>>,
 }
 
 impl Mount {
     #[inline]
     pub fn new(fs: Box<dyn FileSystem>, mount_point: Box<str>) -> Self {
-        Self { fs, mount_point }
+        Self {
+            fs,
+            mount_point,
+            device: "none".into(),
+            fs_type: "unknown".into(),
+            read_only: false,
+            protected: Vec::new(),
+        }
     }
 
+    /// Record where the file system comes from, for the mount listing
     #[inline]
-    fn trim_mount_point<'a>(&self, path: &'a str) -> &'a str {
-        path.trim_start_matches(self.mount_point.as_ref())
+    pub fn with_source(mut self, device: &str, fs_type: &str) -> Self {
+        self.device = device.into();
+        self.fs_type = fs_type.into();
+        self
+    }
+
+    /// Reject every change to the file system with `FsError::ReadOnly`
+    #[inline]
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// Reject every change in the directory, given relative to the mount
+    /// point, with `FsError::ReadOnly`
+    #[inline]
+    pub fn protect(mut self, path: &str) -> Self {
+        self.protected.push(path.into());
+        self
+    }
+
+    /// Strip the mount point from the path, the result always starts with `/`
+    ///
+    /// The mount point only matches on whole path components,
+    /// e.g. `/mnt` matches `/mnt/a` but not `/mnt2/a`.
+    #[inline]
+    pub fn trim_mount_point<'a>(&self, path: &'a str) -> &'a str {
+        match path.strip_prefix(self.mount_point.trim_end_matches(PATH_SEPARATOR)) {
+            Some("") => "/",
+            Some(rest) if rest.starts_with(PATH_SEPARATOR) => rest,
+            _ => path,
+        }
+    }
+
+    /// The trimmed path and each of its ancestors, up to the root
+    fn ancestors(path: &str) -> impl Iterator<Item = &str> {
+        let path = path.trim_end_matches(PATH_SEPARATOR);
+        let mut next = Some(path);
+        core::iter::from_fn(move || {
+            let current = next?;
+            next = current
+                .rsplit_once(PATH_SEPARATOR)
+                .map(|(parent, _)| parent);
+            Some(if current.is_empty() { "/" } else { current })
+        })
+    }
+
+    /// Whether the trimmed path is in, or is, a protected directory
+    ///
+    /// Names are compared ignoring the ASCII case, as FAT matches them.
+    fn is_protected(&self, path: &str) -> bool {
+        Self::ancestors(path).any(|dir| {
+            self.protected
+                .iter()
+                .any(|protected| protected.eq_ignore_ascii_case(dir))
+        })
+    }
+
+    /// Fail with `FsError::ReadOnly` if the entry at the trimmed path
+    /// may not be changed, or added to or removed from its directory
+    ///
+    /// The entry and every directory above it are checked, entries that
+    /// do not exist yet, and file systems that can not report the metadata
+    /// of their root, pass the attribute check.
+    fn check_writable(&self, path: &str) -> Result<()> {
+        if self.read_only || self.is_protected(path) {
+            return Err(FsError::ReadOnly);
+        }
+
+        for path in Self::ancestors(path) {
+            if self.fs.metadata(path).is_ok_and(|meta| meta.is_read_only()) {
+                return Err(FsError::ReadOnly);
+            }
+        }
+
+        Ok(())
     }
 }
 
@@ -40,13 +133,541 @@ impl FileSystem for Mount {
     fn exists(&self, path: &str) -> Result<bool> {
         self.fs.exists(self.trim_mount_point(path))
     }
+
+    #[inline]
+    fn create_file(&self, path: &str) -> Result<FileHandle> {
+        let path = self.trim_mount_point(path);
+        self.check_writable(path)?;
+        self.fs.create_file(path)
+    }
+
+    #[inline]
+    fn append_file(&self, path: &str) -> Result<FileHandle> {
+        let path = self.trim_mount_point(path);
+        self.check_writable(path)?;
+        self.fs.append_file(path)
+    }
+
+    #[inline]
+    fn remove_file(&self, path: &str) -> Result<FileHandle> {
+        let path = self.trim_mount_point(path);
+        self.check_writable(path)?;
+        self.fs.remove_file(path)
+    }
+
+    #[inline]
+    fn remove_dir(&self, path: &str) -> Result<FileHandle> {
+        let path = self.trim_mount_point(path);
+        self.check_writable(path)?;
+        self.fs.remove_dir(path)
+    }
+
+    #[inline]
+    fn create_dir(&self, path: &str) -> Result<()> {
+        let path = self.trim_mount_point(path);
+        self.check_writable(path)?;
+        self.fs.create_dir(path)
+    }
+
+    #[inline]
+    fn copy_file(&self, src: &str, dst: &str) -> Result<()> {
+        let (src, dst) = (self.trim_mount_point(src), self.trim_mount_point(dst));
+        self.check_writable(dst)?;
+        self.fs.copy_file(src, dst)
+    }
+
+    #[inline]
+    fn move_file(&self, src: &str, dst: &str) -> Result<()> {
+        let (src, dst) = (self.trim_mount_point(src), self.trim_mount_point(dst));
+        self.check_writable(src)?;
+        self.check_writable(dst)?;
+        self.fs.move_file(src, dst)
+    }
+
+    #[inline]
+    fn move_dir(&self, src: &str, dst: &str) -> Result<()> {
+        let (src, dst) = (self.trim_mount_point(src), self.trim_mount_point(dst));
+        self.check_writable(src)?;
+        self.check_writable(dst)?;
+        self.fs.move_dir(src, dst)
+    }
+
+    /// Allowed on read-only entries, so that they can be made writable again,
+    /// but not in protected directories
+    #[inline]
+    fn set_attributes(&self, path: &str, attributes: FileAttributes) -> Result<()> {
+        if self.read_only || self.is_protected(self.trim_mount_point(path)) {
+            return Err(FsError::ReadOnly);
+        }
+        self.fs
+            .set_attributes(self.trim_mount_point(path), attributes)
+    }
+
+    #[inline]
+    fn sync(&self) -> Result<()> {
+        self.fs.sync()
+    }
 }
 
 impl core::fmt::Debug for Mount {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Mount")
             .field("mount_point", &self.mount_point)
+            .field("device", &self.device)
+            .field("fs_type", &self.fs_type)
             .field("fs", &self.fs)
             .finish()
     }
 }
+
+impl core::fmt::Display for Mount {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} on {} type {}",
+            self.device, self.mount_point, self.fs_type
+        )?;
+        if self.read_only {
+            write!(f, " (ro)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Normalize an absolute path
+///
+/// Empty and `.` components are dropped and `..` removes the previous one,
+/// so the result is either `/` or a path without trailing separator.
+pub fn normalize_path(path: &str) -> Result<String> {
+    if !path.starts_with(PATH_SEPARATOR) {
+        return Err(FsError::InvalidPath(path.into()));
+    }
+
+    let mut parts = Vec::new();
+    for part in path.split(PATH_SEPARATOR) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+
+    let mut normalized = String::with_capacity(path.len());
+    for part in parts {
+        normalized.push(PATH_SEPARATOR);
+        normalized.push_str(part);
+    }
+
+    if normalized.is_empty() {
+        normalized.push(PATH_SEPARATOR);
+    }
+
+    Ok(normalized)
+}
+
+/// The virtual file system, a set of file systems mounted at different paths
+///
+/// Every path is resolved to the mount with the longest matching mount point.
+/// Mount points do not need to exist in the parent file system,
+/// they and their ancestors are listed as directories.
+pub struct MountTable {
+    mounts: RwLock<BTreeMap<String, Arc<Mount>>>,
+}
+
+impl MountTable {
+    pub fn new() -> Self {
+        Self {
+            mounts: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// Add a file system to the table at its mount point
+    pub fn mount(&self, mut mount: Mount) -> Result<()> {
+        let path = normalize_path(&mount.mount_point)?;
+        mount.mount_point = path.as_str().into();
+
+        let mut mounts = self.mounts.write();
+        if mounts.contains_key(&path) {
+            return Err(FsError::AlreadyExists);
+        }
+
+        info!("Mounted {}", mount);
+        mounts.insert(path, Arc::new(mount));
+        Ok(())
+    }
+
+    /// Remove the file system mounted at the path
+    ///
+    /// Fails if other file systems are mounted below it.
+    pub fn umount(&self, path: &str) -> Result<Arc<Mount>> {
+        let path = normalize_path(path)?;
+
+        let mut mounts = self.mounts.write();
+        if !mounts.contains_key(&path) {
+            return Err(FsError::FileNotFound);
+        }
+
+        if mounts.keys().any(|p| Self::child_of(p, &path).is_some()) {
+            return Err(FsError::InvalidOperation);
+        }
+
+        let mount = mounts.remove(&path).unwrap();
+        info!("Unmounted {}", mount);
+        Ok(mount)
+    }
+
+    /// All mounts, ordered by mount point
+    pub fn mounts(&self) -> Vec<Arc<Mount>> {
+        self.mounts.read().values().cloned().collect()
+    }
+
+    /// Find the mount holding the path
+    ///
+    /// Returns the mount and the normalized path
+    pub fn resolve(&self, path: &str) -> Result<(Arc<Mount>, String)> {
+        let path = normalize_path(path)?;
+        let mounts = self.mounts.read();
+
+        let mut prefix = path.as_str();
+        loop {
+            if let Some(mount) = mounts.get(prefix) {
+                return Ok((mount.clone(), path));
+            }
+
+            prefix = match prefix.rfind(PATH_SEPARATOR) {
+                Some(0) if prefix.len() > 1 => "/",
+                Some(idx) if idx > 0 => &prefix[..idx],
+                _ => return Err(FsError::FileNotFound),
+            };
+        }
+    }
+
+    /// The first component of `path` below `dir`, if `path` is inside `dir`
+    fn child_of<'a>(path: &'a str, dir: &str) -> Option<&'a str> {
+        let rest = path.strip_prefix(dir.trim_end_matches(PATH_SEPARATOR))?;
+        let rest = rest.strip_prefix(PATH_SEPARATOR)?;
+        rest.split(PATH_SEPARATOR).next().filter(|s| !s.is_empty())
+    }
+
+    /// Names of the directories in `dir` that lead to mount points
+    fn mounted_children(&self, dir: &str) -> Vec<String> {
+        let mut children: Vec<String> = self
+            .mounts
+            .read()
+            .keys()
+            .filter_map(|p| Self::child_of(p, dir))
+            .map(String::from)
+            .collect();
+        children.sort();
+        children.dedup();
+        children
+    }
+
+    /// Returns true if the path is a mount point or leads to one
+    fn is_mount_path(&self, path: &str) -> bool {
+        let mounts = self.mounts.read();
+        mounts.contains_key(path) || mounts.keys().any(|p| Self::child_of(p, path).is_some())
+    }
+
+    fn mount_dir_metadata(path: &str) -> Metadata {
+        let name = path.rsplit(PATH_SEPARATOR).next().unwrap_or_default();
+        let name = if name.is_empty() { "/" } else { name };
+        Metadata::new(name.into(), FileType::Directory, 0, None, None, None)
+    }
+}
+
+impl Default for MountTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem for MountTable {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = Metadata> + Send>> {
+        let (mount, path) = self.resolve(path)?;
+        let children = self.mounted_children(&path);
+
+        let mut entries: Vec<Metadata> = match mount.read_dir(&path) {
+            Ok(iter) => iter.collect(),
+            // the directory only exists as the parent of a mount point
+            Err(FsError::FileNotFound) if !children.is_empty() => Vec::new(),
+            Err(err) => return Err(err),
+        };
+
+        entries.retain(|e| !children.iter().any(|c| c.eq_ignore_ascii_case(&e.name)));
+        entries.extend(
+            children
+                .into_iter()
+                .map(|name| Metadata::new(name, FileType::Directory, 0, None, None, None)),
+        );
+
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn open_file(&self, path: &str) -> Result<FileHandle> {
+        let (mount, path) = self.resolve(path)?;
+        mount.open_file(&path)
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        let (mount, path) = self.resolve(path)?;
+        if self.is_mount_path(&path) {
+            return Ok(Self::mount_dir_metadata(&path));
+        }
+        mount.metadata(&path)
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        let (mount, path) = self.resolve(path)?;
+        if self.is_mount_path(&path) {
+            return Ok(true);
+        }
+        mount.exists(&path)
+    }
+
+    fn create_file(&self, path: &str) -> Result<FileHandle> {
+        let (mount, path) = self.resolve(path)?;
+        mount.create_file(&path)
+    }
+
+    fn append_file(&self, path: &str) -> Result<FileHandle> {
+        let (mount, path) = self.resolve(path)?;
+        mount.append_file(&path)
+    }
+
+    fn remove_file(&self, path: &str) -> Result<FileHandle> {
+        let (mount, path) = self.resolve(path)?;
+        mount.remove_file(&path)
+    }
+
+    fn remove_dir(&self, path: &str) -> Result<FileHandle> {
+        let (mount, path) = self.resolve(path)?;
+        if self.is_mount_path(&path) {
+            return Err(FsError::InvalidOperation);
+        }
+        mount.remove_dir(&path)
+    }
+
+    fn create_dir(&self, path: &str) -> Result<()> {
+        let (mount, path) = self.resolve(path)?;
+        if self.is_mount_path(&path) {
+            return Err(FsError::AlreadyExists);
+        }
+        mount.create_dir(&path)
+    }
+
+    fn copy_file(&self, src: &str, dst: &str) -> Result<()> {
+        let (src_mount, src) = self.resolve(src)?;
+        let (dst_mount, dst) = self.resolve(dst)?;
+
+        if Arc::ptr_eq(&src_mount, &dst_mount) {
+            return src_mount.copy_file(&src, &dst);
+        }
+
+        // copy the content across file systems
+        let mut src = src_mount.open_file(&src)?;
+        let mut dst = dst_mount.create_file(&dst)?;
+        let mut buf = vec![0u8; 4096];
+        loop {
+            let len = src.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+
+            let mut written = 0;
+            while written < len {
+                match dst.write(&buf[written..len])? {
+                    0 => return Err(FsError::WriteZero),
+                    n => written += n,
+                }
+            }
+        }
+        dst.flush()
+    }
+
+    fn move_file(&self, src: &str, dst: &str) -> Result<()> {
+        let (src_mount, src) = self.resolve(src)?;
+        let (dst_mount, dst) = self.resolve(dst)?;
+
+        if !Arc::ptr_eq(&src_mount, &dst_mount) {
+            return Err(FsError::NotSupported);
+        }
+        src_mount.move_file(&src, &dst)
+    }
+
+    fn move_dir(&self, src: &str, dst: &str) -> Result<()> {
+        let (src_mount, src) = self.resolve(src)?;
+        let (dst_mount, dst) = self.resolve(dst)?;
+
+        if !Arc::ptr_eq(&src_mount, &dst_mount) {
+            return Err(FsError::NotSupported);
+        }
+        if self.is_mount_path(&src) {
+            return Err(FsError::InvalidOperation);
+        }
+        src_mount.move_dir(&src, &dst)
+    }
+
+    fn set_attributes(&self, path: &str, attributes: FileAttributes) -> Result<()> {
+        let (mount, path) = self.resolve(path)?;
+        mount.set_attributes(&path, attributes)
+    }
+
+    /// Sync every mount, all of them are tried even if one fails
+    fn sync(&self) -> Result<()> {
+        let mut result = Ok(());
+        for mount in self.mounts() {
+            if let Err(err) = mount.sync() {
+                warn!("Failed to sync {}: {:?}", mount, err);
+                result = Err(err);
+            }
+        }
+        result
+    }
+}
+
+impl core::fmt::Debug for MountTable {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_list().entries(self.mounts.read().values()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file system that only knows a fixed list of files
+    #[derive(Debug)]
+    struct DummyFs(&'static [&'static str]);
+
+    impl FileSystem for DummyFs {
+        fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = Metadata> + Send>> {
+            if path != "/" {
+                return Err(FsError::FileNotFound);
+            }
+            Ok(Box::new(self.0.iter().map(|name| {
+                Metadata::new(name.to_string(), FileType::File, 0, None, None, None)
+            })))
+        }
+
+        fn open_file(&self, _path: &str) -> Result<FileHandle> {
+            Err(FsError::NotSupported)
+        }
+
+        fn metadata(&self, path: &str) -> Result<Metadata> {
+            self.0
+                .iter()
+                .find(|name| path.strip_prefix('/') == Some(name))
+                .map(|name| Metadata::new(name.to_string(), FileType::File, 0, None, None, None))
+                .ok_or(FsError::FileNotFound)
+        }
+
+        fn exists(&self, path: &str) -> Result<bool> {
+            Ok(self.metadata(path).is_ok())
+        }
+    }
+
+    fn table() -> MountTable {
+        let table = MountTable::new();
+        let mounts: [(&'static [&'static str], &str); 4] = [
+            (&["ROOT.TXT", "MNT"], "/"),
+            (&["A.TXT"], "/mnt/disk"),
+            (&["B.TXT"], "/mnt/disk/sub/"),
+            (&["C.TXT"], "/tmp"),
+        ];
+        for (files, path) in mounts {
+            table
+                .mount(Mount::new(Box::new(DummyFs(files)), path.into()))
+                .unwrap();
+        }
+        table
+    }
+
+    fn names(table: &MountTable, path: &str) -> Vec<String> {
+        table.read_dir(path).unwrap().map(|m| m.name).collect()
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("/").unwrap(), "/");
+        assert_eq!(normalize_path("//a/./b/").unwrap(), "/a/b");
+        assert_eq!(normalize_path("/a/../../b").unwrap(), "/b");
+        assert!(normalize_path("a/b").is_err());
+    }
+
+    #[test]
+    fn test_longest_prefix() {
+        let table = table();
+        let resolve = |path| {
+            let (mount, path) = table.resolve(path).unwrap();
+            (
+                mount.mount_point.to_string(),
+                mount.trim_mount_point(&path).to_string(),
+            )
+        };
+
+        assert_eq!(resolve("/ROOT.TXT"), ("/".into(), "/ROOT.TXT".into()));
+        assert_eq!(resolve("/mnt/disk"), ("/mnt/disk".into(), "/".into()));
+        assert_eq!(
+            resolve("/mnt/disk/A.TXT"),
+            ("/mnt/disk".into(), "/A.TXT".into())
+        );
+        assert_eq!(
+            resolve("/mnt/disk/sub/B.TXT"),
+            ("/mnt/disk/sub".into(), "/B.TXT".into())
+        );
+        assert_eq!(
+            resolve("/mnt/disk2/A.TXT"),
+            ("/".into(), "/mnt/disk2/A.TXT".into())
+        );
+        assert_eq!(
+            resolve("/tmp/../tmp/C.TXT"),
+            ("/tmp".into(), "/C.TXT".into())
+        );
+    }
+
+    #[test]
+    fn test_mount_points_are_listed() {
+        let table = table();
+
+        assert_eq!(names(&table, "/"), ["ROOT.TXT", "mnt", "tmp"]);
+        assert_eq!(names(&table, "/mnt"), ["disk"]);
+        assert_eq!(names(&table, "/mnt/disk"), ["A.TXT", "sub"]);
+        assert_eq!(names(&table, "/mnt/disk/sub"), ["B.TXT"]);
+
+        assert!(table.exists("/mnt/disk/A.TXT").unwrap());
+        assert!(table.metadata("/mnt").unwrap().is_dir());
+        assert_eq!(table.metadata("/tmp/C.TXT").unwrap().name, "C.TXT");
+    }
+
+    #[test]
+    fn test_mount_and_umount() {
+        let table = table();
+
+        let dup = Mount::new(Box::new(DummyFs(&[])), "/tmp/".into());
+        assert_eq!(table.mount(dup).err(), Some(FsError::AlreadyExists));
+
+        assert_eq!(
+            table.umount("/mnt/disk").err(),
+            Some(FsError::InvalidOperation)
+        );
+        assert_eq!(table.umount("/mnt").err(), Some(FsError::FileNotFound));
+        assert_eq!(
+            table.remove_dir("/mnt").err(),
+            Some(FsError::InvalidOperation)
+        );
+
+        table.umount("/mnt/disk/sub").unwrap();
+        table.umount("/mnt/disk").unwrap();
+        assert_eq!(names(&table, "/"), ["ROOT.TXT", "MNT", "tmp"]);
+        assert_eq!(table.exists("/mnt/disk/A.TXT"), Ok(false));
+
+        let mounts: Vec<String> = table.mounts().iter().map(|m| m.to_string()).collect();
+        assert_eq!(
+            mounts,
+            ["none on / type unknown", "none on /tmp type unknown"]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! Helpers shared by the unit tests and the host image tests
+
+use super::*;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+use std::sync::RwLock;
+
+const BLOCK_SIZE: usize = Block512::BLOCK_SIZE;
+
+/// Content of the byte at `offset` in the generated files
+pub fn pattern(seed: usize, offset: usize) -> u8 {
+    ((offset % 251) ^ seed) as u8
+}
+
+/// A block device backed by memory
+pub struct MemoryDevice {
+    data: RwLock<Vec<u8>>,
+}
+
+impl MemoryDevice {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self {
+            data: RwLock::new(data),
+        }
+    }
+}
+
+impl BlockDevice<Block512> for MemoryDevice {
+    fn block_count(&self) -> Result<usize> {
+        Ok(self.data.read().unwrap().len() / BLOCK_SIZE)
+    }
+
+    fn read_block(&self, offset: usize, block: &mut Block512) -> Result<()> {
+        let data = self.data.read().unwrap();
+        let range = offset * BLOCK_SIZE..(offset + 1) * BLOCK_SIZE;
+        block
+            .as_mut()
+            .copy_from_slice(data.get(range).ok_or(FsError::InvalidOffset)?);
+        Ok(())
+    }
+
+    fn write_block(&self, offset: usize, block: &Block512) -> Result<()> {
+        let mut data = self.data.write().unwrap();
+        let range = offset * BLOCK_SIZE..(offset + 1) * BLOCK_SIZE;
+        data.get_mut(range)
+            .ok_or(FsError::InvalidOffset)?
+            .copy_from_slice(block.as_ref());
+        Ok(())
+    }
+}
+
+/// Partition tables need a `Clone` device, a leaked `MemoryDevice` can be shared
+impl BlockDevice<Block512> for &'static MemoryDevice {
+    fn block_count(&self) -> Result<usize> {
+        (*self).block_count()
+    }
+
+    fn read_block(&self, offset: usize, block: &mut Block512) -> Result<()> {
+        (*self).read_block(offset, block)
+    }
+
+    fn write_block(&self, offset: usize, block: &Block512) -> Result<()> {
+        (*self).write_block(offset, block)
+    }
+}
+
+/// A directory removed on drop
+pub struct TempDir(pub PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("ysos-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Run a host tool, panics if it is not installed or fails
+pub fn run(command: &mut Command) -> Output {
+    match command.output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => panic!(
+            "{:?} failed: {}{}",
+            command,
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(err) => panic!(
+            "failed to run {:?}, is it installed? {}",
+            command.get_program(),
+            err
+        ),
+    }
+}
+
+pub fn read_to_end(file: &mut FileHandle) -> Vec<u8> {
+    let mut content = Vec::new();
+    let mut buf = [0u8; 777];
+    loop {
+        let len = file.read(&mut buf).unwrap();
+        if len == 0 {
+            return content;
+        }
+        content.extend_from_slice(&buf[..len]);
+    }
+}
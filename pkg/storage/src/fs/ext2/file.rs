@@ -0,0 +1,104 @@
+//! File
+//!
+//! reference: <https://wiki.osdev.org/Ext2#Determining_which_Block_Contains_the_Data>
+
+use core::cmp::min;
+
+use super::*;
+
+#[derive(Debug, Clone)]
+pub struct File {
+    /// The current offset in the file
+    offset: usize,
+    /// The last resolved block index and its address, 0 for a hole
+    current_block: Option<(usize, u32)>,
+    /// Inode of this file
+    inode: Inode,
+    /// The file system handle that contains this file
+    handle: Ext2Handle,
+}
+
+impl File {
+    pub fn new(handle: Ext2Handle, inode: Inode) -> Self {
+        Self {
+            offset: 0,
+            current_block: None,
+            inode,
+            handle,
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.inode.size()
+    }
+
+    /// The address of the block holding the byte at `offset`
+    fn locate_block(&mut self) -> Result<u32> {
+        let index = self.offset / self.handle.block_size;
+
+        match self.current_block {
+            Some((current, address)) if current == index => Ok(address),
+            _ => {
+                let address = self.handle.block_address(&self.inode, index)?;
+                self.current_block = Some((index, address));
+                Ok(address)
+            }
+        }
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let to_read = min(buf.len(), self.length().saturating_sub(self.offset));
+        let mut read_bytes = 0;
+        let mut block = Block::default();
+
+        while read_bytes < to_read {
+            let address = self.locate_block()?;
+
+            let block_offset = self.offset % self.handle.block_size;
+            let byte_offset = block_offset % BLOCK_SIZE;
+            let bytes_to_read = min(to_read - read_bytes, BLOCK_SIZE - byte_offset);
+            let dest = &mut buf[read_bytes..read_bytes + bytes_to_read];
+
+            // unallocated blocks of sparse files read as zeros
+            if address == 0 {
+                dest.fill(0);
+            } else {
+                let sector = self.handle.block_to_sector(address) + block_offset / BLOCK_SIZE;
+                self.handle.inner.read_block(sector, &mut block)?;
+                dest.copy_from_slice(&block[byte_offset..byte_offset + bytes_to_read]);
+            }
+
+            read_bytes += bytes_to_read;
+            self.offset += bytes_to_read;
+        }
+
+        Ok(read_bytes)
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.length().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+        }
+        .filter(|offset| *offset <= self.length())
+        .ok_or(FsError::InvalidOffset)?;
+
+        self.offset = offset;
+        Ok(offset)
+    }
+}
+
+impl Write for File {
+    fn write(&mut self, _buf: &[u8]) -> Result<usize> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use super::*;
+
+impl Ext2Impl {
+    pub fn new(inner: impl BlockDevice<Block512>) -> Result<Self> {
+        let mut data = [0u8; 1024];
+        read_bytes(&inner, Superblock::OFFSET, &mut data)?;
+
+        let superblock = Superblock::new(&data).map_err(|err| {
+            warn!("Failed to load Ext2 volume: {}", err);
+            FsError::InvalidFileSystem
+        })?;
+
+        trace!("Loading Ext2 Volume: {:#?}", superblock);
+
+        if superblock.rev_level() > 0 && superblock.feature_ro_compat() != 0 {
+            debug!(
+                "Ext2 read-only compatible features {:#x} are ignored",
+                superblock.feature_ro_compat()
+            );
+        }
+
+        // the descriptor table starts at the block after the superblock
+        let block_size = superblock.block_size();
+        let table_start = (superblock.first_data_block() as usize + 1) * block_size;
+        let mut table = vec![0u8; superblock.group_count() * GroupDescriptor::LEN];
+        read_bytes(&inner, table_start, &mut table)?;
+
+        let groups = table
+            .chunks(GroupDescriptor::LEN)
+            .map(GroupDescriptor::new)
+            .collect();
+
+        Ok(Self {
+            inner: Box::new(inner),
+            superblock,
+            groups,
+            block_size,
+        })
+    }
+
+    /// The first sector of the block
+    pub fn block_to_sector(&self, block: u32) -> usize {
+        block as usize * (self.block_size / BLOCK_SIZE)
+    }
+
+    /// Read a whole block into a buffer
+    fn read_block(&self, block: u32, buf: &mut [u8]) -> Result<()> {
+        read_bytes(self.inner.as_ref(), block as usize * self.block_size, buf)
+    }
+
+    pub fn read_inode(&self, ino: u32) -> Result<Inode> {
+        let index = ino.checked_sub(1).ok_or(FsError::FileNotFound)? as usize;
+        let per_group = self.superblock.inodes_per_group() as usize;
+        let group = self
+            .groups
+            .get(index / per_group)
+            .ok_or(FsError::InvalidFileSystem)?;
+
+        let offset = group.inode_table() as usize * self.block_size
+            + (index % per_group) * self.superblock.inode_size();
+
+        let mut data = [0u8; Inode::LEN];
+        read_bytes(self.inner.as_ref(), offset, &mut data)?;
+        Ok(Inode::new(&data))
+    }
+
+    /// Read the `index`-th block number stored in an indirect block
+    fn read_pointer(&self, block: u32, index: usize) -> Result<u32> {
+        let mut data = [0u8; 4];
+        read_bytes(
+            self.inner.as_ref(),
+            block as usize * self.block_size + index * 4,
+            &mut data,
+        )?;
+        Ok(u32::from_le_bytes(data))
+    }
+
+    /// Map a block index in the file to its address on the volume
+    ///
+    /// Returns 0 for holes in sparse files.
+    pub fn block_address(&self, inode: &Inode, index: usize) -> Result<u32> {
+        if index < Inode::DIRECT_BLOCKS {
+            return Ok(inode.block_pointer(index));
+        }
+
+        let per_block = self.block_size / 4;
+        let mut index = index - Inode::DIRECT_BLOCKS;
+        let mut span = 1;
+
+        // singly, doubly and triply indirect blocks
+        for level in 0..3 {
+            span *= per_block;
+            if index >= span {
+                index -= span;
+                continue;
+            }
+
+            let mut block = inode.block_pointer(Inode::DIRECT_BLOCKS + level);
+            for depth in (0..=level as u32).rev() {
+                if block == 0 {
+                    return Ok(0);
+                }
+                let stride = per_block.pow(depth);
+                block = self.read_pointer(block, index / stride)?;
+                index %= stride;
+            }
+            return Ok(block);
+        }
+
+        Err(FsError::InvalidOffset)
+    }
+
+    /// Calls `process_entry` for every used entry in the directory
+    fn traverse_dir_entries<F>(&self, dir: &Inode, mut process_entry: F) -> Result<()>
+    where
+        F: FnMut(DirEntry) -> Result<()>,
+    {
+        let has_filetype = self.superblock.has_filetype();
+        let mut block = vec![0u8; self.block_size];
+
+        for index in 0..dir.size().div_ceil(self.block_size) {
+            match self.block_address(dir, index)? {
+                0 => continue,
+                address => self.read_block(address, &mut block)?,
+            }
+
+            let mut offset = 0;
+            while offset < block.len() {
+                let (entry, rec_len) = DirEntry::parse(&block[offset..], has_filetype)?;
+                if let Some(entry) = entry {
+                    process_entry(entry)?;
+                }
+                offset += rec_len;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_dir_entry_by_name(&self, dir: &Inode, name: &str) -> Result<DirEntry> {
+        let mut result = None;
+        self.traverse_dir_entries(dir, |entry| {
+            if result.is_none() && entry.name == name {
+                result = Some(entry);
+            }
+            Ok(())
+        })?;
+        result.ok_or(FsError::FileNotFound)
+    }
+
+    /// Walk the path from the root directory
+    ///
+    /// Returns the name and the inode of the last component,
+    /// names are case-sensitive.
+    pub fn lookup(&self, path: &str) -> Result<(String, Inode)> {
+        let mut name = String::from("/");
+        let mut inode = self.read_inode(ROOT_INODE)?;
+
+        for part in path.split(PATH_SEPARATOR).filter(|s| !s.is_empty()) {
+            if !inode.is_dir() {
+                return Err(FsError::NotADirectory);
+            }
+
+            let entry = self.get_dir_entry_by_name(&inode, part)?;
+            inode = self.read_inode(entry.inode)?;
+            name = entry.name;
+        }
+
+        Ok((name, inode))
+    }
+}
+
+/// Read `buf.len()` bytes starting at the byte `offset` of the device
+fn read_bytes(
+    device: &(impl BlockDevice<Block512> + ?Sized),
+    offset: usize,
+    buf: &mut [u8],
+) -> Result<()> {
+    let mut block = Block512::default();
+    let mut read = 0;
+
+    while read < buf.len() {
+        let position = offset + read;
+        let byte_offset = position % BLOCK_SIZE;
+        let len = (BLOCK_SIZE - byte_offset).min(buf.len() - read);
+
+        device.read_block(position / BLOCK_SIZE, &mut block)?;
+        buf[read..read + len].copy_from_slice(&block[byte_offset..byte_offset + len]);
+        read += len;
+    }
+
+    Ok(())
+}
+
+impl FileSystem for Ext2 {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = Metadata> + Send>> {
+        let (_, dir) = self.handle.lookup(path)?;
+        if !dir.is_dir() {
+            return Err(FsError::NotADirectory);
+        }
+
+        let mut entries = Vec::new();
+        self.handle.traverse_dir_entries(&dir, |entry| {
+            if entry.is_displayable() {
+                let inode = self.handle.read_inode(entry.inode)?;
+                entries.push(inode.metadata(entry.name));
+            }
+            Ok(())
+        })?;
+
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn open_file(&self, path: &str) -> Result<FileHandle> {
+        match self.handle.lookup(path)? {
+            (name, inode) if !inode.is_dir() => Ok(FileHandle::new(
+                inode.metadata(name),
+                Box::new(File::new(self.handle.clone(), inode)),
+            )),
+            _ => Err(FsError::NotAFile),
+        }
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        let (name, inode) = self.handle.lookup(path)?;
+        Ok(inode.metadata(name))
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        match self.handle.lookup(path) {
+            Ok(_) => Ok(true),
+            Err(FsError::FileNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn sync(&self) -> Result<()> {
+        self.handle.inner.sync()
+    }
+}
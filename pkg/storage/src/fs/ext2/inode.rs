@@ -0,0 +1,167 @@
+//! Ext2 Inode and Directory Entry
+//!
+//! reference:
+//! - <https://wiki.osdev.org/Ext2#Inodes>
+//! - <https://www.nongnu.org/ext2-doc/ext2.html#linked-directories>
+
+use crate::*;
+use chrono::DateTime;
+
+/// Represents an Inode.
+///
+/// Only the first 128 bytes are used, larger inodes hold
+/// extended fields that are not needed to read the file.
+#[derive(Clone)]
+pub struct Inode {
+    data: [u8; 128],
+}
+
+impl Inode {
+    pub const LEN: usize = 128;
+
+    /// The number of direct block pointers
+    pub const DIRECT_BLOCKS: usize = 12;
+
+    pub const TYPE_MASK: u16 = 0xF000;
+    pub const TYPE_DIRECTORY: u16 = 0x4000;
+    pub const TYPE_FILE: u16 = 0x8000;
+    pub const TYPE_SYMLINK: u16 = 0xA000;
+
+    pub fn new(data: &[u8]) -> Self {
+        Self {
+            data: data[..Self::LEN].try_into().unwrap(),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.mode() & Self::TYPE_MASK == Self::TYPE_DIRECTORY
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.mode() & Self::TYPE_MASK == Self::TYPE_FILE
+    }
+
+    /// Size in bytes, the upper half is only valid for regular files
+    pub fn size(&self) -> usize {
+        let high = if self.is_file() { self.size_high() } else { 0 };
+        ((high as u64) << 32 | self.size_lo() as u64) as usize
+    }
+
+    /// The `index`-th of the 15 block pointers
+    ///
+    /// 0..12 are direct, followed by the singly, doubly
+    /// and triply indirect block pointers.
+    pub fn block_pointer(&self, index: usize) -> u32 {
+        let offset = 0x28 + index * 4;
+        u32::from_le_bytes(self.data[offset..offset + 4].try_into().unwrap())
+    }
+
+    pub fn metadata(&self, name: String) -> Metadata {
+        let (entry_type, len) = if self.is_dir() {
+            (FileType::Directory, 0)
+        } else {
+            (FileType::File, self.size())
+        };
+        let time = |secs: u32| DateTime::from_timestamp(secs as i64, 0);
+
+        // without any write permission bit the entry is read-only
+        let attributes = if self.mode() & 0o222 == 0 {
+            FileAttributes::READ_ONLY
+        } else {
+            FileAttributes::empty()
+        };
+
+        Metadata::new(
+            name,
+            entry_type,
+            len,
+            time(self.ctime()),
+            time(self.mtime()),
+            time(self.atime()),
+        )
+        .with_attributes(attributes)
+    }
+
+    define_field!(u16, 0x00, mode);
+    define_field!(u16, 0x02, uid);
+    define_field!(u32, 0x04, size_lo);
+    define_field!(u32, 0x08, atime);
+    define_field!(u32, 0x0C, ctime);
+    define_field!(u32, 0x10, mtime);
+    define_field!(u32, 0x14, dtime);
+    define_field!(u16, 0x18, gid);
+    define_field!(u16, 0x1A, links_count);
+    define_field!(u32, 0x1C, sectors);
+    define_field!(u32, 0x20, flags);
+    define_field!(u32, 0x6C, size_high);
+}
+
+impl core::fmt::Debug for Inode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Inode")
+            .field("Mode", &format_args!("{:#o}", self.mode()))
+            .field("Size", &self.size())
+            .field("Links Count", &self.links_count())
+            .field("Sectors", &self.sectors())
+            .field("Flags", &format_args!("{:#x}", self.flags()))
+            .finish()
+    }
+}
+
+/// A linked directory entry
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    pub inode: u32,
+    pub name: String,
+}
+
+impl DirEntry {
+    /// Length of the fixed part before the name
+    pub const HEADER_LEN: usize = 8;
+
+    /// Parse the entry at the start of `data`
+    ///
+    /// Returns the entry, or `None` for unused entries,
+    /// and the record length to skip to the next entry.
+    pub fn parse(data: &[u8], has_filetype: bool) -> Result<(Option<DirEntry>, usize)> {
+        if data.len() < Self::HEADER_LEN {
+            return Err(FsError::InvalidFileSystem);
+        }
+
+        let inode = u32::from_le_bytes(data[0..4].try_into().unwrap());
+        let rec_len = u16::from_le_bytes(data[4..6].try_into().unwrap()) as usize;
+        // without the file type feature, the name length is 16 bits
+        let name_len = if has_filetype {
+            data[6] as usize
+        } else {
+            u16::from_le_bytes(data[6..8].try_into().unwrap()) as usize
+        };
+
+        if rec_len < Self::HEADER_LEN
+            || rec_len > data.len()
+            || Self::HEADER_LEN + name_len > rec_len
+        {
+            return Err(FsError::InvalidFileSystem);
+        }
+
+        if inode == 0 {
+            return Ok((None, rec_len));
+        }
+
+        let name = core::str::from_utf8(&data[Self::HEADER_LEN..Self::HEADER_LEN + name_len])
+            .map_err(|_| FilenameError::Utf8Error)?;
+
+        Ok((
+            Some(DirEntry {
+                inode,
+                name: name.into(),
+            }),
+            rec_len,
+        ))
+    }
+
+    /// `.` and `..` are not reported when listing a directory
+    pub fn is_displayable(&self) -> bool {
+        self.name != "." && self.name != ".."
+    }
+}
@@ -0,0 +1,81 @@
+pub mod file;
+pub mod impls;
+pub mod inode;
+pub mod superblock;
+
+#[cfg(test)]
+mod tests;
+
+use crate::*;
+use file::File;
+use inode::*;
+use superblock::*;
+
+const BLOCK_SIZE: usize = 512;
+
+/// The inode of the root directory
+const ROOT_INODE: u32 = 2;
+
+/// Identifies an Ext2 filesystem on the disk.
+pub struct Ext2 {
+    handle: Ext2Handle,
+}
+
+impl Ext2 {
+    pub fn new(inner: impl BlockDevice<Block512>) -> Self {
+        Self::try_new(inner).expect("Failed to load Ext2 volume")
+    }
+
+    /// Load the volume, failing if the device does not hold an Ext2 file system
+    pub fn try_new(inner: impl BlockDevice<Block512>) -> Result<Self> {
+        Ok(Self {
+            handle: Arc::new(Ext2Impl::new(inner)?),
+        })
+    }
+
+    /// Check for the superblock magic on the device
+    pub fn detect(device: &impl BlockDevice<Block512>) -> Result<bool> {
+        let mut data = [0u8; 1024];
+        let mut block = Block512::default();
+
+        for (index, chunk) in data.chunks_mut(BLOCK_SIZE).enumerate() {
+            device.read_block(Superblock::OFFSET / BLOCK_SIZE + index, &mut block)?;
+            chunk.copy_from_slice(block.as_ref());
+        }
+
+        Ok(Superblock::new(&data).is_ok())
+    }
+}
+
+type Ext2Handle = Arc<Ext2Impl>;
+
+/// The Ext2 filesystem, read-only.
+///
+/// The volume is split into block groups, each with its own bitmaps
+/// and part of the inode table. Files are described by inodes,
+/// directories map names to inode numbers.
+///
+/// [ Boot ] [ Superblock ] [ Group Descriptors ] [ Block Group 0 ] ...
+pub struct Ext2Impl {
+    pub(crate) inner: Box<dyn BlockDevice<Block512>>,
+    pub superblock: Superblock,
+    pub groups: Vec<GroupDescriptor>,
+    pub block_size: usize,
+}
+
+impl core::fmt::Debug for Ext2 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Ext2")
+            .field("superblock", &self.handle.superblock)
+            .field("groups", &self.handle.groups.len())
+            .finish()
+    }
+}
+
+impl core::fmt::Debug for Ext2Impl {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Ext2Impl")
+            .field("superblock", &self.superblock)
+            .finish()
+    }
+}
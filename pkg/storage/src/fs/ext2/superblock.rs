@@ -0,0 +1,230 @@
+//! Ext2 Superblock and Block Group Descriptor
+//!
+//! reference:
+//! - <https://wiki.osdev.org/Ext2#Superblock>
+//! - <https://www.nongnu.org/ext2-doc/ext2.html#superblock>
+
+/// Represents the Superblock.
+///
+/// It is located 1024 bytes from the start of the volume
+/// and describes the layout of the file system.
+pub struct Superblock {
+    data: [u8; 1024],
+}
+
+impl Superblock {
+    pub const OFFSET: usize = 1024;
+    pub const MAGIC: u16 = 0xEF53;
+
+    /// The directory entries have a file type byte
+    pub const INCOMPAT_FILETYPE: u32 = 0x0002;
+    /// Incompatible features that can be read by this driver
+    pub const INCOMPAT_SUPPORTED: u32 = Self::INCOMPAT_FILETYPE;
+
+    /// Attempt to parse a superblock from the 1024 bytes at `OFFSET`.
+    pub fn new(data: &[u8]) -> Result<Superblock, &'static str> {
+        let data = data.try_into().map_err(|_| "Bad superblock size")?;
+        let sb = Superblock { data };
+
+        if sb.magic() != Self::MAGIC {
+            return Err("Bad superblock magic");
+        }
+
+        if sb.log_block_size() > 2 || sb.blocks_per_group() == 0 || sb.inodes_per_group() == 0 {
+            return Err("Unsupported superblock parameters");
+        }
+
+        // the bitmaps of a group fit in a block each
+        let bits_per_block = sb.block_size() as u32 * 8;
+        if sb.blocks_per_group() > bits_per_block || sb.inodes_per_group() > bits_per_block {
+            return Err("Bad group size");
+        }
+
+        if sb.first_data_block() >= sb.blocks_count() {
+            return Err("Bad block counts");
+        }
+
+        // the descriptor table is in the first group, after the superblock
+        if sb.group_count() * GroupDescriptor::LEN
+            > sb.blocks_per_group() as usize * sb.block_size()
+        {
+            return Err("Bad group count");
+        }
+
+        let inode_size = sb.inode_size();
+        if inode_size < 128 || !inode_size.is_power_of_two() || inode_size > sb.block_size() {
+            return Err("Bad inode size");
+        }
+
+        if sb.rev_level() > 0 && sb.feature_incompat() & !Self::INCOMPAT_SUPPORTED != 0 {
+            return Err("Unsupported incompatible features");
+        }
+
+        Ok(sb)
+    }
+
+    /// Block size in bytes, 1024, 2048 or 4096
+    pub fn block_size(&self) -> usize {
+        1024 << self.log_block_size()
+    }
+
+    /// Inode size in bytes, fixed to 128 in revision 0
+    pub fn inode_size(&self) -> usize {
+        match self.rev_level() {
+            0 => 128,
+            _ => self.inode_size_raw() as usize,
+        }
+    }
+
+    pub fn group_count(&self) -> usize {
+        (self.blocks_count() - self.first_data_block()).div_ceil(self.blocks_per_group()) as usize
+    }
+
+    pub fn has_filetype(&self) -> bool {
+        self.rev_level() > 0 && self.feature_incompat() & Self::INCOMPAT_FILETYPE != 0
+    }
+
+    // define all the fields in the superblock
+    define_field!(u32, 0x00, inodes_count);
+    define_field!(u32, 0x04, blocks_count);
+    define_field!(u32, 0x08, reserved_blocks_count);
+    define_field!(u32, 0x0C, free_blocks_count);
+    define_field!(u32, 0x10, free_inodes_count);
+    define_field!(u32, 0x14, first_data_block);
+    define_field!(u32, 0x18, log_block_size);
+    define_field!(u32, 0x20, blocks_per_group);
+    define_field!(u32, 0x28, inodes_per_group);
+    define_field!(u32, 0x2C, mount_time);
+    define_field!(u32, 0x30, write_time);
+    define_field!(u16, 0x38, magic);
+    define_field!(u16, 0x3A, state);
+    define_field!(u32, 0x4C, rev_level);
+    define_field!(u32, 0x54, first_inode);
+    define_field!(u16, 0x58, inode_size_raw);
+    define_field!(u32, 0x5C, feature_compat);
+    define_field!(u32, 0x60, feature_incompat);
+    define_field!(u32, 0x64, feature_ro_compat);
+    define_field!([u8; 16], 0x68, uuid);
+    define_field!([u8; 16], 0x78, volume_name);
+}
+
+impl core::fmt::Debug for Superblock {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Ext2 Superblock")
+            .field("Inodes Count", &self.inodes_count())
+            .field("Blocks Count", &self.blocks_count())
+            .field("Free Blocks Count", &self.free_blocks_count())
+            .field("Free Inodes Count", &self.free_inodes_count())
+            .field("First Data Block", &self.first_data_block())
+            .field("Block Size", &self.block_size())
+            .field("Blocks per Group", &self.blocks_per_group())
+            .field("Inodes per Group", &self.inodes_per_group())
+            .field("Revision", &self.rev_level())
+            .field("Inode Size", &self.inode_size())
+            .field(
+                "Compatible Features",
+                &format_args!("{:#x}", self.feature_compat()),
+            )
+            .field(
+                "Incompatible Features",
+                &format_args!("{:#x}", self.feature_incompat()),
+            )
+            .field(
+                "RO Compatible Features",
+                &format_args!("{:#x}", self.feature_ro_compat()),
+            )
+            .field(
+                "Volume Name",
+                &self.volume_name_str().trim_end_matches('\0'),
+            )
+            .finish()
+    }
+}
+
+/// Represents a Block Group Descriptor.
+///
+/// The descriptor table follows the superblock, one 32 bytes entry per group.
+#[derive(Clone, Copy)]
+pub struct GroupDescriptor {
+    data: [u8; 32],
+}
+
+impl GroupDescriptor {
+    pub const LEN: usize = 32;
+
+    pub fn new(data: &[u8]) -> Self {
+        Self {
+            data: data.try_into().unwrap(),
+        }
+    }
+
+    define_field!(u32, 0x00, block_bitmap);
+    define_field!(u32, 0x04, inode_bitmap);
+    define_field!(u32, 0x08, inode_table);
+    define_field!(u16, 0x0C, free_blocks_count);
+    define_field!(u16, 0x0E, free_inodes_count);
+    define_field!(u16, 0x10, used_dirs_count);
+}
+
+impl core::fmt::Debug for GroupDescriptor {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Group Descriptor")
+            .field("Block Bitmap", &self.block_bitmap())
+            .field("Inode Bitmap", &self.inode_bitmap())
+            .field("Inode Table", &self.inode_table())
+            .field("Free Blocks Count", &self.free_blocks_count())
+            .field("Free Inodes Count", &self.free_inodes_count())
+            .field("Used Dirs Count", &self.used_dirs_count())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 1 KiB block volume of 8192 blocks in a single group
+    fn superblock() -> [u8; 1024] {
+        let mut data = [0u8; 1024];
+        data[0x00..0x04].copy_from_slice(&2048u32.to_le_bytes());
+        data[0x04..0x08].copy_from_slice(&8192u32.to_le_bytes());
+        data[0x14..0x18].copy_from_slice(&1u32.to_le_bytes());
+        data[0x20..0x24].copy_from_slice(&8192u32.to_le_bytes());
+        data[0x28..0x2C].copy_from_slice(&2048u32.to_le_bytes());
+        data[0x38..0x3A].copy_from_slice(&Superblock::MAGIC.to_le_bytes());
+        data[0x4C..0x50].copy_from_slice(&1u32.to_le_bytes());
+        data[0x58..0x5A].copy_from_slice(&256u16.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_superblock() {
+        let sb = Superblock::new(&superblock()).unwrap();
+
+        assert_eq!(sb.block_size(), 1024);
+        assert_eq!(sb.group_count(), 1);
+        assert_eq!(sb.inode_size(), 256);
+    }
+
+    #[test]
+    fn test_corrupted_superblock_is_rejected() {
+        let corrupt = |offset: usize, value: &[u8]| {
+            let mut data = superblock();
+            data[offset..offset + value.len()].copy_from_slice(value);
+            Superblock::new(&data).map(|_| ())
+        };
+
+        // first data block past the end
+        assert_eq!(
+            corrupt(0x14, &9000u32.to_le_bytes()),
+            Err("Bad block counts")
+        );
+        // more blocks per group than the bitmap holds
+        assert_eq!(corrupt(0x20, &9000u32.to_le_bytes()), Err("Bad group size"));
+        // a group per block, the descriptor table does not fit
+        assert_eq!(corrupt(0x20, &1u32.to_le_bytes()), Err("Bad group count"));
+        assert_eq!(corrupt(0x58, &0u16.to_le_bytes()), Err("Bad inode size"));
+        assert_eq!(corrupt(0x58, &200u16.to_le_bytes()), Err("Bad inode size"));
+        assert_eq!(corrupt(0x58, &2048u16.to_le_bytes()), Err("Bad inode size"));
+    }
+}
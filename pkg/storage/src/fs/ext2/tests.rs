@@ -0,0 +1,253 @@
+//! Tests against Ext2 images generated by `mke2fs`
+//!
+//! As the other tests needing host tools, they are ignored by default,
+//! run them with `cargo test -- --ignored`. They fail if `mke2fs` is not
+//! installed.
+
+use super::*;
+use crate::testing::{pattern, read_to_end, run, MemoryDevice, TempDir};
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+
+const SMALL_FILE: &str = "hello, ext2!\n";
+/// More than the direct blocks of a 1 KiB block volume
+const INDIRECT_FILE_SIZE: usize = 100_000;
+/// More than the singly indirect blocks of a 1 KiB block volume
+const DOUBLY_INDIRECT_FILE_SIZE: usize = 600_000;
+const SPARSE_FILE_SIZE: usize = 300_000;
+const DIR_FILE_COUNT: usize = 120;
+const MODIFIED: u64 = 1_700_000_000;
+
+fn write_file(path: &Path, content: &[u8]) {
+    std::fs::write(path, content).unwrap();
+}
+
+/// Populates a directory with the following layout:
+///
+/// - `/hello.txt`: `SMALL_FILE`, modified at `MODIFIED`
+/// - `/indirect.bin`, `/double.bin`: files using indirect blocks
+/// - `/sparse.bin`: a file with a hole before its last bytes
+/// - `/dir/f<i>.txt`: `DIR_FILE_COUNT` files, filling several directory blocks
+/// - `/dir/nested/deep/Leaf.TXT`: a file in nested directories
+fn populate(root: &Path) {
+    let hello = root.join("hello.txt");
+    write_file(&hello, SMALL_FILE.as_bytes());
+    std::fs::File::options()
+        .write(true)
+        .open(&hello)
+        .unwrap()
+        .set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(MODIFIED))
+        .unwrap();
+
+    let content: Vec<u8> = (0..INDIRECT_FILE_SIZE).map(|i| pattern(1, i)).collect();
+    write_file(&root.join("indirect.bin"), &content);
+
+    let content: Vec<u8> = (0..DOUBLY_INDIRECT_FILE_SIZE)
+        .map(|i| pattern(2, i))
+        .collect();
+    write_file(&root.join("double.bin"), &content);
+
+    let sparse = std::fs::File::create(root.join("sparse.bin")).unwrap();
+    sparse.set_len(SPARSE_FILE_SIZE as u64 - 4).unwrap();
+    drop(sparse);
+    let mut content = std::fs::read(root.join("sparse.bin")).unwrap();
+    content.extend_from_slice(b"tail");
+    write_file(&root.join("sparse.bin"), &content);
+
+    let dir = root.join("dir");
+    std::fs::create_dir_all(dir.join("nested/deep")).unwrap();
+    for i in 0..DIR_FILE_COUNT {
+        let content: Vec<u8> = (0..=i).map(|o| pattern(i, o)).collect();
+        write_file(&dir.join(format!("f{}.txt", i)), &content);
+    }
+    write_file(&dir.join("nested/deep/Leaf.TXT"), b"leaf");
+}
+
+/// Build an image with the given block size
+fn build_image(name: &str, block_size: usize) -> Vec<u8> {
+    let temp = TempDir::new(&format!("ext2-{}", name));
+    let root = temp.0.join("root");
+    let image = temp.0.join("image");
+    std::fs::create_dir(&root).unwrap();
+    populate(&root);
+
+    run(Command::new("mke2fs")
+        .args([
+            "-q",
+            "-F",
+            "-t",
+            "ext2",
+            "-b",
+            &block_size.to_string(),
+            "-d",
+        ])
+        .arg(&root)
+        .arg(&image)
+        .arg("4M")
+        .env("MKE2FS_CONFIG", "/dev/null"));
+
+    std::fs::read(&image).unwrap()
+}
+
+fn open_image(name: &str, block_size: usize) -> Ext2 {
+    Ext2::new(MemoryDevice::new(build_image(name, block_size)))
+}
+
+fn check_files(fs: &Ext2) {
+    let content = read_to_end(&mut fs.open_file("/hello.txt").unwrap());
+    assert_eq!(content, SMALL_FILE.as_bytes());
+
+    for (path, seed, size) in [
+        ("/indirect.bin", 1, INDIRECT_FILE_SIZE),
+        ("/double.bin", 2, DOUBLY_INDIRECT_FILE_SIZE),
+    ] {
+        let content = read_to_end(&mut fs.open_file(path).unwrap());
+        assert_eq!(content.len(), size);
+        assert!(content
+            .iter()
+            .enumerate()
+            .all(|(i, b)| *b == pattern(seed, i)));
+    }
+
+    let content = read_to_end(&mut fs.open_file("/sparse.bin").unwrap());
+    assert_eq!(content.len(), SPARSE_FILE_SIZE);
+    assert!(content[..SPARSE_FILE_SIZE - 4].iter().all(|b| *b == 0));
+    assert_eq!(&content[SPARSE_FILE_SIZE - 4..], b"tail");
+
+    for i in [0, 63, DIR_FILE_COUNT - 1] {
+        let mut file = fs.open_file(&format!("/dir/f{}.txt", i)).unwrap();
+        let content = read_to_end(&mut file);
+        assert_eq!(content.len(), i + 1);
+        assert!(content.iter().enumerate().all(|(o, b)| *b == pattern(i, o)));
+    }
+
+    let content = read_to_end(&mut fs.open_file("/dir/nested/deep/Leaf.TXT").unwrap());
+    assert_eq!(content, b"leaf");
+}
+
+#[test]
+#[ignore = "needs mke2fs"]
+fn test_load_volume() {
+    let fs = open_image("load", 1024);
+
+    assert_eq!(fs.handle.block_size, 1024);
+    assert_eq!(fs.handle.superblock.first_data_block(), 1);
+    assert!(fs.handle.superblock.has_filetype());
+    assert!(!fs.handle.groups.is_empty());
+
+    // unsupported incompatible features are rejected
+    let mut data = build_image("load-extents", 1024);
+    data[Superblock::OFFSET + 0x60] |= 0x40;
+    assert_eq!(
+        Ext2::try_new(MemoryDevice::new(data)).err(),
+        Some(FsError::InvalidFileSystem)
+    );
+
+    let fat16 = MemoryDevice::new(crate::fat16::tests::build_image());
+    assert_eq!(Ext2::detect(&fat16), Ok(false));
+}
+
+#[test]
+#[ignore = "needs mke2fs"]
+fn test_read_dir() {
+    let fs = open_image("read-dir", 1024);
+
+    let mut names: Vec<String> = fs.read_dir("/").unwrap().map(|m| m.name).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "dir",
+            "double.bin",
+            "hello.txt",
+            "indirect.bin",
+            "lost+found",
+            "sparse.bin"
+        ]
+    );
+
+    let entries: Vec<Metadata> = fs.read_dir("/dir").unwrap().collect();
+    assert_eq!(entries.len(), DIR_FILE_COUNT + 1);
+    assert!(entries.iter().any(|m| m.name == "nested" && m.is_dir()));
+
+    let names: Vec<String> = fs
+        .read_dir("/dir/nested/deep/")
+        .unwrap()
+        .map(|m| m.name)
+        .collect();
+    assert_eq!(names, ["Leaf.TXT"]);
+}
+
+#[test]
+#[ignore = "needs mke2fs"]
+fn test_metadata() {
+    let fs = open_image("metadata", 1024);
+
+    let meta = fs.metadata("/hello.txt").unwrap();
+    assert_eq!(meta.name, "hello.txt");
+    assert!(meta.is_file());
+    assert_eq!(meta.len, SMALL_FILE.len());
+    assert_eq!(meta.modified.unwrap().timestamp(), MODIFIED as i64);
+    assert!(meta.created.is_some() && meta.accessed.is_some());
+
+    let meta = fs.metadata("/double.bin").unwrap();
+    assert_eq!(meta.len, DOUBLY_INDIRECT_FILE_SIZE);
+
+    assert!(fs.metadata("/").unwrap().is_dir());
+    assert!(fs.metadata("/dir/nested").unwrap().is_dir());
+}
+
+#[test]
+#[ignore = "needs mke2fs"]
+fn test_read_files() {
+    for block_size in [1024, 4096] {
+        let fs = open_image(&format!("read-{}", block_size), block_size);
+        assert_eq!(fs.handle.block_size, block_size);
+        check_files(&fs);
+    }
+}
+
+#[test]
+#[ignore = "needs mke2fs"]
+fn test_seek_file() {
+    let fs = open_image("seek", 1024);
+    let mut file = fs.open_file("/double.bin").unwrap();
+    let mut buf = [0u8; 10];
+
+    for offset in [500_000, 1000, 270_000, DOUBLY_INDIRECT_FILE_SIZE - 5] {
+        assert_eq!(file.seek(SeekFrom::Start(offset)).unwrap(), offset);
+        let len = file.read(&mut buf).unwrap();
+        assert_eq!(len, 10.min(DOUBLY_INDIRECT_FILE_SIZE - offset));
+        assert!(buf[..len]
+            .iter()
+            .enumerate()
+            .all(|(i, b)| *b == pattern(2, offset + i)));
+    }
+
+    assert_eq!(
+        file.seek(SeekFrom::End(1)).err(),
+        Some(FsError::InvalidOffset)
+    );
+    assert_eq!(file.write(b"x").err(), Some(FsError::ReadOnly));
+}
+
+#[test]
+#[ignore = "needs mke2fs"]
+fn test_lookup_errors() {
+    let fs = open_image("lookup", 1024);
+
+    assert_eq!(fs.exists("/nope.txt"), Ok(false));
+    // names are case-sensitive
+    assert_eq!(fs.exists("/HELLO.TXT"), Ok(false));
+    assert!(fs.exists("/dir/nested/deep/Leaf.TXT").unwrap());
+    assert_eq!(fs.open_file("/dir").err(), Some(FsError::NotAFile));
+    assert_eq!(
+        fs.read_dir("/hello.txt").err(),
+        Some(FsError::NotADirectory)
+    );
+    assert_eq!(
+        fs.open_file("/hello.txt/x").err(),
+        Some(FsError::NotADirectory)
+    );
+}
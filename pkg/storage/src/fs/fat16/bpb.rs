@@ -22,6 +22,13 @@ impl Fat16Bpb {
             return Err("Bad BPB format");
         }
 
+        if bpb.bytes_per_sector() != 512
+            || bpb.sectors_per_cluster() == 0
+            || bpb.sectors_per_fat() == 0
+        {
+            return Err("Unsupported BPB parameters");
+        }
+
         Ok(bpb)
     }
 
@@ -0,0 +1,88 @@
+//! Cluster Chain
+//!
+//! reference: <https://wiki.osdev.org/FAT#Cluster_Chains>
+
+use super::*;
+
+/// Access to the file allocation table, shared by the FAT variants
+pub trait FatTable {
+    /// The number of data clusters in the volume
+    fn cluster_count(&self) -> usize;
+
+    /// Returns true if the cluster number refers to the data region
+    fn is_data_cluster(&self, cluster: &Cluster) -> bool {
+        (2..self.cluster_count() as u32 + 2).contains(&cluster.0)
+    }
+
+    /// Look up the cluster following `cluster` in the FAT
+    fn get_next_cluster(&self, cluster: &Cluster) -> Result<Cluster>;
+}
+
+/// Iterator over the clusters of a cluster chain.
+///
+/// The first item is the start cluster itself, the following ones are looked
+/// up lazily in the FAT. The iteration stops at the end-of-chain marker, and
+/// yields an error on bad clusters or on a chain longer than the volume.
+pub struct ClusterChain<'a, T: FatTable + ?Sized> {
+    fs: &'a T,
+    current: Option<Cluster>,
+    started: bool,
+    /// Upper bound of the remaining clusters, guards against looped chains
+    remaining: usize,
+}
+
+impl<'a, T: FatTable + ?Sized> ClusterChain<'a, T> {
+    pub fn new(fs: &'a T, start: Cluster) -> Self {
+        let current = match start {
+            Cluster::ROOT_DIR => Some(start),
+            c if fs.is_data_cluster(&c) => Some(c),
+            _ => None,
+        };
+
+        Self {
+            fs,
+            current,
+            started: false,
+            remaining: fs.cluster_count(),
+        }
+    }
+}
+
+impl<T: FatTable + ?Sized> Iterator for ClusterChain<'_, T> {
+    type Item = Result<Cluster>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.current?;
+
+        if !self.started {
+            self.started = true;
+            return Some(Ok(current));
+        }
+
+        let next = match self.fs.get_next_cluster(&current) {
+            Ok(next) => next,
+            Err(err) => {
+                self.current = None;
+                return Some(Err(err));
+            }
+        };
+
+        if !self.fs.is_data_cluster(&next) {
+            self.current = None;
+            return match next {
+                Cluster::END_OF_FILE => None,
+                _ => Some(Err(FsError::BadCluster)),
+            };
+        }
+
+        if self.remaining == 0 {
+            warn!("Cluster chain from {} is longer than the volume", current);
+            self.current = None;
+            return Some(Err(FsError::BadCluster));
+        }
+
+        self.remaining -= 1;
+        self.current = Some(next);
+        Some(Ok(next))
+    }
+}
@@ -0,0 +1,407 @@
+//! Check
+//!
+//! Finds inconsistencies between the FATs and the directory tree,
+//! and optionally repairs them, like `fsck.fat`.
+
+use super::*;
+use core::fmt::{Display, Formatter};
+
+const END_OF_CHAIN: u16 = 0xFFFF;
+const DELETED: u8 = 0xE5;
+
+/// An inconsistency found in a Fat16 volume
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// A FAT copy differs from the first FAT in some sectors
+    FatMismatch { copy: usize, sectors: usize },
+    /// The chain of the entry links to a free, bad or out of range
+    /// cluster, or loops back into itself
+    BadChain { path: String, cluster: u32 },
+    /// The chain of the entry runs into a cluster of an earlier entry
+    CrossLinked { path: String, cluster: u32 },
+    /// The size of the file does not match the length of its chain
+    SizeMismatch {
+        path: String,
+        size: u32,
+        clusters: usize,
+    },
+    /// Clusters in use that no entry refers to
+    LostClusters(usize),
+}
+
+impl Display for Problem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Problem::FatMismatch { copy, sectors } => {
+                write!(
+                    f,
+                    "FAT #{} differs from the first FAT in {} sectors",
+                    copy + 1,
+                    sectors
+                )
+            }
+            Problem::BadChain { path, cluster } => {
+                write!(f, "{}: broken cluster chain at cluster {}", path, cluster)
+            }
+            Problem::CrossLinked { path, cluster } => {
+                write!(f, "{}: cross-linked at cluster {}", path, cluster)
+            }
+            Problem::SizeMismatch {
+                path,
+                size,
+                clusters,
+            } => write!(
+                f,
+                "{}: size of {} bytes does not match a chain of {} clusters",
+                path, size, clusters
+            ),
+            Problem::LostClusters(count) => write!(f, "{} lost clusters", count),
+        }
+    }
+}
+
+/// The result of checking a volume
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CheckReport {
+    pub problems: Vec<Problem>,
+    /// Whether the problems were repaired
+    pub repaired: bool,
+}
+
+impl CheckReport {
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// A directory entry, with where it is stored
+struct Located {
+    sector: usize,
+    offset: usize,
+    entry: DirEntry,
+}
+
+/// The state of a check, the FAT is loaded and repaired in memory
+struct Checker<'a> {
+    fs: &'a Fat16Impl,
+    repair: bool,
+    /// The entries of the first FAT
+    fat: Vec<u16>,
+    /// The id of the entry using each cluster, 0 if unused
+    owners: Vec<usize>,
+    next_id: usize,
+    problems: Vec<Problem>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(fs: &'a Fat16Impl, repair: bool) -> Result<Self> {
+        let mut fat = Vec::new();
+        let mut block = Block512::default();
+        for sector in 0..fs.bpb.sectors_per_fat() as usize {
+            fs.inner.read_block(fs.fat_start + sector, &mut block)?;
+            fat.extend(
+                block
+                    .chunks(2)
+                    .map(|entry| u16::from_le_bytes([entry[0], entry[1]])),
+            );
+        }
+
+        // every data cluster needs an entry in the FAT
+        if fat.len() < fs.cluster_count() + 2 {
+            return Err(FsError::InvalidFileSystem);
+        }
+
+        let owners = vec![0; fat.len()];
+        Ok(Self {
+            fs,
+            repair,
+            fat,
+            owners,
+            next_id: 1,
+            problems: Vec::new(),
+        })
+    }
+
+    fn fat_sector(&self, sector: usize) -> Block512 {
+        let mut block = Block512::default();
+        for (i, entry) in self.fat[sector * 256..(sector + 1) * 256]
+            .iter()
+            .enumerate()
+        {
+            block.as_mut()[i * 2..i * 2 + 2].copy_from_slice(&entry.to_le_bytes());
+        }
+        block
+    }
+
+    /// Compare the other FAT copies with the first one
+    fn check_fat_copies(&mut self) -> Result<()> {
+        let sectors = self.fs.bpb.sectors_per_fat() as usize;
+        let mut block = Block512::default();
+
+        for copy in 1..self.fs.bpb.fat_count() as usize {
+            let mut mismatches = 0;
+            for sector in 0..sectors {
+                self.fs
+                    .inner
+                    .read_block(self.fs.fat_start + copy * sectors + sector, &mut block)?;
+                if block.as_ref() != self.fat_sector(sector).as_ref() {
+                    mismatches += 1;
+                }
+            }
+
+            if mismatches > 0 {
+                self.problems.push(Problem::FatMismatch {
+                    copy,
+                    sectors: mismatches,
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read the entries of a directory, except the deleted, long name,
+    /// volume label and dot entries
+    fn read_dir(&self, clusters: &[Cluster]) -> Result<Vec<Located>> {
+        let mut entries = Vec::new();
+        let mut block = Block512::default();
+
+        for cluster in clusters {
+            let first_sector = self.fs.cluster_to_first_sector(cluster);
+            for sector in first_sector..first_sector + self.fs.sectors_in_cluster(cluster) {
+                self.fs.inner.read_block(sector, &mut block)?;
+
+                for (index, data) in block.chunks(DirEntry::LEN).enumerate() {
+                    let entry = DirEntry::parse(data)?;
+                    if entry.filename.is_eod() {
+                        return Ok(entries);
+                    }
+                    if !entry.is_valid()
+                        || entry.is_long_name()
+                        || entry.attributes.contains(Attributes::VOLUME_ID)
+                        || entry.filename.name[0] == b'.'
+                    {
+                        continue;
+                    }
+
+                    entries.push(Located {
+                        sector,
+                        offset: index * DirEntry::LEN,
+                        entry,
+                    });
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Follow the chain of an entry, claiming its clusters
+    ///
+    /// Returns the clusters up to the first problem, and whether the
+    /// chain was cut short by a problem.
+    fn walk_chain(&mut self, path: &str, start: u32) -> (Vec<Cluster>, bool) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut clusters = Vec::new();
+        let mut current = start;
+        loop {
+            if !self.fs.is_data_cluster(&Cluster(current)) {
+                self.problems.push(Problem::BadChain {
+                    path: path.into(),
+                    cluster: current,
+                });
+                return (clusters, true);
+            }
+
+            match self.owners[current as usize] {
+                0 => {}
+                owner if owner == id => {
+                    self.problems.push(Problem::BadChain {
+                        path: path.into(),
+                        cluster: current,
+                    });
+                    return (clusters, true);
+                }
+                _ => {
+                    self.problems.push(Problem::CrossLinked {
+                        path: path.into(),
+                        cluster: current,
+                    });
+                    return (clusters, true);
+                }
+            }
+
+            self.owners[current as usize] = id;
+            clusters.push(Cluster(current));
+
+            match self.fat[current as usize] {
+                0xFFF8..=0xFFFF => return (clusters, false),
+                next if self.fs.is_data_cluster(&Cluster(next as u32)) => current = next as u32,
+                next => {
+                    self.problems.push(Problem::BadChain {
+                        path: path.into(),
+                        cluster: next as u32,
+                    });
+                    return (clusters, true);
+                }
+            }
+        }
+    }
+
+    /// End the chain after `keep` clusters, the rest is left to the lost
+    /// cluster pass unless a later entry turns out to own it
+    fn truncate(&mut self, clusters: &mut Vec<Cluster>, keep: usize) {
+        for cluster in clusters.drain(keep..) {
+            self.owners[cluster.0 as usize] = 0;
+        }
+        if let Some(last) = clusters.last() {
+            self.fat[last.0 as usize] = END_OF_CHAIN;
+        }
+    }
+
+    /// Check the entries of a directory and everything below it
+    fn check_dir(&mut self, path: &str, clusters: &[Cluster]) -> Result<()> {
+        let cluster_size = self.fs.bytes_per_cluster();
+
+        for Located {
+            sector,
+            offset,
+            mut entry,
+        } in self.read_dir(clusters)?
+        {
+            let path = format!("{}/{}", path, entry.filename);
+            let original = entry.clone();
+
+            let (mut chain, broken) = match entry.cluster.0 {
+                0 => (Vec::new(), false),
+                start => self.walk_chain(&path, start),
+            };
+            if broken && self.repair {
+                let keep = chain.len();
+                self.truncate(&mut chain, keep);
+            }
+
+            if entry.is_directory() {
+                if chain.is_empty() {
+                    if entry.cluster.0 == 0 {
+                        self.problems.push(Problem::BadChain {
+                            path: path.clone(),
+                            cluster: 0,
+                        });
+                    }
+                    // a directory without clusters can not be kept
+                    entry.filename.name[0] = DELETED;
+                } else {
+                    self.check_dir(&path, &chain)?;
+                }
+            } else {
+                let expected = (entry.size as usize).div_ceil(cluster_size);
+                if expected != chain.len() {
+                    self.problems.push(Problem::SizeMismatch {
+                        path: path.clone(),
+                        size: entry.size,
+                        clusters: chain.len(),
+                    });
+
+                    if self.repair {
+                        if chain.len() > expected {
+                            self.truncate(&mut chain, expected);
+                        } else {
+                            entry.size = (chain.len() * cluster_size) as u32;
+                        }
+                    }
+                }
+            }
+
+            if chain.is_empty() {
+                entry.cluster = Cluster(0);
+            }
+
+            if self.repair && entry != original {
+                self.write_entry(sector, offset, &entry)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Write back the name, start cluster and size of a repaired entry
+    fn write_entry(&self, sector: usize, offset: usize, entry: &DirEntry) -> Result<()> {
+        let mut block = Block512::default();
+        self.fs.inner.read_block(sector, &mut block)?;
+
+        let data = &mut block.as_mut()[offset..offset + DirEntry::LEN];
+        data[0] = entry.filename.name[0];
+        data[20..22].copy_from_slice(&((entry.cluster.0 >> 16) as u16).to_le_bytes());
+        data[26..28].copy_from_slice(&(entry.cluster.0 as u16).to_le_bytes());
+        data[28..32].copy_from_slice(&entry.size.to_le_bytes());
+
+        self.fs.inner.write_block(sector, &block)
+    }
+
+    /// Find the used clusters that no entry owns, and free them on repair
+    fn check_lost_clusters(&mut self) {
+        let mut lost = 0;
+        for cluster in 2..self.fs.cluster_count() + 2 {
+            let entry = self.fat[cluster];
+            if entry != 0 && entry != 0xFFF7 && self.owners[cluster] == 0 {
+                lost += 1;
+                if self.repair {
+                    self.fat[cluster] = 0;
+                }
+            }
+        }
+
+        if lost > 0 {
+            self.problems.push(Problem::LostClusters(lost));
+        }
+    }
+
+    /// Write the repaired FAT to every copy
+    fn write_fats(&self) -> Result<()> {
+        let sectors = self.fs.bpb.sectors_per_fat() as usize;
+        for sector in 0..sectors {
+            let block = self.fat_sector(sector);
+            for copy in 0..self.fs.bpb.fat_count() as usize {
+                self.fs
+                    .inner
+                    .write_block(self.fs.fat_start + copy * sectors + sector, &block)?;
+            }
+        }
+
+        *self.fs.fat_cache.write() = vec![None; sectors];
+        Ok(())
+    }
+}
+
+impl Fat16 {
+    /// Check the volume for inconsistencies
+    ///
+    /// Compares the FAT copies, follows the chain of every entry to find
+    /// broken and cross-linked chains and files whose size does not match
+    /// their chain, and counts the lost clusters left over.
+    ///
+    /// With `repair`, chains are cut before the problem, sizes are fitted to
+    /// the chains, directories without clusters are removed, lost clusters
+    /// are freed and the first FAT is copied over the others.
+    /// Open files must not be used while repairing.
+    pub fn check(&self, repair: bool) -> Result<CheckReport> {
+        let mut checker = Checker::new(&self.handle, repair)?;
+
+        checker.check_fat_copies()?;
+        checker.check_dir("", &[Cluster::ROOT_DIR])?;
+        checker.check_lost_clusters();
+
+        let repaired = repair && !checker.problems.is_empty();
+        if repaired {
+            checker.write_fats()?;
+        }
+
+        Ok(CheckReport {
+            problems: checker.problems,
+            repaired,
+        })
+    }
+}
@@ -259,6 +259,7 @@ impl From<&DirEntry> for Metadata {
             created: Some(entry.created_time),
             accessed: Some(entry.accessed_time),
             modified: Some(entry.modified_time),
+            attributes: FileAttributes::from_bits_truncate(entry.attributes.bits()),
         }
     }
 }
@@ -12,6 +12,8 @@ pub struct File {
     offset: usize,
     /// The current cluster of this file
     current_cluster: Cluster,
+    /// The index of `current_cluster` in the cluster chain
+    cluster_index: usize,
     /// DirEntry of this file
     entry: DirEntry,
     /// The file system handle that contains this file
@@ -23,6 +25,7 @@ impl File {
         Self {
             offset: 0,
             current_cluster: entry.cluster,
+            cluster_index: 0,
             entry,
             handle,
         }
@@ -31,62 +34,92 @@ impl File {
     pub fn length(&self) -> usize {
         self.entry.size as usize
     }
+
+    /// Walk the cluster chain until `current_cluster` holds the byte at `offset`
+    fn locate_cluster(&mut self) -> Result<()> {
+        let target = self.offset / self.handle.bytes_per_cluster();
+
+        if target > self.cluster_index {
+            self.current_cluster = self
+                .handle
+                .cluster_chain(self.current_cluster)
+                .nth(target - self.cluster_index)
+                .ok_or(FsError::EndOfFile)??;
+            self.cluster_index = target;
+        }
+
+        Ok(())
+    }
 }
 
 impl Read for File {
     fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
         // read file content from disk
         let bps = self.handle.bpb.bytes_per_sector() as usize;
-        let spc = self.handle.bpb.sectors_per_cluster() as usize;
+        let cluster_size = self.handle.bytes_per_cluster();
+        let to_read = min(buf.len(), self.length().saturating_sub(self.offset));
         let mut read_bytes = 0;
-        let mut sector_offset = self.offset / bps;
-        let mut byte_offset = self.offset % bps;
-        let mut block = Block::default();
-        loop {
-            self.handle.inner.read_block(
-                self.handle.cluster_to_first_sector(&self.current_cluster) + sector_offset,
-                &mut block,
-            )?;
-
-            let bytes_to_read = min(
-                min(buf.len() - read_bytes, bps - byte_offset),
-                self.entry.size as usize - self.offset,
-            );
-
-            buf[read_bytes..read_bytes + bytes_to_read]
-                .copy_from_slice(&block[byte_offset..byte_offset + bytes_to_read]);
-            
-            read_bytes += bytes_to_read;
-            self.offset += bytes_to_read;
-            if read_bytes == buf.len() || self.offset == self.entry.size as usize {
-                break;
-            } else {
+
+        while read_bytes < to_read {
+            self.locate_cluster()?;
+
+            // the sectors of the cluster are read in one request
+            let cluster_offset = self.offset % cluster_size;
+            let mut byte_offset = cluster_offset % bps;
+            let sector =
+                self.handle.cluster_to_first_sector(&self.current_cluster) + cluster_offset / bps;
+            let bytes_to_read = min(to_read - read_bytes, cluster_size - cluster_offset);
+            let mut blocks = vec![Block::default(); (byte_offset + bytes_to_read).div_ceil(bps)];
+            self.handle.inner.read_blocks(sector, &mut blocks)?;
+
+            let end = read_bytes + bytes_to_read;
+            for block in blocks.iter() {
+                let len = min(end - read_bytes, bps - byte_offset);
+                buf[read_bytes..read_bytes + len]
+                    .copy_from_slice(&block[byte_offset..byte_offset + len]);
+                read_bytes += len;
                 byte_offset = 0;
-                sector_offset += 1;
-                if sector_offset == spc {
-                    sector_offset = 0;
-                    self.current_cluster = self.handle.get_next_cluster(&self.current_cluster)?;
-                }
             }
+
+            self.offset += bytes_to_read;
         }
+
         Ok(read_bytes)
     }
 }
 
-// NOTE: `Seek` trait is not required for this lab
 impl Seek for File {
-    fn seek(&mut self, _pos: SeekFrom) -> Result<usize> {
-        unimplemented!()
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.length().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+        }
+        .filter(|offset| *offset <= self.length())
+        .ok_or(FsError::InvalidOffset)?;
+
+        // the chain can only be walked forward, restart from the first cluster
+        if offset / self.handle.bytes_per_cluster() < self.cluster_index {
+            self.current_cluster = self.entry.cluster;
+            self.cluster_index = 0;
+        }
+
+        self.offset = offset;
+        if offset < self.length() {
+            self.locate_cluster()?;
+        }
+
+        Ok(offset)
     }
 }
 
 // NOTE: `Write` trait is not required for this lab
 impl Write for File {
     fn write(&mut self, _buf: &[u8]) -> Result<usize> {
-        unimplemented!()
+        Err(FsError::ReadOnly)
     }
 
     fn flush(&mut self) -> Result<()> {
-        unimplemented!()
+        Ok(())
     }
 }
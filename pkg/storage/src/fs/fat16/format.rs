@@ -0,0 +1,189 @@
+//! Format
+//!
+//! Writes a fresh Fat16 volume, like `mkfs.fat -F 16`.
+//!
+//! reference: <https://wiki.osdev.org/FAT#Boot_Record>
+
+use super::*;
+
+/// The most clusters a Fat16 volume can have
+pub const MAX_CLUSTERS: usize = 65524;
+/// The fewest clusters a Fat16 volume can have, fewer make it Fat12
+pub const MIN_CLUSTERS: usize = 4085;
+
+const RESERVED_SECTORS: usize = 1;
+const FAT_COUNT: usize = 2;
+const MEDIA_DESCRIPTOR: u8 = 0xF8;
+
+/// Options for formatting a Fat16 volume
+#[derive(Debug, Clone)]
+pub struct FormatOptions {
+    /// The volume label, at most 11 characters
+    pub label: String,
+    /// The volume serial number
+    pub volume_id: u32,
+    /// Sectors per cluster, the smallest fitting the device if `None`
+    pub sectors_per_cluster: Option<u8>,
+    /// Entries of the root directory, a multiple of 16
+    pub root_entries: u16,
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self {
+            label: String::from("NO NAME"),
+            volume_id: 0,
+            sectors_per_cluster: None,
+            root_entries: 512,
+        }
+    }
+}
+
+/// The layout of a volume, in sectors
+#[derive(Debug, PartialEq, Eq)]
+struct Layout {
+    total_sectors: usize,
+    sectors_per_cluster: usize,
+    sectors_per_fat: usize,
+    root_sectors: usize,
+}
+
+impl Layout {
+    /// Lay out a volume with the given cluster size
+    ///
+    /// The FAT size depends on the cluster count and the other way around,
+    /// so the FAT grows until it covers every cluster left beside it.
+    fn new(total_sectors: usize, sectors_per_cluster: usize, root_sectors: usize) -> Self {
+        let mut sectors_per_fat = 1;
+        loop {
+            let clusters = total_sectors
+                .saturating_sub(RESERVED_SECTORS + FAT_COUNT * sectors_per_fat + root_sectors)
+                / sectors_per_cluster;
+            let needed = ((clusters + 2) * 2).div_ceil(BLOCK_SIZE);
+            if needed <= sectors_per_fat {
+                break;
+            }
+            sectors_per_fat = needed;
+        }
+
+        Self {
+            total_sectors,
+            sectors_per_cluster,
+            sectors_per_fat,
+            root_sectors,
+        }
+    }
+
+    fn root_start(&self) -> usize {
+        RESERVED_SECTORS + FAT_COUNT * self.sectors_per_fat
+    }
+
+    fn clusters(&self) -> usize {
+        self.total_sectors
+            .saturating_sub(self.root_start() + self.root_sectors)
+            / self.sectors_per_cluster
+    }
+
+    fn is_valid(&self) -> bool {
+        (MIN_CLUSTERS..=MAX_CLUSTERS).contains(&self.clusters())
+    }
+}
+
+/// Pad the label with spaces, labels are upper case like short names
+fn parse_label(label: &str) -> Result<[u8; 11]> {
+    let label = label.to_uppercase();
+    if label.len() > 11 || !label.bytes().all(|c| c.is_ascii_graphic() || c == b' ') {
+        return Err(FsError::InvalidOperation);
+    }
+
+    let mut bytes = [b' '; 11];
+    bytes[..label.len()].copy_from_slice(label.as_bytes());
+    Ok(bytes)
+}
+
+impl Fat16 {
+    /// Format the whole device as an empty Fat16 volume
+    ///
+    /// Writes the BPB, both FATs and an empty root directory,
+    /// the data region is left as is. Fails with `NotSupported` if the
+    /// device is too small or too large for Fat16 with the cluster size.
+    pub fn format(
+        device: &(impl BlockDevice<Block512> + ?Sized),
+        options: &FormatOptions,
+    ) -> Result<()> {
+        let label = parse_label(&options.label)?;
+        if options.root_entries == 0 || options.root_entries % 16 != 0 {
+            return Err(FsError::InvalidOperation);
+        }
+
+        let total_sectors = device.block_count()?.min(u32::MAX as usize);
+        let root_sectors = options.root_entries as usize * DirEntry::LEN / BLOCK_SIZE;
+
+        let layout = match options.sectors_per_cluster {
+            Some(spc) if spc.is_power_of_two() && spc <= 128 => {
+                Layout::new(total_sectors, spc as usize, root_sectors)
+            }
+            Some(_) => return Err(FsError::InvalidOperation),
+            None => (0..8)
+                .map(|shift| Layout::new(total_sectors, 1 << shift, root_sectors))
+                .find(|layout| layout.clusters() <= MAX_CLUSTERS)
+                .ok_or(FsError::NotSupported)?,
+        };
+
+        if !layout.is_valid() {
+            warn!(
+                "Fat16 needs {} to {} clusters, the device has room for {}",
+                MIN_CLUSTERS,
+                MAX_CLUSTERS,
+                layout.clusters()
+            );
+            return Err(FsError::NotSupported);
+        }
+
+        trace!("Formatting Fat16 volume: {:?}", layout);
+
+        let mut block = Block512::default();
+        let bpb = block.as_mut();
+        bpb[0x00..0x03].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+        bpb[0x03..0x0B].copy_from_slice(b"YSOS    ");
+        bpb[0x0B..0x0D].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+        bpb[0x0D] = layout.sectors_per_cluster as u8;
+        bpb[0x0E..0x10].copy_from_slice(&(RESERVED_SECTORS as u16).to_le_bytes());
+        bpb[0x10] = FAT_COUNT as u8;
+        bpb[0x11..0x13].copy_from_slice(&options.root_entries.to_le_bytes());
+        match u16::try_from(total_sectors) {
+            Ok(total) => bpb[0x13..0x15].copy_from_slice(&total.to_le_bytes()),
+            Err(_) => bpb[0x20..0x24].copy_from_slice(&(total_sectors as u32).to_le_bytes()),
+        }
+        bpb[0x15] = MEDIA_DESCRIPTOR;
+        bpb[0x16..0x18].copy_from_slice(&(layout.sectors_per_fat as u16).to_le_bytes());
+        bpb[0x18..0x1A].copy_from_slice(&32u16.to_le_bytes());
+        bpb[0x1A..0x1C].copy_from_slice(&64u16.to_le_bytes());
+        bpb[0x24] = 0x80;
+        bpb[0x26] = 0x29;
+        bpb[0x27..0x2B].copy_from_slice(&options.volume_id.to_le_bytes());
+        bpb[0x2B..0x36].copy_from_slice(&label);
+        bpb[0x36..0x3E].copy_from_slice(b"FAT16   ");
+        bpb[0x1FE..0x200].copy_from_slice(&[0x55, 0xAA]);
+        device.write_block(0, &block)?;
+
+        // the first two entries hold the media descriptor and the end of chain marker
+        let mut first = Block512::default();
+        first.as_mut()[0..4].copy_from_slice(&[MEDIA_DESCRIPTOR, 0xFF, 0xFF, 0xFF]);
+        let empty = Block512::default();
+
+        for fat in 0..FAT_COUNT {
+            let start = RESERVED_SECTORS + fat * layout.sectors_per_fat;
+            device.write_block(start, &first)?;
+            for sector in start + 1..start + layout.sectors_per_fat {
+                device.write_block(sector, &empty)?;
+            }
+        }
+
+        for sector in layout.root_start()..layout.root_start() + layout.root_sectors {
+            device.write_block(sector, &empty)?;
+        }
+
+        Ok(())
+    }
+}
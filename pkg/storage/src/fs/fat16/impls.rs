@@ -1,29 +1,33 @@
-use core::{cmp::min, f32::INFINITY, ops::Add};
-
 use super::*;
 
 impl Fat16Impl {
-    pub fn new(inner: impl BlockDevice<Block512>) -> Self {
+    pub fn new(inner: impl BlockDevice<Block512>) -> Result<Self> {
         let mut block = Block::default();
 
-        inner.read_block(0, &mut block).unwrap();
-        let bpb = Fat16Bpb::new(block.as_ref()).unwrap();
+        inner.read_block(0, &mut block)?;
+        let bpb = Fat16Bpb::new(block.as_ref()).map_err(|err| {
+            warn!("Failed to load Fat16 volume: {}", err);
+            FsError::InvalidFileSystem
+        })?;
 
         trace!("Loading Fat16 Volume: {:#?}", bpb);
 
         let fat_start = bpb.reserved_sector_count() as usize;
-        let root_dir_sector_num = bpb.root_entries_count() as usize * 32 / BLOCK_SIZE;
+        let root_dir_sector_num =
+            (bpb.root_entries_count() as usize * DirEntry::LEN).div_ceil(BLOCK_SIZE);
         let first_root_dir_sector =
             fat_start + (bpb.fat_count() as usize * bpb.sectors_per_fat() as usize);
         let first_data_sector = first_root_dir_sector + root_dir_sector_num;
+        let fat_cache = RwLock::new(vec![None; bpb.sectors_per_fat() as usize]);
 
-        Self {
+        Ok(Self {
             bpb,
             inner: Box::new(inner),
             fat_start,
             first_data_sector,
             first_root_dir_sector,
-        }
+            fat_cache,
+        })
     }
 
     // calculate the first sector of the cluster
@@ -38,71 +42,84 @@ impl Fat16Impl {
         }
     }
 
-    // read the FAT and get next
-    pub fn get_next_cluster(&self, cluster: &Cluster) -> Result<Cluster> {
-        if *cluster == Cluster::ROOT_DIR {
-            Ok(Cluster::END_OF_FILE)
-        } else {
-            let mut block = Block::default();
-            self.inner.read_block(self.fat_start, &mut block).unwrap();
-            let tem = (cluster.0 * 2) as usize;
-            let next = u16::from_le_bytes(block[tem..tem + 2].try_into().unwrap()) as u32;
-            match Cluster(next) {
-                Cluster::EMPTY => Ok(Cluster::EMPTY),
-                Cluster::BAD => Err(FsError::BadCluster),
-                Cluster(c) => {
-                    if (0x0000_0002..0x0000_FFF6).contains(&c) {
-                        Ok(Cluster(c))
-                    } else if c >= 0xFFFF_FFF8 {
-                        Ok(Cluster::END_OF_FILE)
-                    } else {
-                        Ok(Cluster::INVALID)
-                    }
-                }
-            }
+    /// The number of sectors the cluster spans,
+    /// the root directory region is treated as a single cluster
+    pub fn sectors_in_cluster(&self, cluster: &Cluster) -> usize {
+        match *cluster {
+            Cluster::ROOT_DIR => self.first_data_sector - self.first_root_dir_sector,
+            _ => self.bpb.sectors_per_cluster() as usize,
         }
     }
 
+    pub fn bytes_per_cluster(&self) -> usize {
+        self.bpb.bytes_per_sector() as usize * self.bpb.sectors_per_cluster() as usize
+    }
+
+    /// Iterates over the cluster chain beginning at `start`
+    pub fn cluster_chain(&self, start: Cluster) -> ClusterChain<'_, Self> {
+        ClusterChain::new(self, start)
+    }
+
+    /// Read the raw FAT entry of the given cluster
+    ///
+    /// Every entry takes 2 bytes, so one sector of the FAT holds 256 entries.
+    /// The sectors are read from the first FAT once and cached afterwards.
+    fn read_fat_entry(&self, cluster: &Cluster) -> Result<u16> {
+        let fat_offset = cluster.0 as usize * 2;
+        let sector = fat_offset / BLOCK_SIZE;
+        let offset = fat_offset % BLOCK_SIZE;
+
+        if let Some(Some(block)) = self.fat_cache.read().get(sector) {
+            return Ok(u16::from_le_bytes([block[offset], block[offset + 1]]));
+        }
+
+        let mut cache = self.fat_cache.write();
+        let slot = cache.get_mut(sector).ok_or(FsError::BadCluster)?;
+
+        let mut block = Block::default();
+        self.inner.read_block(self.fat_start + sector, &mut block)?;
+        let entry = u16::from_le_bytes([block[offset], block[offset + 1]]);
+        *slot = Some(block);
+
+        Ok(entry)
+    }
+
     // traverse all dir entries in the dir
     pub fn traverse_dir_entries<F>(&self, dir: &Directory, mut process_entry: F) -> Result<()>
     where
         F: FnMut(DirEntry) -> Result<()>,
+    {
+        self.traverse_dir_slots(dir, |_, _, entry| process_entry(entry))
+    }
+
+    /// Traverse all dir entries in the dir, with the sector and
+    /// the byte offset in that sector where each entry is stored
+    fn traverse_dir_slots<F>(&self, dir: &Directory, mut process_entry: F) -> Result<()>
+    where
+        F: FnMut(usize, usize, DirEntry) -> Result<()>,
     {
         let mut block = Block::default();
-        let mut cluster = dir.cluster;
-        let entry_per_block = BLOCK_SIZE / DirEntry::LEN;
-        loop {
-            let mut now_sector = self.cluster_to_first_sector(&cluster);
-            let mut entry_num = {
-                if cluster == Cluster::ROOT_DIR {
-                    self.bpb.root_entries_count() as usize
-                } else {
-                    entry_per_block * self.bpb.sectors_per_cluster() as usize
-                }
-            };
 
-            while entry_num > 0 {
-                self.inner.read_block(now_sector, &mut block).unwrap();
-                let mut offset = 0;
-                for _ in 0..min(entry_num, entry_per_block) {
-                    let entry = DirEntry::parse(&block[offset..offset + DirEntry::LEN]).unwrap();
-                    if entry.is_valid() {
-                        process_entry(entry)?;
-                    } else {
+        for cluster in self.cluster_chain(dir.cluster) {
+            let cluster = cluster?;
+            let first_sector = self.cluster_to_first_sector(&cluster);
+
+            for sector in first_sector..first_sector + self.sectors_in_cluster(&cluster) {
+                self.inner.read_block(sector, &mut block)?;
+
+                for (index, data) in block.chunks(DirEntry::LEN).enumerate() {
+                    let entry = DirEntry::parse(data)?;
+                    if entry.filename.is_eod() {
                         return Ok(());
                     }
-                    entry_num -= 1;
-                    offset += DirEntry::LEN;
+                    if entry.is_valid() {
+                        process_entry(sector, index * DirEntry::LEN, entry)?;
+                    }
                 }
-
-                now_sector += 1;
-            }
-
-            cluster = self.get_next_cluster(&cluster)?;
-            if cluster == Cluster::END_OF_FILE {
-                return Ok(());
             }
         }
+
+        Ok(())
     }
 
     pub fn get_dir_entry_by_name(&self, dir: &Directory, name: &str) -> Result<DirEntry> {
@@ -117,6 +134,35 @@ impl Fat16Impl {
         result.ok_or(FsError::FileNotFound)
     }
 
+    /// Replace the read-only, hidden and system bits of the entry,
+    /// the other attribute bits are kept
+    pub fn set_entry_attributes(
+        &self,
+        dir: &Directory,
+        name: &str,
+        attributes: FileAttributes,
+    ) -> Result<()> {
+        let parse_name = ShortFileName::parse(name)?;
+        let mut slot = None;
+        self.traverse_dir_slots(dir, |sector, offset, entry| {
+            if entry.filename.matches(&parse_name) {
+                slot = Some((sector, offset));
+            }
+            Ok(())
+        })?;
+        let (sector, offset) = slot.ok_or(FsError::FileNotFound)?;
+
+        let mut block = Block::default();
+        self.inner.read_block(sector, &mut block)?;
+
+        let byte = &mut block.as_mut()[offset + 11];
+        let kept = Attributes::from_bits_retain(*byte)
+            - (Attributes::READ_ONLY | Attributes::HIDDEN | Attributes::SYSTEM);
+        *byte = kept.bits() | attributes.bits();
+
+        self.inner.write_block(sector, &block)
+    }
+
     pub fn get_all_file_under_dir(
         &self,
         dir: &Directory,
@@ -145,6 +191,28 @@ impl Fat16Impl {
     }
 }
 
+impl FatTable for Fat16Impl {
+    fn cluster_count(&self) -> usize {
+        (self.bpb.total_sectors() as usize).saturating_sub(self.first_data_sector)
+            / self.bpb.sectors_per_cluster() as usize
+    }
+
+    // read the FAT and get next
+    fn get_next_cluster(&self, cluster: &Cluster) -> Result<Cluster> {
+        if *cluster == Cluster::ROOT_DIR {
+            return Ok(Cluster::END_OF_FILE);
+        }
+
+        match self.read_fat_entry(cluster)? {
+            0x0000 => Ok(Cluster::EMPTY),
+            0xFFF7 => Err(FsError::BadCluster),
+            0xFFF8..=0xFFFF => Ok(Cluster::END_OF_FILE),
+            next if self.is_data_cluster(&Cluster(next as u32)) => Ok(Cluster(next as u32)),
+            _ => Ok(Cluster::INVALID),
+        }
+    }
+}
+
 impl FileSystem for Fat16 {
     fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = Metadata> + Send>> {
         // read dir and return an iterator for all entries
@@ -213,7 +281,11 @@ impl FileSystem for Fat16 {
 
         for i in 0..parts.len() {
             let part = parts[i];
-            let entry = self.handle.get_dir_entry_by_name(&dir, part)?;
+            let entry = match self.handle.get_dir_entry_by_name(&dir, part) {
+                Ok(entry) => entry,
+                Err(FsError::FileNotFound) => return Ok(false),
+                Err(err) => return Err(err),
+            };
             if i == parts.len() - 1 {
                 return Ok(true);
             } else {
@@ -223,4 +295,25 @@ impl FileSystem for Fat16 {
 
         Err(FsError::FileNotFound)
     }
+
+    fn set_attributes(&self, path: &str, attributes: FileAttributes) -> Result<()> {
+        let mut parts = self.handle.parse_path(path);
+        // the root directory has no entry to keep attributes in
+        let name = parts.pop().ok_or(FsError::InvalidOperation)?;
+        let mut dir = self.handle.open_root_dir();
+
+        for part in parts {
+            let entry = self.handle.get_dir_entry_by_name(&dir, part)?;
+            if !entry.is_directory() {
+                return Err(FsError::NotADirectory);
+            }
+            dir = Directory::from_entry(entry);
+        }
+
+        self.handle.set_entry_attributes(&dir, name, attributes)
+    }
+
+    fn sync(&self) -> Result<()> {
+        self.handle.inner.sync()
+    }
 }
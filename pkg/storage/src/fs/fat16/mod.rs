@@ -1,15 +1,25 @@
 pub mod bpb;
+pub mod chain;
+pub mod check;
 pub mod directory;
 pub mod direntry;
 pub mod file;
+pub mod format;
 pub mod impls;
 
+#[cfg(test)]
+pub(crate) mod tests;
+
 use crate::*;
+use chain::{ClusterChain, FatTable};
 use directory::Directory;
 use direntry::*;
 use file::File;
 
 use bpb::Fat16Bpb;
+pub use check::{CheckReport, Problem};
+pub use format::FormatOptions;
+use spin::RwLock;
 
 const BLOCK_SIZE: usize = 512;
 
@@ -20,9 +30,14 @@ pub struct Fat16 {
 
 impl Fat16 {
     pub fn new(inner: impl BlockDevice<Block512>) -> Self {
-        Self {
-            handle: Arc::new(Fat16Impl::new(inner)),
-        }
+        Self::try_new(inner).expect("Failed to load Fat16 volume")
+    }
+
+    /// Load the volume, failing if the device does not hold a Fat16 file system
+    pub fn try_new(inner: impl BlockDevice<Block512>) -> Result<Self> {
+        Ok(Self {
+            handle: Arc::new(Fat16Impl::new(inner)?),
+        })
     }
 }
 
@@ -41,6 +56,8 @@ pub struct Fat16Impl {
     pub fat_start: usize,
     pub first_data_sector: usize,
     pub first_root_dir_sector: usize,
+    /// Sectors of the first FAT, loaded on first access
+    fat_cache: RwLock<Vec<Option<Block512>>>,
 }
 
 impl core::fmt::Debug for Fat16 {
@@ -0,0 +1,594 @@
+//! Tests against a generated multi-megabyte Fat16 image
+//!
+//! The image is built in memory with fragmented cluster chains,
+//! so that files and directories span several sectors of the FAT.
+
+use super::*;
+use crate::testing::{pattern, MemoryDevice};
+
+const TOTAL_SECTORS: usize = 32768; // 16 MiB
+const SECTORS_PER_CLUSTER: usize = 4;
+const SECTORS_PER_FAT: usize = 32;
+const ROOT_ENTRIES: usize = 512;
+const FAT_START: usize = 1;
+const ROOT_START: usize = FAT_START + 2 * SECTORS_PER_FAT;
+const DATA_START: usize = ROOT_START + ROOT_ENTRIES * DirEntry::LEN / BLOCK_SIZE;
+const CLUSTER_SIZE: usize = SECTORS_PER_CLUSTER * BLOCK_SIZE;
+
+pub(super) const BIG_FILE_SIZE: usize = 1_000_000;
+pub(super) const DIR_FILE_COUNT: usize = 150;
+
+struct ImageBuilder {
+    data: Vec<u8>,
+}
+
+impl ImageBuilder {
+    fn new() -> Self {
+        let mut data = vec![0u8; TOTAL_SECTORS * BLOCK_SIZE];
+
+        data[0..3].copy_from_slice(&[0xEB, 0x3C, 0x90]);
+        data[0x03..0x0B].copy_from_slice(b"YSOSTEST");
+        data[0x0B..0x0D].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+        data[0x0D] = SECTORS_PER_CLUSTER as u8;
+        data[0x0E..0x10].copy_from_slice(&(FAT_START as u16).to_le_bytes());
+        data[0x10] = 2;
+        data[0x11..0x13].copy_from_slice(&(ROOT_ENTRIES as u16).to_le_bytes());
+        data[0x13..0x15].copy_from_slice(&(TOTAL_SECTORS as u16).to_le_bytes());
+        data[0x15] = 0xF8;
+        data[0x16..0x18].copy_from_slice(&(SECTORS_PER_FAT as u16).to_le_bytes());
+        data[0x26] = 0x29;
+        data[0x2B..0x36].copy_from_slice(b"YSOS TEST  ");
+        data[0x36..0x3E].copy_from_slice(b"FAT16   ");
+        data[0x1FE..0x200].copy_from_slice(&[0x55, 0xAA]);
+
+        let mut builder = Self { data };
+        builder.set_fat(0, 0xFFF8);
+        builder.set_fat(1, 0xFFFF);
+        builder
+    }
+
+    fn set_fat(&mut self, cluster: u32, value: u16) {
+        for fat in 0..2 {
+            let offset = (FAT_START + fat * SECTORS_PER_FAT) * BLOCK_SIZE + cluster as usize * 2;
+            self.data[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// Link the clusters into a chain
+    fn link(&mut self, clusters: &[u32]) {
+        for pair in clusters.windows(2) {
+            self.set_fat(pair[0], pair[1] as u16);
+        }
+        if let Some(last) = clusters.last() {
+            self.set_fat(*last, 0xFFFF);
+        }
+    }
+
+    fn cluster_range(cluster: u32) -> core::ops::Range<usize> {
+        let start = (DATA_START + (cluster as usize - 2) * SECTORS_PER_CLUSTER) * BLOCK_SIZE;
+        start..start + CLUSTER_SIZE
+    }
+
+    fn write_chain(&mut self, clusters: &[u32], content: &[u8]) {
+        for (cluster, chunk) in clusters.iter().zip(content.chunks(CLUSTER_SIZE)) {
+            let range = Self::cluster_range(*cluster);
+            self.data[range.start..range.start + chunk.len()].copy_from_slice(chunk);
+        }
+    }
+
+    /// Write a dir entry into the `index`-th slot of a directory
+    fn add_entry(
+        &mut self,
+        dir: Option<&[u32]>,
+        index: usize,
+        name: &str,
+        attr: u8,
+        cluster: u32,
+        size: u32,
+    ) {
+        let offset = match dir {
+            None => ROOT_START * BLOCK_SIZE + index * DirEntry::LEN,
+            Some(clusters) => {
+                let per_cluster = CLUSTER_SIZE / DirEntry::LEN;
+                Self::cluster_range(clusters[index / per_cluster]).start
+                    + (index % per_cluster) * DirEntry::LEN
+            }
+        };
+
+        let sfn = ShortFileName::parse(name).unwrap();
+        let entry = &mut self.data[offset..offset + DirEntry::LEN];
+        entry[0..8].copy_from_slice(&sfn.name);
+        entry[8..11].copy_from_slice(&sfn.ext);
+        entry[11] = attr;
+        entry[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+        entry[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+        entry[28..32].copy_from_slice(&size.to_le_bytes());
+    }
+}
+
+/// Builds an image with the following layout:
+///
+/// - `/BIG.BIN`: a file of `BIG_FILE_SIZE` bytes using every other cluster
+/// - `/DELETED.TXT`: a deleted entry in front of the following ones
+/// - `/DIR`: a directory with `DIR_FILE_COUNT` files and a sub directory
+/// - `/DIR/F<i>.TXT`: a file of `i + 1` bytes
+/// - `/DIR/SUB/DEEP.TXT`: a file spanning three clusters
+pub(crate) fn build_image() -> Vec<u8> {
+    let mut image = ImageBuilder::new();
+
+    // the big file takes the even clusters, spanning several FAT sectors
+    let big_count = BIG_FILE_SIZE.div_ceil(CLUSTER_SIZE);
+    let big: Vec<u32> = (0..big_count as u32).map(|i| 2 + i * 2).collect();
+    let content: Vec<u8> = (0..BIG_FILE_SIZE).map(|i| pattern(0, i)).collect();
+    image.link(&big);
+    image.write_chain(&big, &content);
+    image.add_entry(None, 0, "BIG.BIN", 0x20, big[0], BIG_FILE_SIZE as u32);
+
+    // the directory is placed at odd clusters, in reverse order
+    let dir: Vec<u32> = vec![1501, 1201, 3];
+    image.link(&dir);
+    image.add_entry(None, 1, "DELETED.TXT", 0x20, 0, 0);
+    image.data[ROOT_START * BLOCK_SIZE + DirEntry::LEN] = 0xE5;
+    image.add_entry(None, 2, "DIR", 0x10, dir[0], 0);
+
+    for i in 0..DIR_FILE_COUNT {
+        let next = 2001 + i as u32;
+        let content: Vec<u8> = (0..=i).map(|offset| pattern(i, offset)).collect();
+        image.link(&[next]);
+        image.write_chain(&[next], &content);
+        image.add_entry(
+            Some(&dir),
+            i,
+            &format!("F{}.TXT", i),
+            0x20,
+            next,
+            content.len() as u32,
+        );
+    }
+
+    let sub = [5u32];
+    image.link(&sub);
+    image.add_entry(Some(&dir), DIR_FILE_COUNT, "SUB", 0x10, sub[0], 0);
+
+    let deep = [4001u32, 7, 3001];
+    let deep_size = CLUSTER_SIZE * 2 + 100;
+    let content: Vec<u8> = (0..deep_size).map(|i| pattern(0x5A, i)).collect();
+    image.link(&deep);
+    image.write_chain(&deep, &content);
+    image.add_entry(Some(&sub), 0, "DEEP.TXT", 0x20, deep[0], deep_size as u32);
+
+    image.data
+}
+
+pub(super) fn open_image() -> Fat16 {
+    Fat16::new(MemoryDevice::new(build_image()))
+}
+
+#[test]
+fn test_cluster_chain_across_fat_sectors() {
+    let fs = open_image();
+    let entry = fs
+        .handle
+        .get_dir_entry_by_name(&fs.handle.open_root_dir(), "BIG.BIN")
+        .unwrap();
+
+    let chain: Vec<Cluster> = fs
+        .handle
+        .cluster_chain(entry.cluster)
+        .collect::<Result<_>>()
+        .unwrap();
+
+    assert_eq!(chain.len(), BIG_FILE_SIZE.div_ceil(CLUSTER_SIZE));
+    assert!(chain.iter().any(|c| c.0 >= 256));
+    assert!(chain
+        .iter()
+        .enumerate()
+        .all(|(i, c)| c.0 == 2 + i as u32 * 2));
+}
+
+#[test]
+fn test_cluster_chain_stops_on_bad_entries() {
+    let mut data = build_image();
+    // cluster 5 (`/DIR/SUB`) points to a cluster out of the volume
+    let offset = FAT_START * BLOCK_SIZE + 5 * 2;
+    data[offset..offset + 2].copy_from_slice(&0xFF00u16.to_le_bytes());
+    let fs = Fat16::new(MemoryDevice::new(data));
+
+    let chain: Vec<Result<Cluster>> = fs.handle.cluster_chain(Cluster(5)).collect();
+    assert_eq!(chain, vec![Ok(Cluster(5)), Err(FsError::BadCluster)]);
+}
+
+#[test]
+fn test_read_big_file() {
+    let fs = open_image();
+    let mut file = fs.open_file("/BIG.BIN").unwrap();
+
+    // an odd buffer size makes reads cross sector and cluster boundaries
+    let mut content = Vec::new();
+    let mut buf = [0u8; 1000];
+    loop {
+        let len = file.read(&mut buf).unwrap();
+        if len == 0 {
+            break;
+        }
+        content.extend_from_slice(&buf[..len]);
+    }
+
+    assert_eq!(content.len(), BIG_FILE_SIZE);
+    assert!(content.iter().enumerate().all(|(i, b)| *b == pattern(0, i)));
+}
+
+#[test]
+fn test_read_dir_spanning_clusters() {
+    let fs = open_image();
+
+    let root: Vec<String> = fs.read_dir("/").unwrap().map(|m| m.name).collect();
+    assert_eq!(root, vec!["BIG.BIN", "DIR"]);
+
+    let entries: Vec<Metadata> = fs.read_dir("/DIR").unwrap().collect();
+    assert_eq!(entries.len(), DIR_FILE_COUNT + 1);
+    assert!(entries.last().unwrap().is_dir());
+
+    for i in [0, 63, 64, 127, 128, DIR_FILE_COUNT - 1] {
+        let path = format!("/DIR/F{}.TXT", i);
+        assert_eq!(fs.metadata(&path).unwrap().len, i + 1);
+
+        let mut buf = vec![0u8; i + 10];
+        let len = fs.open_file(&path).unwrap().read(&mut buf).unwrap();
+        assert_eq!(len, i + 1);
+        assert!(buf[..len]
+            .iter()
+            .enumerate()
+            .all(|(o, b)| *b == pattern(i, o)));
+    }
+}
+
+#[test]
+fn test_read_deep_path() {
+    let fs = open_image();
+    assert!(fs.exists("/DIR/SUB/DEEP.TXT").unwrap());
+
+    let mut file = fs.open_file("/dir/sub/deep.txt").unwrap();
+    let mut content = Vec::new();
+    file.read_all(&mut content).unwrap();
+
+    let size = CLUSTER_SIZE * 2 + 100;
+    assert!(content.len() >= size);
+    assert!(content[..size]
+        .iter()
+        .enumerate()
+        .all(|(i, b)| *b == pattern(0x5A, i)));
+}
+
+#[test]
+fn test_set_attributes() {
+    let fs = open_image();
+
+    let flags = FileAttributes::READ_ONLY | FileAttributes::HIDDEN;
+    fs.set_attributes("/DIR/F1.TXT", flags).unwrap();
+    assert_eq!(fs.metadata("/DIR/F1.TXT").unwrap().attributes, flags);
+    assert!(!fs.read_dir("/DIR").unwrap().any(|m| m.name == "F1.TXT"));
+
+    // the directory bit is kept
+    fs.set_attributes("/DIR", FileAttributes::READ_ONLY)
+        .unwrap();
+    let dir = fs.metadata("/DIR").unwrap();
+    assert!(dir.is_dir() && dir.is_read_only());
+
+    fs.set_attributes("/DIR/F1.TXT", FileAttributes::empty())
+        .unwrap();
+    assert_eq!(
+        fs.metadata("/DIR/F1.TXT").unwrap().attributes,
+        FileAttributes::empty()
+    );
+
+    assert_eq!(
+        fs.set_attributes("/", FileAttributes::empty()).err(),
+        Some(FsError::InvalidOperation)
+    );
+    assert_eq!(
+        fs.set_attributes("/BIG.BIN/A", FileAttributes::empty())
+            .err(),
+        Some(FsError::NotADirectory)
+    );
+}
+
+#[test]
+fn test_read_only_entries_are_protected() {
+    let table = MountTable::new();
+    table
+        .mount(Mount::new(Box::new(open_image()), "/".into()))
+        .unwrap();
+
+    table
+        .set_attributes("/BIG.BIN", FileAttributes::READ_ONLY)
+        .unwrap();
+    table
+        .set_attributes("/DIR", FileAttributes::READ_ONLY)
+        .unwrap();
+
+    let read_only = Some(FsError::ReadOnly);
+    assert_eq!(table.append_file("/BIG.BIN").err(), read_only);
+    assert_eq!(table.remove_file("/BIG.BIN").err(), read_only);
+    assert_eq!(table.create_file("/DIR/NEW.TXT").err(), read_only);
+    assert_eq!(table.remove_file("/DIR/F0.TXT").err(), read_only);
+    assert_eq!(table.move_file("/DIR/F0.TXT", "/F0.TXT").err(), read_only);
+    // and neither can anything further down
+    assert_eq!(table.append_file("/DIR/SUB/DEEP.TXT").err(), read_only);
+    assert_eq!(table.create_dir("/DIR/SUB/NEW").err(), read_only);
+
+    // reading is not affected, and the entries can be made writable again
+    assert!(table.open_file("/DIR/F0.TXT").is_ok());
+    table
+        .set_attributes("/DIR", FileAttributes::empty())
+        .unwrap();
+    assert_eq!(
+        table.create_file("/DIR/NEW.TXT").err(),
+        Some(FsError::NotSupported)
+    );
+}
+
+#[test]
+fn test_sync_writes_back_the_cache() {
+    let device: &'static MemoryDevice = Box::leak(Box::new(MemoryDevice::new(build_image())));
+    let table = MountTable::new();
+    table
+        .mount(Mount::new(
+            Box::new(Fat16::new(CachedDevice::new(device, 64))),
+            "/".into(),
+        ))
+        .unwrap();
+
+    table
+        .set_attributes("/BIG.BIN", FileAttributes::READ_ONLY)
+        .unwrap();
+    assert!(table.metadata("/BIG.BIN").unwrap().is_read_only());
+
+    // the change is only in the cache until synced
+    let read_only = || {
+        Fat16::new(device)
+            .metadata("/BIG.BIN")
+            .unwrap()
+            .is_read_only()
+    };
+    assert!(!read_only());
+    table.sync().unwrap();
+    assert!(read_only());
+}
+
+#[test]
+fn test_seek_big_file() {
+    let fs = open_image();
+    let mut file = fs.open_file("/BIG.BIN").unwrap();
+    let mut buf = [0u8; 100];
+
+    let check = |file: &mut FileHandle, buf: &mut [u8], offset: usize| {
+        let len = file.read(buf).unwrap();
+        assert_eq!(len, buf.len().min(BIG_FILE_SIZE - offset));
+        assert!(buf[..len]
+            .iter()
+            .enumerate()
+            .all(|(i, b)| *b == pattern(0, offset + i)));
+    };
+
+    // forward from the current cluster
+    assert_eq!(file.seek(SeekFrom::Start(700_000)).unwrap(), 700_000);
+    check(&mut file, &mut buf, 700_000);
+
+    // backward restarts from the first cluster
+    assert_eq!(file.seek(SeekFrom::Current(-500_100)).unwrap(), 200_000);
+    check(&mut file, &mut buf, 200_000);
+
+    assert_eq!(file.seek(SeekFrom::End(-10)).unwrap(), BIG_FILE_SIZE - 10);
+    check(&mut file, &mut buf, BIG_FILE_SIZE - 10);
+
+    assert_eq!(file.seek(SeekFrom::End(0)).unwrap(), BIG_FILE_SIZE);
+    assert_eq!(file.read(&mut buf).unwrap(), 0);
+
+    assert_eq!(file.seek(SeekFrom::Start(CLUSTER_SIZE)).unwrap(), CLUSTER_SIZE);
+    check(&mut file, &mut buf, CLUSTER_SIZE);
+}
+
+#[test]
+fn test_seek_out_of_range() {
+    let fs = open_image();
+    let mut file = fs.open_file("/DIR/SUB/DEEP.TXT").unwrap();
+
+    assert_eq!(file.seek(SeekFrom::Current(-1)), Err(FsError::InvalidOffset));
+    assert_eq!(file.seek(SeekFrom::End(1)), Err(FsError::InvalidOffset));
+    assert_eq!(
+        file.seek(SeekFrom::Start(usize::MAX)),
+        Err(FsError::InvalidOffset)
+    );
+
+    // a failed seek keeps the position
+    let mut buf = [0u8; 4];
+    assert_eq!(file.read(&mut buf).unwrap(), 4);
+    assert!(buf.iter().enumerate().all(|(i, b)| *b == pattern(0x5A, i)));
+}
+
+/// Format a leaked zeroed device of `sectors` sectors
+fn format_device(sectors: usize, options: &FormatOptions) -> Result<&'static MemoryDevice> {
+    let device = Box::leak(Box::new(MemoryDevice::new(vec![0u8; sectors * BLOCK_SIZE])));
+    Fat16::format(device, options).map(|_| &*device)
+}
+
+#[test]
+fn test_format() {
+    for sectors in [8192, 32768, 131072, 1 << 20] {
+        let device = format_device(sectors, &FormatOptions::default()).unwrap();
+        assert_eq!(FatType::detect(&device), Ok(Some(FatType::Fat16)));
+
+        let fs = Fat16::try_new(device).unwrap();
+        let clusters = fs.handle.cluster_count();
+        assert!((format::MIN_CLUSTERS..=format::MAX_CLUSTERS).contains(&clusters));
+        assert!((fs.handle.bpb.sectors_per_fat() as usize) * 256 >= clusters + 2);
+        assert_eq!(fs.handle.bpb.total_sectors() as usize, sectors);
+        assert_eq!(fs.handle.bpb.volume_label(), b"NO NAME    ");
+
+        assert_eq!(fs.read_dir("/").unwrap().count(), 0);
+        assert_eq!(fs.exists("/FILE.TXT"), Ok(false));
+        assert!(fs.check(false).unwrap().is_clean());
+    }
+}
+
+#[test]
+fn test_format_options() {
+    let options = FormatOptions {
+        label: "scratch".into(),
+        volume_id: 0x1234_5678,
+        sectors_per_cluster: Some(8),
+        root_entries: 256,
+    };
+    let fs = Fat16::try_new(format_device(65536, &options).unwrap()).unwrap();
+    assert_eq!(fs.handle.bpb.volume_label(), b"SCRATCH    ");
+    assert_eq!(fs.handle.bpb.volume_id(), 0x1234_5678);
+    assert_eq!(fs.handle.bpb.sectors_per_cluster(), 8);
+    assert_eq!(fs.handle.bpb.root_entries_count(), 256);
+
+    // too small for Fat16, it would be Fat12
+    let small = format_device(4096, &FormatOptions::default());
+    assert_eq!(small.err(), Some(FsError::NotSupported));
+
+    let invalid = [
+        FormatOptions {
+            label: "LABEL TOO LONG".into(),
+            ..Default::default()
+        },
+        FormatOptions {
+            sectors_per_cluster: Some(3),
+            ..Default::default()
+        },
+        FormatOptions {
+            root_entries: 100,
+            ..Default::default()
+        },
+    ];
+    for options in invalid {
+        assert_eq!(
+            format_device(32768, &options).err(),
+            Some(FsError::InvalidOperation)
+        );
+    }
+}
+
+#[test]
+fn test_check_clean() {
+    let report = open_image().check(false).unwrap();
+    assert_eq!(report, CheckReport::default());
+}
+
+#[test]
+fn test_check_and_repair() {
+    let mut image = ImageBuilder { data: build_image() };
+    let dir = [1501u32, 1201, 3];
+    let big_clusters = BIG_FILE_SIZE.div_ceil(CLUSTER_SIZE);
+    let big_size = (BIG_FILE_SIZE + 10 * CLUSTER_SIZE) as u32;
+    let deep_size = (CLUSTER_SIZE * 2 + 100) as u32;
+
+    // the second FAT differs in one sector
+    image.data[(FAT_START + SECTORS_PER_FAT) * BLOCK_SIZE + 5000 * 2] = 0x12;
+    // a cluster in use without an entry
+    image.set_fat(8000, 0xFFFF);
+    // `/DIR/F1.TXT` starts in the cluster of `/DIR/F0.TXT`, losing its own
+    image.add_entry(Some(&dir), 1, "F1.TXT", 0x20, 2001, 2);
+    // `/BIG.BIN` claims 10 clusters more than its chain
+    image.add_entry(None, 0, "BIG.BIN", 0x20, 2, big_size);
+    // the chain of `/DIR/SUB/DEEP.TXT` links to a free cluster, losing its tail
+    image.set_fat(7, 0);
+
+    let expected = vec![
+        Problem::FatMismatch {
+            copy: 1,
+            sectors: 1,
+        },
+        Problem::SizeMismatch {
+            path: "/BIG.BIN".into(),
+            size: big_size,
+            clusters: big_clusters,
+        },
+        Problem::CrossLinked {
+            path: "/DIR/F1.TXT".into(),
+            cluster: 2001,
+        },
+        Problem::SizeMismatch {
+            path: "/DIR/F1.TXT".into(),
+            size: 2,
+            clusters: 0,
+        },
+        Problem::BadChain {
+            path: "/DIR/SUB/DEEP.TXT".into(),
+            cluster: 0,
+        },
+        Problem::SizeMismatch {
+            path: "/DIR/SUB/DEEP.TXT".into(),
+            size: deep_size,
+            clusters: 2,
+        },
+        Problem::LostClusters(3),
+    ];
+
+    let fs = Fat16::new(MemoryDevice::new(image.data));
+
+    // checking alone changes nothing
+    let report = fs.check(false).unwrap();
+    assert_eq!(report.problems, expected);
+    assert!(!report.repaired);
+    assert_eq!(fs.check(false).unwrap().problems, expected);
+
+    let report = fs.check(true).unwrap();
+    assert_eq!(report.problems, expected);
+    assert!(report.repaired);
+    assert!(fs.check(false).unwrap().is_clean());
+
+    // the sizes are fitted to the chains, the other files are intact
+    assert_eq!(fs.metadata("/DIR/F1.TXT").unwrap().len, 0);
+    assert_eq!(
+        fs.metadata("/BIG.BIN").unwrap().len,
+        big_clusters * CLUSTER_SIZE
+    );
+    assert_eq!(
+        fs.metadata("/DIR/SUB/DEEP.TXT").unwrap().len,
+        CLUSTER_SIZE * 2
+    );
+
+    let mut buf = [0u8; 8];
+    assert_eq!(fs.open_file("/DIR/F0.TXT").unwrap().read(&mut buf), Ok(1));
+    assert_eq!(buf[0], pattern(0, 0));
+    assert_eq!(fs.open_file("/DIR/F1.TXT").unwrap().read(&mut buf), Ok(0));
+
+    let mut content = Vec::new();
+    fs.open_file("/DIR/SUB/DEEP.TXT")
+        .unwrap()
+        .read_all(&mut content)
+        .unwrap();
+    assert!(content[..CLUSTER_SIZE * 2]
+        .iter()
+        .enumerate()
+        .all(|(i, b)| *b == pattern(0x5A, i)));
+}
+
+#[test]
+fn test_repair_removes_broken_directory() {
+    let mut image = ImageBuilder { data: build_image() };
+    // `/DIR/SUB` points out of the volume
+    image.add_entry(Some(&[1501, 1201, 3]), DIR_FILE_COUNT, "SUB", 0x10, 0xFFF0, 0);
+
+    let fs = Fat16::new(MemoryDevice::new(image.data));
+    let report = fs.check(true).unwrap();
+    assert_eq!(
+        report.problems,
+        vec![
+            Problem::BadChain {
+                path: "/DIR/SUB".into(),
+                cluster: 0xFFF0,
+            },
+            // the directory and the file below it
+            Problem::LostClusters(4),
+        ]
+    );
+
+    assert_eq!(fs.exists("/DIR/SUB"), Ok(false));
+    assert_eq!(fs.read_dir("/DIR").unwrap().count(), DIR_FILE_COUNT);
+    assert!(fs.check(false).unwrap().is_clean());
+}
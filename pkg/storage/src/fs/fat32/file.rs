@@ -0,0 +1,119 @@
+//! File
+//!
+//! reference: <https://wiki.osdev.org/FAT#Directories_on_FAT12.2F16.2F32>
+
+use core::cmp::min;
+
+use super::*;
+
+#[derive(Debug, Clone)]
+pub struct File {
+    /// The current offset in the file
+    offset: usize,
+    /// The current cluster of this file
+    current_cluster: Cluster,
+    /// The index of `current_cluster` in the cluster chain
+    cluster_index: usize,
+    /// DirEntry of this file
+    entry: DirEntry,
+    /// The file system handle that contains this file
+    handle: Fat32Handle,
+}
+
+impl File {
+    pub fn new(handle: Fat32Handle, entry: DirEntry) -> Self {
+        Self {
+            offset: 0,
+            current_cluster: entry.cluster,
+            cluster_index: 0,
+            entry,
+            handle,
+        }
+    }
+
+    pub fn length(&self) -> usize {
+        self.entry.size as usize
+    }
+
+    /// Walk the cluster chain until `current_cluster` holds the byte at `offset`
+    fn locate_cluster(&mut self) -> Result<()> {
+        let target = self.offset / self.handle.bytes_per_cluster();
+
+        if target > self.cluster_index {
+            self.current_cluster = self
+                .handle
+                .cluster_chain(self.current_cluster)
+                .nth(target - self.cluster_index)
+                .ok_or(FsError::EndOfFile)??;
+            self.cluster_index = target;
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for File {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        // read file content from disk
+        let bps = self.handle.bpb.bytes_per_sector() as usize;
+        let cluster_size = self.handle.bytes_per_cluster();
+        let to_read = min(buf.len(), self.length().saturating_sub(self.offset));
+        let mut read_bytes = 0;
+        let mut block = Block::default();
+
+        while read_bytes < to_read {
+            self.locate_cluster()?;
+
+            let cluster_offset = self.offset % cluster_size;
+            let byte_offset = cluster_offset % bps;
+            let sector =
+                self.handle.cluster_to_first_sector(&self.current_cluster) + cluster_offset / bps;
+            self.handle.inner.read_block(sector, &mut block)?;
+
+            let bytes_to_read = min(to_read - read_bytes, bps - byte_offset);
+            buf[read_bytes..read_bytes + bytes_to_read]
+                .copy_from_slice(&block[byte_offset..byte_offset + bytes_to_read]);
+
+            read_bytes += bytes_to_read;
+            self.offset += bytes_to_read;
+        }
+
+        Ok(read_bytes)
+    }
+}
+
+impl Seek for File {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.length().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+        }
+        .filter(|offset| *offset <= self.length())
+        .ok_or(FsError::InvalidOffset)?;
+
+        // the chain can only be walked forward, restart from the first cluster
+        if offset / self.handle.bytes_per_cluster() < self.cluster_index {
+            self.current_cluster = self.entry.cluster;
+            self.cluster_index = 0;
+        }
+
+        self.offset = offset;
+        if offset < self.length() {
+            self.locate_cluster()?;
+        }
+
+        Ok(offset)
+    }
+}
+
+// NOTE: `Write` trait is not required for this lab
+impl Write for File {
+    fn write(&mut self, _buf: &[u8]) -> Result<usize> {
+        Err(FsError::ReadOnly)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
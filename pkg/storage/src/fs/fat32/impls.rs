@@ -0,0 +1,223 @@
+use super::*;
+
+impl Fat32Impl {
+    pub fn new(inner: impl BlockDevice<Block512>) -> Result<Self> {
+        let mut block = Block::default();
+
+        inner.read_block(0, &mut block)?;
+        let bpb = Fat32Bpb::new(block.as_ref()).map_err(|err| {
+            warn!("Failed to load Fat32 volume: {}", err);
+            FsError::InvalidFileSystem
+        })?;
+
+        trace!("Loading Fat32 Volume: {:#?}", bpb);
+
+        // the FSInfo only holds hints, the volume is usable without it
+        let fs_info = match bpb.fs_info_sector() {
+            0 | 0xFFFF => None,
+            sector => {
+                inner.read_block(sector as usize, &mut block)?;
+                FsInfo::new(block.as_ref())
+                    .inspect_err(|err| warn!("Ignoring FSInfo: {}", err))
+                    .ok()
+            }
+        };
+
+        // use the active FAT if mirroring is disabled
+        let active_fat = if bpb.ext_flags() & 0x80 != 0 {
+            (bpb.ext_flags() & 0x0F) as usize
+        } else {
+            0
+        };
+
+        let fat_start =
+            bpb.reserved_sector_count() as usize + active_fat * bpb.sectors_per_fat() as usize;
+        let first_data_sector = bpb.reserved_sector_count() as usize
+            + bpb.fat_count() as usize * bpb.sectors_per_fat() as usize;
+
+        Ok(Self {
+            bpb,
+            fs_info,
+            inner: Box::new(inner),
+            fat_start,
+            first_data_sector,
+        })
+    }
+
+    pub fn cluster_to_first_sector(&self, cluster: &Cluster) -> usize {
+        self.first_data_sector + (cluster.0 as usize - 2) * self.sectors_per_cluster()
+    }
+
+    pub fn sectors_per_cluster(&self) -> usize {
+        self.bpb.sectors_per_cluster() as usize
+    }
+
+    pub fn bytes_per_cluster(&self) -> usize {
+        self.bpb.bytes_per_sector() as usize * self.sectors_per_cluster()
+    }
+
+    /// Iterates over the cluster chain beginning at `start`
+    pub fn cluster_chain(&self, start: Cluster) -> ClusterChain<'_, Self> {
+        ClusterChain::new(self, start)
+    }
+
+    /// Read the raw FAT entry of the given cluster
+    ///
+    /// Every entry takes 4 bytes, only the lower 28 bits are used.
+    /// The FAT sectors are not kept here, the volume may be much larger than
+    /// the memory, they are cached by the device when mounted.
+    fn read_fat_entry(&self, cluster: &Cluster) -> Result<u32> {
+        let fat_offset = cluster.0 as usize * 4;
+        let sector = fat_offset / BLOCK_SIZE;
+        let offset = fat_offset % BLOCK_SIZE;
+
+        if sector >= self.bpb.sectors_per_fat() as usize {
+            return Err(FsError::BadCluster);
+        }
+
+        let mut block = Block::default();
+        self.inner.read_block(self.fat_start + sector, &mut block)?;
+        Ok(u32::from_le_bytes(block[offset..offset + 4].try_into().unwrap()) & 0x0FFF_FFFF)
+    }
+
+    pub fn open_root_dir(&self) -> Directory {
+        Directory::new(Cluster(self.bpb.root_cluster()))
+    }
+
+    /// Calls `process_entry` for every valid entry in the directory
+    fn traverse_dir_entries<F>(&self, dir: &Directory, mut process_entry: F) -> Result<()>
+    where
+        F: FnMut(DirEntry) -> Result<()>,
+    {
+        let mut block = Block::default();
+
+        for cluster in self.cluster_chain(dir.cluster) {
+            let first_sector = self.cluster_to_first_sector(&cluster?);
+
+            for sector in first_sector..first_sector + self.sectors_per_cluster() {
+                self.inner.read_block(sector, &mut block)?;
+
+                for data in block.chunks(DirEntry::LEN) {
+                    let entry = DirEntry::parse(data)?;
+                    if entry.filename.is_eod() {
+                        return Ok(());
+                    }
+                    if entry.is_valid() {
+                        process_entry(entry)?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn get_dir_entry_by_name(&self, dir: &Directory, name: &str) -> Result<DirEntry> {
+        let name = ShortFileName::parse(name)?;
+        let mut result = None;
+        self.traverse_dir_entries(dir, |entry| {
+            if result.is_none() && entry.filename.matches(&name) {
+                result = Some(entry);
+            }
+            Ok(())
+        })?;
+        result.ok_or(FsError::FileNotFound)
+    }
+
+    /// Walk the path from the root directory
+    ///
+    /// Returns `None` for the root directory, which has no entry.
+    pub fn lookup(&self, path: &str) -> Result<Option<DirEntry>> {
+        let mut dir = self.open_root_dir();
+        let mut entry = None;
+
+        for part in path.split(PATH_SEPARATOR).filter(|s| !s.is_empty()) {
+            if entry.as_ref().is_some_and(|e: &DirEntry| !e.is_directory()) {
+                return Err(FsError::NotADirectory);
+            }
+
+            let found = self.get_dir_entry_by_name(&dir, part)?;
+            // `..` of a child of the root points to cluster 0
+            dir = match found.cluster {
+                Cluster::EMPTY => self.open_root_dir(),
+                cluster => Directory::new(cluster),
+            };
+            entry = Some(found);
+        }
+
+        Ok(entry)
+    }
+}
+
+impl FatTable for Fat32Impl {
+    fn cluster_count(&self) -> usize {
+        (self.bpb.total_sectors() as usize).saturating_sub(self.first_data_sector)
+            / self.sectors_per_cluster()
+    }
+
+    fn get_next_cluster(&self, cluster: &Cluster) -> Result<Cluster> {
+        match self.read_fat_entry(cluster)? {
+            0x0000_0000 => Ok(Cluster::EMPTY),
+            0x0FFF_FFF7 => Err(FsError::BadCluster),
+            0x0FFF_FFF8..=0x0FFF_FFFF => Ok(Cluster::END_OF_FILE),
+            next if self.is_data_cluster(&Cluster(next)) => Ok(Cluster(next)),
+            _ => Ok(Cluster::INVALID),
+        }
+    }
+}
+
+impl FileSystem for Fat32 {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = Metadata> + Send>> {
+        let dir = match self.handle.lookup(path)? {
+            None => self.handle.open_root_dir(),
+            Some(entry) if entry.is_directory() => Directory::from_entry(entry),
+            Some(_) => return Err(FsError::NotADirectory),
+        };
+
+        let mut entries = Vec::new();
+        self.handle.traverse_dir_entries(&dir, |entry| {
+            if entry.is_displayable() {
+                entries.push(Metadata::from(&entry));
+            }
+            Ok(())
+        })?;
+
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn open_file(&self, path: &str) -> Result<FileHandle> {
+        match self.handle.lookup(path)? {
+            Some(entry) if !entry.is_directory() => Ok(FileHandle::new(
+                Metadata::from(&entry),
+                Box::new(File::new(self.handle.clone(), entry)),
+            )),
+            _ => Err(FsError::NotAFile),
+        }
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        match self.handle.lookup(path)? {
+            Some(entry) => Ok(Metadata::from(&entry)),
+            None => Ok(Metadata::new(
+                "/".into(),
+                FileType::Directory,
+                0,
+                None,
+                None,
+                None,
+            )),
+        }
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        match self.handle.lookup(path) {
+            Ok(_) => Ok(true),
+            Err(FsError::FileNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn sync(&self) -> Result<()> {
+        self.handle.inner.sync()
+    }
+}
@@ -0,0 +1,65 @@
+pub mod bpb;
+pub mod file;
+pub mod impls;
+
+#[cfg(test)]
+mod tests;
+
+use crate::fat16::chain::{ClusterChain, FatTable};
+use crate::fat16::directory::Directory;
+use crate::fat16::direntry::*;
+use crate::*;
+use file::File;
+
+use bpb::{Fat32Bpb, FsInfo};
+
+const BLOCK_SIZE: usize = 512;
+
+/// Identifies a Fat32 filesystem on the disk.
+pub struct Fat32 {
+    handle: Fat32Handle,
+}
+
+impl Fat32 {
+    pub fn new(inner: impl BlockDevice<Block512>) -> Self {
+        Self::try_new(inner).expect("Failed to load Fat32 volume")
+    }
+
+    /// Load the volume, failing if the device does not hold a Fat32 file system
+    pub fn try_new(inner: impl BlockDevice<Block512>) -> Result<Self> {
+        Ok(Self {
+            handle: Arc::new(Fat32Impl::new(inner)?),
+        })
+    }
+}
+
+type Fat32Handle = Arc<Fat32Impl>;
+
+/// The Fat32 filesystem.
+///
+/// Unlike Fat16, the root directory is an ordinary cluster chain
+/// starting at the root cluster recorded in the BPB.
+///
+/// [ Fat32 BPB ] [ FSInfo ] [ Reserved ] [ FATs ] [ Data ]
+pub struct Fat32Impl {
+    pub(crate) inner: Box<dyn BlockDevice<Block512>>,
+    pub bpb: Fat32Bpb,
+    pub fs_info: Option<FsInfo>,
+    pub fat_start: usize,
+    pub first_data_sector: usize,
+}
+
+impl core::fmt::Debug for Fat32 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Fat32")
+            .field("bpb", &self.handle.bpb)
+            .field("fs_info", &self.handle.fs_info)
+            .finish()
+    }
+}
+
+impl core::fmt::Debug for Fat32Impl {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Fat32Impl").field("bpb", &self.bpb).finish()
+    }
+}
@@ -0,0 +1,281 @@
+//! Tests against a generated Fat32 image
+//!
+//! The root directory and the files are spread over fragmented cluster chains.
+
+use super::*;
+use crate::testing::{pattern, read_to_end, MemoryDevice};
+
+const TOTAL_SECTORS: usize = 70_000; // 34 MiB, more than 65536 clusters
+const RESERVED_SECTORS: usize = 32;
+const SECTORS_PER_FAT: usize = 547;
+const FAT_START: usize = RESERVED_SECTORS;
+const DATA_START: usize = FAT_START + 2 * SECTORS_PER_FAT;
+const CLUSTER_SIZE: usize = BLOCK_SIZE;
+
+const ROOT_CLUSTERS: [u32; 3] = [2, 500, 300];
+const ROOT_FILE_COUNT: usize = 40;
+const BIG_FILE_SIZE: usize = 300_000;
+
+struct ImageBuilder {
+    data: Vec<u8>,
+}
+
+impl ImageBuilder {
+    fn new() -> Self {
+        let mut data = vec![0u8; TOTAL_SECTORS * BLOCK_SIZE];
+
+        data[0..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+        data[0x03..0x0B].copy_from_slice(b"YSOSTEST");
+        data[0x0B..0x0D].copy_from_slice(&(BLOCK_SIZE as u16).to_le_bytes());
+        data[0x0D] = 1;
+        data[0x0E..0x10].copy_from_slice(&(RESERVED_SECTORS as u16).to_le_bytes());
+        data[0x10] = 2;
+        data[0x15] = 0xF8;
+        data[0x20..0x24].copy_from_slice(&(TOTAL_SECTORS as u32).to_le_bytes());
+        data[0x24..0x28].copy_from_slice(&(SECTORS_PER_FAT as u32).to_le_bytes());
+        data[0x2C..0x30].copy_from_slice(&ROOT_CLUSTERS[0].to_le_bytes());
+        data[0x30..0x32].copy_from_slice(&1u16.to_le_bytes());
+        data[0x32..0x34].copy_from_slice(&6u16.to_le_bytes());
+        data[0x42] = 0x29;
+        data[0x47..0x52].copy_from_slice(b"YSOS TEST  ");
+        data[0x52..0x5A].copy_from_slice(b"FAT32   ");
+        data[0x1FE..0x200].copy_from_slice(&[0x55, 0xAA]);
+
+        // FSInfo
+        let info = BLOCK_SIZE;
+        data[info..info + 4].copy_from_slice(&0x4161_5252u32.to_le_bytes());
+        data[info + 0x1E4..info + 0x1E8].copy_from_slice(&0x6141_7272u32.to_le_bytes());
+        data[info + 0x1E8..info + 0x1EC].copy_from_slice(&1234u32.to_le_bytes());
+        data[info + 0x1EC..info + 0x1F0].copy_from_slice(&5678u32.to_le_bytes());
+        data[info + 0x1FC..info + 0x200].copy_from_slice(&0xAA55_0000u32.to_le_bytes());
+
+        let mut builder = Self { data };
+        builder.set_fat(0, 0x0FFF_FFF8);
+        builder.set_fat(1, 0x0FFF_FFFF);
+        builder
+    }
+
+    fn set_fat(&mut self, cluster: u32, value: u32) {
+        for fat in 0..2 {
+            let offset = (FAT_START + fat * SECTORS_PER_FAT) * BLOCK_SIZE + cluster as usize * 4;
+            self.data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// Link the clusters into a chain, with the reserved high bits set
+    fn link(&mut self, clusters: &[u32]) {
+        for pair in clusters.windows(2) {
+            self.set_fat(pair[0], 0xF000_0000 | pair[1]);
+        }
+        if let Some(last) = clusters.last() {
+            self.set_fat(*last, 0x0FFF_FFFF);
+        }
+    }
+
+    fn cluster_offset(cluster: u32) -> usize {
+        (DATA_START + cluster as usize - 2) * BLOCK_SIZE
+    }
+
+    fn write_chain(&mut self, clusters: &[u32], content: &[u8]) {
+        for (cluster, chunk) in clusters.iter().zip(content.chunks(CLUSTER_SIZE)) {
+            let start = Self::cluster_offset(*cluster);
+            self.data[start..start + chunk.len()].copy_from_slice(chunk);
+        }
+    }
+
+    /// Write a dir entry into the `index`-th slot of a directory
+    fn add_entry(
+        &mut self,
+        dir: &[u32],
+        index: usize,
+        name: &str,
+        attr: u8,
+        cluster: u32,
+        size: u32,
+    ) {
+        let per_cluster = CLUSTER_SIZE / DirEntry::LEN;
+        let offset =
+            Self::cluster_offset(dir[index / per_cluster]) + (index % per_cluster) * DirEntry::LEN;
+
+        let (name, ext) = match name {
+            "." | ".." => (name.as_bytes(), &b""[..]),
+            _ => name
+                .split_once('.')
+                .map_or((name.as_bytes(), &b""[..]), |(n, e)| {
+                    (n.as_bytes(), e.as_bytes())
+                }),
+        };
+
+        let entry = &mut self.data[offset..offset + DirEntry::LEN];
+        entry[0..11].fill(b' ');
+        entry[0..name.len()].copy_from_slice(name);
+        entry[8..8 + ext.len()].copy_from_slice(ext);
+        entry[11] = attr;
+        entry[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+        entry[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+        entry[28..32].copy_from_slice(&size.to_le_bytes());
+    }
+}
+
+/// Builds an image with the following layout:
+///
+/// - `/F<i>.TXT`: `ROOT_FILE_COUNT` files of `i + 1` bytes, filling three root clusters
+/// - `/BIG.DAT`: a file of `BIG_FILE_SIZE` bytes using every third cluster
+/// - `/SUB/INNER.TXT`: a file in a sub directory, starting above cluster 65535
+fn build_image() -> Vec<u8> {
+    let mut image = ImageBuilder::new();
+    image.link(&ROOT_CLUSTERS);
+
+    for i in 0..ROOT_FILE_COUNT {
+        let next = 4000 + i as u32;
+        let content: Vec<u8> = (0..=i).map(|o| pattern(i, o)).collect();
+        image.link(&[next]);
+        image.write_chain(&[next], &content);
+        image.add_entry(
+            &ROOT_CLUSTERS,
+            i,
+            &format!("F{}.TXT", i),
+            0x20,
+            next,
+            content.len() as u32,
+        );
+    }
+
+    let big: Vec<u32> = (0..BIG_FILE_SIZE.div_ceil(CLUSTER_SIZE) as u32)
+        .map(|i| 1000 + i * 3)
+        .collect();
+    let content: Vec<u8> = (0..BIG_FILE_SIZE).map(|i| pattern(0, i)).collect();
+    image.link(&big);
+    image.write_chain(&big, &content);
+    image.add_entry(
+        &ROOT_CLUSTERS,
+        ROOT_FILE_COUNT,
+        "BIG.DAT",
+        0x20,
+        big[0],
+        BIG_FILE_SIZE as u32,
+    );
+
+    let sub = [3u32];
+    image.link(&sub);
+    image.add_entry(&ROOT_CLUSTERS, ROOT_FILE_COUNT + 1, "SUB", 0x10, sub[0], 0);
+    image.add_entry(&sub, 0, ".", 0x10, sub[0], 0);
+    image.add_entry(&sub, 1, "..", 0x10, 0, 0);
+
+    let inner = [66_000u32, 4];
+    let content: Vec<u8> = (0..700).map(|i| pattern(0x33, i)).collect();
+    image.link(&inner);
+    image.write_chain(&inner, &content);
+    image.add_entry(&sub, 2, "INNER.TXT", 0x20, inner[0], content.len() as u32);
+
+    image.data
+}
+
+fn open_image() -> Fat32 {
+    Fat32::new(MemoryDevice::new(build_image()))
+}
+
+#[test]
+fn test_load_volume() {
+    let fs = open_image();
+    let info = fs.handle.fs_info.as_ref().unwrap();
+    assert_eq!(info.free_cluster_count(), 1234);
+    assert_eq!(info.next_free_cluster(), 5678);
+
+    let root: Vec<Cluster> = fs
+        .handle
+        .cluster_chain(fs.handle.open_root_dir().cluster)
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(root, ROOT_CLUSTERS.map(Cluster));
+
+    // a Fat16 volume is rejected
+    let mut data = build_image();
+    data[0x16] = 32;
+    assert!(Fat32::try_new(MemoryDevice::new(data)).is_err());
+}
+
+#[test]
+fn test_read_root_dir() {
+    let fs = open_image();
+    let names: Vec<String> = fs.read_dir("/").unwrap().map(|m| m.name).collect();
+
+    assert_eq!(names.len(), ROOT_FILE_COUNT + 2);
+    assert_eq!(
+        names[ROOT_FILE_COUNT - 1],
+        format!("F{}.TXT", ROOT_FILE_COUNT - 1)
+    );
+    assert_eq!(names[ROOT_FILE_COUNT..], ["BIG.DAT", "SUB"]);
+    assert!(fs.metadata("/").unwrap().is_dir());
+    assert!(fs.metadata("/SUB").unwrap().is_dir());
+}
+
+#[test]
+fn test_read_files() {
+    let fs = open_image();
+
+    for i in [0, 15, 16, 39] {
+        let mut file = fs.open_file(&format!("/f{}.txt", i)).unwrap();
+        let content = read_to_end(&mut file);
+        assert_eq!(content.len(), i + 1);
+        assert!(content.iter().enumerate().all(|(o, b)| *b == pattern(i, o)));
+    }
+
+    let content = read_to_end(&mut fs.open_file("/BIG.DAT").unwrap());
+    assert_eq!(content.len(), BIG_FILE_SIZE);
+    assert!(content.iter().enumerate().all(|(i, b)| *b == pattern(0, i)));
+
+    let content = read_to_end(&mut fs.open_file("/SUB/INNER.TXT").unwrap());
+    assert_eq!(content.len(), 700);
+    assert!(content
+        .iter()
+        .enumerate()
+        .all(|(i, b)| *b == pattern(0x33, i)));
+}
+
+#[test]
+fn test_seek_file() {
+    let fs = open_image();
+    let mut file = fs.open_file("/BIG.DAT").unwrap();
+    let mut buf = [0u8; 10];
+
+    for offset in [250_000, 1000, BIG_FILE_SIZE - 5] {
+        assert_eq!(file.seek(SeekFrom::Start(offset)).unwrap(), offset);
+        let len = file.read(&mut buf).unwrap();
+        assert_eq!(len, 10.min(BIG_FILE_SIZE - offset));
+        assert!(buf[..len]
+            .iter()
+            .enumerate()
+            .all(|(i, b)| *b == pattern(0, offset + i)));
+    }
+}
+
+#[test]
+fn test_lookup_errors() {
+    let fs = open_image();
+
+    assert_eq!(fs.exists("/NOPE.TXT"), Ok(false));
+    assert!(fs.exists("/SUB/INNER.TXT").unwrap());
+    assert_eq!(fs.open_file("/SUB").err(), Some(FsError::NotAFile));
+    assert_eq!(fs.read_dir("/BIG.DAT").err(), Some(FsError::NotADirectory));
+    assert_eq!(
+        fs.open_file("/BIG.DAT/X").err(),
+        Some(FsError::NotADirectory)
+    );
+}
+
+#[test]
+fn test_detect_fat_type() {
+    let fat32 = MemoryDevice::new(build_image());
+    assert_eq!(FatType::detect(&fat32), Ok(Some(FatType::Fat32)));
+
+    let fat16 = MemoryDevice::new(crate::fat16::tests::build_image());
+    assert_eq!(FatType::detect(&fat16), Ok(Some(FatType::Fat16)));
+
+    let empty = MemoryDevice::new(vec![0u8; 4 * BLOCK_SIZE]);
+    assert_eq!(FatType::detect(&empty), Ok(None));
+
+    assert_eq!(FatType::from_partition_type(0x0C), Some(FatType::Fat32));
+    assert_eq!(FatType::from_partition_type(0x06), Some(FatType::Fat16));
+    assert_eq!(FatType::from_partition_type(0x83), None);
+}
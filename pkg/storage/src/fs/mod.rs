@@ -1 +1,65 @@
+pub mod ext2;
 pub mod fat16;
+pub mod fat32;
+pub mod tmpfs;
+
+use crate::*;
+use fat16::bpb::Fat16Bpb;
+use fat32::bpb::Fat32Bpb;
+
+/// The variants of the FAT file system
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatType {
+    Fat16,
+    Fat32,
+}
+
+impl FatType {
+    /// The variant implied by an MBR partition type
+    pub fn from_partition_type(kind: u8) -> Option<Self> {
+        match kind {
+            0x04 | 0x06 | 0x0E => Some(Self::Fat16),
+            0x0B | 0x0C => Some(Self::Fat32),
+            _ => None,
+        }
+    }
+
+    /// Detect the variant from the BPB in the first sector of the volume
+    ///
+    /// Fat32 is recognized by its layout fields like Linux does, since small
+    /// Fat32 volumes can have fewer clusters than the specification requires.
+    /// Volumes with too few clusters for Fat16 are Fat12, which is unsupported.
+    pub fn detect(device: &impl BlockDevice<Block512>) -> Result<Option<Self>> {
+        let mut block = Block512::default();
+        device.read_block(0, &mut block)?;
+
+        if Fat32Bpb::new(block.as_ref()).is_ok() {
+            return Ok(Some(Self::Fat32));
+        }
+
+        let bpb = match Fat16Bpb::new(block.as_ref()) {
+            Ok(bpb) => bpb,
+            Err(_) => return Ok(None),
+        };
+
+        let root_dir_sectors = (bpb.root_entries_count() as usize * 32).div_ceil(512);
+        let data_sectors = (bpb.total_sectors() as usize).saturating_sub(
+            bpb.reserved_sector_count() as usize
+                + bpb.fat_count() as usize * bpb.sectors_per_fat() as usize
+                + root_dir_sectors,
+        );
+
+        if data_sectors / (bpb.sectors_per_cluster() as usize) < 4085 {
+            return Ok(None);
+        }
+
+        Ok(Some(Self::Fat16))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Fat16 => "fat16",
+            Self::Fat32 => "fat32",
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! File
+//!
+//! An open tmpfs file, a cursor over the shared file data.
+
+use core::cmp::min;
+
+use super::*;
+
+#[derive(Debug, Clone)]
+pub struct TmpFile {
+    /// The current offset in the file
+    offset: usize,
+    /// The content, shared with the directory tree
+    data: SharedData,
+}
+
+impl TmpFile {
+    pub fn new(data: SharedData, offset: usize) -> Self {
+        Self { offset, data }
+    }
+
+    pub fn length(&self) -> usize {
+        self.data.read().len()
+    }
+}
+
+impl Read for TmpFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let data = self.data.read();
+        let start = min(self.offset, data.len());
+        let len = min(buf.len(), data.len() - start);
+
+        buf[..len].copy_from_slice(&data.bytes[start..start + len]);
+        self.offset = start + len;
+        Ok(len)
+    }
+}
+
+impl Write for TmpFile {
+    /// Write at the offset, growing the file if needed
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let mut data = self.data.write();
+        let end = self
+            .offset
+            .checked_add(buf.len())
+            .ok_or(FsError::InvalidOffset)?;
+
+        if end > data.len() {
+            data.resize(end)?;
+        }
+
+        data.bytes[self.offset..end].copy_from_slice(buf);
+        self.offset = end;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for TmpFile {
+    fn seek(&mut self, pos: SeekFrom) -> Result<usize> {
+        let offset = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.length().checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.offset.checked_add_signed(delta),
+        }
+        .filter(|offset| *offset <= self.length())
+        .ok_or(FsError::InvalidOffset)?;
+
+        self.offset = offset;
+        Ok(offset)
+    }
+}
@@ -0,0 +1,390 @@
+pub mod file;
+
+#[cfg(test)]
+mod tests;
+
+use crate::*;
+use alloc::collections::BTreeMap;
+use file::*;
+use spin::{Mutex, RwLock};
+
+/// The bytes used by the files of a tmpfs, and how many are allowed
+#[derive(Debug)]
+struct Usage {
+    used: Mutex<usize>,
+    capacity: usize,
+}
+
+impl Usage {
+    /// Take `len` bytes from the capacity
+    fn reserve(&self, len: usize) -> Result<()> {
+        let mut used = self.used.lock();
+        match used.checked_add(len) {
+            Some(total) if total <= self.capacity => {
+                *used = total;
+                Ok(())
+            }
+            _ => Err(FsError::WriteZero),
+        }
+    }
+
+    fn release(&self, len: usize) {
+        *self.used.lock() -= len;
+    }
+}
+
+/// The content of a file, shared by the directory tree and the open handles
+///
+/// The bytes count against the capacity until the last reference is dropped,
+/// so a removed file keeps its space while it is still open.
+#[derive(Debug)]
+pub struct FileData {
+    bytes: Vec<u8>,
+    usage: Arc<Usage>,
+}
+
+impl FileData {
+    fn new(usage: Arc<Usage>) -> Self {
+        Self {
+            bytes: Vec::new(),
+            usage,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Grow with zeros or truncate, failing if the file system is full
+    /// or the heap can not hold the new length
+    fn resize(&mut self, len: usize) -> Result<()> {
+        let current = self.bytes.len();
+        if len > current {
+            self.usage.reserve(len - current)?;
+            if self.bytes.try_reserve(len - current).is_err() {
+                self.usage.release(len - current);
+                return Err(FsError::WriteZero);
+            }
+        } else {
+            self.usage.release(current - len);
+        }
+
+        self.bytes.resize(len, 0);
+        Ok(())
+    }
+}
+
+impl Drop for FileData {
+    fn drop(&mut self) {
+        self.usage.release(self.bytes.len());
+    }
+}
+
+type SharedData = Arc<RwLock<FileData>>;
+type Directory = BTreeMap<String, Node>;
+
+#[derive(Debug)]
+enum Node {
+    File(SharedData),
+    Directory(Directory),
+}
+
+impl Node {
+    fn metadata(&self, name: &str) -> Metadata {
+        let (entry_type, len) = match self {
+            Node::File(data) => (FileType::File, data.read().len()),
+            Node::Directory(_) => (FileType::Directory, 0),
+        };
+        Metadata::new(name.into(), entry_type, len, None, None, None)
+    }
+}
+
+/// Split a path into its components
+fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split(PATH_SEPARATOR).filter(|part| !part.is_empty())
+}
+
+/// Split a path into the components of its parent and its name
+fn split_parent(path: &str) -> Result<(Vec<&str>, &str)> {
+    let mut parts: Vec<&str> = components(path).collect();
+    match parts.pop() {
+        Some(name) if name != "." && name != ".." => Ok((parts, name)),
+        _ => Err(FsError::InvalidPath(path.into())),
+    }
+}
+
+fn lookup_dir<'a, 'p>(
+    mut dir: &'a Directory,
+    parts: impl IntoIterator<Item = &'p str>,
+) -> Result<&'a Directory> {
+    for part in parts {
+        dir = match dir.get(part) {
+            Some(Node::Directory(child)) => child,
+            Some(Node::File(_)) => return Err(FsError::NotADirectory),
+            None => return Err(FsError::FileNotFound),
+        };
+    }
+    Ok(dir)
+}
+
+fn lookup_dir_mut<'a, 'p>(
+    mut dir: &'a mut Directory,
+    parts: impl IntoIterator<Item = &'p str>,
+) -> Result<&'a mut Directory> {
+    for part in parts {
+        dir = match dir.get_mut(part) {
+            Some(Node::Directory(child)) => child,
+            Some(Node::File(_)) => return Err(FsError::NotADirectory),
+            None => return Err(FsError::FileNotFound),
+        };
+    }
+    Ok(dir)
+}
+
+/// An in-memory file system, without a block device below it
+///
+/// Files and directories live on the heap and are lost on unmount.
+/// The file contents are limited to `capacity` bytes in total.
+pub struct TmpFs {
+    root: RwLock<Directory>,
+    usage: Arc<Usage>,
+}
+
+impl TmpFs {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            root: RwLock::new(BTreeMap::new()),
+            usage: Arc::new(Usage {
+                used: Mutex::new(0),
+                capacity,
+            }),
+        }
+    }
+
+    /// The bytes used by file contents
+    pub fn used(&self) -> usize {
+        *self.usage.used.lock()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.usage.capacity
+    }
+
+    /// Run `f` on the node at the path, the root is an empty name
+    fn with_node<R>(&self, path: &str, f: impl FnOnce(&str, &Node) -> R) -> Result<R> {
+        let root = self.root.read();
+        match split_parent(path) {
+            Ok((parent, name)) => {
+                let node = lookup_dir(&root, parent)?
+                    .get(name)
+                    .ok_or(FsError::FileNotFound)?;
+                Ok(f(name, node))
+            }
+            // the root has no name, and can not be borrowed as a node
+            Err(_) if components(path).next().is_none() => {
+                Ok(f("/", &Node::Directory(Directory::new())))
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    fn handle(meta: Metadata, data: SharedData, offset: usize) -> FileHandle {
+        FileHandle::new(meta, Box::new(TmpFile::new(data, offset)))
+    }
+
+    /// Open the file at the path, creating it if needed
+    fn open_or_create(&self, path: &str) -> Result<(String, SharedData)> {
+        let (parent, name) = split_parent(path)?;
+        let mut root = self.root.write();
+        let dir = lookup_dir_mut(&mut root, parent)?;
+
+        match dir.get(name) {
+            Some(Node::File(data)) => Ok((name.into(), data.clone())),
+            Some(Node::Directory(_)) => Err(FsError::NotAFile),
+            None => {
+                let data = Arc::new(RwLock::new(FileData::new(self.usage.clone())));
+                dir.insert(name.into(), Node::File(data.clone()));
+                Ok((name.into(), data))
+            }
+        }
+    }
+
+    /// Detach the node at the path from its parent
+    fn take(&self, path: &str, dir: bool) -> Result<(String, Node)> {
+        let (parent, name) = split_parent(path)?;
+        let mut root = self.root.write();
+        let parent = lookup_dir_mut(&mut root, parent)?;
+
+        match parent.get(name) {
+            None => return Err(FsError::FileNotFound),
+            Some(Node::File(_)) if dir => return Err(FsError::NotADirectory),
+            Some(Node::Directory(_)) if !dir => return Err(FsError::NotAFile),
+            Some(Node::Directory(children)) if !children.is_empty() => {
+                return Err(FsError::InvalidOperation)
+            }
+            Some(_) => {}
+        }
+
+        Ok((name.into(), parent.remove(name).unwrap()))
+    }
+
+    /// Move the node at `src` to `dst`, which must not exist
+    fn rename(&self, src: &str, dst: &str, dir: bool) -> Result<()> {
+        let (src_parent, src_name) = split_parent(src)?;
+        let (dst_parent, dst_name) = split_parent(dst)?;
+
+        // a directory can not be moved into itself
+        let src_parts: Vec<&str> = components(src).collect();
+        if dir
+            && components(dst)
+                .take(src_parts.len())
+                .eq(src_parts.iter().copied())
+        {
+            return Err(FsError::InvalidOperation);
+        }
+
+        let mut root = self.root.write();
+        match lookup_dir(&root, src_parent.iter().copied())?.get(src_name) {
+            None => return Err(FsError::FileNotFound),
+            Some(Node::File(_)) if dir => return Err(FsError::NotADirectory),
+            Some(Node::Directory(_)) if !dir => return Err(FsError::NotAFile),
+            Some(_) => {}
+        }
+        if lookup_dir(&root, dst_parent.iter().copied())?.contains_key(dst_name) {
+            return Err(FsError::AlreadyExists);
+        }
+
+        let node = lookup_dir_mut(&mut root, src_parent)?
+            .remove(src_name)
+            .unwrap();
+        lookup_dir_mut(&mut root, dst_parent)?.insert(dst_name.into(), node);
+        Ok(())
+    }
+}
+
+impl core::fmt::Debug for TmpFs {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TmpFs")
+            .field("used", &self.used())
+            .field("capacity", &self.capacity())
+            .finish()
+    }
+}
+
+impl FileSystem for TmpFs {
+    fn read_dir(&self, path: &str) -> Result<Box<dyn Iterator<Item = Metadata> + Send>> {
+        let root = self.root.read();
+        let entries: Vec<Metadata> = lookup_dir(&root, components(path))?
+            .iter()
+            .map(|(name, node)| node.metadata(name))
+            .collect();
+
+        Ok(Box::new(entries.into_iter()))
+    }
+
+    fn open_file(&self, path: &str) -> Result<FileHandle> {
+        self.with_node(path, |name, node| match node {
+            Node::File(data) => Ok(Self::handle(node.metadata(name), data.clone(), 0)),
+            Node::Directory(_) => Err(FsError::NotAFile),
+        })?
+    }
+
+    fn metadata(&self, path: &str) -> Result<Metadata> {
+        self.with_node(path, |name, node| node.metadata(name))
+    }
+
+    fn exists(&self, path: &str) -> Result<bool> {
+        match self.with_node(path, |_, _| ()) {
+            Ok(()) => Ok(true),
+            Err(FsError::FileNotFound) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates the file, or truncates it if it exists
+    fn create_file(&self, path: &str) -> Result<FileHandle> {
+        let (name, data) = self.open_or_create(path)?;
+        data.write().resize(0)?;
+
+        let meta = Metadata::new(name, FileType::File, 0, None, None, None);
+        Ok(Self::handle(meta, data, 0))
+    }
+
+    /// Opens the file positioned at its end, creating it if needed
+    fn append_file(&self, path: &str) -> Result<FileHandle> {
+        let (name, data) = self.open_or_create(path)?;
+        let len = data.read().len();
+
+        let meta = Metadata::new(name, FileType::File, len, None, None, None);
+        Ok(Self::handle(meta, data, len))
+    }
+
+    /// Removes the file, the returned handle keeps its content alive
+    fn remove_file(&self, path: &str) -> Result<FileHandle> {
+        let (name, node) = self.take(path, false)?;
+        let meta = node.metadata(&name);
+        match node {
+            Node::File(data) => Ok(Self::handle(meta, data, 0)),
+            Node::Directory(_) => unreachable!(),
+        }
+    }
+
+    /// Removes the empty directory, the returned handle has no content
+    fn remove_dir(&self, path: &str) -> Result<FileHandle> {
+        let (name, node) = self.take(path, true)?;
+        let data = Arc::new(RwLock::new(FileData::new(self.usage.clone())));
+        Ok(Self::handle(node.metadata(&name), data, 0))
+    }
+
+    fn create_dir(&self, path: &str) -> Result<()> {
+        let (parent, name) = split_parent(path)?;
+        let mut root = self.root.write();
+        let dir = lookup_dir_mut(&mut root, parent)?;
+
+        if dir.contains_key(name) {
+            return Err(FsError::AlreadyExists);
+        }
+
+        dir.insert(name.into(), Node::Directory(Directory::new()));
+        Ok(())
+    }
+
+    fn copy_file(&self, src: &str, dst: &str) -> Result<()> {
+        let src = self.with_node(src, |_, node| match node {
+            Node::File(data) => Ok(data.clone()),
+            Node::Directory(_) => Err(FsError::NotAFile),
+        })??;
+
+        let (_, dst) = self.open_or_create(dst)?;
+        if Arc::ptr_eq(&src, &dst) {
+            return Ok(());
+        }
+
+        // copied out first, the locks of both files are never held together
+        let bytes = {
+            let src = src.read();
+            let mut bytes = Vec::new();
+            bytes
+                .try_reserve_exact(src.len())
+                .map_err(|_| FsError::WriteZero)?;
+            bytes.extend_from_slice(&src.bytes);
+            bytes
+        };
+        let mut dst = dst.write();
+        dst.resize(bytes.len())?;
+        dst.bytes.copy_from_slice(&bytes);
+        Ok(())
+    }
+
+    fn move_file(&self, src: &str, dst: &str) -> Result<()> {
+        self.rename(src, dst, false)
+    }
+
+    fn move_dir(&self, src: &str, dst: &str) -> Result<()> {
+        self.rename(src, dst, true)
+    }
+}
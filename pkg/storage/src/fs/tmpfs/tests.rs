@@ -0,0 +1,216 @@
+//! Tests for the in-memory file system
+
+use super::*;
+
+const CAPACITY: usize = 4096;
+
+fn names(fs: &impl FileSystem, path: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs.read_dir(path).unwrap().map(|meta| meta.name).collect();
+    names.sort();
+    names
+}
+
+fn read_all(fs: &impl FileSystem, path: &str) -> Vec<u8> {
+    let mut file = fs.open_file(path).unwrap();
+    let mut buf = vec![0u8; CAPACITY * 2];
+    let len = file.read(&mut buf).unwrap();
+    buf.truncate(len);
+    buf
+}
+
+fn write_file(fs: &impl FileSystem, path: &str, content: &[u8]) {
+    let mut file = fs.create_file(path).unwrap();
+    assert_eq!(file.write(content).unwrap(), content.len());
+}
+
+#[test]
+fn test_create_and_read() {
+    let fs = TmpFs::new(CAPACITY);
+
+    fs.create_dir("/dir").unwrap();
+    fs.create_dir("/dir/sub").unwrap();
+    write_file(&fs, "/hello.txt", b"hello, tmpfs!");
+    write_file(&fs, "/dir/sub/deep.txt", b"deep");
+
+    assert_eq!(names(&fs, "/"), ["dir", "hello.txt"]);
+    assert_eq!(names(&fs, "/dir"), ["sub"]);
+    assert_eq!(read_all(&fs, "/hello.txt"), b"hello, tmpfs!");
+    assert_eq!(read_all(&fs, "/dir/sub/deep.txt"), b"deep");
+
+    let meta = fs.metadata("/hello.txt").unwrap();
+    assert!(meta.is_file());
+    assert_eq!(meta.len, 13);
+    assert!(fs.metadata("/dir/sub").unwrap().is_dir());
+    assert!(fs.metadata("/").unwrap().is_dir());
+
+    assert_eq!(fs.exists("/dir/sub/deep.txt"), Ok(true));
+    assert_eq!(fs.exists("/dir/missing"), Ok(false));
+    assert_eq!(fs.used(), 17);
+}
+
+#[test]
+fn test_path_errors() {
+    let fs = TmpFs::new(CAPACITY);
+    write_file(&fs, "/file", b"x");
+    fs.create_dir("/dir").unwrap();
+
+    assert_eq!(fs.create_dir("/dir").err(), Some(FsError::AlreadyExists));
+    assert_eq!(
+        fs.create_dir("/missing/dir").err(),
+        Some(FsError::FileNotFound)
+    );
+    assert_eq!(
+        fs.create_dir("/file/dir").err(),
+        Some(FsError::NotADirectory)
+    );
+    assert_eq!(fs.open_file("/dir").err(), Some(FsError::NotAFile));
+    assert_eq!(fs.create_file("/dir").err(), Some(FsError::NotAFile));
+    assert_eq!(fs.read_dir("/file").err(), Some(FsError::NotADirectory));
+    assert!(matches!(fs.create_dir("/"), Err(FsError::InvalidPath(_))));
+}
+
+#[test]
+fn test_write_and_seek() {
+    let fs = TmpFs::new(CAPACITY);
+    let mut file = fs.create_file("/file").unwrap();
+
+    file.write(b"0123456789").unwrap();
+    assert_eq!(file.seek(SeekFrom::Start(4)).unwrap(), 4);
+    file.write(b"ab").unwrap();
+    assert_eq!(file.seek(SeekFrom::End(-1)).unwrap(), 9);
+    file.write(b"XYZ").unwrap();
+    assert_eq!(file.seek(SeekFrom::End(1)), Err(FsError::InvalidOffset));
+
+    assert_eq!(read_all(&fs, "/file"), b"0123ab678XYZ");
+
+    // appending keeps the content, creating truncates it
+    let mut file = fs.append_file("/file").unwrap();
+    file.write(b"!").unwrap();
+    assert_eq!(read_all(&fs, "/file"), b"0123ab678XYZ!");
+
+    fs.create_file("/file").unwrap();
+    assert_eq!(read_all(&fs, "/file"), b"");
+    assert_eq!(fs.used(), 0);
+}
+
+#[test]
+fn test_capacity() {
+    let fs = TmpFs::new(CAPACITY);
+    write_file(&fs, "/a", &[1u8; CAPACITY - 10]);
+
+    let mut file = fs.create_file("/b").unwrap();
+    assert_eq!(file.write(&[2u8; 20]), Err(FsError::WriteZero));
+    assert_eq!(file.write(&[2u8; 10]), Ok(10));
+    assert_eq!(fs.used(), CAPACITY);
+
+    // an open removed file keeps its space until it is closed
+    let removed = fs.remove_file("/a").unwrap();
+    assert_eq!(removed.meta.len, CAPACITY - 10);
+    assert_eq!(fs.used(), CAPACITY);
+    drop(removed);
+    assert_eq!(fs.used(), 10);
+    assert_eq!(fs.exists("/a"), Ok(false));
+}
+
+#[test]
+fn test_remove_and_move() {
+    let fs = TmpFs::new(CAPACITY);
+    fs.create_dir("/dir").unwrap();
+    fs.create_dir("/dir/sub").unwrap();
+    write_file(&fs, "/dir/sub/file", b"content");
+
+    // a directory must be empty to be removed
+    assert_eq!(fs.remove_dir("/dir").err(), Some(FsError::InvalidOperation));
+    assert_eq!(
+        fs.move_dir("/dir", "/dir/sub/dir").err(),
+        Some(FsError::InvalidOperation)
+    );
+
+    fs.move_dir("/dir/sub", "/sub").unwrap();
+    fs.move_file("/sub/file", "/dir/moved").unwrap();
+    fs.copy_file("/dir/moved", "/copy").unwrap();
+    assert_eq!(
+        fs.move_file("/copy", "/dir/moved").err(),
+        Some(FsError::AlreadyExists)
+    );
+
+    assert_eq!(names(&fs, "/"), ["copy", "dir", "sub"]);
+    assert_eq!(read_all(&fs, "/dir/moved"), b"content");
+    assert_eq!(read_all(&fs, "/copy"), b"content");
+
+    fs.remove_dir("/sub").unwrap();
+    fs.remove_file("/dir/moved").unwrap();
+    fs.remove_dir("/dir").unwrap();
+    assert_eq!(names(&fs, "/"), ["copy"]);
+    assert_eq!(fs.used(), 7);
+}
+
+#[test]
+fn test_mounted() {
+    let table = MountTable::new();
+    table
+        .mount(Mount::new(Box::new(TmpFs::new(CAPACITY)), "/tmp".into()))
+        .unwrap();
+
+    table.create_dir("/tmp/dir").unwrap();
+    write_file(&table, "/tmp/dir/file", b"mounted");
+
+    assert_eq!(names(&table, "/tmp"), ["dir"]);
+    assert_eq!(read_all(&table, "/tmp/dir/file"), b"mounted");
+    assert_eq!(table.create_dir("/tmp").err(), Some(FsError::AlreadyExists));
+}
+
+#[test]
+fn test_mounted_read_only() {
+    let fs = TmpFs::new(CAPACITY);
+    write_file(&fs, "/file", b"kept");
+
+    let table = MountTable::new();
+    let mount = Mount::new(Box::new(fs), "/ro".into()).read_only();
+    table.mount(mount).unwrap();
+
+    let read_only = Some(FsError::ReadOnly);
+    assert_eq!(table.create_file("/ro/file").err(), read_only);
+    assert_eq!(table.append_file("/ro/new").err(), read_only);
+    assert_eq!(table.create_dir("/ro/dir").err(), read_only);
+    assert_eq!(table.remove_file("/ro/file").err(), read_only);
+    assert_eq!(
+        table
+            .set_attributes("/ro/file", FileAttributes::empty())
+            .err(),
+        read_only
+    );
+
+    assert_eq!(read_all(&table, "/ro/file"), b"kept");
+    assert_eq!(
+        table.mounts()[0].to_string(),
+        "none on /ro type unknown (ro)"
+    );
+}
+
+#[test]
+fn test_protected_directory() {
+    let fs = TmpFs::new(CAPACITY);
+    fs.create_dir("/apps").unwrap();
+    fs.create_dir("/apps/sub").unwrap();
+    write_file(&fs, "/apps/sub/file", b"kept");
+
+    let table = MountTable::new();
+    table
+        .mount(Mount::new(Box::new(fs), "/".into()).protect("/apps"))
+        .unwrap();
+
+    let read_only = Some(FsError::ReadOnly);
+    assert_eq!(table.create_file("/apps/new").err(), read_only);
+    assert_eq!(table.create_file("/APPS/SUB/new").err(), read_only);
+    assert_eq!(table.remove_file("/apps/sub/file").err(), read_only);
+    assert_eq!(table.create_dir("/apps/sub/dir").err(), read_only);
+    assert_eq!(
+        table.set_attributes("/apps", FileAttributes::empty()).err(),
+        read_only
+    );
+
+    // the rest of the file system and reading are not affected
+    assert_eq!(read_all(&table, "/apps/sub/file"), b"kept");
+    table.create_dir("/application").unwrap();
+}
@@ -1,4 +1,4 @@
-#![cfg_attr(not(test), no_std)]
+#![cfg_attr(not(any(test, feature = "std")), no_std)]
 #![allow(dead_code, unused_imports)]
 #![cfg_attr(test, feature(concat_bytes))]
 #![feature(trait_alias)]
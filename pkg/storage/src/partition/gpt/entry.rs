@@ -0,0 +1,182 @@
+//! GPT Partition Entry
+//!
+//! reference: <https://en.wikipedia.org/wiki/GUID_Partition_Table#Partition_entries_(LBA_2%E2%80%9333)>
+
+use super::*;
+
+/// Represents a GPT partition entry.
+///
+/// Only the first 128 bytes are used, larger entries are allowed
+/// by the specification but hold no defined fields.
+#[derive(Clone)]
+pub struct GptEntry {
+    data: [u8; 128],
+}
+
+impl GptEntry {
+    pub const LEN: usize = 128;
+
+    pub fn parse(data: &[u8]) -> GptEntry {
+        GptEntry {
+            data: data[..Self::LEN].try_into().unwrap(),
+        }
+    }
+
+    /// Entries with a zero type GUID are unused
+    pub fn is_used(&self) -> bool {
+        !self.type_guid().is_nil()
+    }
+
+    pub fn type_guid(&self) -> Guid {
+        Guid::new(self.type_guid_raw())
+    }
+
+    pub fn unique_guid(&self) -> Guid {
+        Guid::new(self.unique_guid_raw())
+    }
+
+    /// Number of blocks, the last LBA is inclusive
+    pub fn total_lba(&self) -> u64 {
+        (self.last_lba() + 1).saturating_sub(self.first_lba())
+    }
+
+    /// The partition name, stored as UTF-16LE and padded with zeros
+    pub fn name(&self) -> String {
+        let units = self
+            .name_raw()
+            .chunks(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]));
+        char::decode_utf16(units.take_while(|c| *c != 0))
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    }
+
+    define_field!([u8; 16], 0x00, type_guid_raw);
+    define_field!([u8; 16], 0x10, unique_guid_raw);
+    define_field!(u64, 0x20, first_lba);
+    define_field!(u64, 0x28, last_lba);
+    define_field!(u64, 0x30, attributes);
+    define_field!([u8; 72], 0x38, name_raw);
+}
+
+impl core::fmt::Debug for GptEntry {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("GPT Partition Entry")
+            .field("Type GUID", &self.type_guid())
+            .field("Unique GUID", &self.unique_guid())
+            .field("First LBA", &self.first_lba())
+            .field("Last LBA", &self.last_lba())
+            .field("Attributes", &format!("{:#x}", self.attributes()))
+            .field("Name", &self.name())
+            .finish()
+    }
+}
+
+/// A globally unique identifier
+///
+/// The first three groups are stored little-endian,
+/// the last two as plain bytes.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Guid([u8; 16]);
+
+impl Guid {
+    pub const NIL: Guid = Guid([0; 16]);
+
+    /// EFI System Partition
+    pub const EFI_SYSTEM: Guid = Guid::from_fields(
+        0xC12A7328,
+        0xF81F,
+        0x11D2,
+        [0xBA, 0x4B, 0x00, 0xA0, 0xC9, 0x3E, 0xC9, 0x3B],
+    );
+    /// Microsoft Basic Data, used for FAT volumes
+    pub const BASIC_DATA: Guid = Guid::from_fields(
+        0xEBD0A0A2,
+        0xB9E5,
+        0x4433,
+        [0x87, 0xC0, 0x68, 0xB6, 0xB7, 0x26, 0x99, 0xC7],
+    );
+    /// Linux Filesystem Data
+    pub const LINUX_FILESYSTEM: Guid = Guid::from_fields(
+        0x0FC63DAF,
+        0x8483,
+        0x4772,
+        [0x8E, 0x79, 0x3D, 0x69, 0xD8, 0x47, 0x7D, 0xE4],
+    );
+
+    /// Create a GUID from its on-disk representation
+    pub const fn new(bytes: &[u8; 16]) -> Self {
+        Guid(*bytes)
+    }
+
+    /// Create a GUID from the groups of its textual form
+    pub const fn from_fields(a: u32, b: u16, c: u16, d: [u8; 8]) -> Self {
+        let a = a.to_le_bytes();
+        let b = b.to_le_bytes();
+        let c = c.to_le_bytes();
+        Guid([
+            a[0], a[1], a[2], a[3], b[0], b[1], c[0], c[1], d[0], d[1], d[2], d[3], d[4], d[5],
+            d[6], d[7],
+        ])
+    }
+
+    pub fn is_nil(&self) -> bool {
+        *self == Self::NIL
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+}
+
+impl core::fmt::Display for Guid {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let b = &self.0;
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-",
+            u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            u16::from_le_bytes([b[4], b[5]]),
+            u16::from_le_bytes([b[6], b[7]]),
+            b[8],
+            b[9]
+        )?;
+        b[10..]
+            .iter()
+            .try_for_each(|byte| write!(f, "{:02X}", byte))
+    }
+}
+
+impl core::fmt::Debug for Guid {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_guid() {
+        // the ESP type GUID as stored on disk
+        let data = hex_literal::hex!("28 73 2a c1 1f f8 d2 11 ba 4b 00 a0 c9 3e c9 3b");
+        let guid = Guid::new(&data);
+
+        assert_eq!(guid, Guid::EFI_SYSTEM);
+        assert_eq!(guid.to_string(), "C12A7328-F81F-11D2-BA4B-00A0C93EC93B");
+        assert!(Guid::NIL.is_nil());
+    }
+
+    #[test]
+    fn test_entry_name() {
+        let mut data = [0u8; 128];
+        for (i, c) in "EFI système".encode_utf16().enumerate() {
+            data[0x38 + i * 2..0x38 + i * 2 + 2].copy_from_slice(&c.to_le_bytes());
+        }
+
+        let entry = GptEntry::parse(&data);
+        assert_eq!(entry.name(), "EFI système");
+        assert!(!entry.is_used());
+    }
+}
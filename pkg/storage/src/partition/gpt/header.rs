@@ -0,0 +1,102 @@
+//! GPT Header
+//!
+//! reference: <https://en.wikipedia.org/wiki/GUID_Partition_Table#Partition_table_header_(LBA_1)>
+
+use super::*;
+
+/// Represents the GPT header.
+///
+/// The primary header is in LBA 1, the backup header in the last LBA.
+#[derive(Clone)]
+pub struct GptHeader {
+    data: [u8; 92],
+}
+
+impl GptHeader {
+    pub const SIGNATURE: &'static [u8; 8] = b"EFI PART";
+    /// The size of the header covered by the CRC in revision 1.0
+    pub const LEN: usize = 92;
+    /// The most entries accepted, the number every tool creates
+    pub const MAX_ENTRY_COUNT: u32 = 128;
+    /// The largest entry accepted, so that the array stays small
+    pub const MAX_ENTRY_SIZE: u32 = 1024;
+
+    /// Attempt to parse the header from the start of a block,
+    /// verifying its signature and CRC32.
+    pub fn new(data: &[u8]) -> core::result::Result<GptHeader, &'static str> {
+        let header = GptHeader {
+            data: data
+                .get(..Self::LEN)
+                .and_then(|data| data.try_into().ok())
+                .ok_or("Bad GPT header size")?,
+        };
+
+        if header.signature() != Self::SIGNATURE {
+            return Err("Bad GPT header signature");
+        }
+
+        let size = header.header_size() as usize;
+        if size < Self::LEN || size > data.len() {
+            return Err("Unsupported GPT header size");
+        }
+
+        // the CRC is computed with its own field zeroed
+        let mut covered = data[..size].to_vec();
+        covered[0x10..0x14].fill(0);
+        if crc::crc32(&covered) != header.header_crc32() {
+            return Err("Bad GPT header CRC32");
+        }
+
+        // read from the disk, it must not make the array huge
+        let entry_size = header.entry_size();
+        if !entry_size.is_power_of_two()
+            || !(GptEntry::LEN as u32..=Self::MAX_ENTRY_SIZE).contains(&entry_size)
+        {
+            return Err("Unsupported GPT entry size");
+        }
+        if header.entry_count() > Self::MAX_ENTRY_COUNT {
+            return Err("Too many GPT entries");
+        }
+
+        Ok(header)
+    }
+
+    /// The size of the entry array in bytes
+    pub fn entries_len(&self) -> usize {
+        self.entry_count() as usize * self.entry_size() as usize
+    }
+
+    pub fn disk_guid(&self) -> Guid {
+        Guid::new(self.disk_guid_raw())
+    }
+
+    define_field!([u8; 8], 0x00, signature);
+    define_field!(u32, 0x08, revision);
+    define_field!(u32, 0x0C, header_size);
+    define_field!(u32, 0x10, header_crc32);
+    define_field!(u64, 0x18, current_lba);
+    define_field!(u64, 0x20, backup_lba);
+    define_field!(u64, 0x28, first_usable_lba);
+    define_field!(u64, 0x30, last_usable_lba);
+    define_field!([u8; 16], 0x38, disk_guid_raw);
+    define_field!(u64, 0x48, entries_lba);
+    define_field!(u32, 0x50, entry_count);
+    define_field!(u32, 0x54, entry_size);
+    define_field!(u32, 0x58, entries_crc32);
+}
+
+impl core::fmt::Debug for GptHeader {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("GPT Header")
+            .field("Revision", &format!("{:#x}", self.revision()))
+            .field("Current LBA", &self.current_lba())
+            .field("Backup LBA", &self.backup_lba())
+            .field("First Usable LBA", &self.first_usable_lba())
+            .field("Last Usable LBA", &self.last_usable_lba())
+            .field("Disk GUID", &self.disk_guid())
+            .field("Entries LBA", &self.entries_lba())
+            .field("Entry Count", &self.entry_count())
+            .field("Entry Size", &self.entry_size())
+            .finish()
+    }
+}
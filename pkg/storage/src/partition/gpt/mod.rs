@@ -0,0 +1,162 @@
+//! GptTable
+
+mod crc;
+mod entry;
+mod header;
+
+#[cfg(test)]
+mod tests;
+
+use core::marker::PhantomData;
+
+use crate::*;
+pub use entry::*;
+pub use header::*;
+
+/// The partition type marking the protective MBR entry
+const PROTECTIVE_MBR_TYPE: u8 = 0xEE;
+
+/// The GPT Table
+///
+/// GPT (GUID Partition Table) keeps a protective MBR in the first sector,
+/// so that legacy tools see the disk as fully used. The header in LBA 1
+/// describes an array of partition entries, and a backup copy of both
+/// is kept at the end of the disk.
+///
+/// [ Protective MBR ] [ Header ] [ Entries ] [ Partitions ] [ Entries ] [ Header ]
+pub struct GptTable<T, B>
+where
+    T: BlockDevice<B> + Clone,
+    B: BlockTrait,
+{
+    inner: T,
+    header: GptHeader,
+    entries: Vec<GptEntry>,
+    _block: PhantomData<B>,
+}
+
+impl<T, B> GptTable<T, B>
+where
+    T: BlockDevice<B> + Clone,
+    B: BlockTrait,
+{
+    /// Check for a protective MBR in the first block
+    pub fn detect(inner: &T) -> Result<bool> {
+        let mut block = B::default();
+        inner.read_block(0, &mut block)?;
+        let buffer = block.as_ref();
+
+        if buffer[0x1FE..0x200] != [0x55, 0xAA] {
+            return Ok(false);
+        }
+
+        Ok((0..4).any(|i| buffer[0x1BE + i * 16 + 4] == PROTECTIVE_MBR_TYPE))
+    }
+
+    pub fn header(&self) -> &GptHeader {
+        &self.header
+    }
+
+    /// The used partition entries
+    pub fn entries(&self) -> impl Iterator<Item = &GptEntry> {
+        self.entries.iter().filter(|entry| entry.is_used())
+    }
+
+    /// Load and verify the header at `lba` and its entry array
+    fn load(
+        inner: &T,
+        lba: usize,
+    ) -> core::result::Result<(GptHeader, Vec<GptEntry>), &'static str> {
+        let mut block = B::default();
+        inner
+            .read_block(lba, &mut block)
+            .map_err(|_| "Failed to read the GPT header")?;
+
+        let header = GptHeader::new(block.as_ref())?;
+        if header.current_lba() != lba as u64 {
+            return Err("Bad GPT header location");
+        }
+
+        let entry_size = header.entry_size() as usize;
+        let mut array = vec![0u8; header.entries_len()];
+        for (index, chunk) in array.chunks_mut(B::size()).enumerate() {
+            inner
+                .read_block(header.entries_lba() as usize + index, &mut block)
+                .map_err(|_| "Failed to read the GPT entries")?;
+            chunk.copy_from_slice(&block.as_ref()[..chunk.len()]);
+        }
+
+        if crc::crc32(&array) != header.entries_crc32() {
+            return Err("Bad GPT entries CRC32");
+        }
+
+        let entries = array.chunks(entry_size).map(GptEntry::parse).collect();
+        Ok((header, entries))
+    }
+}
+
+impl<T, B> PartitionTable<T, B> for GptTable<T, B>
+where
+    T: BlockDevice<B> + Clone,
+    B: BlockTrait,
+{
+    fn parse(inner: T) -> Result<Self> {
+        if !Self::detect(&inner)? {
+            return Err(FsError::InvalidPartitionTable);
+        }
+
+        // fall back to the backup header at the end of the disk
+        let (header, entries) = Self::load(&inner, 1)
+            .or_else(|err| {
+                warn!("Primary GPT is invalid: {}, trying the backup", err);
+                let last = inner.block_count().map_err(|_| "Unknown disk size")?;
+                Self::load(&inner, last.saturating_sub(1))
+            })
+            .map_err(|err| {
+                warn!("Failed to load GPT: {}", err);
+                FsError::InvalidPartitionTable
+            })?;
+
+        info!("GPT Header: {:#?}", header);
+
+        for (index, entry) in entries.iter().enumerate().filter(|(_, e)| e.is_used()) {
+            trace!("Partition {}: {:#?}", index, entry);
+        }
+
+        Ok(Self {
+            inner,
+            header,
+            entries,
+            _block: PhantomData,
+        })
+    }
+
+    fn partitions(&self) -> Result<Vec<Partition<T, B>>> {
+        let mut parts = Vec::new();
+        let usable = self.header.first_usable_lba()..=self.header.last_usable_lba();
+
+        for (index, entry) in self.entries.iter().enumerate() {
+            if !entry.is_used() {
+                continue;
+            }
+
+            if !usable.contains(&entry.first_lba()) || !usable.contains(&entry.last_lba()) {
+                warn!(
+                    "Skipping GPT partition out of the usable range: {:#?}",
+                    entry
+                );
+                continue;
+            }
+
+            parts.push(Partition::new(
+                self.inner.clone(),
+                index + 1,
+                PartitionKind::Gpt(entry.type_guid()),
+                entry.first_lba() as usize,
+                entry.total_lba() as usize,
+            ));
+        }
+
+        Ok(parts)
+    }
+}
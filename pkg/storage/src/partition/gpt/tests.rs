@@ -0,0 +1,183 @@
+//! Tests against a generated GPT disk image
+
+use super::*;
+use crate::testing::MemoryDevice;
+
+const BLOCK_SIZE: usize = 512;
+const DISK_BLOCKS: usize = 2048; // 1 MiB
+const ENTRY_COUNT: usize = 128;
+const ENTRY_BLOCKS: usize = ENTRY_COUNT * GptEntry::LEN / BLOCK_SIZE;
+const FIRST_USABLE: u64 = 2 + ENTRY_BLOCKS as u64;
+const LAST_USABLE: u64 = (DISK_BLOCKS - 2 - ENTRY_BLOCKS) as u64;
+
+/// (type, first LBA, last LBA, name) of the partitions, the second entry is unused
+const PARTITIONS: [(Guid, u64, u64, &str); 3] = [
+    (Guid::EFI_SYSTEM, FIRST_USABLE, 1023, "EFI system partition"),
+    (Guid::NIL, 0, 0, ""),
+    (Guid::LINUX_FILESYSTEM, 1024, LAST_USABLE, "data"),
+];
+
+fn unique_guid(index: usize) -> Guid {
+    Guid::from_fields(0x1234_0000 + index as u32, 0xABCD, 0x4EF0, [index as u8; 8])
+}
+
+fn write_header(data: &mut [u8], lba: u64, backup: u64, entries_lba: u64, entries_crc: u32) {
+    let header = &mut data[lba as usize * BLOCK_SIZE..][..GptHeader::LEN];
+    header[0x00..0x08].copy_from_slice(GptHeader::SIGNATURE);
+    header[0x08..0x0C].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+    header[0x0C..0x10].copy_from_slice(&(GptHeader::LEN as u32).to_le_bytes());
+    header[0x18..0x20].copy_from_slice(&lba.to_le_bytes());
+    header[0x20..0x28].copy_from_slice(&backup.to_le_bytes());
+    header[0x28..0x30].copy_from_slice(&FIRST_USABLE.to_le_bytes());
+    header[0x30..0x38].copy_from_slice(&LAST_USABLE.to_le_bytes());
+    header[0x38..0x48].copy_from_slice(Guid::BASIC_DATA.as_bytes());
+    header[0x48..0x50].copy_from_slice(&entries_lba.to_le_bytes());
+    header[0x50..0x54].copy_from_slice(&(ENTRY_COUNT as u32).to_le_bytes());
+    header[0x54..0x58].copy_from_slice(&(GptEntry::LEN as u32).to_le_bytes());
+    header[0x58..0x5C].copy_from_slice(&entries_crc.to_le_bytes());
+
+    let crc = crc::crc32(header);
+    header[0x10..0x14].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Replace a field of the header at `lba`, keeping its CRC32 valid
+fn patch_header(data: &mut [u8], lba: usize, offset: usize, value: u32) {
+    let header = &mut data[lba * BLOCK_SIZE..][..GptHeader::LEN];
+    header[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    header[0x10..0x14].fill(0);
+    let crc = crc::crc32(header);
+    header[0x10..0x14].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Builds a disk with a protective MBR, both GPT copies, and the
+/// index of each partition written at the start of its first block
+fn build_image() -> Vec<u8> {
+    let mut data = vec![0u8; DISK_BLOCKS * BLOCK_SIZE];
+
+    let mbr_entry = &mut data[0x1BE..0x1CE];
+    mbr_entry[4] = PROTECTIVE_MBR_TYPE;
+    mbr_entry[8..12].copy_from_slice(&1u32.to_le_bytes());
+    mbr_entry[12..16].copy_from_slice(&(DISK_BLOCKS as u32 - 1).to_le_bytes());
+    data[0x1FE..0x200].copy_from_slice(&[0x55, 0xAA]);
+
+    let mut array = vec![0u8; ENTRY_COUNT * GptEntry::LEN];
+    for (index, (kind, first, last, name)) in PARTITIONS.iter().enumerate() {
+        if kind.is_nil() {
+            continue;
+        }
+
+        let entry = &mut array[index * GptEntry::LEN..][..GptEntry::LEN];
+        entry[0x00..0x10].copy_from_slice(kind.as_bytes());
+        entry[0x10..0x20].copy_from_slice(unique_guid(index).as_bytes());
+        entry[0x20..0x28].copy_from_slice(&first.to_le_bytes());
+        entry[0x28..0x30].copy_from_slice(&last.to_le_bytes());
+        for (i, c) in name.encode_utf16().enumerate() {
+            entry[0x38 + i * 2..0x3A + i * 2].copy_from_slice(&c.to_le_bytes());
+        }
+
+        data[*first as usize * BLOCK_SIZE] = 0xA0 + index as u8;
+    }
+
+    let crc = crc::crc32(&array);
+    let last = DISK_BLOCKS as u64 - 1;
+    let backup_entries = last - ENTRY_BLOCKS as u64;
+
+    data[2 * BLOCK_SIZE..][..array.len()].copy_from_slice(&array);
+    data[backup_entries as usize * BLOCK_SIZE..][..array.len()].copy_from_slice(&array);
+    write_header(&mut data, 1, last, 2, crc);
+    write_header(&mut data, last, 1, backup_entries, crc);
+
+    data
+}
+
+fn parse(data: Vec<u8>) -> Result<GptTable<&'static MemoryDevice, Block512>> {
+    let device: &'static MemoryDevice = Box::leak(Box::new(MemoryDevice::new(data)));
+    GptTable::parse(device)
+}
+
+fn first_bytes(table: &GptTable<&'static MemoryDevice, Block512>) -> Vec<u8> {
+    let mut block = Block512::default();
+    table
+        .partitions()
+        .unwrap()
+        .iter()
+        .map(|part| {
+            part.read_block(0, &mut block).unwrap();
+            block[0]
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_gpt() {
+    let table = parse(build_image()).unwrap();
+
+    assert_eq!(table.header().entry_count(), ENTRY_COUNT as u32);
+    assert_eq!(table.header().disk_guid(), Guid::BASIC_DATA);
+
+    let entries: Vec<&GptEntry> = table.entries().collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].type_guid(), Guid::EFI_SYSTEM);
+    assert_eq!(entries[0].name(), "EFI system partition");
+    assert_eq!(entries[1].type_guid(), Guid::LINUX_FILESYSTEM);
+    assert_eq!(entries[1].unique_guid(), unique_guid(2));
+    assert_eq!(entries[1].name(), "data");
+    assert_eq!(entries[1].total_lba(), LAST_USABLE - 1024 + 1);
+
+    assert_eq!(first_bytes(&table), [0xA0, 0xA2]);
+}
+
+#[test]
+fn test_backup_gpt() {
+    // a broken primary header
+    let mut data = build_image();
+    data[BLOCK_SIZE + 0x30] ^= 1;
+    let table = parse(data).unwrap();
+    assert_eq!(table.header().current_lba(), DISK_BLOCKS as u64 - 1);
+    assert_eq!(first_bytes(&table), [0xA0, 0xA2]);
+
+    // a broken primary entry array
+    let mut data = build_image();
+    data[2 * BLOCK_SIZE + 0x38] ^= 1;
+    let table = parse(data).unwrap();
+    assert_eq!(
+        table.entries().next().unwrap().name(),
+        "EFI system partition"
+    );
+
+    // both copies broken
+    let mut data = build_image();
+    data[BLOCK_SIZE + 0x30] ^= 1;
+    data[(DISK_BLOCKS - 1) * BLOCK_SIZE + 0x30] ^= 1;
+    assert_eq!(parse(data).err(), Some(FsError::InvalidPartitionTable));
+}
+
+#[test]
+fn test_hostile_header() {
+    let last = DISK_BLOCKS - 1;
+
+    // an entry size that is not a power of two, the backup is used
+    let mut data = build_image();
+    patch_header(&mut data, 1, 0x54, 136);
+    let table = parse(data).unwrap();
+    assert_eq!(table.header().current_lba(), last as u64);
+
+    // a huge array is rejected before it is allocated
+    for (offset, value) in [(0x50, u32::MAX), (0x50, 129), (0x54, 1 << 31)] {
+        let mut data = build_image();
+        patch_header(&mut data, 1, offset, value);
+        patch_header(&mut data, last, offset, value);
+        assert_eq!(parse(data).err(), Some(FsError::InvalidPartitionTable));
+    }
+}
+
+#[test]
+fn test_detect_gpt() {
+    let device: &'static MemoryDevice = Box::leak(Box::new(MemoryDevice::new(build_image())));
+    assert_eq!(GptTable::<_, Block512>::detect(&device), Ok(true));
+
+    // a plain MBR disk
+    let mut data = build_image();
+    data[0x1BE + 4] = 0x0C;
+    assert_eq!(parse(data).err(), Some(FsError::InvalidPartitionTable));
+}
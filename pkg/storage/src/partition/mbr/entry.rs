@@ -16,10 +16,56 @@ impl MbrPartition {
             data: data.to_owned(),
         }
     }
+
+    /// Create an inactive partition entry addressed by LBA only
+    ///
+    /// The CHS fields are set to the maximum, as done for partitions
+    /// beyond the CHS limit, so that nothing relies on them.
+    pub fn new(partition_type: u8, begin_lba: u32, total_lba: u32) -> MbrPartition {
+        let mut data = [0u8; 16];
+        data[0x01..0x04].copy_from_slice(&[0xFE, 0xFF, 0xFF]);
+        data[0x05..0x08].copy_from_slice(&[0xFE, 0xFF, 0xFF]);
+
+        let mut entry = MbrPartition { data };
+        entry.set_partition_type(partition_type);
+        entry.data[0x08..0x0C].copy_from_slice(&begin_lba.to_le_bytes());
+        entry.set_total_lba(total_lba);
+        entry
+    }
+
+    /// The raw entry, as stored in the MBR
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.data
+    }
+
+    pub fn set_partition_type(&mut self, partition_type: u8) {
+        self.data[0x04] = partition_type;
+    }
+
+    pub fn set_total_lba(&mut self, total_lba: u32) {
+        self.data[0x0C..0x10].copy_from_slice(&total_lba.to_le_bytes());
+    }
+
+    /// The block after the last one of the partition
+    pub fn end_lba(&self) -> u64 {
+        self.begin_lba() as u64 + self.total_lba() as u64
+    }
+
+    /// The boot flag, set on the partition to boot from
     pub fn is_active(&self) -> bool {
         self.status() == 0x80
     }
 
+    /// Entries with a zero partition type are unused
+    pub fn is_used(&self) -> bool {
+        self.partition_type() != 0
+    }
+
+    /// Extended partitions hold a chain of EBRs describing logical partitions
+    pub fn is_extended(&self) -> bool {
+        matches!(self.partition_type(), 0x05 | 0x0F | 0x85)
+    }
+
     define_field!(u8, 0x00, status);
     define_field!(u8, 0x01, begin_head);
     define_field!(u8, 0x04, partition_type);
@@ -76,6 +122,8 @@ mod tests {
         println!("{:#?}", meta);
 
         assert!(meta.is_active());
+        assert!(meta.is_used());
+        assert!(!meta.is_extended());
         assert_eq!(meta.begin_head(), 1);
         assert_eq!(meta.begin_sector(), 1);
         assert_eq!(meta.begin_cylinder(), 0);
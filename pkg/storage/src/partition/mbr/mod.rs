@@ -2,11 +2,17 @@
 
 mod entry;
 
+#[cfg(test)]
+mod tests;
+
 use core::marker::PhantomData;
 
 use crate::*;
 pub use entry::*;
 
+/// The most logical partitions read from an EBR chain, to stop on loops
+const MAX_LOGICAL_PARTITIONS: usize = 64;
+
 /// The MBR Table
 ///
 /// The disk is a collection of partitions.
@@ -14,6 +20,14 @@ pub use entry::*;
 /// The MBR contains information about the partitions.
 ///
 /// [ MBR | Partitions ] [ Partition 1 ] [ Partition 2 ] [ Partition 3 ] [ Partition 4 ]
+///
+/// An extended partition is a container for logical partitions.
+/// Each logical partition is preceded by an EBR (Extended Boot Record),
+/// laid out like the MBR, whose first entry is the logical partition
+/// (relative to the EBR) and whose second entry links to the next EBR
+/// (relative to the start of the extended partition).
+///
+/// [ EBR | Logical 5 ] [ EBR | Logical 6 ] ...
 pub struct MbrTable<T, B>
 where
     T: BlockDevice<B> + Clone,
@@ -21,9 +35,195 @@ where
 {
     inner: T,
     partitions: [MbrPartition; 4],
+    /// The logical partitions and their absolute begin LBA
+    logical: Vec<(u32, MbrPartition)>,
     _block: PhantomData<B>,
 }
 
+/// Parse the four partition entries of an MBR or EBR
+fn parse_entries(buffer: &[u8]) -> [MbrPartition; 4] {
+    core::array::from_fn(|i| {
+        MbrPartition::parse(
+            &buffer[(0x1BE + i * 16)..(0x1BE + (i + 1) * 16)]
+                .try_into()
+                .unwrap(),
+        )
+    })
+}
+
+impl<T, B> MbrTable<T, B>
+where
+    T: BlockDevice<B> + Clone,
+    B: BlockTrait,
+{
+    /// The raw entries of the four primary partitions
+    pub fn entries(&self) -> &[MbrPartition; 4] {
+        &self.partitions
+    }
+
+    /// The raw entries of the logical partitions, in chain order
+    pub fn logical_entries(&self) -> impl Iterator<Item = &MbrPartition> {
+        self.logical.iter().map(|(_, entry)| entry)
+    }
+
+    /// Walk the EBR chain of an extended partition
+    ///
+    /// A broken chain is logged and ends the walk,
+    /// the logical partitions found so far are kept.
+    fn walk_ebr(inner: &T, extended: &MbrPartition) -> Vec<(u32, MbrPartition)> {
+        let mut logical = Vec::new();
+        let mut block = B::default();
+        let base = extended.begin_lba();
+        let mut next = 0;
+
+        while logical.len() < MAX_LOGICAL_PARTITIONS {
+            let lba = base.saturating_add(next);
+            if let Err(err) = inner.read_block(lba as usize, &mut block) {
+                warn!("Failed to read the EBR at {:#x}: {:?}", lba, err);
+                break;
+            }
+
+            let buffer = block.as_ref();
+            if buffer[0x1FE..0x200] != [0x55, 0xAA] {
+                warn!("Bad EBR signature at {:#x}", lba);
+                break;
+            }
+
+            let [entry, link, ..] = parse_entries(buffer);
+            if entry.is_used() {
+                trace!("Logical partition at {:#x}: {:#?}", lba, entry);
+                logical.push((lba.saturating_add(entry.begin_lba()), entry));
+            }
+
+            if !link.is_extended() {
+                break;
+            }
+
+            if link.begin_lba() == next || link.begin_lba() >= extended.total_lba() {
+                warn!("Bad EBR link at {:#x}: {:#?}", lba, link);
+                break;
+            }
+
+            next = link.begin_lba();
+        }
+
+        logical
+    }
+
+    /// Get the used primary entry in `slot`
+    fn used_entry(&self, slot: usize) -> Result<&MbrPartition> {
+        self.partitions
+            .get(slot)
+            .filter(|part| part.is_used())
+            .ok_or(FsError::InvalidOperation)
+    }
+
+    /// Check that the blocks fit on the disk without overlapping
+    /// other primary partitions, the MBR itself is block 0
+    fn check_range(&self, slot: usize, begin: u32, total: u32) -> Result<()> {
+        let end = begin as u64 + total as u64;
+        if begin == 0 || total == 0 || end > self.inner.block_count()? as u64 {
+            return Err(FsError::InvalidOffset);
+        }
+
+        let overlap = self.partitions.iter().enumerate().any(|(i, part)| {
+            i != slot
+                && part.is_used()
+                && (begin as u64) < part.end_lba()
+                && (part.begin_lba() as u64) < end
+        });
+
+        if overlap {
+            Err(FsError::PartitionOverlap)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Create a primary partition in the unused `slot` (0 to 3)
+    ///
+    /// Only one extended partition is allowed. Changes are kept
+    /// in memory until [`MbrTable::write`] is called.
+    pub fn create(&mut self, slot: usize, kind: u8, begin: u32, total: u32) -> Result<()> {
+        let entry = self.partitions.get(slot).ok_or(FsError::InvalidOperation)?;
+        if entry.is_used() {
+            return Err(FsError::AlreadyExists);
+        }
+
+        let new = MbrPartition::new(kind, begin, total);
+        if !new.is_used() {
+            return Err(FsError::InvalidOperation);
+        }
+        if new.is_extended() && self.partitions.iter().any(|part| part.is_extended()) {
+            return Err(FsError::AlreadyExists);
+        }
+
+        self.check_range(slot, begin, total)?;
+        self.partitions[slot] = new;
+        Ok(())
+    }
+
+    /// Delete the primary partition in `slot`
+    ///
+    /// Deleting the extended partition also drops its logical partitions.
+    pub fn delete(&mut self, slot: usize) -> Result<()> {
+        if self.used_entry(slot)?.is_extended() {
+            self.logical.clear();
+        }
+
+        self.partitions[slot] = MbrPartition::default();
+        Ok(())
+    }
+
+    /// Change the size of the primary partition in `slot`, keeping its start
+    ///
+    /// The extended partition can not be resized, its logical partitions
+    /// would be left outside of it.
+    pub fn resize(&mut self, slot: usize, total: u32) -> Result<()> {
+        let entry = self.used_entry(slot)?;
+        if entry.is_extended() {
+            return Err(FsError::NotSupported);
+        }
+
+        self.check_range(slot, entry.begin_lba(), total)?;
+        self.partitions[slot].set_total_lba(total);
+        Ok(())
+    }
+
+    /// Change the type of the primary partition in `slot`
+    ///
+    /// Partitions can not be turned into or from extended partitions.
+    pub fn set_type(&mut self, slot: usize, kind: u8) -> Result<()> {
+        let entry = self.used_entry(slot)?;
+        if kind == 0 {
+            return Err(FsError::InvalidOperation);
+        }
+        if entry.is_extended() || MbrPartition::new(kind, 0, 0).is_extended() {
+            return Err(FsError::NotSupported);
+        }
+
+        self.partitions[slot].set_partition_type(kind);
+        Ok(())
+    }
+
+    /// Write the primary entries back to the first block
+    ///
+    /// The boot code is kept, and the boot signature is set
+    /// so that a blank disk gets a valid MBR.
+    pub fn write(&self) -> Result<()> {
+        let mut block = B::default();
+        self.inner.read_block(0, &mut block)?;
+
+        let buffer = block.as_mut();
+        for (i, part) in self.partitions.iter().enumerate() {
+            buffer[(0x1BE + i * 16)..(0x1BE + (i + 1) * 16)].copy_from_slice(part.as_bytes());
+        }
+        buffer[0x1FE..0x200].copy_from_slice(&[0x55, 0xAA]);
+
+        self.inner.write_block(0, &block)
+    }
+}
+
 impl<T, B> PartitionTable<T, B> for MbrTable<T, B>
 where
     T: BlockDevice<B> + Clone,
@@ -33,46 +233,66 @@ where
         let mut block = B::default();
         inner.read_block(0, &mut block)?;
 
-        let mut partitions = Vec::with_capacity(4);
-        let buffer = block.as_ref();
-
-        for i in 0..4 {
-            partitions.push(
-                // parse the mbr partition from the buffer
-                MbrPartition::parse(
-                    &buffer[(0x1BE + i * 16)..(0x1BE + (i + 1) * 16)]
-                        .try_into()
-                        .unwrap(),
-                ),
-            );
-
-            if partitions[i].is_active() {
-                trace!("Partition {}: {:#?}", i, partitions[i]);
+        let partitions = parse_entries(block.as_ref());
+
+        for (i, part) in partitions.iter().enumerate() {
+            if part.is_used() {
+                trace!("Partition {}: {:#?}", i, part);
             }
         }
 
         info!("MBR Table: {:#?}", partitions);
 
+        let logical = partitions
+            .iter()
+            .filter(|part| part.is_extended())
+            .flat_map(|part| Self::walk_ebr(&inner, part))
+            .collect();
+
         Ok(Self {
             inner,
-            partitions: partitions.try_into().unwrap(),
+            partitions,
+            logical,
             _block: PhantomData,
         })
     }
 
     fn partitions(&self) -> Result<Vec<Partition<T, B>>> {
         let mut parts = Vec::new();
+        let disk_end = self.inner.block_count()? as u64;
+
+        for (i, part) in self.partitions.iter().enumerate() {
+            if part.is_used() && !part.is_extended() {
+                if part.end_lba() > disk_end {
+                    warn!("Skipping MBR partition beyond the disk: {:#?}", part);
+                    continue;
+                }
 
-        for part in self.partitions {
-            if part.is_active() {
                 parts.push(Partition::new(
                     self.inner.clone(),
+                    i + 1,
+                    PartitionKind::Mbr(part.partition_type()),
                     part.begin_lba() as usize,
                     part.total_lba() as usize,
                 ));
             }
         }
 
+        for (i, (begin, part)) in self.logical.iter().enumerate() {
+            if *begin as u64 + part.total_lba() as u64 > disk_end {
+                warn!("Skipping logical partition beyond the disk: {:#?}", part);
+                continue;
+            }
+
+            parts.push(Partition::new(
+                self.inner.clone(),
+                i + 5,
+                PartitionKind::Mbr(part.partition_type()),
+                *begin as usize,
+                part.total_lba() as usize,
+            ));
+        }
+
         Ok(parts)
     }
 }
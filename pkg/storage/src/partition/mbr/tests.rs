@@ -0,0 +1,200 @@
+//! Tests against a generated MBR disk image with logical partitions
+
+use super::*;
+use crate::testing::MemoryDevice;
+
+const BLOCK_SIZE: usize = 512;
+const DISK_BLOCKS: usize = 2048; // 1 MiB
+
+/// The extended partition in the third slot
+const EXTENDED_BEGIN: u32 = 1024;
+const EXTENDED_TOTAL: u32 = 1024;
+
+/// (status, type, begin LBA, total LBA) of the primary partitions, the second is unused
+const PRIMARY: [(u8, u8, u32, u32); 4] = [
+    (0x00, 0x06, 64, 448),
+    (0x00, 0x00, 0, 0),
+    (0x00, 0x0F, EXTENDED_BEGIN, EXTENDED_TOTAL),
+    (0x80, 0x83, 512, 512),
+];
+
+/// (EBR LBA relative to the extended partition, type, total LBA) of the logical partitions
+const LOGICAL: [(u32, u8, u32); 2] = [(0, 0x0C, 255), (256, 0x83, 767)];
+
+/// The offset of a logical partition from its EBR
+const LOGICAL_OFFSET: u32 = 1;
+
+fn write_entry(data: &mut [u8], lba: usize, slot: usize, entry: (u8, u8, u32, u32)) {
+    let (status, kind, begin, total) = entry;
+    let raw = &mut data[lba * BLOCK_SIZE + 0x1BE + slot * 16..][..16];
+    raw[0] = status;
+    raw[4] = kind;
+    raw[8..12].copy_from_slice(&begin.to_le_bytes());
+    raw[12..16].copy_from_slice(&total.to_le_bytes());
+    data[lba * BLOCK_SIZE + 0x1FE..][..2].copy_from_slice(&[0x55, 0xAA]);
+}
+
+/// Builds a disk with the partitions above and the index of
+/// each partition written at the start of its first block
+fn build_image() -> Vec<u8> {
+    let mut data = vec![0u8; DISK_BLOCKS * BLOCK_SIZE];
+
+    for (slot, entry) in PRIMARY.iter().enumerate() {
+        write_entry(&mut data, 0, slot, *entry);
+        if entry.1 != 0 && entry.1 != 0x0F {
+            data[entry.2 as usize * BLOCK_SIZE] = 0xA1 + slot as u8;
+        }
+    }
+
+    for (i, (ebr, kind, total)) in LOGICAL.iter().enumerate() {
+        let lba = (EXTENDED_BEGIN + ebr) as usize;
+        write_entry(&mut data, lba, 0, (0, *kind, LOGICAL_OFFSET, *total));
+        if let Some((next, _, _)) = LOGICAL.get(i + 1) {
+            write_entry(&mut data, lba, 1, (0, 0x05, *next, EXTENDED_TOTAL - next));
+        }
+        data[(lba + LOGICAL_OFFSET as usize) * BLOCK_SIZE] = 0xA5 + i as u8;
+    }
+
+    data
+}
+
+fn parse(data: Vec<u8>) -> MbrTable<&'static MemoryDevice, Block512> {
+    let device: &'static MemoryDevice = Box::leak(Box::new(MemoryDevice::new(data)));
+    MbrTable::parse(device).unwrap()
+}
+
+/// Parse the table again from the disk the table was read from
+fn reparse(
+    table: &MbrTable<&'static MemoryDevice, Block512>,
+) -> MbrTable<&'static MemoryDevice, Block512> {
+    MbrTable::parse(table.inner).unwrap()
+}
+
+fn summary(table: &MbrTable<&'static MemoryDevice, Block512>) -> Vec<(usize, u8, u8)> {
+    let mut block = Block512::default();
+    table
+        .partitions()
+        .unwrap()
+        .iter()
+        .map(|part| {
+            part.read_block(0, &mut block).unwrap();
+            (part.index(), part.kind().mbr_type().unwrap(), block[0])
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_mbr() {
+    let table = parse(build_image());
+
+    assert_eq!(table.logical_entries().count(), 2);
+    assert_eq!(
+        summary(&table),
+        [
+            (1, 0x06, 0xA1),
+            (4, 0x83, 0xA4),
+            (5, 0x0C, 0xA5),
+            (6, 0x83, 0xA6),
+        ]
+    );
+}
+
+#[test]
+fn test_broken_ebr_chain() {
+    // the second EBR lost its signature
+    let mut data = build_image();
+    data[(EXTENDED_BEGIN + LOGICAL[1].0) as usize * BLOCK_SIZE + 0x1FE] = 0;
+    let table = parse(data);
+    assert_eq!(summary(&table).len(), 3);
+
+    // the first EBR links back to itself
+    let mut data = build_image();
+    let link = EXTENDED_BEGIN as usize * BLOCK_SIZE + 0x1CE;
+    data[link + 8..link + 12].copy_from_slice(&0u32.to_le_bytes());
+    let table = parse(data);
+    assert_eq!(table.logical_entries().count(), 1);
+}
+
+#[test]
+fn test_partition_bounds() {
+    let table = parse(build_image());
+    let parts = table.partitions().unwrap();
+    assert_eq!(parts[0].block_count(), Ok(448));
+    assert_eq!(parts[3].block_count(), Ok(767));
+
+    let mut block = Block512::default();
+    assert_eq!(
+        parts[0].read_block(448, &mut block),
+        Err(FsError::InvalidOffset)
+    );
+
+    // the first partition runs past the end of the disk
+    let mut data = build_image();
+    data[0x1BE + 12..0x1BE + 16].copy_from_slice(&(DISK_BLOCKS as u32).to_le_bytes());
+    assert_eq!(summary(&parse(data))[0].0, 4);
+}
+
+#[test]
+fn test_edit_mbr() {
+    let mut table = parse(build_image());
+
+    // the second slot is free, the gap between the first and fourth partition too
+    assert_eq!(table.create(0, 0x06, 1, 16), Err(FsError::AlreadyExists));
+    assert_eq!(
+        table.create(1, 0x06, 500, 16),
+        Err(FsError::PartitionOverlap)
+    );
+    assert_eq!(table.create(1, 0x06, 2040, 16), Err(FsError::InvalidOffset));
+    assert_eq!(table.create(1, 0x05, 1, 16), Err(FsError::AlreadyExists));
+    assert_eq!(table.create(4, 0x06, 1, 16), Err(FsError::InvalidOperation));
+    table.create(1, 0x0C, 1, 63).unwrap();
+
+    assert_eq!(table.resize(0, 449), Err(FsError::PartitionOverlap));
+    assert_eq!(table.resize(2, 512), Err(FsError::NotSupported));
+    table.resize(0, 400).unwrap();
+    table.set_type(3, 0x06).unwrap();
+    assert_eq!(table.set_type(3, 0x0F), Err(FsError::NotSupported));
+
+    // nothing is written before `write`
+    assert_eq!(reparse(&table).entries()[1].partition_type(), 0);
+    table.write().unwrap();
+
+    let table = reparse(&table);
+    let entries = table.entries();
+    assert_eq!(entries[0].total_lba(), 400);
+    assert_eq!(entries[1].partition_type(), 0x0C);
+    assert_eq!(entries[1].begin_lba(), 1);
+    assert_eq!(entries[1].total_lba(), 63);
+    assert_eq!(entries[3].partition_type(), 0x06);
+    assert!(entries[3].is_active());
+    assert_eq!(table.logical_entries().count(), 2);
+}
+
+#[test]
+fn test_delete_partitions() {
+    let mut table = parse(build_image());
+
+    assert_eq!(table.delete(1), Err(FsError::InvalidOperation));
+    table.delete(2).unwrap();
+    table.delete(0).unwrap();
+    table.write().unwrap();
+
+    let table = reparse(&table);
+    assert_eq!(summary(&table), [(4, 0x83, 0xA4)]);
+}
+
+#[test]
+fn test_blank_disk() {
+    let mut table = parse(vec![0u8; DISK_BLOCKS * BLOCK_SIZE]);
+    assert!(table.partitions().unwrap().is_empty());
+
+    table.create(0, 0x06, 2048 - 1024, 1024).unwrap();
+    table.write().unwrap();
+
+    let table = reparse(&table);
+    assert_eq!(
+        table.entries()[0].as_bytes(),
+        MbrPartition::new(0x06, 1024, 1024).as_bytes()
+    );
+    assert_eq!(summary(&table), [(1, 0x06, 0)]);
+}
@@ -2,6 +2,7 @@ use core::marker::PhantomData;
 
 use crate::*;
 
+pub mod gpt;
 pub mod mbr;
 
 /// Partition table trait
@@ -18,6 +19,25 @@ where
     fn partitions(&self) -> Result<Vec<Partition<T, B>>>;
 }
 
+/// The type of a partition, as recorded in its partition table
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PartitionKind {
+    /// The one byte system id of an MBR entry
+    Mbr(u8),
+    /// The type GUID of a GPT entry
+    Gpt(gpt::Guid),
+}
+
+impl PartitionKind {
+    /// The MBR system id, if this is an MBR partition
+    pub fn mbr_type(&self) -> Option<u8> {
+        match self {
+            PartitionKind::Mbr(kind) => Some(*kind),
+            PartitionKind::Gpt(_) => None,
+        }
+    }
+}
+
 /// Identifies a partition on the disk.
 #[derive(Clone, Copy)]
 pub struct Partition<T, B>
@@ -26,6 +46,8 @@ where
     B: BlockTrait,
 {
     inner: T,
+    index: usize,
+    kind: PartitionKind,
     offset: usize,
     size: usize,
     _block: PhantomData<B>,
@@ -36,14 +58,33 @@ where
     T: BlockDevice<B>,
     B: BlockTrait,
 {
-    pub fn new(inner: T, offset: usize, size: usize) -> Self {
+    pub fn new(inner: T, index: usize, kind: PartitionKind, offset: usize, size: usize) -> Self {
         Self {
             inner,
+            index,
+            kind,
             offset,
             size,
             _block: PhantomData,
         }
     }
+
+    /// The 1-based partition number, as in `hda1`
+    ///
+    /// MBR primary partitions are numbered 1 to 4 by their slot,
+    /// logical partitions start from 5. GPT partitions follow the entry index.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn kind(&self) -> PartitionKind {
+        self.kind
+    }
+
+    /// The first block of the partition on the disk
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
 }
 
 impl<T, B> core::fmt::Debug for Partition<T, B>
@@ -53,6 +94,8 @@ where
 {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Partition")
+            .field("index", &self.index)
+            .field("kind", &self.kind)
             .field("offset", &self.offset)
             .field("size", &self.size)
             .finish()
@@ -65,7 +108,7 @@ where
     B: BlockTrait,
 {
     fn block_count(&self) -> Result<usize> {
-        self.inner.block_count()
+        Ok(self.size)
     }
 
     fn read_block(&self, offset: usize, block: &mut B) -> Result<()> {
@@ -83,4 +126,16 @@ where
             self.inner.write_block(offset + self.offset, block)
         }
     }
+
+    fn read_blocks(&self, offset: usize, blocks: &mut [B]) -> Result<()> {
+        if offset + blocks.len() > self.size {
+            Err(FsError::InvalidOffset)
+        } else {
+            self.inner.read_blocks(offset + self.offset, blocks)
+        }
+    }
+
+    fn sync(&self) -> Result<()> {
+        self.inner.sync()
+    }
 }
@@ -0,0 +1,280 @@
+//! Tests against Fat16 images made by `mkfs.fat` and `mtools`
+//!
+//! Every image is checked against the directory tree it was built from,
+//! so the host's view is the reference. The tests needing the tools are
+//! ignored by default, run them with `cargo test --features std -- --ignored`.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use ysos_storage::fat16::{Fat16, FormatOptions};
+use ysos_storage::testing::{pattern, read_to_end, run, TempDir};
+use ysos_storage::*;
+
+/// 4 sectors per cluster
+const CLUSTER_SIZE: usize = 2048;
+const BIG_FILE_SIZE: usize = 300_000;
+const DIR_FILE_COUNT: usize = 100;
+
+/// Populates a directory with the following layout, in 8.3 names since
+/// long file names are not supported:
+///
+/// - `/HELLO.TXT`, `/EMPTY.TXT`: a small and an empty file
+/// - `/BIG.BIN`: a file spanning many clusters
+/// - `/DIR/F<i>.TXT`: `DIR_FILE_COUNT` files, filling several directory clusters
+/// - `/DIR/A/B/C/DEEP.BIN`: a file of three clusters in nested directories
+fn populate(root: &Path) {
+    std::fs::write(root.join("HELLO.TXT"), b"hello, fat16!\n").unwrap();
+    std::fs::write(root.join("EMPTY.TXT"), b"").unwrap();
+
+    let content: Vec<u8> = (0..BIG_FILE_SIZE).map(|i| pattern(1, i)).collect();
+    std::fs::write(root.join("BIG.BIN"), content).unwrap();
+
+    let dir = root.join("DIR");
+    std::fs::create_dir_all(dir.join("A/B/C")).unwrap();
+    for i in 0..DIR_FILE_COUNT {
+        let content: Vec<u8> = (0..=i).map(|o| pattern(i, o)).collect();
+        std::fs::write(dir.join(format!("F{}.TXT", i)), content).unwrap();
+    }
+
+    let content: Vec<u8> = (0..CLUSTER_SIZE * 2 + 100)
+        .map(|i| pattern(0x5A, i))
+        .collect();
+    std::fs::write(dir.join("A/B/C/DEEP.BIN"), content).unwrap();
+}
+
+/// A 16 MiB image holding a copy of the populated `root`
+struct Image {
+    /// Removes the tree and the image on drop
+    _temp: TempDir,
+    root: PathBuf,
+    image: PathBuf,
+}
+
+impl Image {
+    fn build(name: &str) -> Self {
+        let temp = TempDir::new(&format!("fat16-{}", name));
+        let root = temp.0.join("root");
+        let image = temp.0.join("image");
+        std::fs::create_dir(&root).unwrap();
+        populate(&root);
+
+        run(Command::new("mkfs.fat")
+            .args(["-C", "-F", "16", "-s", "4"])
+            .arg(&image)
+            .arg("16384"));
+
+        for entry in std::fs::read_dir(&root).unwrap() {
+            run(Command::new("mcopy")
+                .env("MTOOLS_SKIP_CHECK", "1")
+                .args(["-s", "-i"])
+                .arg(&image)
+                .arg(entry.unwrap().path())
+                .arg("::/"));
+        }
+
+        Self {
+            _temp: temp,
+            root,
+            image,
+        }
+    }
+
+    fn open(&self) -> Fat16 {
+        Fat16::new(FileBlockDevice::open(&self.image).unwrap())
+    }
+}
+
+/// The entries of a directory on the host, sorted by name
+fn host_entries(dir: &Path) -> Vec<(String, std::fs::Metadata)> {
+    let mut entries: Vec<(String, std::fs::Metadata)> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let entry = entry.unwrap();
+            (
+                entry.file_name().into_string().unwrap(),
+                entry.metadata().unwrap(),
+            )
+        })
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+    entries
+}
+
+/// Compare the directory at `path` and everything below it with the host
+fn check_tree(fs: &Fat16, host: &Path, path: &str) {
+    // the host does not list the dot entries of sub directories
+    let mut entries: Vec<Metadata> = fs
+        .read_dir(path)
+        .unwrap()
+        .filter(|meta| meta.name != "." && meta.name != "..")
+        .collect();
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let expected = host_entries(host);
+    let names: Vec<&str> = entries.iter().map(|meta| meta.name.as_str()).collect();
+    let expected_names: Vec<&str> = expected.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, expected_names, "entries of {}", path);
+
+    for (entry, (name, host_meta)) in entries.iter().zip(expected.iter()) {
+        let child = format!("{}/{}", path.trim_end_matches('/'), name);
+        assert_eq!(fs.exists(&child), Ok(true), "{}", child);
+
+        let meta = fs.metadata(&child).unwrap();
+        assert_eq!(meta.name, entry.name);
+        assert_eq!(meta.is_dir(), host_meta.is_dir(), "{}", child);
+
+        if host_meta.is_dir() {
+            check_tree(fs, &host.join(name), &child);
+        } else {
+            assert_eq!(meta.len as u64, host_meta.len(), "{}", child);
+            assert_eq!(entry.len, meta.len, "{}", child);
+
+            let content = read_to_end(&mut fs.open_file(&child).unwrap());
+            assert_eq!(
+                content,
+                std::fs::read(host.join(name)).unwrap(),
+                "{}",
+                child
+            );
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs mkfs.fat and mtools"]
+fn test_detect_image() {
+    let image = Image::build("detect");
+
+    let device = FileBlockDevice::open(&image.image).unwrap();
+    assert_eq!(device.block_count(), Ok(16384 * 1024 / 512));
+    assert_eq!(FatType::detect(&device), Ok(Some(FatType::Fat16)));
+    assert!(Fat16::try_new(device).is_ok());
+}
+
+#[test]
+#[ignore = "needs mkfs.fat and mtools"]
+fn test_tree_matches_host() {
+    let image = Image::build("tree");
+
+    check_tree(&image.open(), &image.root, "/");
+}
+
+#[test]
+#[ignore = "needs mkfs.fat and mtools"]
+fn test_deep_paths() {
+    let image = Image::build("deep");
+    let fs = image.open();
+
+    assert_eq!(fs.exists("/DIR/A/B/C/DEEP.BIN"), Ok(true));
+    assert_eq!(fs.exists("/DIR/A/B/C/MISSING.BIN"), Ok(false));
+    assert!(fs.metadata("/DIR/A/B").unwrap().is_dir());
+
+    // names are matched case-insensitively
+    let content = read_to_end(&mut fs.open_file("/dir/a/b/c/deep.bin").unwrap());
+    assert_eq!(content.len(), CLUSTER_SIZE * 2 + 100);
+    assert!(content
+        .iter()
+        .enumerate()
+        .all(|(i, b)| *b == pattern(0x5A, i)));
+
+    assert_eq!(fs.open_file("/DIR/A/B").err(), Some(FsError::NotAFile));
+    assert_eq!(
+        fs.read_dir("/HELLO.TXT").err(),
+        Some(FsError::NotADirectory)
+    );
+    assert_eq!(
+        fs.open_file("/DIR/MISSING/F0.TXT").err(),
+        Some(FsError::FileNotFound)
+    );
+}
+
+#[test]
+#[ignore = "needs mkfs.fat and mtools"]
+fn test_multi_cluster_file() {
+    let image = Image::build("big");
+    let fs = image.open();
+
+    let mut file = fs.open_file("/BIG.BIN").unwrap();
+    assert_eq!(file.meta.len, BIG_FILE_SIZE);
+
+    let content = read_to_end(&mut file);
+    assert_eq!(content, std::fs::read(image.root.join("BIG.BIN")).unwrap());
+
+    // seeking across clusters lands on the same bytes as the host file
+    let mut buf = [0u8; 100];
+    for offset in [CLUSTER_SIZE - 50, 200_000, BIG_FILE_SIZE - 100] {
+        assert_eq!(file.seek(SeekFrom::Start(offset)), Ok(offset));
+        assert_eq!(file.read(&mut buf), Ok(100));
+        assert_eq!(&buf[..], &content[offset..offset + 100]);
+    }
+}
+
+#[test]
+#[ignore = "needs mkfs.fat and mtools"]
+fn test_check_host_image() {
+    let image = Image::build("check");
+
+    // the dot entries of the sub directories are not checked as entries
+    let report = image.open().check(false).unwrap();
+    assert!(report.is_clean(), "{:?}", report);
+}
+
+/// Format a 32 MiB image file, checked clean by the crate itself
+fn format_image(temp: &TempDir) -> PathBuf {
+    let path = temp.0.join("image");
+    std::fs::write(&path, vec![0u8; 32 * 1024 * 1024]).unwrap();
+
+    let options = FormatOptions {
+        label: "ysos".into(),
+        volume_id: 0xCAFE_F00D,
+        ..Default::default()
+    };
+    let device = FileBlockDevice::open(&path).unwrap();
+    Fat16::format(&device, &options).unwrap();
+    assert!(Fat16::try_new(device)
+        .unwrap()
+        .check(false)
+        .unwrap()
+        .is_clean());
+    path
+}
+
+#[test]
+fn test_format_is_clean() {
+    format_image(&TempDir::new("fat16-format"));
+}
+
+#[test]
+#[ignore = "needs fsck.fat"]
+fn test_format_passes_host_fsck() {
+    let temp = TempDir::new("fat16-fsck");
+    let path = format_image(&temp);
+
+    // fails on any finding, since nothing may be repaired
+    run(Command::new("fsck.fat").arg("-n").arg(&path));
+}
+
+#[test]
+fn test_file_block_device() {
+    let temp = TempDir::new("fat16-device");
+    let path = temp.0.join("disk");
+    // trailing bytes that do not make a whole block are ignored
+    std::fs::write(&path, vec![0u8; 4 * 512 + 100]).unwrap();
+
+    let device = FileBlockDevice::open(&path).unwrap();
+    assert_eq!(device.block_count(), Ok(4));
+
+    let block = Block512::new(&[0xA5; 512]);
+    device.write_block(3, &block).unwrap();
+    assert_eq!(device.write_block(4, &block), Err(FsError::InvalidOffset));
+
+    let mut read = Block512::default();
+    device.read_block(3, &mut read).unwrap();
+    assert_eq!(read.as_ref(), block.as_ref());
+    assert_eq!(device.read_block(4, &mut read), Err(FsError::InvalidOffset));
+
+    drop(device);
+    let data = std::fs::read(&path).unwrap();
+    assert!(data[3 * 512..4 * 512].iter().all(|b| *b == 0xA5));
+    assert!(data[..3 * 512].iter().all(|b| *b == 0));
+}
@@ -0,0 +1,114 @@
+//! File status
+//!
+//! The structs filled by the `FileStat`, `Fstat` and `GetDents` syscalls,
+//! shared by the kernel and user programs. Their layout is part of the
+//! syscall interface and must not change. The attribute bits of `Stat`
+//! are also the ones changed by the `Chmod` syscall.
+
+/// The longest file name a `Dirent` holds, in bytes
+pub const NAME_MAX: usize = 255;
+
+/// The status of a file
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stat {
+    /// `Stat::FILE`, `Stat::DIRECTORY` or `Stat::DEVICE`
+    pub kind: u32,
+    /// `Stat::READ_ONLY`, `Stat::HIDDEN` and `Stat::SYSTEM` bits
+    pub attributes: u32,
+    /// The size in bytes, the size of the disk for block devices
+    pub size: u64,
+    /// Seconds since the Unix epoch, 0 if the file system does not record it
+    pub created: i64,
+    pub modified: i64,
+    pub accessed: i64,
+}
+
+impl Stat {
+    pub const FILE: u32 = 1;
+    pub const DIRECTORY: u32 = 2;
+    pub const DEVICE: u32 = 3;
+
+    /// The file can not be written, nor entries added to a read-only directory
+    pub const READ_ONLY: u32 = 0x01;
+    pub const HIDDEN: u32 = 0x02;
+    pub const SYSTEM: u32 = 0x04;
+
+    pub fn is_file(&self) -> bool {
+        self.kind == Self::FILE
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == Self::DIRECTORY
+    }
+
+    pub fn is_device(&self) -> bool {
+        self.kind == Self::DEVICE
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.attributes & Self::READ_ONLY != 0
+    }
+}
+
+/// An entry of a directory
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dirent {
+    pub stat: Stat,
+    /// The length of the name in bytes
+    pub name_len: u32,
+    /// Always 0, keeps the layout free of padding
+    pub reserved: u32,
+    /// The name in UTF-8, not terminated
+    pub name: [u8; NAME_MAX + 1],
+}
+
+impl Dirent {
+    pub const EMPTY: Self = Self {
+        stat: Stat {
+            kind: 0,
+            attributes: 0,
+            size: 0,
+            created: 0,
+            modified: 0,
+            accessed: 0,
+        },
+        name_len: 0,
+        reserved: 0,
+        name: [0; NAME_MAX + 1],
+    };
+
+    pub fn new(name: &str, stat: Stat) -> Self {
+        let mut entry = Self::EMPTY;
+        entry.stat = stat;
+        entry.set_name(name);
+        entry
+    }
+
+    pub fn name(&self) -> &str {
+        let len = (self.name_len as usize).min(NAME_MAX);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("?")
+    }
+
+    /// Set the name, cut at a character boundary if longer than `NAME_MAX`
+    pub fn set_name(&mut self, name: &str) {
+        let mut len = name.len().min(NAME_MAX);
+        while !name.is_char_boundary(len) {
+            len -= 1;
+        }
+
+        self.name = [0; NAME_MAX + 1];
+        self.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+        self.name_len = len as u32;
+    }
+}
+
+impl Default for Dirent {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+const _: () = assert!(core::mem::size_of::<Stat>() == 40);
+const _: () = assert!(core::mem::size_of::<Dirent>() == 304);
@@ -2,7 +2,12 @@
 
 use num_enum::FromPrimitive;
 
+pub mod fs;
 pub mod macros;
+pub mod tty;
+
+/// The environment variable holding the arguments a process is spawned with
+pub const ARGS_ENV: &str = "ARGS";
 
 #[repr(usize)]
 #[derive(Clone, Debug, FromPrimitive)]
@@ -11,6 +16,16 @@ pub enum Syscall {
     Write = 1,
     Open = 2,
     Close = 3,
+    FileStat = 4,
+    Fstat = 5,
+
+    Lseek = 8,
+
+    TtyAttr = 16,
+
+    GetDents = 78,
+    Mkdir = 83,
+    Chmod = 90,
 
     Brk = 12,
 
@@ -22,7 +37,15 @@ pub enum Syscall {
     WaitPid = 61,
     Sem = 64,
 
-    ListDir = 65521,
+    Sync = 162,
+    Mount = 165,
+    Umount = 166,
+
+    GetEnv = 65516,
+    Fsck = 65517,
+    Mkfs = 65518,
+    Partition = 65519,
+    ListMount = 65520,
     Time = 65529,
     PrintInfo = 65530,
     ListApp = 65531,
@@ -0,0 +1,87 @@
+//! Terminal attributes
+//!
+//! The struct read and written by the `TtyAttr` syscall, shared by the
+//! kernel and user programs, in the manner of `tcgetattr` and `tcsetattr`.
+//! Its layout is part of the syscall interface and must not change.
+
+/// Get the attributes of the terminal
+pub const TCGETATTR: usize = 0;
+/// Set the attributes of the terminal, keeping the pending input
+pub const TCSANOW: usize = 1;
+/// Set the attributes of the terminal, discarding the pending input
+pub const TCSAFLUSH: usize = 2;
+
+/// The number of control characters
+pub const NCCS: usize = 8;
+
+/// The attributes of a terminal
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Termios {
+    /// The input flags, `Termios::ICRNL`
+    pub iflag: u32,
+    /// The local flags, `Termios::ISIG`, `Termios::ICANON`, `Termios::ECHO`
+    /// and `Termios::ECHOCTL`
+    pub lflag: u32,
+    /// The control characters, indexed by `Termios::VINTR` and the others,
+    /// 0 disables one
+    pub cc: [u8; NCCS],
+}
+
+impl Termios {
+    /// Translate a carriage return to a newline on input
+    pub const ICRNL: u32 = 0x01;
+
+    /// Send the signals of the interrupt and suspend characters
+    pub const ISIG: u32 = 0x01;
+    /// Canonical mode, the input is edited and read a line at a time
+    pub const ICANON: u32 = 0x02;
+    /// Echo the input
+    pub const ECHO: u32 = 0x04;
+    /// Echo the control characters as `^X`
+    pub const ECHOCTL: u32 = 0x08;
+
+    /// Interrupt the foreground process, `^C`
+    pub const VINTR: usize = 0;
+    /// Erase the last character of the line, `^?`
+    pub const VERASE: usize = 1;
+    /// Erase the line, `^U`
+    pub const VKILL: usize = 2;
+    /// End of file, `^D`
+    pub const VEOF: usize = 3;
+    /// Suspend or resume the foreground process, `^Z`
+    pub const VSUSP: usize = 4;
+    /// The bytes a read waits for in raw mode, 0 to not wait
+    pub const VMIN: usize = 5;
+
+    pub fn is_canonical(&self) -> bool {
+        self.lflag & Self::ICANON != 0
+    }
+
+    /// Switch to raw mode, the bytes are read as they come, without echo
+    /// nor signals, like `cfmakeraw`
+    pub fn make_raw(&mut self) {
+        self.iflag &= !Self::ICRNL;
+        self.lflag &= !(Self::ISIG | Self::ICANON | Self::ECHO | Self::ECHOCTL);
+        self.cc[Self::VMIN] = 1;
+    }
+}
+
+impl Default for Termios {
+    /// Canonical mode with echo and signals
+    fn default() -> Self {
+        let mut cc = [0; NCCS];
+        cc[Self::VINTR] = 0x03;
+        cc[Self::VERASE] = 0x7F;
+        cc[Self::VKILL] = 0x15;
+        cc[Self::VEOF] = 0x04;
+        cc[Self::VSUSP] = 0x1A;
+        cc[Self::VMIN] = 1;
+
+        Self {
+            iflag: Self::ICRNL,
+            lflag: Self::ISIG | Self::ICANON | Self::ECHO | Self::ECHOCTL,
+            cc,
+        }
+    }
+}